@@ -27,6 +27,11 @@ pub mod cached;
 #[cfg(feature = "std")]
 pub use cached::CachedOnDiskCorpus;
 
+#[cfg(all(feature = "std", unix))]
+pub mod shared_mmap;
+#[cfg(all(feature = "std", unix))]
+pub use shared_mmap::{SharedMmapCorpus, SharedMmapStore};
+
 #[cfg(all(feature = "cmin", unix))]
 pub mod minimizer;
 
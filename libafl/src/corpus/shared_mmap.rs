@@ -0,0 +1,856 @@
+//! The [`SharedMmapCorpus`] keeps the inputs of all [`Testcase`]s in a [`SharedMmapStore`],
+//! an append-only, memory-mapped file that all clients on one machine share.
+//!
+//! Each client only keeps its own [`Testcase`] metadata (for example, what the scheduler needs),
+//! and a small cache of deserialized inputs. The inputs themselves are read in place from the shared mapping,
+//! so the same testcase, added by many clients, only takes up memory once.
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use core::{
+    cell::{Ref, RefCell, RefMut},
+    fmt, ptr, slice,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use std::{
+    fs::{File, OpenOptions},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use libafl_bolts::{hash_std, rands::random_seed};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    Error, HasMetadata,
+    corpus::{Corpus, CorpusId, HasTestcase, InMemoryCorpus, Testcase},
+    inputs::Input,
+};
+
+/// Magic value at the start of an initialized [`SharedMmapStore`] file
+const SHARED_MMAP_STORE_MAGIC: u64 = 0x4c49_4241_464c_5353; // "LIBAFLSS"
+
+/// The header at the start of the store file
+#[repr(C)]
+struct StoreHeader {
+    /// [`SHARED_MMAP_STORE_MAGIC`], once initialized
+    magic: u64,
+    /// The size of the store, including this header
+    capacity: u64,
+    /// A random id, to tell the store apart from one that got recreated at the same path later
+    id: u64,
+    /// The offset at which the next record will be appended
+    end: AtomicU64,
+}
+
+/// The header in front of each record in the store
+#[repr(C)]
+struct RecordHeader {
+    /// The room the record takes up, including this header.
+    /// Writers claim a record by setting it before they move the store's `end` past it,
+    /// so it's set for all records before `end`, even if their writer died before committing them.
+    size: AtomicU64,
+    /// The length of the record, without this header
+    len: u64,
+    /// The [`hash_std`] of the record
+    hash: u64,
+    /// Set to `1` by the writer once the record is complete
+    committed: AtomicU64,
+}
+
+const STORE_HEADER_LEN: usize = size_of::<StoreHeader>();
+const RECORD_HEADER_LEN: usize = size_of::<RecordHeader>();
+
+/// Records start at 8 byte aligned offsets
+#[inline]
+const fn record_len(len: usize) -> usize {
+    (RECORD_HEADER_LEN + len + 7) & !7
+}
+
+/// Which records of the store this process has seen so far, to find duplicates
+#[derive(Debug, Default)]
+struct StoreIndex {
+    /// The offset up to which we indexed all records
+    scanned: u64,
+    /// The offsets of all records, by hash
+    offsets: hashbrown::HashMap<u64, Vec<u64>>,
+    /// Records before `scanned` that were not committed yet when we looked, we check them again each time
+    pending: Vec<u64>,
+}
+
+/// The mapping of a [`SharedMmapStore`]
+struct StoreMapping {
+    path: PathBuf,
+    map: *mut u8,
+    capacity: usize,
+    index: Mutex<StoreIndex>,
+}
+
+// # Safety
+// The mapping is shared between processes anyway: the header is only accessed using atomics,
+// and records are never written to after they got committed.
+unsafe impl Send for StoreMapping {}
+unsafe impl Sync for StoreMapping {}
+
+impl Drop for StoreMapping {
+    fn drop(&mut self) {
+        // # Safety
+        // We mapped exactly this range in `SharedMmapStore::open`.
+        unsafe {
+            libc::munmap(self.map.cast(), self.capacity);
+        }
+    }
+}
+
+/// An append-only store of byte records, backed by a memory-mapped file.
+///
+/// All processes that open the same file share the same records, in memory.
+/// Records that are already in the store don't get added again.
+/// The file is sparse, so only the used part takes up space.
+#[derive(Clone)]
+pub struct SharedMmapStore {
+    mapping: Arc<StoreMapping>,
+}
+
+impl fmt::Debug for SharedMmapStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedMmapStore")
+            .field("path", &self.mapping.path)
+            .field("capacity", &self.mapping.capacity)
+            .field("used", &self.used())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SharedMmapStore {
+    /// Opens the store at `path`, creating it with room for `capacity` bytes if it does not exist yet.
+    ///
+    /// If another process created the store already, its capacity is used instead.
+    /// To share the records in memory, pick a path on a `tmpfs`, such as `/dev/shm`, or on a local disk.
+    pub fn open<P>(path: P, capacity: usize) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if capacity <= STORE_HEADER_LEN {
+            return Err(Error::illegal_argument(format!(
+                "The capacity of the shared mmap store {} is too small: {capacity}",
+                path.display()
+            )));
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Self::lock_and_map(&file, path, Some(capacity))
+    }
+
+    /// Reopens the existing store at `path`, which has to have the given [`Self::id`].
+    ///
+    /// Fails if the file is gone, or got recreated in the meantime, for example after a reboot cleared `/dev/shm`.
+    /// The offsets of the records in the old store would point at nothing, or at different records, otherwise.
+    pub fn open_existing<P>(path: P, id: u64) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| {
+                Error::illegal_state(format!(
+                    "Could not reopen shared mmap store {}: {e}",
+                    path.display()
+                ))
+            })?;
+        let store = Self::lock_and_map(&file, path, None)?;
+        if store.id() != id {
+            return Err(Error::illegal_state(format!(
+                "Shared mmap store {} got recreated (id {:#x} instead of {id:#x}), its old records are gone",
+                path.display(),
+                store.id()
+            )));
+        }
+        Ok(store)
+    }
+
+    /// Maps the store under the file lock.
+    /// Only initializes the store if it's new and we got a `capacity` for it.
+    fn lock_and_map(file: &File, path: &Path, capacity: Option<usize>) -> Result<Self, Error> {
+        // Only one process may initialize the store.
+        // # Safety
+        // Plain syscall on an fd we own, the result is checked below.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(Error::last_os_error(format!(
+                "Could not lock shared mmap store {}",
+                path.display()
+            )));
+        }
+        let mapping = Self::map_locked(file, path, capacity);
+        // # Safety
+        // Plain syscall on an fd we own. Closing the file would unlock it, too.
+        unsafe {
+            libc::flock(file.as_raw_fd(), libc::LOCK_UN);
+        }
+
+        Ok(Self {
+            mapping: Arc::new(mapping?),
+        })
+    }
+
+    /// Maps the store while holding the file lock, initializing the header if it is new.
+    #[expect(clippy::cast_ptr_alignment)] // mappings are page aligned
+    fn map_locked(
+        file: &File,
+        path: &Path,
+        capacity: Option<usize>,
+    ) -> Result<StoreMapping, Error> {
+        let file_len = file.metadata()?.len();
+        let initialized = file_len >= STORE_HEADER_LEN as u64 && {
+            let mut header = [0_u8; 8];
+            std::os::unix::fs::FileExt::read_exact_at(file, &mut header, 0)?;
+            u64::from_ne_bytes(header) == SHARED_MMAP_STORE_MAGIC
+        };
+
+        let capacity = if initialized {
+            let mut header = [0_u8; 8];
+            std::os::unix::fs::FileExt::read_exact_at(file, &mut header, 8)?;
+            usize::try_from(u64::from_ne_bytes(header))?
+        } else if let Some(capacity) = capacity {
+            file.set_len(capacity as u64)?;
+            capacity
+        } else {
+            return Err(Error::illegal_state(format!(
+                "Shared mmap store {} is not initialized",
+                path.display()
+            )));
+        };
+
+        // # Safety
+        // We map a range of the file that exists, and check the result.
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                capacity,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr::addr_eq(map, libc::MAP_FAILED) {
+            return Err(Error::last_os_error(format!(
+                "mmap() failed for shared mmap store {}",
+                path.display()
+            )));
+        }
+        let map = map.cast::<u8>();
+
+        if !initialized {
+            // # Safety
+            // The mapping is large enough for the header, and we hold the lock.
+            unsafe {
+                map.cast::<StoreHeader>().write(StoreHeader {
+                    magic: SHARED_MMAP_STORE_MAGIC,
+                    capacity: capacity as u64,
+                    id: random_seed(),
+                    end: AtomicU64::new(STORE_HEADER_LEN as u64),
+                });
+            }
+        }
+
+        Ok(StoreMapping {
+            path: path.to_path_buf(),
+            map,
+            capacity,
+            index: Mutex::new(StoreIndex {
+                scanned: STORE_HEADER_LEN as u64,
+                offsets: hashbrown::HashMap::default(),
+                pending: vec![],
+            }),
+        })
+    }
+
+    #[expect(clippy::cast_ptr_alignment)] // mappings are page aligned
+    fn header(&self) -> &StoreHeader {
+        // # Safety
+        // The header got initialized in `open`, and stays mapped as long as we live.
+        unsafe { &*self.mapping.map.cast::<StoreHeader>() }
+    }
+
+    /// The path of the file backing this store
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.mapping.path
+    }
+
+    /// The random id of this store, see [`Self::open_existing`]
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        self.header().id
+    }
+
+    /// The total size of this store in bytes
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.mapping.capacity
+    }
+
+    /// The bytes used by all records in this store, across all processes
+    #[must_use]
+    pub fn used(&self) -> usize {
+        usize::try_from(self.header().end.load(Ordering::Acquire))
+            .unwrap_or(usize::MAX)
+            .min(self.capacity())
+    }
+
+    /// The header of the record at `offset`, if it's inside the store
+    #[expect(clippy::cast_ptr_alignment)] // records are 8 byte aligned
+    fn record_header(&self, offset: u64) -> Option<&RecordHeader> {
+        let offset = usize::try_from(offset).ok()?;
+        if offset < STORE_HEADER_LEN
+            || offset % 8 != 0
+            || offset + RECORD_HEADER_LEN > self.capacity()
+        {
+            return None;
+        }
+        // # Safety
+        // The record header is aligned and inside the mapping, its contents are only trusted after further checks.
+        Some(unsafe { &*self.mapping.map.add(offset).cast::<RecordHeader>() })
+    }
+
+    /// The committed record at `offset`, if any
+    fn record(&self, offset: u64) -> Option<(&RecordHeader, &[u8])> {
+        let header = self.record_header(offset)?;
+        if header.committed.load(Ordering::Acquire) != 1 {
+            return None;
+        }
+        let offset = usize::try_from(offset).ok()?;
+        let len = usize::try_from(header.len).ok()?;
+        if len > self.capacity() - offset - RECORD_HEADER_LEN {
+            return None;
+        }
+        // # Safety
+        // The committed record is inside the mapping, and never gets written to again.
+        let data =
+            unsafe { slice::from_raw_parts(self.mapping.map.add(offset + RECORD_HEADER_LEN), len) };
+        Some((header, data))
+    }
+
+    /// Reads the record at `offset` in place.
+    pub fn get(&self, offset: u64) -> Result<&[u8], Error> {
+        self.record(offset).map(|(_, data)| data).ok_or_else(|| {
+            Error::key_not_found(format!(
+                "No record at offset {offset} in shared mmap store {}",
+                self.path().display()
+            ))
+        })
+    }
+
+    /// Returns the offset of a record holding exactly `data`, if there is one.
+    /// Indexes all records that got committed since we last looked.
+    fn find(&self, hash: u64, data: &[u8]) -> Option<u64> {
+        let mut index = self.mapping.index.lock().unwrap();
+        let index = &mut *index;
+        index.pending.retain(|offset| {
+            let Some((header, _)) = self.record(*offset) else {
+                return true;
+            };
+            index.offsets.entry(header.hash).or_default().push(*offset);
+            false
+        });
+
+        let end = self.used() as u64;
+        while index.scanned < end {
+            let scanned = index.scanned;
+            let Some(header) = self.record_header(scanned) else {
+                break;
+            };
+            let size = header.size.load(Ordering::Acquire);
+            if size < RECORD_HEADER_LEN as u64 {
+                // Never happens for claimed records, don't trust anything after it.
+                break;
+            }
+            if let Some((header, _)) = self.record(scanned) {
+                index.offsets.entry(header.hash).or_default().push(scanned);
+            } else {
+                // Still being written, or its writer died. Skip it, and check again next time.
+                index.pending.push(scanned);
+            }
+            index.scanned += size;
+        }
+
+        index.offsets.get(&hash)?.iter().copied().find(|offset| {
+            self.record(*offset)
+                .is_some_and(|(_, record)| record == data)
+        })
+    }
+
+    /// Claims room for a record of `size` bytes at the end of the store, and returns its offset.
+    fn claim(&self, size: usize) -> Result<usize, Error> {
+        let end = &self.header().end;
+        loop {
+            let offset = end.load(Ordering::Acquire);
+            let start = usize::try_from(offset)?;
+            if start + size > self.capacity() {
+                return Err(Error::illegal_state(format!(
+                    "Shared mmap store {} is full ({} bytes), cannot add {size} more bytes",
+                    self.path().display(),
+                    self.capacity(),
+                )));
+            }
+            let header = self.record_header(offset).ok_or_else(|| {
+                Error::illegal_state(format!(
+                    "Shared mmap store {} is corrupted, its end is at {offset}",
+                    self.path().display()
+                ))
+            })?;
+            // Whoever claims the record moves `end` past it, or any other writer does, should the first one die.
+            match header
+                .size
+                .compare_exchange(0, size as u64, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    let _ = end.compare_exchange(
+                        offset,
+                        offset + size as u64,
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    );
+                    return Ok(start);
+                }
+                Err(claimed) => {
+                    let _ = end.compare_exchange(
+                        offset,
+                        offset + claimed,
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Appends `data` to the store and returns its offset.
+    /// If the store already holds the same data, returns the offset of the existing record instead.
+    #[expect(clippy::cast_ptr_alignment)] // records are 8 byte aligned
+    pub fn append(&self, data: &[u8]) -> Result<u64, Error> {
+        let hash = hash_std(data);
+        if let Some(offset) = self.find(hash, data) {
+            return Ok(offset);
+        }
+
+        let offset = self.claim(record_len(data.len()))?;
+
+        // # Safety
+        // We claimed `offset..offset + record_len(data.len())` for us alone, and it's inside the mapping.
+        // Only the `size` of the header is shared with other writers, so we leave it alone.
+        unsafe {
+            let record = self.mapping.map.add(offset);
+            let header = record.cast::<RecordHeader>();
+            (&raw mut (*header).len).write(data.len() as u64);
+            (&raw mut (*header).hash).write(hash);
+            ptr::copy_nonoverlapping(data.as_ptr(), record.add(RECORD_HEADER_LEN), data.len());
+            (*header).committed.store(1, Ordering::Release);
+        }
+        Ok(offset as u64)
+    }
+}
+
+/// How a [`SharedMmapStore`] gets serialized: the same store gets reopened on deserialization.
+#[derive(Serialize, Deserialize)]
+struct SharedMmapStoreFile {
+    path: PathBuf,
+    id: u64,
+}
+
+impl Serialize for SharedMmapStore {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        SharedMmapStoreFile {
+            path: self.path().to_path_buf(),
+            id: self.id(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedMmapStore {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let file = SharedMmapStoreFile::deserialize(deserializer)?;
+        Self::open_existing(&file.path, file.id).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Where the input of a [`Testcase`] lives in the [`SharedMmapStore`] of a [`SharedMmapCorpus`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SharedMmapInputMetadata {
+    /// The offset of the serialized input in the store
+    pub offset: u64,
+}
+
+libafl_bolts::impl_serdeany!(SharedMmapInputMetadata);
+
+/// A corpus that keeps all inputs in a [`SharedMmapStore`], shared by all clients on the machine.
+///
+/// Each client keeps its own [`Testcase`]s and their metadata, but without inputs.
+/// Inputs get deserialized from the shared mapping when they are being used,
+/// keeping at most `cache_max_len` of them around. The eviction policy is FIFO.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SharedMmapCorpus<I> {
+    inner: InMemoryCorpus<I>,
+    store: SharedMmapStore,
+    cached_indexes: RefCell<VecDeque<CorpusId>>,
+    cache_max_len: usize,
+}
+
+impl<I> SharedMmapCorpus<I> {
+    /// Creates a [`SharedMmapCorpus`], storing inputs in the [`SharedMmapStore`] at `path`.
+    ///
+    /// All clients on a machine should pass the same `path`, see [`SharedMmapStore::open`].
+    pub fn new<P>(path: P, capacity: usize, cache_max_len: usize) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_store(SharedMmapStore::open(path, capacity)?, cache_max_len)
+    }
+
+    /// Creates a [`SharedMmapCorpus`] on top of an already opened [`SharedMmapStore`]
+    pub fn with_store(store: SharedMmapStore, cache_max_len: usize) -> Result<Self, Error> {
+        if cache_max_len == 0 {
+            return Err(Error::illegal_argument(
+                "The max cache len in SharedMmapCorpus cannot be 0",
+            ));
+        }
+        Ok(Self {
+            inner: InMemoryCorpus::new(),
+            store,
+            cached_indexes: RefCell::new(VecDeque::new()),
+            cache_max_len,
+        })
+    }
+
+    /// The shared store holding the inputs
+    #[must_use]
+    pub fn store(&self) -> &SharedMmapStore {
+        &self.store
+    }
+
+    /// The serialized input of the [`Testcase`] with the given id, read in place from the shared store
+    pub fn input_bytes(&self, id: CorpusId) -> Result<&[u8], Error> {
+        let offset = self
+            .inner
+            .get_from_all(id)?
+            .borrow()
+            .metadata::<SharedMmapInputMetadata>()?
+            .offset;
+        self.store.get(offset)
+    }
+}
+
+impl<I> SharedMmapCorpus<I>
+where
+    I: Input,
+{
+    /// Moves the input of the `testcase` into the shared store, leaving only its offset behind.
+    fn store_input(&self, testcase: &mut Testcase<I>) -> Result<(), Error> {
+        match testcase.input_mut().take() {
+            Some(input) => {
+                let offset = self.store.append(&postcard::to_allocvec(&input)?)?;
+                testcase.add_metadata(SharedMmapInputMetadata { offset });
+                Ok(())
+            }
+            None if testcase.has_metadata::<SharedMmapInputMetadata>() => Ok(()),
+            None => Err(Error::illegal_argument(
+                "Cannot add a testcase without input to a SharedMmapCorpus",
+            )),
+        }
+    }
+
+    fn cache_testcase<'a>(
+        &'a self,
+        testcase: &'a RefCell<Testcase<I>>,
+        id: CorpusId,
+    ) -> Result<(), Error> {
+        if testcase.borrow().input().is_none() {
+            self.load_input_into(&mut testcase.borrow_mut())?;
+            let mut borrowed_num = 0;
+            while self.cached_indexes.borrow().len() >= self.cache_max_len {
+                let removed = self.cached_indexes.borrow_mut().pop_front().unwrap();
+
+                if let Ok(mut borrowed) = self.inner.get_from_all(removed)?.try_borrow_mut() {
+                    *borrowed.input_mut() = None;
+                } else {
+                    self.cached_indexes.borrow_mut().push_back(removed);
+                    borrowed_num += 1;
+                    if self.cache_max_len == borrowed_num {
+                        break;
+                    }
+                }
+            }
+            self.cached_indexes.borrow_mut().push_back(id);
+        }
+        Ok(())
+    }
+}
+
+impl<I> Corpus<I> for SharedMmapCorpus<I>
+where
+    I: Input,
+{
+    /// Returns the number of all enabled entries
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    /// Returns the number of all disabled entries
+    fn count_disabled(&self) -> usize {
+        self.inner.count_disabled()
+    }
+
+    /// Returns the number of elements including disabled entries
+    #[inline]
+    fn count_all(&self) -> usize {
+        self.inner.count_all()
+    }
+
+    /// Add an enabled testcase to the corpus and return its index
+    fn add(&mut self, mut testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        self.store_input(&mut testcase)?;
+        self.inner.add(testcase)
+    }
+
+    /// Add a disabled testcase to the corpus and return its index
+    fn add_disabled(&mut self, mut testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        self.store_input(&mut testcase)?;
+        self.inner.add_disabled(testcase)
+    }
+
+    /// Replaces the testcase at the given idx
+    fn replace(&mut self, id: CorpusId, mut testcase: Testcase<I>) -> Result<Testcase<I>, Error> {
+        self.store_input(&mut testcase)?;
+        let mut old = self.inner.replace(id, testcase)?;
+        self.cached_indexes.borrow_mut().retain(|e| *e != id);
+        self.load_input_into(&mut old)?;
+        Ok(old)
+    }
+
+    /// Removes an entry from the corpus, returning it if it was present; considers both enabled and disabled testcases.
+    fn remove(&mut self, id: CorpusId) -> Result<Testcase<I>, Error> {
+        let mut testcase = self.inner.remove(id)?;
+        self.cached_indexes.borrow_mut().retain(|e| *e != id);
+        self.load_input_into(&mut testcase)?;
+        Ok(testcase)
+    }
+
+    /// Get by id; considers only enabled testcases
+    #[inline]
+    fn get(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        let testcase = { self.inner.get(id)? };
+        self.cache_testcase(testcase, id)?;
+        Ok(testcase)
+    }
+
+    /// Get by id; considers both enabled and disabled testcases
+    #[inline]
+    fn get_from_all(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        let testcase = { self.inner.get_from_all(id)? };
+        self.cache_testcase(testcase, id)?;
+        Ok(testcase)
+    }
+
+    /// Current testcase scheduled
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    /// Current testcase scheduled (mutable)
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+
+    #[inline]
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.next(id)
+    }
+
+    /// Peek the next free corpus id
+    #[inline]
+    fn peek_free_id(&self) -> CorpusId {
+        self.inner.peek_free_id()
+    }
+
+    #[inline]
+    fn prev(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.prev(id)
+    }
+
+    #[inline]
+    fn first(&self) -> Option<CorpusId> {
+        self.inner.first()
+    }
+
+    #[inline]
+    fn last(&self) -> Option<CorpusId> {
+        self.inner.last()
+    }
+
+    /// Get the nth corpus id; considers only enabled testcases
+    #[inline]
+    fn nth(&self, nth: usize) -> CorpusId {
+        self.inner.nth(nth)
+    }
+
+    /// Get the nth corpus id; considers both enabled and disabled testcases
+    #[inline]
+    fn nth_from_all(&self, nth: usize) -> CorpusId {
+        self.inner.nth_from_all(nth)
+    }
+
+    fn load_input_into(&self, testcase: &mut Testcase<I>) -> Result<(), Error> {
+        if testcase.input().is_none() {
+            let offset = testcase.metadata::<SharedMmapInputMetadata>()?.offset;
+            *testcase.input_mut() = Some(postcard::from_bytes(self.store.get(offset)?)?);
+        }
+        Ok(())
+    }
+
+    /// The inputs already live in the shared store, nothing to do.
+    #[inline]
+    fn store_input_from(&self, _testcase: &Testcase<I>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<I> HasTestcase<I> for SharedMmapCorpus<I>
+where
+    I: Input,
+{
+    fn testcase(&self, id: CorpusId) -> Result<Ref<'_, Testcase<I>>, Error> {
+        Ok(self.get(id)?.borrow())
+    }
+
+    fn testcase_mut(&self, id: CorpusId) -> Result<RefMut<'_, Testcase<I>>, Error> {
+        Ok(self.get(id)?.borrow_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::{SharedMmapCorpus, SharedMmapStore, record_len};
+    use crate::{
+        corpus::{Corpus, Testcase},
+        inputs::BytesInput,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_shared_mmap_corpus() {
+        let path = env::temp_dir().join(format!("libafl_shared_mmap_test_{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut first = SharedMmapCorpus::<BytesInput>::new(&path, 1 << 20, 1).unwrap();
+        // Another client on the same machine
+        let mut second = SharedMmapCorpus::<BytesInput>::new(&path, 1 << 16, 1).unwrap();
+        assert_eq!(second.store().capacity(), 1 << 20);
+
+        let id = first
+            .add(Testcase::new(BytesInput::new(vec![1, 2, 3])))
+            .unwrap();
+        let used = first.store().used();
+
+        // Adding the same input again does not take up more room in the store
+        let second_id = second
+            .add(Testcase::new(BytesInput::new(vec![1, 2, 3])))
+            .unwrap();
+        assert_eq!(second.store().used(), used);
+        assert_eq!(
+            first.input_bytes(id).unwrap(),
+            second.input_bytes(second_id).unwrap()
+        );
+
+        let other_id = second
+            .add(Testcase::new(BytesInput::new(vec![4, 5])))
+            .unwrap();
+        assert!(second.store().used() > used);
+
+        // Only one input stays in the cache at a time
+        assert_eq!(
+            second.cloned_input_for_id(second_id).unwrap(),
+            BytesInput::new(vec![1, 2, 3])
+        );
+        assert_eq!(
+            second.cloned_input_for_id(other_id).unwrap(),
+            BytesInput::new(vec![4, 5])
+        );
+        assert!(
+            second
+                .inner
+                .get(second_id)
+                .unwrap()
+                .borrow()
+                .input()
+                .is_none()
+        );
+
+        // The store gets reopened after (de)serializing the corpus
+        let serialized = postcard::to_allocvec(&first).unwrap();
+        let restored: SharedMmapCorpus<BytesInput> = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(
+            restored.cloned_input_for_id(id).unwrap(),
+            BytesInput::new(vec![1, 2, 3])
+        );
+
+        drop((first, second, restored));
+        fs::remove_file(&path).unwrap();
+        assert!(SharedMmapStore::open(&path, 8).is_err());
+
+        // Restoring fails once the store is gone, or got recreated in the meantime
+        assert!(postcard::from_bytes::<SharedMmapCorpus<BytesInput>>(&serialized).is_err());
+        let recreated = SharedMmapStore::open(&path, 1 << 16).unwrap();
+        assert!(postcard::from_bytes::<SharedMmapCorpus<BytesInput>>(&serialized).is_err());
+        drop(recreated);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_shared_mmap_store_dead_writer() {
+        let path = env::temp_dir().join(format!(
+            "libafl_shared_mmap_dead_writer_test_{}",
+            process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let store = SharedMmapStore::open(&path, 1 << 16).unwrap();
+        let first = store.append(&[1, 2, 3]).unwrap();
+        // A writer that died after it claimed its record, before it committed it
+        let dead = store.claim(record_len(16)).unwrap();
+        assert!(store.get(dead as u64).is_err());
+
+        // Records after the dead one still get deduplicated, also by other processes
+        let other = SharedMmapStore::open(&path, 1 << 16).unwrap();
+        let second = store.append(&[4, 5]).unwrap();
+        let used = store.used();
+        assert_eq!(other.append(&[4, 5]).unwrap(), second);
+        assert_eq!(other.append(&[1, 2, 3]).unwrap(), first);
+        assert_eq!(store.used(), used);
+
+        drop((store, other));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -6,7 +6,7 @@ use libafl_bolts::{
     llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
 };
 #[cfg(feature = "llmp_compression")]
-use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
+use libafl_bolts::{compress::CodecCompressor, llmp::LLMP_FLAG_COMPRESSED};
 use serde::de::DeserializeOwned;
 
 #[cfg(feature = "llmp_compression")]
@@ -16,7 +16,7 @@ use crate::events::{_LLMP_TAG_TO_MAIN, BrokerEventResult, Event, EventWithStats}
 /// An LLMP-backed event manager for scalable multi-processed fuzzing
 pub struct CentralizedLlmpHook<I> {
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: CodecCompressor,
     phantom: PhantomData<I>,
 }
 
@@ -29,7 +29,7 @@ where
         _broker_inner: &mut LlmpBrokerInner<SHM, SP>,
         client_id: ClientId,
         msg_tag: &mut Tag,
-        _msg_flags: &mut Flags,
+        msg_flags: &mut Flags,
         msg: &mut [u8],
         _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
     ) -> Result<LlmpMsgHookResult, Error> {
@@ -41,13 +41,17 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if *_msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = compressor.decompress(msg)?;
+            let event_bytes = if *msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+                compressed = compressor.decompress_with(
+                    msg_flags.codec()?,
+                    msg_flags.uses_dictionary(),
+                    msg,
+                )?;
                 &compressed
             } else {
                 &*msg
             };
-            let event: EventWithStats<I> = postcard::from_bytes(event_bytes)?;
+            let event: EventWithStats<I> = msg_flags.format()?.deserialize(event_bytes)?;
             match Self::handle_in_broker(client_id, &event)? {
                 BrokerEventResult::Forward => Ok(LlmpMsgHookResult::ForwardToClients),
                 BrokerEventResult::Handled => Ok(LlmpMsgHookResult::Handled),
@@ -76,7 +80,7 @@ impl<I> CentralizedLlmpHook<I> {
     pub fn new() -> Result<Self, Error> {
         Ok(Self {
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: CodecCompressor::with_threshold(COMPRESS_THRESHOLD),
             phantom: PhantomData,
         })
     }
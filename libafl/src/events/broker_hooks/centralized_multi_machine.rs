@@ -6,10 +6,13 @@ use core::{
 };
 
 #[cfg(feature = "llmp_compression")]
-use libafl_bolts::llmp::LLMP_FLAG_COMPRESSED;
+use libafl_bolts::compress::Compressor;
 use libafl_bolts::{
     ClientId, Error,
-    llmp::{Flags, LLMP_FLAG_FROM_MM, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
+    llmp::{
+        Flags, LLMP_FLAG_COMPRESSED, LLMP_FLAG_FROM_MM, LlmpBrokerInner, LlmpHook,
+        LlmpMsgHookResult, Tag,
+    },
     ownedref::OwnedRef,
 };
 use serde::Serialize;
@@ -130,20 +133,24 @@ where
         state_lock: &mut RwLockWriteGuard<TcpMultiMachineState<A>>,
         event: &EventWithStats<I>,
     ) -> Result<(Flags, Vec<u8>), Error> {
-        let serialized = postcard::to_allocvec(&event)?;
-
-        match state_lock.compressor().maybe_compress(&serialized) {
-            Some(comp_buf) => Ok((LLMP_FLAG_COMPRESSED, comp_buf)),
-            None => Ok((Flags(0), serialized)),
+        let format = state_lock.serialization_format();
+        let serialized = format.serialize(event)?;
+        let flags = Flags::serialized_with(format);
+
+        let compressor = state_lock.compressor();
+        match compressor.maybe_compress(&serialized)? {
+            Some(comp_buf) => Ok((flags | Flags::compressed_by(compressor), comp_buf)),
+            None => Ok((flags, serialized)),
         }
     }
 
     #[cfg(not(feature = "llmp_compression"))]
     fn try_compress(
-        _state_lock: &mut RwLockWriteGuard<TcpMultiMachineState<A>>,
+        state_lock: &mut RwLockWriteGuard<TcpMultiMachineState<A>>,
         event: &EventWithStats<I>,
     ) -> Result<(Flags, Vec<u8>), Error> {
-        Ok((Flags(0), postcard::to_allocvec(&event)?))
+        let format = state_lock.serialization_format();
+        Ok((Flags::serialized_with(format), format.serialize(event)?))
     }
 }
 
@@ -158,17 +165,17 @@ where
         _broker_inner: &mut LlmpBrokerInner<SHM, SP>,
         _client_id: ClientId,
         _msg_tag: &mut Tag,
-        _msg_flags: &mut Flags,
+        msg_flags: &mut Flags,
         msg: &mut [u8],
         _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
     ) -> Result<LlmpMsgHookResult, Error> {
         let shared_state = self.shared_state.clone();
+        let flags = *msg_flags;
 
         // # Safety
         // Here, we suppose msg will *never* be written again and will always be available.
         // Thus, it is safe to handle this in a separate thread.
         let msg_lock = unsafe { NullLock::new((msg.as_ptr(), msg.len())) };
-        // let flags = msg_flags.clone();
 
         let _handle: JoinHandle<Result<(), Error>> = self.rt.spawn(async move {
             let mut state_wr_lock = shared_state.write().await;
@@ -188,10 +195,10 @@ where
             // };
             // let event: Event<I> = postcard::from_bytes(event_bytes)?;
 
-            let mm_msg: MultiMachineMsg<I> = MultiMachineMsg::llmp_msg(OwnedRef::Ref(msg));
+            let mm_msg: MultiMachineMsg<I> = MultiMachineMsg::llmp_msg(flags, OwnedRef::Ref(msg));
 
             // TODO: do not copy here
-            state_wr_lock.add_past_msg(msg);
+            state_wr_lock.add_past_msg(flags, msg);
 
             log::debug!("Sending msg...");
 
@@ -238,19 +245,31 @@ where
             let msgs_to_forward: Result<Vec<(Tag, Flags, Vec<u8>)>, Error> = incoming_msgs
                 .into_iter()
                 .map(|mm_msg| match mm_msg {
-                    MultiMachineMsg::LlmpMsg(msg) => {
+                    // Compressed messages arrive in a codec we negotiated with the node
+                    MultiMachineMsg::LlmpMsg(flags, msg)
+                        if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED =>
+                    {
+                        Ok((
+                            _LLMP_TAG_TO_MAIN,
+                            flags | LLMP_FLAG_FROM_MM,
+                            msg.into_owned().unwrap().into_vec(),
+                        ))
+                    }
+                    MultiMachineMsg::LlmpMsg(flags, msg) => {
                         let msg = msg.into_owned().unwrap().into_vec();
                         #[cfg(feature = "llmp_compression")]
-                        match state_wr_lock.compressor().maybe_compress(msg.as_ref()) {
+                        match state_wr_lock.compressor().maybe_compress(msg.as_ref())? {
                             Some(comp_buf) => Ok((
                                 _LLMP_TAG_TO_MAIN,
-                                LLMP_FLAG_COMPRESSED | LLMP_FLAG_FROM_MM,
+                                flags
+                                    | Flags::compressed_by(state_wr_lock.compressor())
+                                    | LLMP_FLAG_FROM_MM,
                                 comp_buf,
                             )),
-                            None => Ok((_LLMP_TAG_TO_MAIN, LLMP_FLAG_FROM_MM, msg)),
+                            None => Ok((_LLMP_TAG_TO_MAIN, flags | LLMP_FLAG_FROM_MM, msg)),
                         }
                         #[cfg(not(feature = "llmp_compression"))]
-                        Ok((_LLMP_TAG_TO_MAIN, LLMP_FLAG_FROM_MM, msg))
+                        Ok((_LLMP_TAG_TO_MAIN, flags | LLMP_FLAG_FROM_MM, msg))
                     }
                     MultiMachineMsg::Event(evt) => {
                         let evt = evt.into_owned().unwrap();
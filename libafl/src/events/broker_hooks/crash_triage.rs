@@ -0,0 +1,556 @@
+//! A broker hook deduplicating the objectives found by all clients, so that the same bug found by
+//! many clients ends up as a single bucket with one representative input, instead of as many files.
+use alloc::{borrow::Cow, string::ToString, sync::Arc, vec::Vec};
+use core::{fmt, marker::PhantomData, time::Duration};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use hashbrown::HashMap;
+use libafl_bolts::{
+    ClientId, current_time, generic_hash_std,
+    llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
+};
+#[cfg(feature = "llmp_compression")]
+use libafl_bolts::{compress::CodecCompressor, llmp::LLMP_FLAG_COMPRESSED};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "llmp_compression")]
+use crate::events::llmp::COMPRESS_THRESHOLD;
+use crate::{
+    Error,
+    events::{Event, EventWithStats, llmp::LLMP_TAG_EVENT_TO_BOTH},
+    inputs::Input,
+    monitors::{
+        Monitor,
+        stats::{AggregatorOps, ClientStatsManager, UserStats, UserStatsValue},
+    },
+    observers::ObjectiveSignature,
+};
+
+/// The file in the triage directory keeping the state of all buckets
+pub const CRASH_BUCKETS_FILE: &str = "buckets.json";
+
+/// What a crash bucket groups its objectives by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CrashBucketKey {
+    /// The hash of the crash site, i.e., the faulting address or the innermost frames of the stack trace
+    CrashSite(u64),
+    /// The hash of the whole stack trace of the crash, if the crash site is not known
+    Stack(u64),
+    /// The hash of the coverage of the crashing execution, if neither the crash site nor a stack trace is available
+    Coverage(u64),
+    /// The hash of the input, if the objective came without any signature
+    Input(u64),
+}
+
+impl CrashBucketKey {
+    /// The key of an objective, preferring the crash site over the stack hash over the coverage hash.
+    /// The input hash is only the last resort.
+    #[must_use]
+    pub fn for_objective<I>(signature: &ObjectiveSignature, input: Option<&I>) -> Option<Self>
+    where
+        I: Input,
+    {
+        signature
+            .crash_site_hash
+            .map(Self::CrashSite)
+            .or(signature.stack_hash.map(Self::Stack))
+            .or(signature.coverage_hash.map(Self::Coverage))
+            .or_else(|| input.map(|input| Self::Input(generic_hash_std(input))))
+    }
+}
+
+impl fmt::Display for CrashBucketKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CrashSite(hash) => write!(f, "site-{hash:016x}"),
+            Self::Stack(hash) => write!(f, "stack-{hash:016x}"),
+            Self::Coverage(hash) => write!(f, "cov-{hash:016x}"),
+            Self::Input(hash) => write!(f, "input-{hash:016x}"),
+        }
+    }
+}
+
+/// All objectives with the same [`CrashBucketKey`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashBucket {
+    /// The key of this bucket
+    pub key: CrashBucketKey,
+    /// How many objectives ended up in this bucket
+    pub count: u64,
+    /// The clients that found objectives of this bucket
+    pub clients: Vec<ClientId>,
+    /// When the first objective of this bucket arrived
+    pub first_seen: Duration,
+    /// When the latest objective of this bucket arrived
+    pub last_seen: Duration,
+    /// The file name of the representative input, in the triage directory, if any objective came with its input
+    pub representative: Option<PathBuf>,
+}
+
+/// The crash buckets of a campaign, as kept by the [`CrashTriageHook`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrashBuckets {
+    buckets: Vec<CrashBucket>,
+    #[serde(skip)]
+    index: HashMap<CrashBucketKey, usize>,
+    duplicates: u64,
+    untriaged: u64,
+}
+
+impl CrashBuckets {
+    /// Loads the buckets from the `path` written by [`CrashBuckets::save`]
+    pub fn load<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut buckets: Self = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| Error::serialize(format!("Failed to parse crash buckets: {err:?}")))?;
+        buckets.index = buckets
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(idx, bucket)| (bucket.key, idx))
+            .collect();
+        Ok(buckets)
+    }
+
+    /// Saves the buckets to `path`, replacing the previous state atomically
+    pub fn save<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        let serialized = serde_json::to_vec_pretty(self).map_err(|err| {
+            Error::serialize(format!("Failed to json-ify crash buckets: {err:?}"))
+        })?;
+        fs::write(&tmp_path, serialized)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// The number of buckets
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// If no objective was bucketed yet
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Iterates over all buckets, in the order they were found
+    pub fn iter(&self) -> impl Iterator<Item = &CrashBucket> {
+        self.buckets.iter()
+    }
+
+    /// The bucket with the given `key`
+    #[must_use]
+    pub fn get(&self, key: &CrashBucketKey) -> Option<&CrashBucket> {
+        self.index.get(key).map(|idx| &self.buckets[*idx])
+    }
+
+    /// The bucket with the most objectives
+    #[must_use]
+    pub fn largest(&self) -> Option<&CrashBucket> {
+        self.buckets.iter().max_by_key(|bucket| bucket.count)
+    }
+
+    /// The number of objectives that ended up in an existing bucket
+    #[must_use]
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// The number of objectives that could not be bucketed, as they came without signature and input
+    #[must_use]
+    pub fn untriaged(&self) -> u64 {
+        self.untriaged
+    }
+
+    /// Adds an objective with `key` from `client_id`, returning the bucket and if it is new
+    fn add(
+        &mut self,
+        key: CrashBucketKey,
+        client_id: ClientId,
+        now: Duration,
+    ) -> (&mut CrashBucket, bool) {
+        if let Some(idx) = self.index.get(&key) {
+            self.duplicates += 1;
+            let bucket = &mut self.buckets[*idx];
+            bucket.count += 1;
+            bucket.last_seen = now;
+            if !bucket.clients.contains(&client_id) {
+                bucket.clients.push(client_id);
+            }
+            return (bucket, false);
+        }
+
+        self.index.insert(key, self.buckets.len());
+        self.buckets.push(CrashBucket {
+            key,
+            count: 1,
+            clients: vec![client_id],
+            first_seen: now,
+            last_seen: now,
+            representative: None,
+        });
+        (self.buckets.last_mut().unwrap(), true)
+    }
+}
+
+/// A broker hook bucketing the [`Event::Objective`]s of all clients.
+///
+/// Objectives are bucketed by the crash site of their [`ObjectiveSignature`], e.g., the faulting
+/// instruction of an in-process crash or the innermost frames of an [`crate::observers::AsanBacktraceObserver`],
+/// by the stack hash, e.g., from a [`crate::observers::BacktraceObserver`], if the crash site is not known,
+/// by the coverage of the crashing execution, e.g., from a map observer, if neither is known,
+/// or by the input as a last resort.
+/// Only the first input of each bucket is kept in the triage directory, if the clients share their objectives.
+/// The buckets are saved there as well, whenever a new bucket shows up and on the broker's timeouts,
+/// so that they survive restarts of the broker.
+///
+/// Only the first objective of each bucket is forwarded to the clients, unless
+/// [`CrashTriageHook::with_forward_duplicates`] is set.
+/// Add it after the [`super::StdLlmpEventHook`], so that the monitor still counts every objective,
+/// and wrap the monitor in a [`CrashTriageMonitor`] to show the bucket stats.
+#[derive(Debug)]
+pub struct CrashTriageHook<I> {
+    dir: PathBuf,
+    buckets: Arc<Mutex<CrashBuckets>>,
+    /// If the counters changed since the buckets were last saved
+    dirty: bool,
+    forward_duplicates: bool,
+    #[cfg(feature = "llmp_compression")]
+    compressor: CodecCompressor,
+    phantom: PhantomData<I>,
+}
+
+impl<I> CrashTriageHook<I> {
+    /// Creates a new [`CrashTriageHook`], keeping its state in `dir`, and loading the buckets found before, if any
+    pub fn new<P>(dir: P) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let buckets_path = dir.join(CRASH_BUCKETS_FILE);
+        let buckets = if buckets_path.exists() {
+            CrashBuckets::load(&buckets_path)?
+        } else {
+            CrashBuckets::default()
+        };
+
+        Ok(Self {
+            dir,
+            buckets: Arc::new(Mutex::new(buckets)),
+            dirty: false,
+            forward_duplicates: false,
+            #[cfg(feature = "llmp_compression")]
+            compressor: CodecCompressor::with_threshold(COMPRESS_THRESHOLD),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Also forwards objectives that end up in an existing bucket to the clients
+    #[must_use]
+    pub fn with_forward_duplicates(mut self, forward_duplicates: bool) -> Self {
+        self.forward_duplicates = forward_duplicates;
+        self
+    }
+
+    /// The buckets, shared with a [`CrashTriageMonitor`]
+    #[must_use]
+    pub fn buckets(&self) -> Arc<Mutex<CrashBuckets>> {
+        self.buckets.clone()
+    }
+
+    /// The directory keeping the buckets and their representative inputs
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl<I> CrashTriageHook<I>
+where
+    I: Input,
+{
+    /// Buckets an objective, returning if it should be forwarded to the clients.
+    ///
+    /// The buckets are saved right away if this objective got a new bucket or representative.
+    /// Otherwise, only the counters changed, and they get saved on the next timeout.
+    fn triage(
+        &mut self,
+        client_id: ClientId,
+        input: Option<&I>,
+        signature: &ObjectiveSignature,
+    ) -> Result<bool, Error> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let Some(key) = CrashBucketKey::for_objective(signature, input) else {
+            buckets.untriaged += 1;
+            self.dirty = true;
+            return Ok(true);
+        };
+
+        let (bucket, is_new) = buckets.add(key, client_id, current_time());
+        let mut changed = is_new;
+        if bucket.representative.is_none() {
+            if let Some(input) = input {
+                let file_name = PathBuf::from(key.to_string());
+                input.to_file(self.dir.join(&file_name))?;
+                bucket.representative = Some(file_name);
+                changed = true;
+            }
+        }
+        if is_new {
+            log::info!("New crash bucket {key} from client {client_id:?}");
+        }
+        if changed {
+            buckets.save(self.dir.join(CRASH_BUCKETS_FILE))?;
+            self.dirty = false;
+        } else {
+            self.dirty = true;
+        }
+
+        Ok(is_new || self.forward_duplicates)
+    }
+
+    /// Saves the buckets, if their counters changed since they were last saved
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if self.dirty {
+            self.buckets
+                .lock()
+                .unwrap()
+                .save(self.dir.join(CRASH_BUCKETS_FILE))?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+impl<I, SHM, SP> LlmpHook<SHM, SP> for CrashTriageHook<I>
+where
+    I: Input,
+{
+    fn on_new_message(
+        &mut self,
+        _broker_inner: &mut LlmpBrokerInner<SHM, SP>,
+        client_id: ClientId,
+        msg_tag: &mut Tag,
+        msg_flags: &mut Flags,
+        msg: &mut [u8],
+        _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<LlmpMsgHookResult, Error> {
+        if *msg_tag != LLMP_TAG_EVENT_TO_BOTH {
+            return Ok(LlmpMsgHookResult::ForwardToClients);
+        }
+
+        #[cfg(not(feature = "llmp_compression"))]
+        let event_bytes = &*msg;
+        #[cfg(feature = "llmp_compression")]
+        let compressed;
+        #[cfg(feature = "llmp_compression")]
+        let event_bytes = if *msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+            compressed = self.compressor.decompress_with(
+                msg_flags.codec()?,
+                msg_flags.uses_dictionary(),
+                msg,
+            )?;
+            &compressed
+        } else {
+            &*msg
+        };
+        let event: EventWithStats<I> = msg_flags.format()?.deserialize(event_bytes)?;
+
+        if let Event::Objective {
+            input, signature, ..
+        } = event.event()
+        {
+            if !self.triage(client_id, input.as_ref(), signature)? {
+                return Ok(LlmpMsgHookResult::Handled);
+            }
+        }
+        Ok(LlmpMsgHookResult::ForwardToClients)
+    }
+
+    fn on_timeout(&mut self) -> Result<(), Error> {
+        self.flush()
+    }
+}
+
+/// A monitor wrapper adding the stats of the [`CrashBuckets`] of a [`CrashTriageHook`] as user stats
+#[derive(Debug)]
+pub struct CrashTriageMonitor<MT> {
+    monitor: MT,
+    buckets: Arc<Mutex<CrashBuckets>>,
+}
+
+impl<MT> CrashTriageMonitor<MT> {
+    /// Wraps `monitor`, showing the stats of `buckets`, as returned by [`CrashTriageHook::buckets`]
+    #[must_use]
+    pub fn new(monitor: MT, buckets: Arc<Mutex<CrashBuckets>>) -> Self {
+        Self { monitor, buckets }
+    }
+}
+
+impl<MT> Monitor for CrashTriageMonitor<MT>
+where
+    MT: Monitor,
+{
+    fn display(
+        &mut self,
+        client_stats_manager: &mut ClientStatsManager,
+        event_msg: &str,
+        sender_id: ClientId,
+    ) -> Result<(), Error> {
+        let stats = {
+            let buckets = self.buckets.lock().unwrap();
+            [
+                ("crash_buckets", buckets.len() as u64),
+                ("crash_duplicates", buckets.duplicates()),
+                (
+                    "largest_crash_bucket",
+                    buckets.largest().map_or(0, |bucket| bucket.count),
+                ),
+            ]
+        };
+
+        // Only attach the stats to clients that exist, not to the broker's own heartbeats
+        let is_client = client_stats_manager.client_stats_for(sender_id).is_ok();
+        for (name, value) in stats {
+            let name = Cow::Borrowed(name);
+            if is_client {
+                client_stats_manager.update_client_stats_for(sender_id, |client_stats| {
+                    client_stats.update_user_stats(
+                        name.clone(),
+                        UserStats::new(UserStatsValue::Number(value), AggregatorOps::Max),
+                    );
+                })?;
+            }
+            client_stats_manager.aggregate(&name);
+        }
+
+        self.monitor
+            .display(client_stats_manager, event_msg, sender_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use std::{env, fs};
+
+    use libafl_bolts::ClientId;
+
+    use super::{CRASH_BUCKETS_FILE, CrashBucketKey, CrashBuckets, CrashTriageHook};
+    use crate::{
+        inputs::BytesInput,
+        observers::{ObjectiveSignature, Observer, StdMapObserver},
+    };
+
+    #[test]
+    fn test_crash_triage() {
+        let dir = env::temp_dir().join(format!("libafl_crash_triage_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut site = ObjectiveSignature {
+            stack_hash: Some(1),
+            ..ObjectiveSignature::default()
+        };
+        site.add_crash_site([0x1000, 0x2000, 0x3000]);
+        // Reached along a different path, but crashing at the same site
+        let mut other_path = ObjectiveSignature {
+            stack_hash: Some(2),
+            ..ObjectiveSignature::default()
+        };
+        other_path.add_crash_site([0x1000, 0x2000, 0x3000, 0x4000]);
+        let stack = ObjectiveSignature {
+            stack_hash: Some(1),
+            ..ObjectiveSignature::default()
+        };
+        let input = BytesInput::new(vec![1, 2, 3]);
+        let site_key = CrashBucketKey::CrashSite(site.crash_site_hash.unwrap());
+
+        let mut hook = CrashTriageHook::<BytesInput>::new(&dir).unwrap();
+        assert!(hook.triage(ClientId(1), Some(&input), &site).unwrap());
+        assert!(!hook.triage(ClientId(2), None, &other_path).unwrap());
+        assert!(dir.join(site_key.to_string()).exists());
+
+        // Duplicates only get saved on the next flush
+        let saved = CrashBuckets::load(dir.join(CRASH_BUCKETS_FILE)).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved.duplicates(), 0);
+
+        assert!(hook.triage(ClientId(2), None, &stack).unwrap());
+        assert!(
+            hook.triage(ClientId(2), None, &ObjectiveSignature::default())
+                .unwrap()
+        );
+        hook.flush().unwrap();
+
+        // The buckets survive a restart
+        let hook = CrashTriageHook::<BytesInput>::new(&dir).unwrap();
+        let buckets = hook.buckets();
+        let buckets = buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets.duplicates(), 1);
+        assert_eq!(buckets.untriaged(), 1);
+        let bucket = buckets.get(&site_key).unwrap();
+        assert_eq!(bucket.count, 2);
+        assert_eq!(bucket.clients, [ClientId(1), ClientId(2)]);
+        assert!(bucket.representative.is_some());
+        assert!(buckets.get(&CrashBucketKey::Stack(1)).is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_crash_bucket_key_fallback() {
+        let input = BytesInput::new(vec![1, 2, 3]);
+        let mut map = [0_u8; 16];
+        map[3] = 1;
+        map[7] = 2;
+        let observer = StdMapObserver::from_mut_slice("map", map.as_mut_slice().into());
+        let mut signature = ObjectiveSignature::default();
+        Observer::<BytesInput, ()>::update_objective_signature(&observer, &mut signature);
+        let coverage = signature.coverage_hash.unwrap();
+
+        // Without crash site and stack, the same coverage ends up in the same bucket for different inputs
+        let other_input = BytesInput::new(vec![4, 5, 6]);
+        assert_eq!(
+            CrashBucketKey::for_objective(&signature, Some(&input)),
+            Some(CrashBucketKey::Coverage(coverage))
+        );
+        assert_eq!(
+            CrashBucketKey::for_objective(&signature, Some(&other_input)),
+            Some(CrashBucketKey::Coverage(coverage))
+        );
+
+        signature.add_stack_hash(1);
+        assert!(matches!(
+            CrashBucketKey::for_objective(&signature, Some(&input)),
+            Some(CrashBucketKey::Stack(_))
+        ));
+        signature.add_crash_site([0x1000]);
+        assert!(matches!(
+            CrashBucketKey::for_objective(&signature, Some(&input)),
+            Some(CrashBucketKey::CrashSite(_))
+        ));
+
+        assert!(matches!(
+            CrashBucketKey::for_objective(&ObjectiveSignature::default(), Some(&input)),
+            Some(CrashBucketKey::Input(_))
+        ));
+        assert_eq!(
+            CrashBucketKey::for_objective::<BytesInput>(&ObjectiveSignature::default(), None),
+            None
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! A broker hook persisting the discoveries of a campaign to disk, so that a restarted broker can
+//! hand them to its clients again, and so that the timeline of a campaign can be analyzed offline.
+use alloc::vec::Vec;
+use core::{marker::PhantomData, time::Duration};
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::{
+    ClientId, current_time, generic_hash_std,
+    llmp::{Flags, LLMP_FLAG_FROM_B2B, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
+    serialization::SerializationFormat,
+};
+#[cfg(feature = "llmp_compression")]
+use libafl_bolts::{
+    compress::{CodecCompressor, Compressor},
+    llmp::LLMP_FLAG_COMPRESSED,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "llmp_compression")]
+use crate::events::llmp::COMPRESS_THRESHOLD;
+use crate::{
+    Error,
+    events::{Event, EventWithStats, llmp::LLMP_TAG_EVENT_TO_BOTH},
+    inputs::Input,
+};
+
+/// How often the [`EventLog`] flushes new entries to disk by default
+const EVENT_LOG_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The kind of a logged event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventLogKind {
+    /// An [`Event::NewTestcase`]
+    NewTestcase,
+    /// An [`Event::Objective`]
+    Objective,
+}
+
+/// A single event in an [`EventLog`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLogEntry {
+    /// The time at which the broker received the event
+    pub time: Duration,
+    /// The client that sent the event
+    pub client_id: ClientId,
+    /// The kind of the event
+    pub kind: EventLogKind,
+    /// The hash of the input of the event, used to compact the log
+    pub input_hash: u64,
+    /// The serialized (uncompressed) [`EventWithStats`]
+    pub event: Vec<u8>,
+}
+
+impl EventLogEntry {
+    /// Deserializes the logged event
+    pub fn event<I>(&self) -> Result<EventWithStats<I>, Error>
+    where
+        I: Input,
+    {
+        Ok(postcard::from_bytes(&self.event)?)
+    }
+}
+
+/// An append-only, on-disk log of the [`Event::NewTestcase`] and [`Event::Objective`] events of a campaign.
+///
+/// Each entry is stored as its `u32` length, followed by the postcard-serialized [`EventLogEntry`].
+/// The log only keeps the first event for each input.
+///
+/// New entries are buffered, and flushed to disk at most every flush interval (one second by default),
+/// on [`EventLog::flush`], and when the log is dropped.
+#[derive(Debug)]
+pub struct EventLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    seen: HashSet<(EventLogKind, u64)>,
+    flush_interval: Duration,
+    last_flush: Duration,
+}
+
+impl EventLog {
+    /// Opens the log at `path`, creating it if it does not exist.
+    ///
+    /// The existing log gets compacted: duplicate entries, and a partially written last entry
+    /// left behind by a crash, are removed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let (entries, compacted) = if path.exists() {
+            Self::read_compacted(&path)?
+        } else {
+            (Vec::new(), false)
+        };
+
+        if compacted {
+            let tmp_path = path.with_extension("compacting");
+            {
+                let mut writer = BufWriter::new(File::create(&tmp_path)?);
+                for entry in &entries {
+                    Self::write_entry(&mut writer, entry)?;
+                }
+                writer.flush()?;
+            }
+            fs::rename(&tmp_path, &path)?;
+        }
+
+        let seen = entries
+            .iter()
+            .map(|entry| (entry.kind, entry.input_hash))
+            .collect();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+            seen,
+            flush_interval: EVENT_LOG_FLUSH_INTERVAL,
+            last_flush: current_time(),
+        })
+    }
+
+    /// Sets how often new entries get flushed to disk.
+    /// With [`Duration::ZERO`], each entry is flushed right away.
+    #[must_use]
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Reads all entries of the log at `path`, e.g., to replay the timeline of a campaign.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<EventLogEntry>, Error> {
+        Ok(Self::read_compacted(path.as_ref())?.0)
+    }
+
+    /// Reads the unique entries of the log, and whether entries were dropped
+    fn read_compacted(path: &Path) -> Result<(Vec<EventLogEntry>, bool), Error> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+
+        let mut entries = Vec::new();
+        let mut seen = HashSet::new();
+        let mut compacted = false;
+        let mut rest = bytes.as_slice();
+        while !rest.is_empty() {
+            let entry = rest
+                .split_first_chunk::<4>()
+                .map(|(len, rest)| (u32::from_le_bytes(*len) as usize, rest))
+                .filter(|(len, rest)| rest.len() >= *len)
+                .and_then(|(len, rest)| {
+                    let entry = postcard::from_bytes::<EventLogEntry>(&rest[..len]).ok()?;
+                    Some((entry, &rest[len..]))
+                });
+            let Some((entry, next)) = entry else {
+                log::warn!(
+                    "Dropping {} trailing bytes of the event log {}",
+                    rest.len(),
+                    path.display()
+                );
+                compacted = true;
+                break;
+            };
+            rest = next;
+
+            if seen.insert((entry.kind, entry.input_hash)) {
+                entries.push(entry);
+            } else {
+                compacted = true;
+            }
+        }
+
+        Ok((entries, compacted))
+    }
+
+    fn write_entry<W: Write>(writer: &mut W, entry: &EventLogEntry) -> Result<(), Error> {
+        let serialized = postcard::to_allocvec(entry)?;
+        let len = u32::try_from(serialized.len())
+            .map_err(|_| Error::illegal_argument("Event too large for the event log"))?;
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&serialized)?;
+        Ok(())
+    }
+
+    /// Appends `entry` to the log, unless an event of the same kind for the same input was logged before.
+    /// Returns if the entry was appended.
+    ///
+    /// The entry is flushed to disk with the next flush, see [`EventLog::with_flush_interval`].
+    pub fn append(&mut self, entry: &EventLogEntry) -> Result<bool, Error> {
+        if !self.seen.insert((entry.kind, entry.input_hash)) {
+            return Ok(false);
+        }
+        Self::write_entry(&mut self.writer, entry)?;
+        self.flush_if_due()?;
+        Ok(true)
+    }
+
+    /// Writes all buffered entries to disk
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        self.last_flush = current_time();
+        Ok(())
+    }
+
+    /// Flushes, if the last flush is more than the flush interval ago
+    pub fn flush_if_due(&mut self) -> Result<(), Error> {
+        if current_time().saturating_sub(self.last_flush) >= self.flush_interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// The path of the log
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The number of entries in the log
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// If the log is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+/// A broker hook appending every [`Event::NewTestcase`] and [`Event::Objective`] to an [`EventLog`].
+///
+/// If replay is enabled (the default), the [`Event::NewTestcase`]s logged by a previous run are broadcast
+/// once, as soon as the first client talks to the restarted broker. This way, the clients rebuild their
+/// corpus from the log, instead of waiting for the other clients to share their findings again.
+/// Clients attaching later read them from the broker's pages, so the broker has to keep its pages,
+/// which is the default (see [`libafl_bolts::llmp::LlmpBroker::with_keep_pages`]).
+///
+/// The log is flushed periodically, and on each broker timeout.
+///
+/// Add it in front of the other hooks of the broker.
+#[derive(Debug)]
+pub struct EventLogHook<I> {
+    log: EventLog,
+    /// The events logged by a previous run, ready to be broadcast
+    replay: Vec<(Tag, Flags, Vec<u8>)>,
+    #[cfg(feature = "llmp_compression")]
+    compressor: CodecCompressor,
+    phantom: PhantomData<I>,
+}
+
+impl<I> EventLogHook<I> {
+    /// Creates a new [`EventLogHook`], logging to (and replaying) the log at `path`
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let log = EventLog::open(path)?;
+        #[cfg(feature = "llmp_compression")]
+        let compressor = CodecCompressor::with_threshold(COMPRESS_THRESHOLD);
+
+        let mut replay = Vec::new();
+        for entry in EventLog::read(log.path())? {
+            if entry.kind != EventLogKind::NewTestcase {
+                continue;
+            }
+            #[cfg(feature = "llmp_compression")]
+            if let Some(compressed) = compressor.maybe_compress(&entry.event)? {
+                replay.push((
+                    LLMP_TAG_EVENT_TO_BOTH,
+                    Flags::compressed_by(&compressor),
+                    compressed,
+                ));
+                continue;
+            }
+            replay.push((LLMP_TAG_EVENT_TO_BOTH, Flags(0), entry.event));
+        }
+
+        Ok(Self {
+            log,
+            replay,
+            #[cfg(feature = "llmp_compression")]
+            compressor,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Disables sending the logged events to the clients on startup
+    #[must_use]
+    pub fn without_replay(mut self) -> Self {
+        self.replay.clear();
+        self
+    }
+
+    /// Sets how often the log gets flushed to disk, see [`EventLog::with_flush_interval`]
+    #[must_use]
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.log = self.log.with_flush_interval(flush_interval);
+        self
+    }
+
+    /// The underlying [`EventLog`]
+    #[must_use]
+    pub fn log(&self) -> &EventLog {
+        &self.log
+    }
+
+    /// Queues the logged events as new broker messages, unless they were replayed already
+    fn replay(&mut self, new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>) {
+        if self.replay.is_empty() {
+            return;
+        }
+        log::info!(
+            "Replaying {} events from {}",
+            self.replay.len(),
+            self.log.path().display()
+        );
+        new_msgs.append(&mut self.replay);
+    }
+}
+
+impl<I, SHM, SP> LlmpHook<SHM, SP> for EventLogHook<I>
+where
+    I: Input,
+{
+    fn on_new_message(
+        &mut self,
+        _broker_inner: &mut LlmpBrokerInner<SHM, SP>,
+        client_id: ClientId,
+        msg_tag: &mut Tag,
+        msg_flags: &mut Flags,
+        msg: &mut [u8],
+        new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<LlmpMsgHookResult, Error> {
+        self.log.flush_if_due()?;
+
+        // Messages of remote brokers do not come from our clients
+        if *msg_flags & LLMP_FLAG_FROM_B2B != LLMP_FLAG_FROM_B2B {
+            self.replay(new_msgs);
+        }
+
+        if *msg_tag != LLMP_TAG_EVENT_TO_BOTH {
+            return Ok(LlmpMsgHookResult::ForwardToClients);
+        }
+
+        #[cfg(not(feature = "llmp_compression"))]
+        let event_bytes = &*msg;
+        #[cfg(feature = "llmp_compression")]
+        let compressed;
+        #[cfg(feature = "llmp_compression")]
+        let event_bytes = if *msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+            compressed = self.compressor.decompress_with(
+                msg_flags.codec()?,
+                msg_flags.uses_dictionary(),
+                msg,
+            )?;
+            &compressed
+        } else {
+            &*msg
+        };
+
+        let format = msg_flags.format()?;
+        let event: EventWithStats<I> = format.deserialize(event_bytes)?;
+        let (kind, input_hash) = match event.event() {
+            Event::NewTestcase { input, .. } => {
+                (EventLogKind::NewTestcase, generic_hash_std(input))
+            }
+            Event::Objective { input, .. } => (EventLogKind::Objective, generic_hash_std(input)),
+            _ => return Ok(LlmpMsgHookResult::ForwardToClients),
+        };
+
+        self.log.append(&EventLogEntry {
+            time: current_time(),
+            client_id,
+            kind,
+            input_hash,
+            // The log always holds `postcard`, independent of the format the client sent
+            event: if format == SerializationFormat::Postcard {
+                event_bytes.to_vec()
+            } else {
+                postcard::to_allocvec(&event)?
+            },
+        })?;
+
+        Ok(LlmpMsgHookResult::ForwardToClients)
+    }
+
+    fn on_timeout(&mut self) -> Result<(), Error> {
+        // The broker is idle, write the remaining entries to disk
+        self.log.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::{env, fs, process};
+
+    use libafl_bolts::{
+        ClientId,
+        llmp::{Flags, LLMP_FLAG_FROM_B2B, LlmpBrokerInner, LlmpHook, Tag},
+        shmem::{ShMemProvider, StdShMemProvider},
+    };
+
+    use super::{EventLog, EventLogEntry, EventLogHook, EventLogKind};
+    use crate::inputs::BytesInput;
+
+    #[test]
+    fn test_event_log_compaction() {
+        let path = env::temp_dir().join(format!("libafl_event_log_test_{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let entry = |input_hash, kind| EventLogEntry {
+            time: Duration::from_secs(input_hash),
+            client_id: ClientId(1),
+            kind,
+            input_hash,
+            event: vec![1, 2, 3],
+        };
+
+        {
+            let mut log = EventLog::open(&path).unwrap();
+            assert!(log.append(&entry(1, EventLogKind::NewTestcase)).unwrap());
+            assert!(log.append(&entry(2, EventLogKind::NewTestcase)).unwrap());
+            assert!(!log.append(&entry(1, EventLogKind::NewTestcase)).unwrap());
+            assert!(log.append(&entry(1, EventLogKind::Objective)).unwrap());
+            assert_eq!(log.len(), 3);
+        }
+
+        // A crash while writing leaves a partial entry behind
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.extend_from_slice(&[42, 0, 0, 0, 1]);
+        fs::write(&path, bytes).unwrap();
+
+        {
+            let mut log = EventLog::open(&path).unwrap();
+            assert_eq!(log.len(), 3);
+            assert!(!log.append(&entry(2, EventLogKind::NewTestcase)).unwrap());
+            assert!(log.append(&entry(3, EventLogKind::NewTestcase)).unwrap());
+        }
+
+        let entries = EventLog::read(&path).unwrap();
+        let times: Vec<_> = entries.iter().map(|e| e.time.as_secs()).collect();
+        assert_eq!(times, [1, 2, 1, 3]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_event_log_buffering() {
+        let path = env::temp_dir().join(format!("libafl_event_log_flush_test_{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut log = EventLog::open(&path)
+            .unwrap()
+            .with_flush_interval(Duration::from_secs(3600));
+        for input_hash in 0..4 {
+            log.append(&EventLogEntry {
+                time: Duration::ZERO,
+                client_id: ClientId(1),
+                kind: EventLogKind::NewTestcase,
+                input_hash,
+                event: vec![1, 2, 3],
+            })
+            .unwrap();
+        }
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+
+        log.flush().unwrap();
+        assert_eq!(EventLog::read(&path).unwrap().len(), 4);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_event_log_replay_once() {
+        let path = env::temp_dir().join(format!("libafl_event_log_replay_test_{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        {
+            let mut log = EventLog::open(&path).unwrap();
+            for input_hash in 0..2 {
+                log.append(&EventLogEntry {
+                    time: Duration::ZERO,
+                    client_id: ClientId(1),
+                    kind: EventLogKind::NewTestcase,
+                    input_hash,
+                    event: vec![1, 2, 3],
+                })
+                .unwrap();
+            }
+        }
+
+        let mut broker = LlmpBrokerInner::new(StdShMemProvider::new().unwrap()).unwrap();
+        let mut hook = EventLogHook::<BytesInput>::new(&path).unwrap();
+        let mut replayed = |hook: &mut EventLogHook<BytesInput>, client_id, flags| {
+            let mut new_msgs = Vec::new();
+            hook.on_new_message(
+                &mut broker,
+                ClientId(client_id),
+                &mut Tag(0x1337),
+                &mut Flags(flags),
+                &mut [],
+                &mut new_msgs,
+            )
+            .unwrap();
+            new_msgs.len()
+        };
+
+        // The log is broadcast once, on the first message of a local client
+        assert_eq!(replayed(&mut hook, 3, LLMP_FLAG_FROM_B2B.0), 0);
+        assert_eq!(replayed(&mut hook, 1, 0), 2);
+        assert_eq!(replayed(&mut hook, 1, 0), 0);
+        assert_eq!(replayed(&mut hook, 2, 0), 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,13 +1,17 @@
 //! Hooks called on broker side
 use alloc::vec::Vec;
 use core::marker::PhantomData;
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+use std::path::PathBuf;
 
 use libafl_bolts::{
     ClientId,
     llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
 };
 #[cfg(feature = "llmp_compression")]
-use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
+use libafl_bolts::{compress::CodecCompressor, llmp::LLMP_FLAG_COMPRESSED};
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+use libafl_bolts::{compress::DictionaryTrainer, fs::write_file_atomic};
 use serde::de::DeserializeOwned;
 
 #[cfg(feature = "llmp_compression")]
@@ -24,6 +28,22 @@ pub mod centralized;
 #[cfg(all(unix, feature = "std"))]
 pub use centralized::*;
 
+/// Crash deduplication and triage hook
+#[cfg(feature = "std")]
+pub mod crash_triage;
+#[cfg(feature = "std")]
+pub use crash_triage::*;
+
+/// Event persistence hook
+#[cfg(feature = "std")]
+pub mod event_log;
+#[cfg(feature = "std")]
+pub use event_log::*;
+
+/// Testcase deduplication and rate limiting hook
+pub mod sharing_filter;
+pub use sharing_filter::*;
+
 /// Multi-machine hook
 #[cfg(all(unix, feature = "multi_machine"))]
 pub mod centralized_multi_machine;
@@ -37,11 +57,46 @@ use super::EventWithStats;
 pub struct StdLlmpEventHook<I, MT> {
     monitor: MT,
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: CodecCompressor,
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    dictionary_training: Option<DictionaryTraining>,
     phantom: PhantomData<I>,
     client_stats_manager: ClientStatsManager,
 }
 
+/// Trains a compression dictionary from the testcases passing the broker, and saves it once trained
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+#[derive(Debug)]
+struct DictionaryTraining {
+    trainer: DictionaryTrainer,
+    path: PathBuf,
+}
+
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+impl DictionaryTraining {
+    /// Observe a serialized testcase event.
+    /// Returns `true` once the dictionary was trained and saved, and training is over.
+    fn observe(&mut self, event_bytes: &[u8]) -> bool {
+        self.trainer.observe(event_bytes);
+        if !self.trainer.is_full() {
+            return false;
+        }
+        match self
+            .trainer
+            .train()
+            .and_then(|dictionary| write_file_atomic(&self.path, &dictionary))
+        {
+            Ok(()) => log::info!(
+                "Trained a compression dictionary from {} testcases, saved it to {}",
+                self.trainer.len(),
+                self.path.display()
+            ),
+            Err(err) => log::warn!("Could not train a compression dictionary: {err}"),
+        }
+        true
+    }
+}
+
 impl<I, MT, SHM, SP> LlmpHook<SHM, SP> for StdLlmpEventHook<I, MT>
 where
     I: DeserializeOwned,
@@ -52,8 +107,7 @@ where
         _broker_inner: &mut LlmpBrokerInner<SHM, SP>,
         client_id: ClientId,
         msg_tag: &mut Tag,
-        #[cfg(feature = "llmp_compression")] msg_flags: &mut Flags,
-        #[cfg(not(feature = "llmp_compression"))] _msg_flags: &mut Flags,
+        msg_flags: &mut Flags,
         msg: &mut [u8],
         _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
     ) -> Result<LlmpMsgHookResult, Error> {
@@ -68,12 +122,24 @@ where
             let compressed;
             #[cfg(feature = "llmp_compression")]
             let event_bytes = if *msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = compressor.decompress(msg)?;
+                compressed = compressor.decompress_with(
+                    msg_flags.codec()?,
+                    msg_flags.uses_dictionary(),
+                    msg,
+                )?;
                 &compressed
             } else {
                 &*msg
             };
-            let event: EventWithStats<I> = postcard::from_bytes(event_bytes)?;
+            let event: EventWithStats<I> = msg_flags.format()?.deserialize(event_bytes)?;
+            #[cfg(all(feature = "std", feature = "llmp_compression"))]
+            if let Some(training) = &mut self.dictionary_training {
+                if matches!(event.event(), Event::NewTestcase { .. })
+                    && training.observe(event_bytes)
+                {
+                    self.dictionary_training = None;
+                }
+            }
             match Self::handle_in_broker(
                 monitor,
                 &mut self.client_stats_manager,
@@ -107,12 +173,27 @@ where
         Ok(Self {
             monitor,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: CodecCompressor::with_threshold(COMPRESS_THRESHOLD),
+            #[cfg(all(feature = "std", feature = "llmp_compression"))]
+            dictionary_training: None,
             client_stats_manager: ClientStatsManager::default(),
             phantom: PhantomData,
         })
     }
 
+    /// Feed the serialized testcases passing this broker to `trainer`.
+    /// Once it is full, the trained dictionary is saved to `path`.
+    ///
+    /// The dictionary is not used right away, as every node of the campaign has to use the same one.
+    /// Install it with [`install_dictionary`](libafl_bolts::compress::install_dictionary) before launching the next campaign,
+    /// or set the `compression_dictionary` of the [`Launcher`](crate::events::Launcher) to `path`.
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    #[must_use]
+    pub fn with_dictionary_training(mut self, trainer: DictionaryTrainer, path: PathBuf) -> Self {
+        self.dictionary_training = Some(DictionaryTraining { trainer, path });
+        self
+    }
+
     /// Handle arriving events in the broker
     fn handle_in_broker(
         monitor: &mut MT,
@@ -200,3 +281,69 @@ where
         }
     }
 }
+
+#[cfg(all(test, feature = "std", feature = "llmp_compression"))]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::{env, fs, process};
+
+    use libafl_bolts::{
+        ClientId,
+        compress::DictionaryTrainer,
+        llmp::{Flags, LlmpBrokerInner, LlmpHook},
+        shmem::{ShMemProvider, StdShMemProvider},
+    };
+
+    use super::StdLlmpEventHook;
+    use crate::{
+        events::{Event, EventConfig, EventWithStats, ExecStats, llmp::LLMP_TAG_EVENT_TO_BOTH},
+        executors::ExitKind,
+        inputs::BytesInput,
+        monitors::NopMonitor,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_dictionary_training() {
+        let path = env::temp_dir().join(format!("libafl_dictionary_test_{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut broker = LlmpBrokerInner::new(StdShMemProvider::new().unwrap()).unwrap();
+        let mut hook = StdLlmpEventHook::<BytesInput, _>::new(NopMonitor::new())
+            .unwrap()
+            .with_dictionary_training(DictionaryTrainer::new(64).with_max_samples(4), path.clone());
+
+        for i in 0..4_u8 {
+            assert!(!path.exists());
+            let event = Event::NewTestcase {
+                input: BytesInput::new(vec![b'A', b'B', b'C', i]),
+                observers_buf: None,
+                exit_kind: ExitKind::Ok,
+                corpus_size: usize::from(i),
+                client_config: EventConfig::AlwaysUnique,
+                forward_id: None,
+                #[cfg(all(unix, feature = "multi_machine"))]
+                node_id: None,
+            };
+            let mut msg = postcard::to_allocvec(&EventWithStats::new(
+                event,
+                ExecStats::new(Duration::ZERO, 0),
+            ))
+            .unwrap();
+            hook.on_new_message(
+                &mut broker,
+                ClientId(1),
+                &mut LLMP_TAG_EVENT_TO_BOTH.clone(),
+                &mut Flags(0),
+                &mut msg,
+                &mut Vec::new(),
+            )
+            .unwrap();
+        }
+
+        // The trainer is full, the dictionary got saved
+        assert!(!fs::read(&path).unwrap().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
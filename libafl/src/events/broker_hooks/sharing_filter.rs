@@ -0,0 +1,702 @@
+//! A broker hook reducing the bandwidth spent on sharing testcases between clients,
+//! by dropping duplicate testcases and rate limiting the rest.
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+
+use hashbrown::{HashMap, HashSet};
+use libafl_bolts::{
+    ClientId, current_time, generic_hash_std, hash_std,
+    llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
+    shmem::{ShMem, ShMemProvider},
+};
+#[cfg(feature = "llmp_compression")]
+use libafl_bolts::{compress::CodecCompressor, llmp::LLMP_FLAG_COMPRESSED};
+
+#[cfg(feature = "llmp_compression")]
+use crate::events::llmp::COMPRESS_THRESHOLD;
+use crate::{
+    Error,
+    events::{Event, EventWithStats, llmp::LLMP_TAG_EVENT_TO_BOTH},
+    inputs::Input,
+};
+
+/// The default number of rate limited testcases kept for later, before the oldest ones get dropped
+pub const DEFAULT_MAX_DEFERRED: usize = 1024;
+
+/// The default number of input hashes and coverage signatures remembered, before the oldest ones get forgotten
+pub const DEFAULT_MAX_SEEN: usize = 1 << 18;
+
+/// A bandwidth budget, enforced as a token bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SharingBudget {
+    /// The maximum number of messages per second, if limited
+    pub msgs_per_sec: Option<f64>,
+    /// The maximum number of bytes per second, if limited
+    pub bytes_per_sec: Option<f64>,
+    /// How many seconds worth of budget may be saved up for bursts
+    pub burst_secs: f64,
+}
+
+impl SharingBudget {
+    /// A budget of `msgs_per_sec` messages per second
+    #[must_use]
+    pub fn msgs_per_sec(msgs_per_sec: f64) -> Self {
+        Self {
+            msgs_per_sec: Some(msgs_per_sec),
+            bytes_per_sec: None,
+            burst_secs: 1.0,
+        }
+    }
+
+    /// A budget of `bytes_per_sec` bytes per second
+    #[must_use]
+    pub fn bytes_per_sec(bytes_per_sec: f64) -> Self {
+        Self {
+            msgs_per_sec: None,
+            bytes_per_sec: Some(bytes_per_sec),
+            burst_secs: 1.0,
+        }
+    }
+
+    /// Additionally limits the number of messages per second
+    #[must_use]
+    pub fn with_msgs_per_sec(mut self, msgs_per_sec: f64) -> Self {
+        self.msgs_per_sec = Some(msgs_per_sec);
+        self
+    }
+
+    /// Additionally limits the number of bytes per second
+    #[must_use]
+    pub fn with_bytes_per_sec(mut self, bytes_per_sec: f64) -> Self {
+        self.bytes_per_sec = Some(bytes_per_sec);
+        self
+    }
+
+    /// Sets how many seconds worth of budget may be saved up for bursts
+    #[must_use]
+    pub fn with_burst_secs(mut self, burst_secs: f64) -> Self {
+        self.burst_secs = burst_secs;
+        self
+    }
+}
+
+/// The remaining budget of a client, or of the broker
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    budget: SharingBudget,
+    msgs: f64,
+    bytes: f64,
+    last_refill: Duration,
+}
+
+impl TokenBucket {
+    fn new(budget: SharingBudget, now: Duration) -> Self {
+        Self {
+            msgs: budget.msgs_per_sec.unwrap_or(0.0) * budget.burst_secs,
+            bytes: budget.bytes_per_sec.unwrap_or(0.0) * budget.burst_secs,
+            budget,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Duration) {
+        let elapsed = now.saturating_sub(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        if let Some(rate) = self.budget.msgs_per_sec {
+            self.msgs = (self.msgs + elapsed * rate).min(rate * self.budget.burst_secs);
+        }
+        if let Some(rate) = self.budget.bytes_per_sec {
+            self.bytes = (self.bytes + elapsed * rate).min(rate * self.budget.burst_secs);
+        }
+    }
+
+    /// If a message of `len` bytes fits the remaining budget.
+    ///
+    /// A message larger than the whole bucket fits once the bucket is full, and overdraws it,
+    /// so that it does not get held back forever.
+    #[expect(clippy::cast_precision_loss)] // messages are far smaller than 2^52 bytes
+    fn fits(&self, len: usize) -> bool {
+        let burst_secs = self.budget.burst_secs;
+        self.budget
+            .msgs_per_sec
+            .is_none_or(|rate| self.msgs >= (rate * burst_secs).min(1.0))
+            && self
+                .budget
+                .bytes_per_sec
+                .is_none_or(|rate| self.bytes >= (rate * burst_secs).min(len as f64))
+    }
+
+    /// Takes a message of `len` bytes from the budget, possibly overdrawing it
+    #[expect(clippy::cast_precision_loss)] // messages are far smaller than 2^52 bytes
+    fn take(&mut self, len: usize) {
+        if self.budget.msgs_per_sec.is_some() {
+            self.msgs -= 1.0;
+        }
+        if self.budget.bytes_per_sec.is_some() {
+            self.bytes -= len as f64;
+        }
+    }
+}
+
+/// A set of hashes, forgetting the oldest ones beyond its capacity
+#[derive(Debug)]
+struct RecentHashes {
+    hashes: HashSet<u64>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl RecentHashes {
+    fn new(capacity: usize) -> Self {
+        Self {
+            hashes: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn contains(&self, hash: u64) -> bool {
+        self.hashes.contains(&hash)
+    }
+
+    fn insert(&mut self, hash: u64) {
+        if self.capacity == 0 || !self.hashes.insert(hash) {
+            return;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity {
+            let oldest = self.order.pop_front().unwrap();
+            self.hashes.remove(&oldest);
+        }
+    }
+}
+
+/// A testcase held back, as it exceeded the budget
+#[derive(Debug)]
+struct DeferredMsg {
+    client_id: ClientId,
+    flags: Flags,
+    msg: Vec<u8>,
+    input_hash: u64,
+    coverage: Option<u64>,
+}
+
+/// Statistics of a [`SharingFilterHook`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SharingFilterStats {
+    /// Testcases forwarded to the clients
+    pub forwarded: u64,
+    /// Testcases dropped, as the same input was shared before, or is held back
+    pub duplicate_inputs: u64,
+    /// Testcases dropped, as a testcase with the same coverage signature was shared before, or is held back
+    pub duplicate_coverage: u64,
+    /// Testcases held back, as they exceeded the budget
+    pub deferred: u64,
+    /// Deferred testcases dropped, as too many were held back
+    pub dropped: u64,
+}
+
+/// The coverage signature function of a [`SharingFilterHook`] that does not deduplicate by coverage
+pub type NoCoverageSignature<I> = fn(&EventWithStats<I>) -> Option<u64>;
+
+/// A broker hook filtering the [`Event::NewTestcase`]s that get shared with the clients.
+///
+/// - Testcases with an input that was shared (or is held back) are dropped.
+/// - Optionally, testcases with the same coverage signature as a testcase shared (or held back) are dropped,
+///   see [`SharingFilterHook::with_coverage_signature`].
+/// - Testcases exceeding the [`SharingBudget`] of their client, or of the broker as a whole, are held back,
+///   and forwarded as soon as the budget allows. Objectives are never held back, but count against the budget.
+///
+/// Only the most recent input hashes and coverage signatures are remembered, see [`SharingFilterHook::with_max_seen`].
+///
+/// Add it after the [`super::StdLlmpEventHook`], so that the monitor still sees every testcase.
+/// Held back testcases are broadcast on behalf of their original sender, which skips them just like
+/// forwarded messages. The broker checks for held back testcases that fit the budget on each iteration,
+/// even if no messages arrive.
+#[derive(Debug)]
+pub struct SharingFilterHook<I, F = NoCoverageSignature<I>> {
+    seen_inputs: RecentHashes,
+    seen_coverage: RecentHashes,
+    deferred_inputs: HashSet<u64>,
+    deferred_coverage: HashSet<u64>,
+    coverage_signature: F,
+    client_budget: Option<SharingBudget>,
+    client_buckets: HashMap<ClientId, TokenBucket>,
+    broker_bucket: Option<TokenBucket>,
+    deferred: VecDeque<DeferredMsg>,
+    max_deferred: usize,
+    stats: SharingFilterStats,
+    #[cfg(feature = "llmp_compression")]
+    compressor: CodecCompressor,
+    phantom: PhantomData<I>,
+}
+
+impl<I> Default for SharingFilterHook<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> SharingFilterHook<I> {
+    /// Creates a new [`SharingFilterHook`], deduplicating testcases by their input only, without a budget
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            seen_inputs: RecentHashes::new(DEFAULT_MAX_SEEN),
+            seen_coverage: RecentHashes::new(DEFAULT_MAX_SEEN),
+            deferred_inputs: HashSet::new(),
+            deferred_coverage: HashSet::new(),
+            coverage_signature: |_| None,
+            client_budget: None,
+            client_buckets: HashMap::new(),
+            broker_bucket: None,
+            deferred: VecDeque::new(),
+            max_deferred: DEFAULT_MAX_DEFERRED,
+            stats: SharingFilterStats::default(),
+            #[cfg(feature = "llmp_compression")]
+            compressor: CodecCompressor::with_threshold(COMPRESS_THRESHOLD),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, F> SharingFilterHook<I, F> {
+    /// Also drops testcases if a testcase with the same coverage signature was shared before.
+    ///
+    /// The signature is computed from the event by `coverage_signature`, e.g., by hashing the
+    /// `observers_buf` of an [`Event::NewTestcase`], if the observers only contain the coverage map.
+    #[must_use]
+    pub fn with_coverage_signature<F2>(self, coverage_signature: F2) -> SharingFilterHook<I, F2>
+    where
+        F2: FnMut(&EventWithStats<I>) -> Option<u64>,
+    {
+        SharingFilterHook {
+            seen_inputs: self.seen_inputs,
+            seen_coverage: self.seen_coverage,
+            deferred_inputs: self.deferred_inputs,
+            deferred_coverage: self.deferred_coverage,
+            coverage_signature,
+            client_budget: self.client_budget,
+            client_buckets: self.client_buckets,
+            broker_bucket: self.broker_bucket,
+            deferred: self.deferred,
+            max_deferred: self.max_deferred,
+            stats: self.stats,
+            #[cfg(feature = "llmp_compression")]
+            compressor: self.compressor,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Limits the testcases shared by each client (or multi-machine node) to `budget`
+    #[must_use]
+    pub fn with_client_budget(mut self, budget: SharingBudget) -> Self {
+        self.client_budget = Some(budget);
+        self.client_buckets.clear();
+        self
+    }
+
+    /// Limits the testcases shared by the broker as a whole to `budget`
+    #[must_use]
+    pub fn with_broker_budget(mut self, budget: SharingBudget) -> Self {
+        self.broker_bucket = Some(TokenBucket::new(budget, current_time()));
+        self
+    }
+
+    /// Sets how many rate limited testcases are kept for later, before the oldest ones get dropped
+    #[must_use]
+    pub fn with_max_deferred(mut self, max_deferred: usize) -> Self {
+        self.max_deferred = max_deferred;
+        self
+    }
+
+    /// Sets how many input hashes, and how many coverage signatures, are remembered to drop duplicates.
+    /// Beyond that, the oldest ones are forgotten, and their testcases may get shared once more.
+    #[must_use]
+    pub fn with_max_seen(mut self, max_seen: usize) -> Self {
+        self.seen_inputs = RecentHashes::new(max_seen);
+        self.seen_coverage = RecentHashes::new(max_seen);
+        self
+    }
+
+    /// The statistics of this hook
+    #[must_use]
+    pub fn stats(&self) -> &SharingFilterStats {
+        &self.stats
+    }
+
+    /// If a message of `len` bytes from `client_id` fits the budgets
+    fn fits_budget(&mut self, client_id: ClientId, len: usize, now: Duration) -> bool {
+        let client_fits = match self.client_budget {
+            Some(budget) => {
+                let bucket = self
+                    .client_buckets
+                    .entry(client_id)
+                    .or_insert_with(|| TokenBucket::new(budget, now));
+                bucket.refill(now);
+                bucket.fits(len)
+            }
+            None => true,
+        };
+        let broker_fits = self.broker_bucket.as_mut().is_none_or(|bucket| {
+            bucket.refill(now);
+            bucket.fits(len)
+        });
+        client_fits && broker_fits
+    }
+
+    /// Takes a message of `len` bytes from `client_id` from the budgets
+    fn take_budget(&mut self, client_id: ClientId, len: usize) {
+        if let Some(bucket) = self.client_buckets.get_mut(&client_id) {
+            bucket.take(len);
+        }
+        if let Some(bucket) = &mut self.broker_bucket {
+            bucket.take(len);
+        }
+    }
+
+    /// Remembers that a testcase was shared, to drop its duplicates
+    fn mark_shared(&mut self, input_hash: u64, coverage: Option<u64>) {
+        self.stats.forwarded += 1;
+        self.seen_inputs.insert(input_hash);
+        if let Some(signature) = coverage {
+            self.seen_coverage.insert(signature);
+        }
+    }
+
+    /// Forgets a testcase that is no longer held back
+    fn undefer(&mut self, deferred: &DeferredMsg) {
+        self.deferred_inputs.remove(&deferred.input_hash);
+        if let Some(signature) = deferred.coverage {
+            self.deferred_coverage.remove(&signature);
+        }
+    }
+
+    /// Holds back a testcase, dropping the oldest held back one if there are too many
+    fn defer(&mut self, deferred: DeferredMsg) {
+        self.stats.deferred += 1;
+        if self.deferred.len() >= self.max_deferred {
+            if let Some(dropped) = self.deferred.pop_front() {
+                self.undefer(&dropped);
+            }
+            self.stats.dropped += 1;
+        }
+        if self.max_deferred > 0 {
+            self.deferred_inputs.insert(deferred.input_hash);
+            if let Some(signature) = deferred.coverage {
+                self.deferred_coverage.insert(signature);
+            }
+            self.deferred.push_back(deferred);
+        }
+    }
+
+    /// Broadcasts the deferred testcases that fit the budget by now, on behalf of their original senders
+    fn release_deferred<SHM, SP>(
+        &mut self,
+        broker_inner: &mut LlmpBrokerInner<SHM, SP>,
+        now: Duration,
+    ) -> Result<(), Error>
+    where
+        SHM: ShMem,
+        SP: ShMemProvider<ShMem = SHM>,
+    {
+        let mut remaining = VecDeque::with_capacity(self.deferred.len());
+        while let Some(deferred) = self.deferred.pop_front() {
+            if self.fits_budget(deferred.client_id, deferred.msg.len(), now) {
+                self.take_budget(deferred.client_id, deferred.msg.len());
+                self.undefer(&deferred);
+                self.mark_shared(deferred.input_hash, deferred.coverage);
+                broker_inner.send_buf_with_flags_from(
+                    deferred.client_id,
+                    LLMP_TAG_EVENT_TO_BOTH,
+                    deferred.flags,
+                    &deferred.msg,
+                )?;
+            } else {
+                remaining.push_back(deferred);
+            }
+        }
+        self.deferred = remaining;
+        Ok(())
+    }
+}
+
+impl<I, F, SHM, SP> LlmpHook<SHM, SP> for SharingFilterHook<I, F>
+where
+    I: Input,
+    F: FnMut(&EventWithStats<I>) -> Option<u64>,
+    SHM: ShMem,
+    SP: ShMemProvider<ShMem = SHM>,
+{
+    fn on_new_message(
+        &mut self,
+        broker_inner: &mut LlmpBrokerInner<SHM, SP>,
+        client_id: ClientId,
+        msg_tag: &mut Tag,
+        msg_flags: &mut Flags,
+        msg: &mut [u8],
+        _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<LlmpMsgHookResult, Error> {
+        let now = current_time();
+        if !self.deferred.is_empty() {
+            self.release_deferred(broker_inner, now)?;
+        }
+
+        if *msg_tag != LLMP_TAG_EVENT_TO_BOTH {
+            return Ok(LlmpMsgHookResult::ForwardToClients);
+        }
+
+        #[cfg(not(feature = "llmp_compression"))]
+        let event_bytes = &*msg;
+        #[cfg(feature = "llmp_compression")]
+        let compressed;
+        #[cfg(feature = "llmp_compression")]
+        let event_bytes = if *msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+            compressed = self.compressor.decompress_with(
+                msg_flags.codec()?,
+                msg_flags.uses_dictionary(),
+                msg,
+            )?;
+            &compressed
+        } else {
+            &*msg
+        };
+        let event: EventWithStats<I> = msg_flags.format()?.deserialize(event_bytes)?;
+
+        match event.event() {
+            Event::NewTestcase { input, .. } => {
+                let input_hash = generic_hash_std(input);
+                if self.seen_inputs.contains(input_hash)
+                    || self.deferred_inputs.contains(&input_hash)
+                {
+                    self.stats.duplicate_inputs += 1;
+                    return Ok(LlmpMsgHookResult::Handled);
+                }
+                let coverage = (self.coverage_signature)(&event);
+                if let Some(signature) = coverage {
+                    if self.seen_coverage.contains(signature)
+                        || self.deferred_coverage.contains(&signature)
+                    {
+                        self.stats.duplicate_coverage += 1;
+                        return Ok(LlmpMsgHookResult::Handled);
+                    }
+                }
+
+                if self.fits_budget(client_id, msg.len(), now) {
+                    self.take_budget(client_id, msg.len());
+                    self.mark_shared(input_hash, coverage);
+                    return Ok(LlmpMsgHookResult::ForwardToClients);
+                }
+
+                // Only remembered as shared once it is forwarded, so that it may be shared again if dropped
+                self.defer(DeferredMsg {
+                    client_id,
+                    flags: *msg_flags,
+                    msg: msg.to_vec(),
+                    input_hash,
+                    coverage,
+                });
+                Ok(LlmpMsgHookResult::Handled)
+            }
+            Event::Objective { .. } => {
+                // Objectives have priority, but the following testcases have to make up for them
+                self.take_budget(client_id, msg.len());
+                Ok(LlmpMsgHookResult::ForwardToClients)
+            }
+            _ => Ok(LlmpMsgHookResult::ForwardToClients),
+        }
+    }
+
+    fn on_tick(
+        &mut self,
+        broker_inner: &mut LlmpBrokerInner<SHM, SP>,
+        _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        if !self.deferred.is_empty() {
+            self.release_deferred(broker_inner, current_time())?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes a coverage signature for [`SharingFilterHook::with_coverage_signature`],
+/// by hashing the serialized observers of an [`Event::NewTestcase`].
+///
+/// Only use this if the observers sent along with testcases do not contain values that differ
+/// for every execution, such as the execution time.
+#[must_use]
+pub fn observers_buf_signature<I>(event: &EventWithStats<I>) -> Option<u64> {
+    match event.event() {
+        Event::NewTestcase {
+            observers_buf: Some(observers_buf),
+            ..
+        } => Some(hash_std(observers_buf)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::thread::sleep;
+
+    use libafl_bolts::{
+        ClientId,
+        llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult},
+        shmem::{ShMemProvider, StdShMemProvider},
+    };
+
+    use super::{SharingBudget, SharingFilterHook, TokenBucket};
+    use crate::{
+        events::{Event, EventConfig, EventWithStats, ExecStats, llmp::LLMP_TAG_EVENT_TO_BOTH},
+        executors::ExitKind,
+        inputs::BytesInput,
+    };
+
+    /// A serialized [`Event::NewTestcase`] for `input`
+    fn testcase_msg(input: &[u8]) -> Vec<u8> {
+        let event = Event::NewTestcase {
+            input: BytesInput::new(input.to_vec()),
+            observers_buf: None,
+            exit_kind: ExitKind::Ok,
+            corpus_size: 0,
+            client_config: EventConfig::AlwaysUnique,
+            forward_id: None,
+            #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
+            node_id: None,
+        };
+        postcard::to_allocvec(&EventWithStats::new(
+            event,
+            ExecStats::new(Duration::ZERO, 0),
+        ))
+        .unwrap()
+    }
+
+    /// Passes a new testcase message to the `hook`
+    fn send(
+        hook: &mut SharingFilterHook<BytesInput>,
+        broker: &mut LlmpBrokerInner<<StdShMemProvider as ShMemProvider>::ShMem, StdShMemProvider>,
+        input: &[u8],
+    ) -> (LlmpMsgHookResult, usize) {
+        let mut new_msgs = Vec::new();
+        let result = hook
+            .on_new_message(
+                broker,
+                ClientId(1),
+                &mut LLMP_TAG_EVENT_TO_BOTH.clone(),
+                &mut Flags(0),
+                &mut testcase_msg(input),
+                &mut new_msgs,
+            )
+            .unwrap();
+        (result, new_msgs.len())
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_sharing_filter_oversized() {
+        let mut broker = LlmpBrokerInner::new(StdShMemProvider::new().unwrap()).unwrap();
+        // The messages are larger than the whole budget
+        let mut hook = SharingFilterHook::<BytesInput>::new()
+            .with_client_budget(SharingBudget::bytes_per_sec(8.0));
+
+        let (result, _) = send(&mut hook, &mut broker, b"first");
+        assert!(matches!(result, LlmpMsgHookResult::ForwardToClients));
+        // The budget is overdrawn now
+        let (result, _) = send(&mut hook, &mut broker, b"second");
+        assert!(matches!(result, LlmpMsgHookResult::Handled));
+        assert_eq!(hook.stats().deferred, 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_sharing_filter_release_on_tick() {
+        let mut broker = LlmpBrokerInner::new(StdShMemProvider::new().unwrap()).unwrap();
+        // A budget of one message per 50 milliseconds
+        let mut hook = SharingFilterHook::<BytesInput>::new()
+            .with_client_budget(SharingBudget::msgs_per_sec(20.0).with_burst_secs(0.05));
+
+        let (result, _) = send(&mut hook, &mut broker, b"first");
+        assert!(matches!(result, LlmpMsgHookResult::ForwardToClients));
+        let (result, _) = send(&mut hook, &mut broker, b"second");
+        assert!(matches!(result, LlmpMsgHookResult::Handled));
+
+        let mut new_msgs = Vec::new();
+        sleep(Duration::from_millis(100));
+        hook.on_tick(&mut broker, &mut new_msgs).unwrap();
+        // Sent by the hook itself, on behalf of the original sender
+        assert!(new_msgs.is_empty());
+        assert_eq!(hook.stats().forwarded, 2);
+        // Shared by now
+        let (result, _) = send(&mut hook, &mut broker, b"second");
+        assert!(matches!(result, LlmpMsgHookResult::Handled));
+        assert_eq!(hook.stats().duplicate_inputs, 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_sharing_filter_dropped_deferred() {
+        let mut broker = LlmpBrokerInner::new(StdShMemProvider::new().unwrap()).unwrap();
+        let mut hook = SharingFilterHook::<BytesInput>::new()
+            .with_client_budget(SharingBudget::msgs_per_sec(1.0).with_burst_secs(1.0))
+            .with_max_deferred(1);
+
+        let (result, _) = send(&mut hook, &mut broker, b"first");
+        assert!(matches!(result, LlmpMsgHookResult::ForwardToClients));
+        send(&mut hook, &mut broker, b"second");
+        // Held back already
+        send(&mut hook, &mut broker, b"second");
+        assert_eq!(hook.stats().duplicate_inputs, 1);
+        // Drops `second`
+        send(&mut hook, &mut broker, b"third");
+        assert_eq!(hook.stats().dropped, 1);
+
+        // `second` was never shared, so it gets held back again
+        send(&mut hook, &mut broker, b"second");
+        assert_eq!(hook.stats().duplicate_inputs, 1);
+        assert_eq!(hook.stats().deferred, 3);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_sharing_filter_max_seen() {
+        let mut broker = LlmpBrokerInner::new(StdShMemProvider::new().unwrap()).unwrap();
+        let mut hook = SharingFilterHook::<BytesInput>::new().with_max_seen(1);
+
+        send(&mut hook, &mut broker, b"first");
+        send(&mut hook, &mut broker, b"first");
+        assert_eq!(hook.stats().duplicate_inputs, 1);
+        // Forgets `first`
+        send(&mut hook, &mut broker, b"second");
+        let (result, _) = send(&mut hook, &mut broker, b"first");
+        assert!(matches!(result, LlmpMsgHookResult::ForwardToClients));
+        assert_eq!(hook.stats().forwarded, 3);
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let budget = SharingBudget::msgs_per_sec(2.0).with_bytes_per_sec(100.0);
+        let mut bucket = TokenBucket::new(budget, Duration::ZERO);
+
+        assert!(bucket.fits(50));
+        bucket.take(50);
+        assert!(bucket.fits(50));
+        bucket.take(50);
+        // out of bytes and messages
+        assert!(!bucket.fits(1));
+
+        bucket.refill(Duration::from_millis(500));
+        assert!(bucket.fits(50));
+        assert!(!bucket.fits(51));
+
+        // the saved up budget is capped, larger messages pass once the bucket is full
+        bucket.refill(Duration::from_secs(60));
+        assert!(bucket.fits(100));
+        assert!(bucket.fits(1000));
+        bucket.take(1);
+        assert!(!bucket.fits(1000));
+        bucket.take(1);
+        assert!(!bucket.fits(1));
+    }
+}
@@ -9,7 +9,7 @@
 
 use alloc::string::String;
 use core::{fmt::Debug, marker::PhantomData, time::Duration};
-use std::process;
+use std::{path::Path, process};
 
 use libafl_bolts::{
     ClientId,
@@ -18,20 +18,21 @@ use libafl_bolts::{
 };
 #[cfg(feature = "llmp_compression")]
 use libafl_bolts::{
-    compress::GzipCompressor,
-    llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
+    compress::{CodecCompressor, CompressionCodec, Compressor},
+    llmp::{Flags, LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
 };
+use serde::Serialize;
 
 use super::{AwaitRestartSafe, EventWithStats};
 #[cfg(feature = "llmp_compression")]
-use crate::events::llmp::COMPRESS_THRESHOLD;
+use crate::events::llmp::broker_compressor;
 use crate::{
     Error,
     common::HasMetadata,
     events::{
-        Event, EventConfig, EventFirer, EventManagerId, EventReceiver, EventRestarter,
-        HasEventManagerId, LogSeverity, ProgressReporter, SendExiting, std_maybe_report_progress,
-        std_report_progress,
+        Event, EventCheckpointer, EventConfig, EventFirer, EventManagerId, EventReceiver,
+        EventRestarter, HasEventManagerId, LogSeverity, ProgressReporter, SendExiting,
+        std_checkpoint, std_maybe_report_progress, std_report_progress,
     },
     inputs::Input,
     state::{HasExecutions, HasLastReportTime, MaybeHasClientPerfMonitor, Stoppable},
@@ -46,7 +47,7 @@ pub struct CentralizedEventManager<EM, I, S, SHM, SP> {
     /// The centralized LLMP client for inter process communication
     client: LlmpClient<SHM, SP>,
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: CodecCompressor,
     is_main: bool,
     phantom: PhantomData<(I, S)>,
 }
@@ -95,9 +96,9 @@ impl CentralizedEventManagerBuilder {
     {
         Ok(CentralizedEventManager {
             inner,
-            client,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: broker_compressor(&client, CompressionCodec::preferred())?,
+            client,
             is_main: self.is_main,
             phantom: PhantomData,
         })
@@ -256,6 +257,18 @@ where
     }
 }
 
+impl<EM, I, S, SHM, SP> EventCheckpointer<S> for CentralizedEventManager<EM, I, S, SHM, SP>
+where
+    SHM: ShMem,
+    EM: AwaitRestartSafe,
+    S: Serialize,
+{
+    /// Waits until both brokers mapped all pages we sent, then writes the state to `path`.
+    fn checkpoint(&mut self, state: &S, path: &Path) -> Result<(), Error> {
+        std_checkpoint(self, state, path)
+    }
+}
+
 impl<EM, I, S, SHM, SP> EventReceiver<I, S> for CentralizedEventManager<EM, I, S, SHM, SP>
 where
     EM: EventReceiver<I, S> + HasEventManagerId + EventFirer<I, S>,
@@ -350,11 +363,11 @@ where
         let serialized = postcard::to_allocvec(event)?;
         let flags = LLMP_FLAG_INITIALIZED;
 
-        match self.compressor.maybe_compress(&serialized) {
+        match self.compressor.maybe_compress(&serialized)? {
             Some(comp_buf) => {
                 self.client.send_buf_with_flags(
                     _LLMP_TAG_TO_MAIN,
-                    flags | LLMP_FLAG_COMPRESSED,
+                    flags | Flags::compressed_by(&self.compressor),
                     &comp_buf,
                 )?;
             }
@@ -378,7 +391,7 @@ where
     ) -> Result<Option<(EventWithStats<I>, bool)>, Error> {
         // TODO: Get around local event copy by moving handle_in_client
         let self_id = self.client.sender().id();
-        while let Some((client_id, tag, _flags, msg)) = self.client.recv_buf_with_flags()? {
+        while let Some((client_id, tag, flags, msg)) = self.client.recv_buf_with_flags()? {
             assert!(
                 tag == _LLMP_TAG_TO_MAIN,
                 "Only _LLMP_TAG_TO_MAIN parcel should have arrived in the main node!"
@@ -392,13 +405,17 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if _flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = self.compressor.decompress(msg)?;
+            let event_bytes = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+                compressed = self.compressor.decompress_with(
+                    flags.codec()?,
+                    flags.uses_dictionary(),
+                    msg,
+                )?;
                 &compressed
             } else {
                 msg
             };
-            let event: EventWithStats<I> = postcard::from_bytes(event_bytes)?;
+            let event: EventWithStats<I> = flags.format()?.deserialize(event_bytes)?;
             log::debug!(
                 "Processor received message {}",
                 event.event().name_detailed()
@@ -5,6 +5,7 @@
 //!
 //! To use multiple [`Launcher`]`s` for individual configurations,
 //! we can set `spawn_broker` to `false` on all but one.
+//! Alternatively, the [`RoleLauncher`] launches clients of different [`ClientRole`]`s` with a single broker.
 //!
 //! To connect multiple nodes together via TCP, we can use the `remote_broker_addr`.
 //! (this requires the `llmp_bind_public` compile-time feature for `LibAFL`).
@@ -12,16 +13,21 @@
 //! On `Unix` systems, the [`Launcher`] will use `fork` if the `fork` feature is used for `LibAFL`.
 //! Else, it will start subsequent nodes with the same commandline, and will set special `env` variables accordingly.
 
-use alloc::string::String;
+use alloc::{borrow::Cow, string::String};
 use core::{
     fmt::{self, Debug, Formatter},
     net::SocketAddr,
     num::NonZeroUsize,
     time::Duration,
 };
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 use libafl_bolts::{
     core_affinity::{CoreId, Cores},
+    serialization::SerializationFormat,
     shmem::ShMemProvider,
     tuples::tuple_list,
 };
@@ -30,14 +36,20 @@ use typed_builder::TypedBuilder;
 #[cfg(all(unix, feature = "fork"))]
 use {
     crate::{
-        events::{CentralizedLlmpHook, StdLlmpEventHook, centralized::CentralizedEventManager},
+        events::{
+            CentralizedLlmpHook, Event, EventWithStats, StdLlmpEventHook,
+            centralized::CentralizedEventManager,
+        },
         inputs::Input,
+        monitors::stats::{AggregatorOps, UserStats, UserStatsValue},
     },
     alloc::boxed::Box,
     alloc::string::ToString,
+    alloc::vec::Vec,
+    core::marker::PhantomData,
     libafl_bolts::{
         core_affinity::get_core_ids,
-        llmp::{Broker, Brokers, LlmpBroker},
+        llmp::{Broker, Brokers, LLMP_DEFAULT_BROKER_SLEEP_TIME, LlmpBroker},
         os::{ForkResult, fork},
     },
 };
@@ -67,12 +79,20 @@ const _AFL_LAUNCHER_CLIENT: &str = "AFL_LAUNCHER_CLIENT";
 #[cfg(all(feature = "fork", unix))]
 const LIBAFL_DEBUG_OUTPUT: &str = "LIBAFL_DEBUG_OUTPUT";
 
+/// All usable physical cores, within the cgroup cpu quota. The default cores of a [`Launcher`].
+fn default_cores() -> &'static Cores {
+    static DEFAULT_CORES: OnceLock<Cores> = OnceLock::new();
+    DEFAULT_CORES.get_or_init(|| Cores::physical().unwrap_or_else(|_| Cores::from(vec![])))
+}
+
 /// Information about this client from the launcher
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientDescription {
     id: usize,
     overcommit_id: usize,
     core_id: CoreId,
+    #[serde(default)]
+    role: Option<Cow<'static, str>>,
 }
 
 impl ClientDescription {
@@ -83,9 +103,20 @@ impl ClientDescription {
             id,
             overcommit_id,
             core_id,
+            role: None,
         }
     }
 
+    /// Sets the role of this client, see [`RoleLauncher`]
+    #[must_use]
+    pub fn with_role<R>(mut self, role: R) -> Self
+    where
+        R: Into<Cow<'static, str>>,
+    {
+        self.role = Some(role.into());
+        self
+    }
+
     /// Id unique to all clients spawned by this launcher
     #[must_use]
     pub fn id(&self) -> usize {
@@ -104,6 +135,21 @@ impl ClientDescription {
         self.overcommit_id
     }
 
+    /// The role of this client, if it was spawned by a [`RoleLauncher`]
+    #[must_use]
+    pub fn role(&self) -> Option<&str> {
+        self.role.as_deref()
+    }
+
+    /// The checkpoint of this client, derived from the checkpoint path `base` of its launcher,
+    /// e.g., `state.ckpt.3` for the client with id 3
+    #[must_use]
+    pub fn checkpoint_path(&self, base: &Path) -> PathBuf {
+        let mut path = base.as_os_str().to_os_string();
+        path.push(format!(".{}", self.id));
+        PathBuf::from(path)
+    }
+
     /// Create a string representation safe for environment variables
     #[must_use]
     pub fn to_safe_string(&self) -> String {
@@ -121,6 +167,7 @@ impl ClientDescription {
             id,
             overcommit_id,
             core_id,
+            role: None,
         }
     }
 }
@@ -142,7 +189,8 @@ pub struct Launcher<'a, CF, MT, SP> {
     /// The broker port to use (or to attach to, in case [`Self::spawn_broker`] is `false`)
     #[builder(default = 1337_u16)]
     broker_port: u16,
-    /// The list of cores to run on
+    /// The list of cores to run on, all usable physical cores by default, see [`Cores::physical`]
+    #[builder(default = default_cores())]
     cores: &'a Cores,
     /// The number of clients to spawn on each core
     #[builder(default = 1)]
@@ -171,6 +219,10 @@ pub struct Launcher<'a, CF, MT, SP> {
     /// clusters.
     #[builder(default = None)]
     remote_broker_addr: Option<SocketAddr>,
+    /// The pre-shared key the brokers of the campaign authenticate each other with, see [`RestartingMgr`].
+    #[cfg(feature = "llmp_noise")]
+    #[builder(default = None)]
+    b2b_psk: Option<[u8; 32]>,
     /// If this launcher should spawn a new `broker` on `[Self::broker_port]` (default).
     /// The reason you may not want this is, if you already have a [`Launcher`]
     /// with a different configuration (for the same target) running on this machine.
@@ -180,6 +232,22 @@ pub struct Launcher<'a, CF, MT, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// The format the clients serialize their state and events with, see [`RestartingMgr`].
+    #[builder(default)]
+    serialization_format: SerializationFormat,
+    /// The master seed of a deterministic campaign, see [`client_rand_stream`](crate::events::client_rand_stream).
+    /// Each client's rand continues with the stream of its client id and how often the client was respawned.
+    #[builder(default = None)]
+    master_seed: Option<u64>,
+    /// The checkpoint the clients resume from, if it exists, see [`RestartingMgr`].
+    /// Each client uses its own file, see [`ClientDescription::checkpoint_path`].
+    #[builder(default = None)]
+    checkpoint: Option<PathBuf>,
+    /// The compression dictionary of the campaign, installed if it exists, else trained by the broker.
+    /// See [`RestartingMgr`].
+    #[cfg(feature = "llmp_compression")]
+    #[builder(default = None)]
+    compression_dictionary: Option<PathBuf>,
 }
 
 impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
@@ -190,7 +258,8 @@ impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
             .field("broker_port", &self.broker_port)
             .field("core", &self.cores)
             .field("spawn_broker", &self.spawn_broker)
-            .field("remote_broker_addr", &self.remote_broker_addr);
+            .field("remote_broker_addr", &self.remote_broker_addr)
+            .field("serialization_format", &self.serialization_format);
         #[cfg(unix)]
         {
             dbg_struct
@@ -257,6 +326,9 @@ where
         let mut handles = vec![];
 
         log::info!("spawning on cores: {:?}", self.cores);
+        if let Ok(layout) = self.cores.layout() {
+            log::info!("core layout: {layout}");
+        }
 
         self.opened_stdout_file = self
             .stdout_file
@@ -318,9 +390,19 @@ where
                                 .kind(ManagerKind::Client {
                                     client_description: client_description.clone(),
                                 })
+                                .checkpoint(
+                                    self.checkpoint
+                                        .as_deref()
+                                        .map(|base| client_description.checkpoint_path(base)),
+                                )
                                 .configuration(self.configuration)
                                 .serialize_state(self.serialize_state)
+                                .serialization_format(self.serialization_format)
+                                .master_seed(self.master_seed)
                                 .hooks(hooks);
+                            #[cfg(feature = "llmp_compression")]
+                            let builder =
+                                builder.compression_dictionary(self.compression_dictionary.clone());
                             let (state, mgr) = builder.build().launch()?;
 
                             return (self.run_client.take().unwrap())(
@@ -347,7 +429,13 @@ where
                 .exit_cleanly_after(Some(NonZeroUsize::try_from(self.cores.ids.len()).unwrap()))
                 .configuration(self.configuration)
                 .serialize_state(self.serialize_state)
+                .serialization_format(self.serialization_format)
+                .master_seed(self.master_seed)
                 .hooks(hooks);
+            #[cfg(feature = "llmp_compression")]
+            let builder = builder.compression_dictionary(self.compression_dictionary.clone());
+            #[cfg(feature = "llmp_noise")]
+            let builder = builder.b2b_psk(self.b2b_psk);
 
             builder.build().launch()?;
 
@@ -406,9 +494,18 @@ where
                     .kind(ManagerKind::Client {
                         client_description: client_description.clone(),
                     })
+                    .checkpoint(
+                        self.checkpoint
+                            .as_deref()
+                            .map(|base| client_description.checkpoint_path(base)),
+                    )
                     .configuration(self.configuration)
                     .serialize_state(self.serialize_state)
+                    .serialization_format(self.serialization_format)
+                    .master_seed(self.master_seed)
                     .hooks(hooks);
+                #[cfg(feature = "llmp_compression")]
+                let builder = builder.compression_dictionary(self.compression_dictionary.clone());
 
                 let (state, mgr) = builder.build().launch()?;
 
@@ -422,6 +519,9 @@ where
                 let mut handles = vec![];
 
                 log::info!("spawning on cores: {:?}", self.cores);
+                if let Ok(layout) = self.cores.layout() {
+                    log::info!("core layout: {layout}");
+                }
 
                 let debug_output = std::env::var("LIBAFL_DEBUG_OUTPUT").is_ok();
                 #[cfg(unix)]
@@ -512,7 +612,13 @@ where
                 .exit_cleanly_after(Some(NonZeroUsize::try_from(self.cores.ids.len()).unwrap()))
                 .configuration(self.configuration)
                 .serialize_state(self.serialize_state)
+                .serialization_format(self.serialization_format)
+                .master_seed(self.master_seed)
                 .hooks(hooks);
+            #[cfg(feature = "llmp_compression")]
+            let builder = builder.compression_dictionary(self.compression_dictionary.clone());
+            #[cfg(feature = "llmp_noise")]
+            let builder = builder.b2b_psk(self.b2b_psk);
 
             builder.build().launch()?;
 
@@ -536,6 +642,366 @@ where
     }
 }
 
+/// How often the fuzzer process of a client gets respawned after it exited, e.g., after a crash or timeout
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RespawnPolicy {
+    /// Always respawn the fuzzer process
+    #[default]
+    Always,
+    /// Never respawn the fuzzer process, the client ends with its first fuzzer process
+    Never,
+    /// Respawn the fuzzer process at most this many times
+    AtMost(u64),
+}
+
+impl RespawnPolicy {
+    /// The maximum number of respawns, or `None` if unlimited
+    #[must_use]
+    pub fn max_respawns(&self) -> Option<u64> {
+        match self {
+            Self::Always => None,
+            Self::Never => Some(0),
+            Self::AtMost(max_respawns) => Some(*max_respawns),
+        }
+    }
+}
+
+/// The name of the user stat carrying the role of a client spawned by a [`RoleLauncher`]
+pub const CLIENT_ROLE_STAT: &str = "role";
+
+#[cfg(all(unix, feature = "fork"))]
+/// The `run_client` closure of a [`ClientRole`]
+pub type RoleClientFn<'a, EM, S> =
+    Box<dyn FnOnce(Option<S>, EM, ClientDescription) -> Result<(), Error> + 'a>;
+
+#[cfg(all(unix, feature = "fork"))]
+/// A kind of client, with its own closure, cores, and configuration, launched by a [`RoleLauncher`]
+pub struct ClientRole<'a, EM, S> {
+    name: Cow<'static, str>,
+    cores: Cores,
+    overcommit: usize,
+    configuration: Option<EventConfig>,
+    respawn_policy: RespawnPolicy,
+    run_client: RoleClientFn<'a, EM, S>,
+}
+
+#[cfg(all(unix, feature = "fork"))]
+impl<EM, S> Debug for ClientRole<'_, EM, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientRole")
+            .field("name", &self.name)
+            .field("cores", &self.cores)
+            .field("overcommit", &self.overcommit)
+            .field("configuration", &self.configuration)
+            .field("respawn_policy", &self.respawn_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(unix, feature = "fork"))]
+impl<'a, EM, S> ClientRole<'a, EM, S> {
+    /// Creates a new [`ClientRole`] called `name`, running `run_client` on each of the `cores`
+    pub fn new<N, CF>(name: N, cores: Cores, run_client: CF) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        CF: FnOnce(Option<S>, EM, ClientDescription) -> Result<(), Error> + 'a,
+    {
+        Self {
+            name: name.into(),
+            cores,
+            overcommit: 1,
+            configuration: None,
+            respawn_policy: RespawnPolicy::Always,
+            run_client: Box::new(run_client),
+        }
+    }
+
+    /// Sets the number of clients of this role to spawn on each core
+    #[must_use]
+    pub fn with_overcommit(mut self, overcommit: usize) -> Self {
+        self.overcommit = overcommit;
+        self
+    }
+
+    /// Uses `configuration` for the clients of this role, instead of the one of the [`RoleLauncher`].
+    ///
+    /// Use different configurations for roles with different observers, so that testcases get re-executed.
+    #[must_use]
+    pub fn with_configuration(mut self, configuration: EventConfig) -> Self {
+        self.configuration = Some(configuration);
+        self
+    }
+
+    /// Sets how often the fuzzer processes of this role get respawned
+    #[must_use]
+    pub fn with_respawn_policy(mut self, respawn_policy: RespawnPolicy) -> Self {
+        self.respawn_policy = respawn_policy;
+        self
+    }
+
+    /// The name of this role
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of clients of this role
+    #[must_use]
+    pub fn client_count(&self) -> usize {
+        self.cores.ids.len() * self.overcommit
+    }
+}
+
+/// Provides a [`RoleLauncher`], which launches clients of different [`ClientRole`]s, e.g., some havoc clients,
+/// some cmplog clients, and some clients running a sanitized target, connected to a single broker.
+///
+/// Each client reports its role as the [`CLIENT_ROLE_STAT`] user stat, so that monitors show it,
+/// and gets it in its [`ClientDescription::role`].
+///
+/// Will hide child output, unless the settings indicate otherwise, or the `LIBAFL_DEBUG_OUTPUT` env variable is set.
+#[cfg(all(unix, feature = "fork"))]
+#[derive(TypedBuilder)]
+pub struct RoleLauncher<'a, EM, MT, S, SP> {
+    /// The `ShmemProvider` to use
+    shmem_provider: SP,
+    /// The monitor instance to use
+    monitor: MT,
+    /// The configuration of all roles that do not set their own
+    configuration: EventConfig,
+    /// The roles to launch
+    roles: Vec<ClientRole<'a, EM, S>>,
+    /// The broker port to use (or to attach to, in case [`Self::spawn_broker`] is `false`)
+    #[builder(default = 1337_u16)]
+    broker_port: u16,
+    /// A file name to write all client output to
+    #[builder(default = None)]
+    stdout_file: Option<&'a str>,
+    /// A file name to write all client stderr output to. If not specified, output is sent to
+    /// `stdout_file`.
+    #[builder(default = None)]
+    stderr_file: Option<&'a str>,
+    /// The time in milliseconds to delay between child launches
+    #[builder(default = 10)]
+    launch_delay: u64,
+    /// The `ip:port` address of another broker to connect our new broker to for multi-machine
+    /// clusters.
+    #[builder(default = None)]
+    remote_broker_addr: Option<SocketAddr>,
+    /// The pre-shared key the brokers of the campaign authenticate each other with, see [`RestartingMgr`].
+    #[cfg(feature = "llmp_noise")]
+    #[builder(default = None)]
+    b2b_psk: Option<[u8; 32]>,
+    /// If this launcher should spawn a new `broker` on `[Self::broker_port]` (default).
+    #[builder(default = true)]
+    spawn_broker: bool,
+    /// Tell the manager to serialize or not the state on restart
+    #[builder(default = LlmpShouldSaveState::OnRestart)]
+    serialize_state: LlmpShouldSaveState,
+    /// The master seed of a deterministic campaign, see [`client_rand_stream`](crate::events::client_rand_stream).
+    /// Each client's rand continues with the stream of its client id and how often the client was respawned.
+    #[builder(default = None)]
+    master_seed: Option<u64>,
+    /// The checkpoint the clients resume from, if it exists, see [`RestartingMgr`].
+    /// Each client uses its own file, see [`ClientDescription::checkpoint_path`].
+    #[builder(default = None)]
+    checkpoint: Option<PathBuf>,
+}
+
+#[cfg(all(unix, feature = "fork"))]
+impl<EM, MT, S, SP> Debug for RoleLauncher<'_, EM, MT, S, SP> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoleLauncher")
+            .field("configuration", &self.configuration)
+            .field("roles", &self.roles)
+            .field("broker_port", &self.broker_port)
+            .field("spawn_broker", &self.spawn_broker)
+            .field("remote_broker_addr", &self.remote_broker_addr)
+            .field("stdout_file", &self.stdout_file)
+            .field("stderr_file", &self.stderr_file)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(unix, feature = "fork"))]
+impl<I, MT, S, SP> RoleLauncher<'_, LlmpRestartingEventManager<(), I, S, SP::ShMem, SP>, MT, S, SP>
+where
+    I: DeserializeOwned + Serialize,
+    MT: Monitor + Clone,
+    S: DeserializeOwned + Serialize,
+    SP: ShMemProvider,
+{
+    /// Launch the broker and the clients of all roles and fuzz
+    pub fn launch(&mut self) -> Result<(), Error> {
+        self.launch_with_hooks(tuple_list!())
+    }
+}
+
+#[cfg(all(unix, feature = "fork"))]
+impl<EMH, I, MT, S, SP>
+    RoleLauncher<'_, LlmpRestartingEventManager<EMH, I, S, SP::ShMem, SP>, MT, S, SP>
+where
+    EMH: EventManagerHooksTuple<I, S> + Clone + Copy,
+    I: DeserializeOwned + Serialize,
+    MT: Monitor + Clone,
+    S: DeserializeOwned + Serialize,
+    SP: ShMemProvider,
+{
+    /// Launch the broker and the clients of all roles and fuzz with a user-supplied hook
+    #[expect(clippy::too_many_lines)]
+    pub fn launch_with_hooks(&mut self, hooks: EMH) -> Result<(), Error> {
+        let client_count: usize = self.roles.iter().map(ClientRole::client_count).sum();
+        if client_count == 0 {
+            return Err(Error::illegal_argument(
+                "No roles with cores to spawn on given, cannot launch anything.",
+            ));
+        }
+
+        let core_ids = get_core_ids()?;
+        for role in &self.roles {
+            if let Some(core_id) = role.cores.ids.iter().find(|id| !core_ids.contains(id)) {
+                return Err(Error::illegal_argument(format!(
+                    "Core {core_id:?} of role {} does not exist",
+                    role.name
+                )));
+            }
+        }
+
+        let opened_stdout_file = self.stdout_file.map(File::create).transpose()?;
+        let opened_stderr_file = self.stderr_file.map(File::create).transpose()?;
+        let debug_output = std::env::var(LIBAFL_DEBUG_OUTPUT).is_ok();
+
+        let mut handles = vec![];
+        let mut index = 0_usize;
+        for role_idx in 0..self.roles.len() {
+            let role = &self.roles[role_idx];
+            let (name, cores, overcommit) =
+                (role.name.clone(), role.cores.ids.clone(), role.overcommit);
+            log::info!(
+                "spawning {} clients of role {name} on cores {cores:?}",
+                role.client_count()
+            );
+
+            for bind_to in cores {
+                for overcommit_id in 0..overcommit {
+                    index += 1;
+                    self.shmem_provider.pre_fork()?;
+                    // # Safety
+                    // Fork is safe in general, apart from potential side effects to the OS and other threads
+                    match unsafe { fork() }? {
+                        ForkResult::Parent(child) => {
+                            self.shmem_provider.post_fork(false)?;
+                            handles.push(child.pid);
+                            log::info!(
+                                "child of role {name} spawned with id {index} and bound to core {bind_to:?}"
+                            );
+                        }
+                        ForkResult::Child => {
+                            self.shmem_provider.post_fork(true)?;
+
+                            std::thread::sleep(Duration::from_millis(
+                                index as u64 * self.launch_delay,
+                            ));
+
+                            if !debug_output {
+                                if let Some(file) = &opened_stdout_file {
+                                    dup2(file.as_raw_fd(), libc::STDOUT_FILENO)?;
+                                    let stderr = opened_stderr_file.as_ref().unwrap_or(file);
+                                    dup2(stderr.as_raw_fd(), libc::STDERR_FILENO)?;
+                                }
+                            }
+
+                            let role = self.roles.swap_remove(role_idx);
+                            let client_description =
+                                ClientDescription::new(index, overcommit_id, bind_to)
+                                    .with_role(name.clone());
+
+                            // Fuzzer client. keeps retrying the connection to broker till the broker starts
+                            let builder = RestartingMgr::<EMH, I, MT, S, SP>::builder()
+                                .shmem_provider(self.shmem_provider.clone())
+                                .broker_port(self.broker_port)
+                                .kind(ManagerKind::Client {
+                                    client_description: client_description.clone(),
+                                })
+                                .checkpoint(
+                                    self.checkpoint
+                                        .as_deref()
+                                        .map(|base| client_description.checkpoint_path(base)),
+                                )
+                                .configuration(role.configuration.unwrap_or(self.configuration))
+                                .serialize_state(self.serialize_state)
+                                .master_seed(self.master_seed)
+                                .max_respawns(role.respawn_policy.max_respawns())
+                                .hooks(hooks);
+                            let (state, mut mgr) = builder.build().launch()?;
+
+                            if state.is_none() {
+                                // Tell the monitor about our role, once
+                                mgr.send_event(&EventWithStats::with_current_time(
+                                    Event::UpdateUserStats {
+                                        name: Cow::Borrowed(CLIENT_ROLE_STAT),
+                                        value: UserStats::new(
+                                            UserStatsValue::String(name),
+                                            AggregatorOps::None,
+                                        ),
+                                        phantom: PhantomData,
+                                    },
+                                    0,
+                                ))?;
+                            }
+
+                            return (role.run_client)(state, mgr, client_description);
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.spawn_broker {
+            log::info!("I am broker!!.");
+
+            let builder = RestartingMgr::<EMH, I, MT, S, SP>::builder()
+                .shmem_provider(self.shmem_provider.clone())
+                .monitor(Some(self.monitor.clone()))
+                .broker_port(self.broker_port)
+                .kind(ManagerKind::Broker)
+                .remote_broker_addr(self.remote_broker_addr)
+                .exit_cleanly_after(Some(NonZeroUsize::try_from(client_count).unwrap()))
+                .configuration(self.configuration)
+                .serialize_state(self.serialize_state)
+                .master_seed(self.master_seed)
+                .hooks(hooks);
+            #[cfg(feature = "llmp_noise")]
+            let builder = builder.b2b_psk(self.b2b_psk);
+
+            builder.build().launch()?;
+
+            // Broker exited. kill all clients.
+            for handle in &handles {
+                // # Safety
+                // Normal libc call, no dereferences whatsoever
+                unsafe {
+                    libc::kill(*handle, libc::SIGINT);
+                }
+            }
+        } else {
+            log::info!(
+                "Not spawning broker (spawn_broker is false). Waiting for fuzzer children to exit..."
+            );
+            for handle in &handles {
+                let mut status = 0;
+                unsafe {
+                    libc::waitpid(*handle, &mut status, 0);
+                    if status != 0 {
+                        log::info!("Client with pid {handle} exited with status {status}");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// A Launcher that minimizes re-execution of shared testcases.
 ///
 /// Provides a Launcher, which can be used to launch a fuzzing run on a specified list of cores with a single main and multiple secondary nodes
@@ -589,6 +1055,10 @@ pub struct CentralizedLauncher<'a, CF, MF, MT, SP> {
     /// clusters.
     #[builder(default = None)]
     remote_broker_addr: Option<SocketAddr>,
+    /// The pre-shared key the brokers of the campaign authenticate each other with, see [`RestartingMgr`].
+    #[cfg(feature = "llmp_noise")]
+    #[builder(default = None)]
+    b2b_psk: Option<[u8; 32]>,
     #[cfg(feature = "multi_machine")]
     multi_machine_node_descriptor: NodeDescriptor<SocketAddr>,
     /// If this launcher should spawn a new `broker` on `[Self::broker_port]` (default).
@@ -600,6 +1070,14 @@ pub struct CentralizedLauncher<'a, CF, MF, MT, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// The master seed of a deterministic campaign, see [`client_rand_stream`](crate::events::client_rand_stream).
+    /// Each client's rand continues with the stream of its client id and how often the client was respawned.
+    #[builder(default = None)]
+    master_seed: Option<u64>,
+    /// The checkpoint the clients resume from, if it exists, see [`RestartingMgr`].
+    /// Each client uses its own file, see [`ClientDescription::checkpoint_path`].
+    #[builder(default = None)]
+    checkpoint: Option<PathBuf>,
 }
 
 #[cfg(all(unix, feature = "fork"))]
@@ -658,12 +1136,18 @@ where
         let restarting_mgr_builder =
             |centralized_launcher: &Self, client_description: ClientDescription| {
                 // Fuzzer client. keeps retrying the connection to broker till the broker starts
+                let checkpoint = centralized_launcher
+                    .checkpoint
+                    .as_deref()
+                    .map(|base| client_description.checkpoint_path(base));
                 let builder = RestartingMgr::<(), I, MT, S, SP>::builder()
                     .shmem_provider(centralized_launcher.shmem_provider.clone())
                     .broker_port(centralized_launcher.broker_port)
                     .kind(ManagerKind::Client { client_description })
+                    .checkpoint(checkpoint)
                     .configuration(centralized_launcher.configuration)
                     .serialize_state(centralized_launcher.serialize_state)
+                    .master_seed(centralized_launcher.master_seed)
                     .hooks(tuple_list!());
 
                 builder.build().launch()
@@ -893,6 +1377,9 @@ where
                 self.broker_port,
             )?;
 
+            #[cfg(feature = "llmp_noise")]
+            broker.inner_mut().set_b2b_psk(self.b2b_psk);
+
             if let Some(remote_broker_addr) = self.remote_broker_addr {
                 log::info!("B2b: Connecting to {:?}", &remote_broker_addr);
                 broker.inner_mut().connect_b2b(remote_broker_addr)?;
@@ -905,7 +1392,10 @@ where
         log::debug!("Broker has been initialized; pid {}.", std::process::id());
 
         // Loop over all the brokers that should be polled
-        brokers.loop_with_timeouts(Duration::from_secs(30), Some(Duration::from_millis(5)));
+        brokers.loop_with_timeouts(
+            Duration::from_secs(30),
+            Some(LLMP_DEFAULT_BROKER_SLEEP_TIME),
+        );
 
         #[cfg(feature = "llmp_debug")]
         log::info!("The last client quit. Exiting.");
@@ -9,8 +9,8 @@ use libafl_bolts::{
 };
 #[cfg(feature = "llmp_compression")]
 use libafl_bolts::{
-    compress::GzipCompressor,
-    llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
+    compress::{CodecCompressor, CompressionCodec, Compressor},
+    llmp::{Flags, LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
 };
 use serde::{Serialize, de::DeserializeOwned};
 
@@ -41,6 +41,18 @@ pub(crate) const _LLMP_TAG_NO_RESTART: Tag = Tag(0x57A7EE71);
 #[cfg(feature = "llmp_compression")]
 pub const COMPRESS_THRESHOLD: usize = 1024;
 
+/// A compressor for the messages `llmp` sends to its broker.
+/// Uses the `preferred` codec if the broker supports it, else the best codec both sides support.
+#[cfg(feature = "llmp_compression")]
+pub(crate) fn broker_compressor<SHM, SP>(
+    llmp: &LlmpClient<SHM, SP>,
+    preferred: CompressionCodec,
+) -> Result<CodecCompressor, Error> {
+    let codec = llmp.negotiate_codec(preferred);
+    log::debug!("Compressing llmp messages to the broker with {codec}");
+    CodecCompressor::with_threshold(COMPRESS_THRESHOLD).with_codec(codec)
+}
+
 /// Specify if the State must be persistent over restarts
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LlmpShouldSaveState {
@@ -82,7 +94,7 @@ pub struct LlmpEventConverter<I, IC, ICB, S, SHM, SP> {
     llmp: LlmpClient<SHM, SP>,
     last_sent: Duration,
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: CodecCompressor,
     converter: Option<IC>,
     converter_back: Option<ICB>,
     phantom: PhantomData<(I, S)>,
@@ -136,9 +148,9 @@ impl LlmpEventConverterBuilder {
         Ok(LlmpEventConverter {
             throttle: self.throttle,
             last_sent: Duration::from_secs(0),
-            llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: broker_compressor(&llmp, CompressionCodec::preferred())?,
+            llmp,
             converter,
             converter_back,
             phantom: PhantomData,
@@ -162,9 +174,9 @@ impl LlmpEventConverterBuilder {
         Ok(LlmpEventConverter {
             throttle: self.throttle,
             last_sent: Duration::from_secs(0),
-            llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: broker_compressor(&llmp, CompressionCodec::preferred())?,
+            llmp,
             converter,
             converter_back,
             phantom: PhantomData,
@@ -188,9 +200,9 @@ impl LlmpEventConverterBuilder {
         Ok(LlmpEventConverter {
             throttle: self.throttle,
             last_sent: Duration::from_secs(0),
-            llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: broker_compressor(&llmp, CompressionCodec::preferred())?,
+            llmp,
             converter,
             converter_back,
             phantom: PhantomData,
@@ -339,7 +351,7 @@ where
         // TODO: Get around local event copy by moving handle_in_client
         let self_id = self.llmp.sender().id();
         let mut count = 0;
-        while let Some((client_id, tag, _flags, msg)) = self.llmp.recv_buf_with_flags()? {
+        while let Some((client_id, tag, flags, msg)) = self.llmp.recv_buf_with_flags()? {
             assert_ne!(
                 tag, _LLMP_TAG_EVENT_TO_BROKER,
                 "EVENT_TO_BROKER parcel should not have arrived in the client!"
@@ -353,14 +365,18 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if _flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = self.compressor.decompress(msg)?;
+            let event_bytes = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+                compressed = self.compressor.decompress_with(
+                    flags.codec()?,
+                    flags.uses_dictionary(),
+                    msg,
+                )?;
                 &compressed
             } else {
                 msg
             };
 
-            let event: Event<DI> = postcard::from_bytes(event_bytes)?;
+            let event: Event<DI> = flags.format()?.deserialize(event_bytes)?;
             log::debug!("Processor received message {}", event.name_detailed());
             self.handle_in_client(fuzzer, executor, state, manager, client_id, event)?;
             count += 1;
@@ -422,11 +438,11 @@ where
         let serialized = postcard::to_allocvec(&converted_event)?;
         let flags = LLMP_FLAG_INITIALIZED;
 
-        match self.compressor.maybe_compress(&serialized) {
+        match self.compressor.maybe_compress(&serialized)? {
             Some(comp_buf) => {
                 self.llmp.send_buf_with_flags(
                     LLMP_TAG_EVENT_TO_BOTH,
-                    flags | LLMP_FLAG_COMPRESSED,
+                    flags | Flags::compressed_by(&self.compressor),
                     &comp_buf,
                 )?;
             }
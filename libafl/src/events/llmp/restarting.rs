@@ -14,7 +14,10 @@ use core::{
     time::Duration,
 };
 #[cfg(feature = "std")]
-use std::net::TcpStream;
+use std::{
+    net::TcpStream,
+    path::{Path, PathBuf},
+};
 
 #[cfg(any(windows, not(feature = "fork")))]
 use libafl_bolts::os::startable_self;
@@ -29,16 +32,21 @@ use libafl_bolts::{
 };
 #[cfg(feature = "llmp_compression")]
 use libafl_bolts::{
-    compress::GzipCompressor,
-    llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
+    compress::{
+        CodecCompressor, CompressionCodec, Compressor, DEFAULT_DICTIONARY_SIZE, DictionaryTrainer,
+        install_dictionary, installed_dictionary,
+    },
+    llmp::LLMP_FLAG_COMPRESSED,
 };
 use libafl_bolts::{
     core_affinity::CoreId,
     current_time,
     llmp::{
-        Broker, LLMP_FLAG_FROM_MM, LlmpBroker, LlmpClient, LlmpClientDescription, LlmpConnection,
+        Broker, Flags, LLMP_DEFAULT_BROKER_SLEEP_TIME, LLMP_FLAG_FROM_MM, LLMP_FLAG_INITIALIZED,
+        LlmpBroker, LlmpClient, LlmpClientDescription, LlmpConnection,
     },
     os::CTRL_C_EXIT,
+    serialization::SerializationFormat,
     shmem::{ShMem, ShMemProvider, StdShMem, StdShMemProvider},
     staterestore::StateRestorer,
     tuples::tuple_list,
@@ -46,25 +54,25 @@ use libafl_bolts::{
 use serde::{Serialize, de::DeserializeOwned};
 use typed_builder::TypedBuilder;
 
-#[cfg(feature = "llmp_compression")]
-use crate::events::COMPRESS_THRESHOLD;
 #[cfg(all(unix, not(miri)))]
 use crate::events::EVENTMGR_SIGHANDLER_STATE;
+#[cfg(feature = "llmp_compression")]
+use crate::events::llmp::broker_compressor;
 use crate::{
     Error,
     common::HasMetadata,
     events::{
-        _LLMP_TAG_EVENT_TO_BROKER, AwaitRestartSafe, Event, EventConfig, EventFirer,
-        EventManagerHooksTuple, EventManagerId, EventReceiver, EventRestarter, EventWithStats,
-        HasEventManagerId, LLMP_TAG_EVENT_TO_BOTH, LlmpShouldSaveState, ProgressReporter,
-        SendExiting, StdLlmpEventHook, launcher::ClientDescription, std_maybe_report_progress,
-        std_report_progress,
+        _LLMP_TAG_EVENT_TO_BROKER, AwaitRestartSafe, Event, EventCheckpointer, EventConfig,
+        EventFirer, EventManagerHooksTuple, EventManagerId, EventReceiver, EventRestarter,
+        EventWithStats, HasEventManagerId, LLMP_TAG_EVENT_TO_BOTH, LlmpShouldSaveState,
+        ProgressReporter, SendExiting, StdLlmpEventHook, launcher::ClientDescription,
+        set_client_rand_stream, std_checkpoint, std_maybe_report_progress, std_report_progress,
     },
     inputs::Input,
     monitors::Monitor,
     state::{
         HasCurrentStageId, HasCurrentTestcase, HasExecutions, HasImported, HasLastReportTime,
-        HasSolutions, MaybeHasClientPerfMonitor, Stoppable,
+        HasSolutions, MaybeHasClientPerfMonitor, Stoppable, read_checkpoint,
     },
 };
 
@@ -80,12 +88,14 @@ pub struct LlmpRestartingEventManager<EMH, I, S, SHM, SP> {
     /// The LLMP client for inter process communication
     llmp: LlmpClient<SHM, SP>,
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: CodecCompressor,
     /// The configuration defines this specific fuzzer.
     /// A node will not re-use the observer values sent over LLMP
     /// from nodes with other configurations.
     configuration: EventConfig,
     event_buffer: Vec<u8>,
+    /// The format events are serialized with
+    serialization_format: SerializationFormat,
     /// The staterestorer to serialize the state for the next runner
     /// If this is Some, this event manager can restart. Else it does not.
     staterestorer: Option<StateRestorer<SHM, SP>>,
@@ -122,47 +132,84 @@ where
     SP: ShMemProvider<ShMem = SHM>,
 {
     fn fire(&mut self, _state: &mut S, event: EventWithStats<I>) -> Result<(), Error> {
-        // Check if we are going to crash in the event, in which case we store our current state for the next runner
-        #[cfg(feature = "llmp_compression")]
-        let flags = LLMP_FLAG_INITIALIZED;
+        self.send_event(&event)
+    }
 
-        self.event_buffer.resize(self.event_buffer.capacity(), 0);
+    fn configuration(&self) -> EventConfig {
+        self.configuration
+    }
+
+    fn should_send(&self) -> bool {
+        if let Some(throttle) = self.throttle {
+            current_time() - self.last_sent > throttle
+        } else {
+            true
+        }
+    }
+}
 
-        // Serialize the event, reallocating event_buffer if needed
-        let written_len = match postcard::to_slice(&event, &mut self.event_buffer) {
-            Ok(written) => written.len(),
-            Err(postcard::Error::SerializeBufferFull) => {
-                let serialized = postcard::to_allocvec(&event)?;
-                self.event_buffer = serialized;
-                self.event_buffer.len()
+impl<EMH, I, S, SHM, SP> LlmpRestartingEventManager<EMH, I, S, SHM, SP>
+where
+    I: Serialize,
+    S: Serialize,
+    SHM: ShMem,
+    SP: ShMemProvider<ShMem = SHM>,
+{
+    /// Sends an event to the broker, without needing a state, e.g., before the fuzzer state is set up.
+    ///
+    /// Usually, events are sent using [`EventFirer::fire`].
+    pub fn send_event(&mut self, event: &EventWithStats<I>) -> Result<(), Error> {
+        // Check if we are going to crash in the event, in which case we store our current state for the next runner
+        let flags = LLMP_FLAG_INITIALIZED | Flags::serialized_with(self.serialization_format);
+
+        let written_len = if self.serialization_format == SerializationFormat::Postcard {
+            self.event_buffer.resize(self.event_buffer.capacity(), 0);
+
+            // Serialize the event, reallocating event_buffer if needed
+            match postcard::to_slice(event, &mut self.event_buffer) {
+                Ok(written) => written.len(),
+                Err(postcard::Error::SerializeBufferFull) => {
+                    let serialized = postcard::to_allocvec(event)?;
+                    self.event_buffer = serialized;
+                    self.event_buffer.len()
+                }
+                Err(e) => return Err(Error::from(e)),
             }
-            Err(e) => return Err(Error::from(e)),
+        } else {
+            self.event_buffer = self.serialization_format.serialize(event)?;
+            self.event_buffer.len()
         };
 
         #[cfg(feature = "llmp_compression")]
         {
             match self
                 .compressor
-                .maybe_compress(&self.event_buffer[..written_len])
+                .maybe_compress(&self.event_buffer[..written_len])?
             {
                 Some(comp_buf) => {
                     self.llmp.send_buf_with_flags(
                         LLMP_TAG_EVENT_TO_BOTH,
-                        flags | LLMP_FLAG_COMPRESSED,
+                        flags | Flags::compressed_by(&self.compressor),
                         &comp_buf,
                     )?;
                 }
                 None => {
-                    self.llmp
-                        .send_buf(LLMP_TAG_EVENT_TO_BOTH, &self.event_buffer[..written_len])?;
+                    self.llmp.send_buf_with_flags(
+                        LLMP_TAG_EVENT_TO_BOTH,
+                        flags,
+                        &self.event_buffer[..written_len],
+                    )?;
                 }
             }
         }
 
         #[cfg(not(feature = "llmp_compression"))]
         {
-            self.llmp
-                .send_buf(LLMP_TAG_EVENT_TO_BOTH, &self.event_buffer[..written_len])?;
+            self.llmp.send_buf_with_flags(
+                LLMP_TAG_EVENT_TO_BOTH,
+                flags,
+                &self.event_buffer[..written_len],
+            )?;
         }
 
         self.last_sent = current_time();
@@ -172,18 +219,6 @@ where
         }
         Ok(())
     }
-
-    fn configuration(&self) -> EventConfig {
-        self.configuration
-    }
-
-    fn should_send(&self) -> bool {
-        if let Some(throttle) = self.throttle {
-            current_time() - self.last_sent > throttle
-        } else {
-            true
-        }
-    }
 }
 
 impl<EMH, I, S, SHM, SP> EventRestarter<S> for LlmpRestartingEventManager<EMH, I, S, SHM, SP>
@@ -216,6 +251,17 @@ where
     }
 }
 
+impl<EMH, I, S, SHM, SP> EventCheckpointer<S> for LlmpRestartingEventManager<EMH, I, S, SHM, SP>
+where
+    S: Serialize,
+    SHM: ShMem,
+{
+    /// Waits until the broker mapped all pages we sent, then writes the state to `path`.
+    fn checkpoint(&mut self, state: &S, path: &Path) -> Result<(), Error> {
+        std_checkpoint(self, state, path)
+    }
+}
+
 impl<EMH, I, S, SHM, SP> SendExiting for LlmpRestartingEventManager<EMH, I, S, SHM, SP>
 where
     SHM: ShMem,
@@ -274,13 +320,17 @@ where
             let compressed;
             #[cfg(feature = "llmp_compression")]
             let event_bytes = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = self.compressor.decompress(msg)?;
+                compressed = self.compressor.decompress_with(
+                    flags.codec()?,
+                    flags.uses_dictionary(),
+                    msg,
+                )?;
                 &compressed
             } else {
                 msg
             };
 
-            let event: EventWithStats<I> = postcard::from_bytes(event_bytes)?;
+            let event: EventWithStats<I> = flags.format()?.deserialize(event_bytes)?;
             log::debug!(
                 "Received event in normal llmp {}",
                 event.event().name_detailed()
@@ -366,11 +416,53 @@ const _ENV_FUZZER_RECEIVER: &str = "_AFL_ENV_FUZZER_RECEIVER";
 /// The llmp (2 way) connection from a fuzzer to the broker (broadcasting all other fuzzer messages)
 const _ENV_FUZZER_BROKER_CLIENT_INITIAL: &str = "_AFL_ENV_FUZZER_BROKER_CLIENT";
 
+/// If the first incarnation of a client installed a compression dictionary, for its respawns
+#[cfg(feature = "llmp_compression")]
+const _ENV_COMPRESSION_DICTIONARY: &str = "_AFL_ENV_COMPRESSION_DICTIONARY";
+
+/// Installs the compression dictionary at `path`, if an earlier campaign trained it.
+/// Returns if a dictionary is installed.
+///
+/// Respawned clients stick to the decision of their first incarnation,
+/// so they keep agreeing with the rest of the campaign even if the broker saved a dictionary in the meantime.
+///
+/// # Safety
+/// Sets an environment variable; may not be called while other threads read the environment.
+#[cfg(feature = "llmp_compression")]
+unsafe fn install_compression_dictionary(path: &Path) -> Result<bool, Error> {
+    let install = match std::env::var(_ENV_COMPRESSION_DICTIONARY) {
+        Ok(installed) => installed == "1",
+        Err(_) => path.exists(),
+    };
+    unsafe {
+        std::env::set_var(_ENV_COMPRESSION_DICTIONARY, if install { "1" } else { "0" });
+    }
+    if !install {
+        return Ok(false);
+    }
+
+    let dictionary = std::fs::read(path)?;
+    match installed_dictionary() {
+        Some(installed) if installed == dictionary.as_slice() => {}
+        Some(_) => {
+            return Err(Error::illegal_state(format!(
+                "Another compression dictionary than {} is installed already",
+                path.display()
+            )));
+        }
+        None => install_dictionary(dictionary)?,
+    }
+    Ok(true)
+}
+
 /// Builder for `LlmpRestartingEventManager`
 #[derive(Debug)]
 pub struct LlmpEventManagerBuilder<EMH> {
     throttle: Option<Duration>,
     save_state: LlmpShouldSaveState,
+    #[cfg(feature = "llmp_compression")]
+    compression_codec: CompressionCodec,
+    serialization_format: SerializationFormat,
     hooks: EMH,
 }
 
@@ -387,6 +479,9 @@ impl LlmpEventManagerBuilder<()> {
         Self {
             throttle: None,
             save_state: LlmpShouldSaveState::OnRestart,
+            #[cfg(feature = "llmp_compression")]
+            compression_codec: CompressionCodec::preferred(),
+            serialization_format: SerializationFormat::default(),
             hooks: (),
         }
     }
@@ -398,6 +493,9 @@ impl LlmpEventManagerBuilder<()> {
         LlmpEventManagerBuilder {
             throttle: self.throttle,
             save_state: self.save_state,
+            #[cfg(feature = "llmp_compression")]
+            compression_codec: self.compression_codec,
+            serialization_format: self.serialization_format,
             hooks,
         }
     }
@@ -418,6 +516,23 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
         self
     }
 
+    /// The compression codec to prefer for messages to the broker.
+    /// Falls back to the best codec both sides support, if the broker does not support it.
+    #[cfg(feature = "llmp_compression")]
+    #[must_use]
+    pub fn compression_codec(mut self, compression_codec: CompressionCodec) -> Self {
+        self.compression_codec = compression_codec;
+        self
+    }
+
+    /// The format to serialize events with.
+    /// All clients connected to the broker need to be able to deserialize this format.
+    #[must_use]
+    pub fn serialization_format(mut self, serialization_format: SerializationFormat) -> Self {
+        self.serialization_format = serialization_format;
+        self
+    }
+
     /// Create a manager from a raw LLMP client
     /// If staterestorer is some then this restarting manager restarts
     /// Otherwise this restarting manager does not restart
@@ -431,11 +546,12 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
             throttle: self.throttle,
             last_sent: Duration::from_secs(0),
             hooks: self.hooks,
-            llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: broker_compressor(&llmp, self.compression_codec)?,
+            llmp,
             configuration,
             event_buffer: Vec::with_capacity(INITIAL_EVENT_BUFFER_SIZE),
+            serialization_format: self.serialization_format,
             staterestorer,
             save_state: LlmpShouldSaveState::OnRestart,
             phantom: PhantomData,
@@ -677,6 +793,11 @@ pub struct RestartingMgr<EMH, I, MT, S, SP> {
     /// The address to connect to
     #[builder(default = None)]
     remote_broker_addr: Option<SocketAddr>,
+    /// The pre-shared key all brokers of the campaign authenticate each other with.
+    /// If set, the broker 2 broker traffic gets encrypted, see `LlmpBrokerInner::set_b2b_psk`.
+    #[cfg(feature = "llmp_noise")]
+    #[builder(default = None)]
+    b2b_psk: Option<[u8; 32]>,
     /// The type of manager to build
     #[builder(default = ManagerKind::Any)]
     kind: ManagerKind,
@@ -691,6 +812,29 @@ pub struct RestartingMgr<EMH, I, MT, S, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// The format to serialize the state and events with.
+    /// Formats other than the default `postcard` need their feature, e.g., `serialization_bincode`.
+    #[builder(default)]
+    serialization_format: SerializationFormat,
+    /// How often a client gets respawned after its fuzzer process exited.
+    /// If `None`, it gets respawned forever.
+    #[builder(default = None)]
+    max_respawns: Option<u64>,
+    /// The master seed of a deterministic campaign.
+    /// If set, each spawned client fuzzes with the [`client_rand_stream`](crate::events::client_rand_stream)
+    /// of this seed, its client id, and how often it was respawned.
+    #[builder(default = None)]
+    master_seed: Option<u64>,
+    /// A checkpoint written by [`EventCheckpointer::checkpoint`].
+    /// If it exists when the client starts for the first time, the state is resumed from it.
+    #[builder(default = None)]
+    checkpoint: Option<PathBuf>,
+    /// The compression dictionary of the campaign.
+    /// If the file exists, every process installs it before compressing anything.
+    /// Else, the broker trains a dictionary from the testcases it sees, and saves it there for the next campaign.
+    #[cfg(feature = "llmp_compression")]
+    #[builder(default = None)]
+    compression_dictionary: Option<PathBuf>,
     /// The hooks passed to event manager:
     hooks: EMH,
     #[builder(setter(skip), default = PhantomData)]
@@ -716,11 +860,23 @@ where
         ),
         Error,
     > {
+        // Without a dictionary yet, the broker trains one for the next campaign
+        #[cfg(feature = "llmp_compression")]
+        let dictionary_training = match &self.compression_dictionary {
+            // # Safety
+            // No other threads are running yet.
+            Some(path) if !unsafe { install_compression_dictionary(path)? } => Some(path.clone()),
+            _ => None,
+        };
+
         // We start ourselves as child process to actually fuzz
         let (staterestorer, new_shmem_provider, core_id) = if std::env::var(_ENV_FUZZER_SENDER)
             .is_err()
         {
             let broker_things = |mut broker: LlmpBroker<_, SP::ShMem, SP>, remote_broker_addr| {
+                #[cfg(feature = "llmp_noise")]
+                broker.inner_mut().set_b2b_psk(self.b2b_psk);
+
                 if let Some(remote_broker_addr) = remote_broker_addr {
                     log::info!("B2b: Connecting to {:?}", &remote_broker_addr);
                     broker.inner_mut().connect_b2b(remote_broker_addr)?;
@@ -730,7 +886,10 @@ where
                     broker.set_exit_after(exit_cleanly_after);
                 }
 
-                broker.loop_with_timeouts(Duration::from_secs(30), Some(Duration::from_millis(5)));
+                broker.loop_with_timeouts(
+                    Duration::from_secs(30),
+                    Some(LLMP_DEFAULT_BROKER_SLEEP_TIME),
+                );
 
                 #[cfg(feature = "llmp_debug")]
                 log::info!("The last client quit. Exiting.");
@@ -746,6 +905,14 @@ where
                         LlmpConnection::IsBroker { broker } => {
                             let llmp_hook =
                                 StdLlmpEventHook::<I, MT>::new(self.monitor.take().unwrap())?;
+                            #[cfg(feature = "llmp_compression")]
+                            let llmp_hook = match dictionary_training.clone() {
+                                Some(path) => llmp_hook.with_dictionary_training(
+                                    DictionaryTrainer::new(DEFAULT_DICTIONARY_SIZE),
+                                    path,
+                                ),
+                                None => llmp_hook,
+                            };
 
                             // Yep, broker. Just loop here.
                             log::info!(
@@ -770,6 +937,14 @@ where
                 }
                 ManagerKind::Broker => {
                     let llmp_hook = StdLlmpEventHook::new(self.monitor.take().unwrap())?;
+                    #[cfg(feature = "llmp_compression")]
+                    let llmp_hook = match dictionary_training.clone() {
+                        Some(path) => llmp_hook.with_dictionary_training(
+                            DictionaryTrainer::new(DEFAULT_DICTIONARY_SIZE),
+                            path,
+                        ),
+                        None => llmp_hook,
+                    };
 
                     let broker = LlmpBroker::create_attach_to_tcp(
                         self.shmem_provider.clone(),
@@ -813,11 +988,13 @@ where
             // First, create a channel from the current fuzzer to the next to store state between restarts.
             #[cfg(unix)]
             let staterestorer: StateRestorer<SP::ShMem, SP> =
-                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?);
+                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?)
+                    .with_format(self.serialization_format)?;
 
             #[cfg(not(unix))]
             let staterestorer: StateRestorer<SP::ShMem, SP> =
-                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?);
+                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?)
+                    .with_format(self.serialization_format)?;
 
             // Store the information to a map.
             // # Safety
@@ -826,11 +1003,24 @@ where
                 staterestorer.write_to_env(_ENV_FUZZER_SENDER)?;
             }
 
+            let rand_stream = match &self.kind {
+                ManagerKind::Client { client_description } => client_description.id() as u64,
+                _ => 0,
+            };
+
             let mut ctr: u64 = 0;
             // Client->parent loop
             loop {
                 log::info!("Spawning next client (id {ctr})");
 
+                if let Some(master_seed) = self.master_seed {
+                    // # Safety
+                    // The respawner is single threaded.
+                    unsafe {
+                        set_client_rand_stream(master_seed, rand_stream, ctr);
+                    }
+                }
+
                 // On Unix, we fork (when fork feature is enabled)
                 #[cfg(all(unix, feature = "fork"))]
                 let child_status = {
@@ -884,6 +1074,17 @@ where
                     return Err(Error::shutting_down());
                 }
 
+                if self
+                    .max_respawns
+                    .is_some_and(|max_respawns| ctr >= max_respawns)
+                {
+                    log::info!("Fuzzer exited with {child_status}, not respawning it again");
+                    if let Err(err) = mgr.detach_from_broker(self.broker_port) {
+                        log::error!("Failed to detach from broker: {err}");
+                    }
+                    return Err(Error::shutting_down());
+                }
+
                 if !staterestorer.has_content() && !self.serialize_state.oom_safe() {
                     if let Err(err) = mgr.detach_from_broker(self.broker_port) {
                         log::error!("Failed to detach from broker: {err}");
@@ -933,6 +1134,7 @@ where
                     LlmpEventManagerBuilder::builder()
                         .hooks(self.hooks)
                         .save_state(self.serialize_state)
+                        .serialization_format(self.serialization_format)
                         .build_existing_client_from_description(
                             new_shmem_provider,
                             &mgr_description,
@@ -942,12 +1144,20 @@ where
                 )
             } else {
                 log::info!("First run. Let's set it all up");
+                let state = match &self.checkpoint {
+                    Some(checkpoint) if checkpoint.exists() => {
+                        log::info!("Resuming from checkpoint {}", checkpoint.display());
+                        Some(read_checkpoint(checkpoint)?)
+                    }
+                    _ => None,
+                };
                 // Mgr to send and receive msgs from/to all other fuzzer instances
                 (
-                    None,
+                    state,
                     LlmpEventManagerBuilder::builder()
                         .hooks(self.hooks)
                         .save_state(self.serialize_state)
+                        .serialization_format(self.serialization_format)
                         .build_existing_client_from_env(
                             new_shmem_provider,
                             _ENV_FUZZER_BROKER_CLIENT_INITIAL,
@@ -28,6 +28,8 @@ use core::{
     marker::PhantomData,
     time::Duration,
 };
+#[cfg(feature = "std")]
+use std::path::Path;
 
 use ahash::RandomState;
 pub use broker_hooks::*;
@@ -38,6 +40,8 @@ use libafl_bolts::current_time;
 use libafl_bolts::os::CTRL_C_EXIT;
 #[cfg(all(unix, feature = "std"))]
 use libafl_bolts::os::unix_signals::{Signal, SignalHandler, siginfo_t, ucontext_t};
+#[cfg(feature = "std")]
+use libafl_bolts::rands::{Rand, Xoshiro256PlusPlusRand, random_seed};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use uuid::Uuid;
@@ -47,6 +51,7 @@ use crate::{
     executors::ExitKind,
     inputs::Input,
     monitors::stats::UserStats,
+    observers::ObjectiveSignature,
     state::{HasExecutions, HasLastReportTime, MaybeHasClientPerfMonitor},
 };
 
@@ -107,6 +112,8 @@ use crate::events::multi_machine::NodeId;
 #[cfg(feature = "introspection")]
 use crate::monitors::stats::ClientPerfStats;
 use crate::state::HasCurrentStageId;
+#[cfg(feature = "std")]
+use crate::state::write_checkpoint;
 
 /// The log event severity
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
@@ -346,6 +353,8 @@ pub enum Event<I> {
         input: Option<I>,
         /// Objective corpus size
         objective_size: usize,
+        /// The signature of the objective, to deduplicate crashes
+        signature: ObjectiveSignature,
     },
     /// Write a new log
     Log {
@@ -579,6 +588,79 @@ where
     Ok(())
 }
 
+/// Event managers that can write a checkpoint of the state to disk,
+/// so that a campaign can be resumed after a full restart of all fuzzer processes.
+#[cfg(feature = "std")]
+pub trait EventCheckpointer<S> {
+    /// Wait until all events sent so far are safe, then write the state to `path`.
+    /// The state itself is left untouched, so the fuzzer can go on right away.
+    /// Resume with [`StdState::from_checkpoint`](crate::state::StdState::from_checkpoint).
+    /// [`std_checkpoint`] is the standard implementation that you can call.
+    fn checkpoint(&mut self, state: &S, path: &Path) -> Result<(), Error>;
+}
+
+/// Default implementation of [`EventCheckpointer::checkpoint`] for implementors with the given
+/// constraints
+#[cfg(feature = "std")]
+pub fn std_checkpoint<EM, S>(checkpointer: &mut EM, state: &S, path: &Path) -> Result<(), Error>
+where
+    EM: EventCheckpointer<S> + AwaitRestartSafe,
+    S: Serialize,
+{
+    checkpointer.await_restart_safe();
+    write_checkpoint(state, path)
+}
+
+/// The environment variable the restarting managers pass the seed of each client in
+#[cfg(feature = "std")]
+const _ENV_RAND_SEED: &str = "_AFL_ENV_RAND_SEED";
+
+/// Sets the random stream the next client (and its respawns) will get from [`client_rand_stream`]:
+/// the campaign's `master_seed`, the client's `stream` and its `restart` count.
+///
+/// # Safety
+/// Sets an environment variable; may not be called while other threads read the environment.
+#[cfg(feature = "std")]
+pub(crate) unsafe fn set_client_rand_stream(master_seed: u64, stream: u64, restart: u64) {
+    unsafe {
+        std::env::set_var(_ENV_RAND_SEED, format!("{master_seed}:{stream}:{restart}"));
+    }
+}
+
+/// The master seed, stream and sub-stream of this client in a deterministic campaign,
+/// i.e., if a `master_seed` was given to the [`Launcher`] or the restarting manager.
+///
+/// The stream is the client id, the sub-stream the number of times the client was respawned,
+/// see [`Xoshiro256PlusPlusRand::with_stream`].
+/// [`StdState::new`](crate::state::StdState::new) continues its rand with this stream, through [`Rand::set_stream`].
+#[cfg(feature = "std")]
+#[must_use]
+pub fn client_rand_stream() -> Option<(u64, u64, u64)> {
+    let value = std::env::var(_ENV_RAND_SEED).ok()?;
+    let mut parts = value.split(':').map(str::parse);
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(master_seed)), Some(Ok(stream)), Some(Ok(restart)), None) => {
+            Some((master_seed, stream, restart))
+        }
+        _ => None,
+    }
+}
+
+/// The rand this client should fuzz with.
+///
+/// In a deterministic campaign, this is the jumped [`Xoshiro256PlusPlusRand`] stream of [`client_rand_stream`],
+/// so that the campaign can be reproduced and the streams of the clients never overlap.
+/// Otherwise, it is seeded with a fresh [`random_seed`].
+#[cfg(feature = "std")]
+#[must_use]
+pub fn client_rand() -> Xoshiro256PlusPlusRand {
+    let mut rand = Xoshiro256PlusPlusRand::with_seed(random_seed());
+    if let Some((master_seed, stream, restart)) = client_rand_stream() {
+        rand.set_stream(master_seed, stream, restart);
+    }
+    rand
+}
+
 /// Send that we're about to exit
 pub trait SendExiting {
     /// Send information that this client is exiting.
@@ -1,26 +1,34 @@
 use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use core::{
     fmt::Display,
+    net::{IpAddr, SocketAddr},
     sync::atomic::{AtomicU64, Ordering},
     time::Duration,
 };
 use std::{collections::HashMap, io::ErrorKind, process, sync::OnceLock};
 
 use enumflags2::{BitFlags, bitflags};
+use libafl_bolts::{
+    Error, current_time, llmp::Flags, ownedref::OwnedRef, serialization::SerializationFormat,
+};
 #[cfg(feature = "llmp_compression")]
-use libafl_bolts::compress::GzipCompressor;
-use libafl_bolts::{Error, current_time, ownedref::OwnedRef};
+use libafl_bolts::{
+    compress::{CodecCompressor, CompressionCodec},
+    llmp::transcode_for_peer,
+};
 use serde::{Deserialize, Serialize};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::{TcpListener, TcpStream, ToSocketAddrs},
     runtime::Runtime,
-    sync::RwLock,
+    sync::{RwLock, RwLockWriteGuard},
     task::JoinHandle,
     time,
 };
 use typed_builder::TypedBuilder;
 
+#[cfg(feature = "llmp_compression")]
+use crate::events::llmp::COMPRESS_THRESHOLD;
 use crate::{
     events::{EventWithStats, TcpMultiMachineLlmpReceiverHook, TcpMultiMachineLlmpSenderHook},
     inputs::{Input, NopInput},
@@ -41,13 +49,142 @@ pub enum NodePolicy {
 
 const DUMMY_BYTE: u8 = 0x14;
 
+/// The Noise protocol pattern, if a pre-shared key is used
+#[cfg(feature = "multi_machine_noise")]
+const NOISE_PATTERN_PSK: &str = "Noise_XXpsk3_25519_ChaChaPoly_BLAKE2s";
+/// The Noise protocol pattern, if peers are authenticated by their static keys only
+#[cfg(feature = "multi_machine_noise")]
+const NOISE_PATTERN: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+/// The maximum length of a single Noise message
+#[cfg(feature = "multi_machine_noise")]
+const NOISE_MAX_MSG_LEN: usize = 65535;
+/// The length of the authentication tag of each encrypted Noise message
+#[cfg(feature = "multi_machine_noise")]
+const NOISE_TAG_LEN: usize = 16;
+
+/// The security of the connections between nodes
+#[derive(Debug, Clone, Default)]
+pub enum TransportSecurity {
+    /// No authentication and no encryption. Only use this on trusted networks.
+    #[default]
+    Plaintext,
+    /// Authenticated and encrypted connections, using the Noise protocol
+    #[cfg(feature = "multi_machine_noise")]
+    Noise(NoiseConfig),
+}
+
+/// The configuration of the Noise protocol transport.
+///
+/// Each node has a static X25519 keypair. Peers authenticate using a pre-shared key,
+/// and/or by the public key of their keypair, which has to be on the allowlist.
+#[cfg(feature = "multi_machine_noise")]
+#[derive(Clone)]
+pub struct NoiseConfig {
+    private_key: Vec<u8>,
+    public_key: Vec<u8>,
+    psk: Option<[u8; 32]>,
+    allowed_keys: Option<Vec<Vec<u8>>>,
+}
+
+#[cfg(feature = "multi_machine_noise")]
+impl core::fmt::Debug for NoiseConfig {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // Never log the secrets
+        f.debug_struct("NoiseConfig")
+            .field("public_key", &self.public_key)
+            .field("psk", &self.psk.map(|_| "<redacted>"))
+            .field("allowed_keys", &self.allowed_keys)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "multi_machine_noise")]
+impl NoiseConfig {
+    /// Creates a new [`NoiseConfig`] from the static keypair of this node.
+    ///
+    /// Without a call to [`Self::with_psk`] or [`Self::with_allowed_keys`], connecting will fail,
+    /// as the peers could not be authenticated.
+    #[must_use]
+    pub fn new(private_key: Vec<u8>, public_key: Vec<u8>) -> Self {
+        Self {
+            private_key,
+            public_key,
+            psk: None,
+            allowed_keys: None,
+        }
+    }
+
+    /// Generates a new static keypair, returned as `(private_key, public_key)`
+    pub fn generate_keypair() -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let keypair = snow::Builder::new(Self::pattern(false).parse().map_err(noise_error)?)
+            .generate_keypair()
+            .map_err(noise_error)?;
+        Ok((keypair.private, keypair.public))
+    }
+
+    /// Authenticate peers with a pre-shared key, all nodes of the campaign need to share it
+    #[must_use]
+    pub fn with_psk(mut self, psk: [u8; 32]) -> Self {
+        self.psk = Some(psk);
+        self
+    }
+
+    /// Only accept peers with one of the given static public keys
+    #[must_use]
+    pub fn with_allowed_keys(mut self, allowed_keys: Vec<Vec<u8>>) -> Self {
+        self.allowed_keys = Some(allowed_keys);
+        self
+    }
+
+    /// The static public key of this node, to be put on the allowlist of other nodes
+    #[must_use]
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn pattern(psk: bool) -> &'static str {
+        if psk {
+            NOISE_PATTERN_PSK
+        } else {
+            NOISE_PATTERN
+        }
+    }
+
+    fn handshake_state(&self, initiator: bool) -> Result<snow::HandshakeState, Error> {
+        if self.psk.is_none() && self.allowed_keys.is_none() {
+            return Err(Error::illegal_argument(
+                "The Noise transport needs a pre-shared key or an allowlist of public keys to authenticate peers",
+            ));
+        }
+        let params = Self::pattern(self.psk.is_some())
+            .parse()
+            .map_err(noise_error)?;
+        let mut builder = snow::Builder::new(params).local_private_key(&self.private_key);
+        if let Some(psk) = &self.psk {
+            builder = builder.psk(3, psk);
+        }
+        if initiator {
+            builder.build_initiator()
+        } else {
+            builder.build_responder()
+        }
+        .map_err(noise_error)
+    }
+}
+
+#[cfg(feature = "multi_machine_noise")]
+#[expect(clippy::needless_pass_by_value)] // for `map_err`
+fn noise_error(e: snow::Error) -> Error {
+    Error::illegal_state(format!("Noise protocol error: {e:?}"))
+}
+
 /// Use `OwnedRef` as much as possible here to avoid useless copies.
 /// An owned TCP message for multi machine
 #[derive(Clone, Debug)]
 // #[serde(bound = "I: serde::de::DeserializeOwned")]
 pub enum MultiMachineMsg<'a, I> {
-    /// A raw llmp message (not deserialized)
-    LlmpMsg(OwnedRef<'a, [u8]>),
+    /// A raw llmp message (not deserialized), and its llmp flags
+    LlmpMsg(Flags, OwnedRef<'a, [u8]>),
 
     /// A `LibAFL` Event (already deserialized)
     Event(OwnedRef<'a, EventWithStats<I>>),
@@ -71,27 +208,46 @@ impl<'a, I> MultiMachineMsg<'a, I> {
         MultiMachineMsg::Event(event)
     }
 
-    /// Create a new [`MultiMachineMsg`] from an llmp msg.
+    /// Create a new [`MultiMachineMsg`] from an llmp msg, and its flags.
     #[must_use]
-    pub fn llmp_msg(msg: OwnedRef<'a, [u8]>) -> Self {
-        MultiMachineMsg::LlmpMsg(msg)
+    pub fn llmp_msg(flags: Flags, msg: OwnedRef<'a, [u8]>) -> Self {
+        MultiMachineMsg::LlmpMsg(flags, msg)
     }
 
     /// Get the message
     #[must_use]
     pub fn serialize_as_ref(&self) -> &[u8] {
         match self {
-            MultiMachineMsg::LlmpMsg(msg) => msg.as_ref(),
+            MultiMachineMsg::LlmpMsg(_, msg) => msg.as_ref(),
             MultiMachineMsg::Event(_) => {
                 panic!("Not supported")
             }
         }
     }
 
+    /// The llmp flags of the message, telling how it is compressed
+    #[must_use]
+    pub fn flags(&self) -> Flags {
+        match self {
+            MultiMachineMsg::LlmpMsg(flags, _) => *flags,
+            MultiMachineMsg::Event(_) => Flags(0),
+        }
+    }
+
     /// To owned message
     #[must_use]
-    pub fn from_llmp_msg(msg: Box<[u8]>) -> MultiMachineMsg<'a, I> {
-        MultiMachineMsg::LlmpMsg(OwnedRef::Owned(msg))
+    pub fn from_llmp_msg(flags: Flags, msg: Box<[u8]>) -> MultiMachineMsg<'a, I> {
+        MultiMachineMsg::LlmpMsg(flags, OwnedRef::Owned(msg))
+    }
+
+    /// Parse a message as sent over the wire: its flags, followed by the llmp message
+    fn from_wire(mut msg: Vec<u8>) -> Result<MultiMachineMsg<'a, I>, Error> {
+        let flags = msg
+            .first_chunk::<4>()
+            .map(|flags| Flags(u32::from_le_bytes(*flags)))
+            .ok_or_else(|| Error::illegal_state("Received a truncated message from a node"))?;
+        msg.drain(..4);
+        Ok(Self::from_llmp_msg(flags, msg.into_boxed_slice()))
     }
 }
 
@@ -113,12 +269,14 @@ impl NodeId {
 pub struct TcpMultiMachineState<A> {
     node_descriptor: NodeDescriptor<A>,
     /// the parent to which the testcases should be forwarded when deemed interesting
-    parent: Option<TcpStream>,
+    parent: Option<NodeConnection>,
     /// The children who connected during the fuzzing session.
-    children: HashMap<NodeId, TcpStream>, // The children who connected during the fuzzing session.
-    old_msgs: Vec<Vec<u8>>,
+    children: HashMap<NodeId, NodeConnection>, // The children who connected during the fuzzing session.
+    old_msgs: Vec<(Flags, Vec<u8>)>,
+    /// The format events we received from other nodes get serialized with, before they are passed on to our clients
+    serialization_format: SerializationFormat,
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: CodecCompressor,
 }
 
 /// The tree descriptor for the
@@ -138,6 +296,271 @@ pub struct NodeDescriptor<A> {
     /// Node flags
     #[builder(default_code = "BitFlags::default()")]
     pub flags: BitFlags<NodePolicy>, // The policy for shared messages between nodes.
+
+    /// The security of the connections to the parent and the children
+    #[builder(default)]
+    pub security: TransportSecurity,
+
+    /// If set, children connecting from other addresses are refused
+    #[builder(default)]
+    pub allowed_addrs: Option<Vec<IpAddr>>,
+
+    /// Other nodes to re-parent to if the parent is unreachable, usually siblings or grandparents.
+    /// They are tried in order, after the parent.
+    #[builder(default)]
+    pub fallback_parents: Vec<A>,
+
+    /// The initial delay before reconnecting to a lost parent, doubled after each failed attempt.
+    /// If `None`, a lost parent is never reconnected. Defaults to 1 second.
+    #[builder(default = Some(Duration::from_secs(1)))]
+    pub reconnect_backoff: Option<Duration>,
+
+    /// The maximum delay between two attempts to reconnect to a parent. Defaults to 60 seconds.
+    #[builder(default = Duration::from_secs(60))]
+    pub max_reconnect_backoff: Duration,
+}
+
+impl<A> NodeDescriptor<A> {
+    /// If a child connecting from `addr` may join
+    #[must_use]
+    pub fn is_allowed_addr(&self, addr: &SocketAddr) -> bool {
+        self.allowed_addrs.as_ref().is_none_or(|allowed| {
+            allowed
+                .iter()
+                .any(|allowed| allowed.to_canonical() == addr.ip().to_canonical())
+        })
+    }
+
+    /// The nodes we may connect to as parent, in order of preference
+    pub fn parent_candidates(&self) -> impl Iterator<Item = &A> {
+        self.parent_addr.iter().chain(&self.fallback_parents)
+    }
+}
+
+/// A connection to another node
+enum NodeStream {
+    /// A plaintext connection
+    Plain(TcpStream),
+    /// A connection using the Noise protocol
+    #[cfg(feature = "multi_machine_noise")]
+    Noise(Box<NoiseStream>),
+}
+
+impl core::fmt::Debug for NodeStream {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Plain(stream) => f.debug_tuple("Plain").field(stream).finish(),
+            #[cfg(feature = "multi_machine_noise")]
+            Self::Noise(stream) => f.debug_tuple("Noise").field(&stream.stream).finish(),
+        }
+    }
+}
+
+impl NodeStream {
+    /// Waits until the node sent something
+    async fn readable(&self) -> Result<(), Error> {
+        match self {
+            Self::Plain(stream) => stream.readable().await?,
+            #[cfg(feature = "multi_machine_noise")]
+            Self::Noise(noise) => noise.stream.readable().await?,
+        }
+        Ok(())
+    }
+
+    /// Sets up the connection to our parent
+    #[cfg_attr(not(feature = "multi_machine_noise"), expect(clippy::unused_async))]
+    async fn connect(stream: TcpStream, security: &TransportSecurity) -> Result<Self, Error> {
+        match security {
+            TransportSecurity::Plaintext => Ok(Self::Plain(stream)),
+            #[cfg(feature = "multi_machine_noise")]
+            TransportSecurity::Noise(config) => Ok(Self::Noise(Box::new(
+                NoiseStream::handshake(stream, config, true).await?,
+            ))),
+        }
+    }
+
+    /// Sets up the connection from a new child
+    #[cfg_attr(not(feature = "multi_machine_noise"), expect(clippy::unused_async))]
+    async fn accept(stream: TcpStream, security: &TransportSecurity) -> Result<Self, Error> {
+        match security {
+            TransportSecurity::Plaintext => Ok(Self::Plain(stream)),
+            #[cfg(feature = "multi_machine_noise")]
+            TransportSecurity::Noise(config) => Ok(Self::Noise(Box::new(
+                NoiseStream::handshake(stream, config, false).await?,
+            ))),
+        }
+    }
+}
+
+/// A connection to another node, with the compression codecs it can decompress
+#[derive(Debug)]
+struct NodeConnection {
+    stream: NodeStream,
+    /// The codecs announced by the node, compressed messages get transcoded to one of them
+    #[cfg(feature = "llmp_compression")]
+    codecs: Vec<CompressionCodec>,
+}
+
+impl NodeConnection {
+    /// Recompresses a message with `compressor`, if the node cannot decompress the codec of the message.
+    /// Returns `None` if the message can be sent as-is.
+    #[cfg_attr(
+        not(feature = "llmp_compression"),
+        expect(clippy::unused_self, clippy::unnecessary_wraps, unused_variables)
+    )]
+    fn transcode(
+        &self,
+        #[cfg(feature = "llmp_compression")] compressor: &CodecCompressor,
+        flags: Flags,
+        msg: &[u8],
+    ) -> Result<Option<(Flags, Vec<u8>)>, Error> {
+        #[cfg(feature = "llmp_compression")]
+        {
+            transcode_for_peer(compressor, &self.codecs, flags, msg)
+        }
+        #[cfg(not(feature = "llmp_compression"))]
+        {
+            Ok(None)
+        }
+    }
+}
+
+/// A connection encrypted with the Noise protocol.
+///
+/// Each message is sent as its `u32` length, followed by the message, split into encrypted Noise messages.
+/// Each Noise message is prefixed by its `u16` length.
+#[cfg(feature = "multi_machine_noise")]
+struct NoiseStream {
+    stream: TcpStream,
+    transport: snow::TransportState,
+    /// Received bytes that do not make up a full Noise message yet
+    encrypted: Vec<u8>,
+    /// Decrypted bytes that do not make up a full message yet
+    decrypted: Vec<u8>,
+}
+
+#[cfg(feature = "multi_machine_noise")]
+impl NoiseStream {
+    async fn send_frame(stream: &mut TcpStream, frame: &[u8]) -> Result<(), Error> {
+        let len = u16::try_from(frame.len())
+            .map_err(|_| Error::illegal_argument("Noise message too long"))?;
+        stream.write_all(&len.to_be_bytes()).await?;
+        stream.write_all(frame).await?;
+        Ok(())
+    }
+
+    async fn recv_frame(stream: &mut TcpStream) -> Result<Vec<u8>, Error> {
+        let mut len = [0; 2];
+        stream.read_exact(&mut len).await?;
+        let mut frame = vec![0; u16::from_be_bytes(len).into()];
+        stream.read_exact(&mut frame).await?;
+        Ok(frame)
+    }
+
+    /// Runs the Noise `XX` handshake, and checks the static key of the peer against the allowlist
+    async fn handshake(
+        mut stream: TcpStream,
+        config: &NoiseConfig,
+        initiator: bool,
+    ) -> Result<Self, Error> {
+        let mut handshake = config.handshake_state(initiator)?;
+        let mut buf = vec![0; NOISE_MAX_MSG_LEN];
+
+        while !handshake.is_handshake_finished() {
+            if handshake.is_my_turn() {
+                let len = handshake
+                    .write_message(&[], &mut buf)
+                    .map_err(noise_error)?;
+                Self::send_frame(&mut stream, &buf[..len]).await?;
+            } else {
+                let frame = Self::recv_frame(&mut stream).await?;
+                handshake
+                    .read_message(&frame, &mut buf)
+                    .map_err(noise_error)?;
+            }
+        }
+
+        if let Some(allowed_keys) = &config.allowed_keys {
+            let remote_key = handshake.get_remote_static().unwrap_or_default();
+            if !allowed_keys.iter().any(|key| key == remote_key) {
+                return Err(Error::illegal_state(format!(
+                    "Peer {:?} authenticated with a public key that is not on the allowlist",
+                    stream.peer_addr()
+                )));
+            }
+        }
+
+        Ok(Self {
+            stream,
+            transport: handshake.into_transport_mode().map_err(noise_error)?,
+            encrypted: Vec::new(),
+            decrypted: Vec::new(),
+        })
+    }
+
+    async fn send(&mut self, msg: &[u8]) -> Result<(), Error> {
+        let msg_len = u32::try_from(msg.len())
+            .map_err(|_| Error::illegal_argument("Message too long"))?
+            .to_le_bytes();
+        let mut buf = vec![0; NOISE_MAX_MSG_LEN];
+        let plaintext = msg_len.iter().chain(msg).copied().collect::<Vec<_>>();
+        for chunk in plaintext.chunks(NOISE_MAX_MSG_LEN - NOISE_TAG_LEN) {
+            let len = self
+                .transport
+                .write_message(chunk, &mut buf)
+                .map_err(noise_error)?;
+            Self::send_frame(&mut self.stream, &buf[..len]).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the next message, if one was received completely.
+    /// Does not wait for the rest of partially received messages.
+    ///
+    /// Failing to decrypt, or a closed connection, are returned as [`Error::OsError`], to drop this connection.
+    fn try_recv(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let mut buf = vec![0; NOISE_MAX_MSG_LEN];
+        loop {
+            if let Some(len) = self.decrypted.first_chunk::<4>() {
+                let len = u32::from_le_bytes(*len) as usize;
+                if self.decrypted.len() >= 4 + len {
+                    let msg = self.decrypted[4..4 + len].to_vec();
+                    self.decrypted.drain(..4 + len);
+                    return Ok(Some(msg));
+                }
+            }
+
+            if let Some(len) = self.encrypted.first_chunk::<2>() {
+                let len = usize::from(u16::from_be_bytes(*len));
+                if self.encrypted.len() >= 2 + len {
+                    let decrypted_len = self
+                        .transport
+                        .read_message(&self.encrypted[2..2 + len], &mut buf)
+                        .map_err(|e| {
+                            Error::os_error(
+                                std::io::Error::new(ErrorKind::InvalidData, format!("{e:?}")),
+                                "Failed to decrypt message from node",
+                            )
+                        })?;
+                    self.decrypted.extend_from_slice(&buf[..decrypted_len]);
+                    self.encrypted.drain(..2 + len);
+                    continue;
+                }
+            }
+
+            match self.stream.try_read(&mut buf) {
+                Ok(0) => {
+                    return Err(Error::os_error(
+                        std::io::Error::from(ErrorKind::UnexpectedEof),
+                        "Node closed the connection",
+                    ));
+                }
+                Ok(n) => self.encrypted.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(Error::os_error(e, "try read failed")),
+            }
+        }
+    }
 }
 
 /// A set of multi-machine `broker_hooks`.
@@ -167,6 +590,7 @@ impl TcpMultiMachineHooks<(), NopInput> {
     pub fn builder() -> TcpMultiMachineHooksBuilder<()> {
         TcpMultiMachineHooksBuilder::<()> {
             node_descriptor: None,
+            serialization_format: SerializationFormat::default(),
         }
     }
 }
@@ -175,6 +599,7 @@ impl TcpMultiMachineHooks<(), NopInput> {
 #[derive(Debug)]
 pub struct TcpMultiMachineHooksBuilder<A> {
     node_descriptor: Option<NodeDescriptor<A>>,
+    serialization_format: SerializationFormat,
 }
 
 impl<A> TcpMultiMachineHooksBuilder<A> {
@@ -188,8 +613,17 @@ impl<A> TcpMultiMachineHooksBuilder<A> {
     {
         TcpMultiMachineHooksBuilder::<A2> {
             node_descriptor: Some(node_descriptor),
+            serialization_format: self.serialization_format,
         }
     }
+
+    /// Set the [`SerializationFormat`] events received from other nodes get serialized with for our clients.
+    /// Defaults to `postcard`.
+    #[must_use]
+    pub fn serialization_format(mut self, serialization_format: SerializationFormat) -> Self {
+        self.serialization_format = serialization_format;
+        self
+    }
 }
 
 impl<A> TcpMultiMachineHooksBuilder<A>
@@ -224,8 +658,9 @@ where
                 parent: None,
                 children: HashMap::default(),
                 old_msgs: Vec::new(),
+                serialization_format: self.serialization_format,
                 #[cfg(feature = "llmp_compression")]
-                compressor: GzipCompressor::new(),
+                compressor: CodecCompressor::with_threshold(COMPRESS_THRESHOLD),
             }));
 
             let rt = Arc::new(
@@ -260,34 +695,43 @@ where
             rt.block_on(async { self_mutex.read().await.node_descriptor.clone() });
 
         // Try to connect to the parent if we should
-        rt.block_on(async {
-            let parent_mutex = self_mutex.clone();
-            let mut parent_lock = parent_mutex.write().await;
-
-            if let Some(parent_addr) = &parent_lock.node_descriptor.parent_addr {
-                let timeout = current_time() + parent_lock.node_descriptor.timeout;
-
-                parent_lock.parent = loop {
-                    log::debug!("Trying to connect to parent @ {parent_addr}..");
-                    match TcpStream::connect(parent_addr).await {
-                        Ok(stream) => {
-                            log::debug!("Connected to parent @ {parent_addr}");
-
-                            break Some(stream);
+        let candidates: Vec<A> = node_descriptor.parent_candidates().cloned().collect();
+        if !candidates.is_empty() {
+            rt.block_on(async {
+                let deadline = current_time() + node_descriptor.timeout;
+
+                let parent = 'connect: loop {
+                    for parent_addr in &candidates {
+                        match Self::connect_to_parent::<I>(parent_addr, &node_descriptor).await {
+                            Ok(stream) => break 'connect Some(stream),
+                            Err(e) => log::debug!("Could not connect to {parent_addr}: {e}"),
                         }
-                        Err(e) => {
-                            if current_time() > timeout {
-                                return Err(Error::os_error(e, "Unable to connect to parent"));
-                            }
+                    }
+
+                    if current_time() > deadline {
+                        if node_descriptor.reconnect_backoff.is_none() {
+                            return Err(Error::unknown("Unable to connect to parent"));
                         }
+                        // The parent may join later
+                        log::warn!("Unable to connect to parent, retrying in the background.");
+                        break None;
                     }
 
                     time::sleep(Duration::from_secs(1)).await;
                 };
-            }
 
-            Ok(())
-        })?;
+                self_mutex.write().await.parent = parent;
+                Ok(())
+            })?;
+
+            if let Some(backoff) = node_descriptor.reconnect_backoff {
+                let bg_state = self_mutex.clone();
+                let bg_descriptor = node_descriptor.clone();
+                let _handle: JoinHandle<()> = rt.spawn(async move {
+                    Self::supervise_parent::<I>(bg_state, bg_descriptor, candidates, backoff).await;
+                });
+            }
+        }
 
         // Now, setup the background tasks for the children to connect to
         if let Some(listening_port) = node_descriptor.node_listening_port {
@@ -301,28 +745,23 @@ where
                 let state = bg_state;
 
                 // The main listening loop. Should never fail.
-                'listening: loop {
+                // The handshakes run in their own tasks, so that a slow child does not keep others from joining.
+                let node_descriptor = Arc::new(node_descriptor);
+                loop {
                     log::debug!("listening for children on {listener:?}...");
                     match listener.accept().await {
-                        Ok((mut stream, addr)) => {
-                            log::debug!("{addr} joined the children.");
-                            let mut state_guard = state.write().await;
-
-                            if let Err(e) = state_guard
-                                .send_old_events_to_stream::<I>(&mut stream)
-                                .await
-                            {
-                                log::error!("Error while send old messages: {e:?}.");
-                                log::error!("The loop will resume");
-                                continue 'listening;
+                        Ok((stream, addr)) => {
+                            if !node_descriptor.is_allowed_addr(&addr) {
+                                log::warn!("Refusing child {addr}, it is not on the allowlist.");
+                                continue;
                             }
 
-                            state_guard.children.insert(NodeId::new(), stream);
-                            log::debug!(
-                                "[pid {}]{addr} added the child. nb children: {}",
-                                process::id(),
-                                state_guard.children.len()
-                            );
+                            let state = state.clone();
+                            let node_descriptor = node_descriptor.clone();
+                            let _handle: JoinHandle<()> = tokio::spawn(async move {
+                                Self::accept_child::<I>(&state, &node_descriptor, stream, addr)
+                                    .await;
+                            });
                         }
                         Err(e) => {
                             log::error!("Error while accepting child {e:?}.");
@@ -335,22 +774,247 @@ where
         Ok(())
     }
 
-    /// Add an event as past event.
-    pub fn add_past_msg(&mut self, msg: &[u8]) {
-        self.old_msgs.push(msg.to_vec());
+    /// Sets up the transport to a new child, and adds it to the children once it caught up with our messages
+    async fn accept_child<I: Input>(
+        self_mutex: &RwLock<Self>,
+        node_descriptor: &NodeDescriptor<A>,
+        stream: TcpStream,
+        addr: SocketAddr,
+    ) {
+        let mut node = match time::timeout(node_descriptor.timeout, async {
+            Self::exchange_codecs::<I>(NodeStream::accept(stream, &node_descriptor.security).await?)
+                .await
+        })
+        .await
+        {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                log::warn!("Refusing child {addr}: {e}");
+                return;
+            }
+            Err(_) => {
+                log::warn!("Refusing child {addr}: handshake timed out");
+                return;
+            }
+        };
+
+        log::debug!("{addr} joined the children.");
+        let mut state_guard = match Self::catch_up::<I>(self_mutex, &mut node).await {
+            Ok(state_guard) => state_guard,
+            Err(e) => {
+                log::error!("Error while send old messages to {addr}: {e:?}.");
+                return;
+            }
+        };
+
+        state_guard.children.insert(NodeId::new(), node);
+        log::debug!(
+            "[pid {}]{addr} added the child. nb children: {}",
+            process::id(),
+            state_guard.children.len()
+        );
+    }
+
+    /// Connects to the node at `parent_addr`, and sets up the transport
+    async fn connect_to_parent<I: Input>(
+        parent_addr: &A,
+        node_descriptor: &NodeDescriptor<A>,
+    ) -> Result<NodeConnection, Error> {
+        log::debug!("Trying to connect to parent @ {parent_addr}..");
+        let stream = time::timeout(node_descriptor.timeout, TcpStream::connect(parent_addr))
+            .await
+            .map_err(|_| Error::unknown(format!("Connecting to {parent_addr} timed out")))?
+            .map_err(|e| Error::os_error(e, format!("Unable to connect to {parent_addr}")))?;
+        log::debug!("Connected to parent @ {parent_addr}");
+
+        time::timeout(node_descriptor.timeout, async {
+            Self::exchange_codecs::<I>(
+                NodeStream::connect(stream, &node_descriptor.security).await?,
+            )
+            .await
+        })
+        .await
+        .map_err(|_| Error::unknown(format!("Handshake with {parent_addr} timed out")))?
+    }
+
+    /// Tells a new node which compression codecs we can decompress, and learns its codecs.
+    /// Both sides send first, so this works the same for parents and children.
+    async fn exchange_codecs<I: Input>(mut stream: NodeStream) -> Result<NodeConnection, Error> {
+        #[cfg(feature = "llmp_compression")]
+        let codec_ids: Vec<u8> = CompressionCodec::available()
+            .into_iter()
+            .map(CompressionCodec::id)
+            .collect();
+        #[cfg(not(feature = "llmp_compression"))]
+        let codec_ids: Vec<u8> = Vec::new();
+
+        let hello: MultiMachineMsg<I> =
+            MultiMachineMsg::llmp_msg(Flags(0), OwnedRef::Ref(codec_ids.as_slice()));
+        Self::write_msg(&mut stream, &hello).await?;
+
+        let peer_hello = loop {
+            if let Some(msg) = Self::read_msg::<I>(&mut stream).await? {
+                break msg;
+            }
+            stream.readable().await?;
+        };
+        #[cfg(feature = "llmp_compression")]
+        let codecs = peer_hello
+            .serialize_as_ref()
+            .iter()
+            .filter_map(|id| CompressionCodec::from_id(*id))
+            .collect();
+        #[cfg(not(feature = "llmp_compression"))]
+        let _ = peer_hello;
+
+        Ok(NodeConnection {
+            stream,
+            #[cfg(feature = "llmp_compression")]
+            codecs,
+        })
+    }
+
+    /// Reconnects to a parent whenever the connection got lost, with exponential backoff.
+    ///
+    /// The `candidates` are tried in turn, so that a node whose parent died gets re-parented to a sibling.
+    /// The new parent receives all messages of this node, so that it catches up with the work done meanwhile.
+    async fn supervise_parent<I: Input>(
+        self_mutex: Arc<RwLock<Self>>,
+        node_descriptor: NodeDescriptor<A>,
+        candidates: Vec<A>,
+        initial_backoff: Duration,
+    ) {
+        let mut backoff = initial_backoff;
+        let mut next_candidate = 0;
+
+        loop {
+            time::sleep(backoff).await;
+
+            if self_mutex.read().await.parent.is_some() {
+                backoff = initial_backoff;
+                next_candidate = 0;
+                continue;
+            }
+
+            let parent_addr = &candidates[next_candidate % candidates.len()];
+            next_candidate += 1;
+
+            let connected = match Self::connect_to_parent::<I>(parent_addr, &node_descriptor).await
+            {
+                Ok(mut stream) => {
+                    let state_guard = if node_descriptor.flags.intersects(NodePolicy::SendToParent)
+                    {
+                        Self::catch_up::<I>(&self_mutex, &mut stream).await
+                    } else {
+                        Ok(self_mutex.write().await)
+                    };
+                    state_guard.map(|mut state_guard| state_guard.parent = Some(stream))
+                }
+                Err(e) => Err(e),
+            };
+
+            match connected {
+                Ok(()) => {
+                    log::info!("Connected to parent {parent_addr}.");
+                    backoff = initial_backoff;
+                    next_candidate = 0;
+                }
+                Err(e) => {
+                    log::warn!("Reconnecting to {parent_addr} failed: {e}");
+                    backoff = (backoff * 2).min(node_descriptor.max_reconnect_backoff);
+                }
+            }
+        }
+    }
+
+    /// If this node is currently connected to a parent
+    #[must_use]
+    pub fn has_parent(&self) -> bool {
+        self.parent.is_some()
+    }
+
+    /// The number of children currently connected to this node
+    #[must_use]
+    pub fn nb_children(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Add an event as past event, with its llmp flags.
+    pub fn add_past_msg(&mut self, flags: Flags, msg: &[u8]) {
+        self.old_msgs.push((flags, msg.to_vec()));
+    }
+
+    /// The format events received from other nodes get serialized with
+    #[must_use]
+    pub fn serialization_format(&self) -> SerializationFormat {
+        self.serialization_format
     }
 
     /// The compressor
     #[cfg(feature = "llmp_compression")]
-    pub fn compressor(&mut self) -> &GzipCompressor {
+    pub fn compressor(&mut self) -> &CodecCompressor {
         &self.compressor
     }
 
-    /// Read a [`TcpMultiMachineMsg`] from a stream.
+    /// Writes a message to `node`, recompressed with `compressor` if the node cannot decompress its codec.
+    async fn write_msg_to_node<I: Input>(
+        #[cfg(feature = "llmp_compression")] compressor: &CodecCompressor,
+        node: &mut NodeConnection,
+        msg: &MultiMachineMsg<'_, I>,
+    ) -> Result<(), Error> {
+        if let MultiMachineMsg::LlmpMsg(flags, payload) = msg {
+            if let Some((flags, payload)) = node.transcode(
+                #[cfg(feature = "llmp_compression")]
+                compressor,
+                *flags,
+                payload.as_ref(),
+            )? {
+                let msg: MultiMachineMsg<I> =
+                    MultiMachineMsg::llmp_msg(flags, OwnedRef::Ref(payload.as_slice()));
+                return Self::write_msg(&mut node.stream, &msg).await;
+            }
+        }
+        Self::write_msg(&mut node.stream, msg).await
+    }
+
+    /// Read a [`TcpMultiMachineMsg`] from a node.
     /// Expects a message written by [`TcpMultiMachineState::write_msg`].
     /// If there is nothing to read from the stream, return asap with Ok(None).
-    #[expect(clippy::uninit_vec)]
     async fn read_msg<'a, I: Input + 'a>(
+        stream: &mut NodeStream,
+    ) -> Result<Option<MultiMachineMsg<'a, I>>, Error> {
+        match stream {
+            NodeStream::Plain(stream) => Self::read_plain_msg(stream).await,
+            #[cfg(feature = "multi_machine_noise")]
+            NodeStream::Noise(stream) => stream
+                .try_recv()?
+                .map(MultiMachineMsg::from_wire)
+                .transpose(),
+        }
+    }
+
+    /// Write an [`OwnedTcpMultiMachineMsg`] to a node.
+    /// Can be read back using [`TcpMultiMachineState::read_msg`].
+    async fn write_msg<I: Input>(
+        stream: &mut NodeStream,
+        msg: &MultiMachineMsg<'_, I>,
+    ) -> Result<(), Error> {
+        match stream {
+            NodeStream::Plain(stream) => Self::write_plain_msg(stream, msg).await,
+            #[cfg(feature = "multi_machine_noise")]
+            NodeStream::Noise(stream) => {
+                let mut wire_msg = msg.flags().0.to_le_bytes().to_vec();
+                wire_msg.extend_from_slice(msg.serialize_as_ref());
+                stream.send(&wire_msg).await
+            }
+        }
+    }
+
+    /// Read a [`TcpMultiMachineMsg`] from a plaintext stream.
+    /// Expects a message written by [`TcpMultiMachineState::write_plain_msg`].
+    /// If there is nothing to read from the stream, return asap with Ok(None).
+    #[expect(clippy::uninit_vec)]
+    async fn read_plain_msg<'a, I: Input + 'a>(
         stream: &mut TcpStream,
     ) -> Result<Option<MultiMachineMsg<'a, I>>, Error> {
         // 0. Check if we should try to fetch something from the stream
@@ -368,8 +1032,11 @@ where
         log::debug!("msg read.");
 
         if n_read == 0 {
-            log::debug!("No dummy byte received...");
-            return Ok(None); // Nothing to read from this stream
+            // The peer closed the connection
+            return Err(Error::os_error(
+                ErrorKind::UnexpectedEof.into(),
+                "The node disconnected",
+            ));
         }
 
         log::debug!("Received dummy byte!");
@@ -394,19 +1061,19 @@ where
         log::debug!("Receiving msg...");
         stream.read_exact(node_msg.as_mut_slice()).await?;
         log::debug!("msg received.");
-        let node_msg = node_msg.into_boxed_slice();
 
-        Ok(Some(MultiMachineMsg::from_llmp_msg(node_msg)))
+        MultiMachineMsg::from_wire(node_msg).map(Some)
     }
 
-    /// Write an [`OwnedTcpMultiMachineMsg`] to a stream.
-    /// Can be read back using [`TcpMultiMachineState::read_msg`].
-    async fn write_msg<I: Input>(
+    /// Write an [`OwnedTcpMultiMachineMsg`] to a plaintext stream.
+    /// Can be read back using [`TcpMultiMachineState::read_plain_msg`].
+    async fn write_plain_msg<I: Input>(
         stream: &mut TcpStream,
         msg: &MultiMachineMsg<'_, I>,
     ) -> Result<(), Error> {
         let serialized_msg = msg.serialize_as_ref();
-        let msg_len = u32::to_le_bytes(serialized_msg.len() as u32);
+        // The flags are sent in front of the message
+        let msg_len = u32::to_le_bytes(serialized_msg.len() as u32 + 4);
 
         // 0. Write the dummy byte
         log::debug!("Sending dummy byte...");
@@ -418,7 +1085,8 @@ where
         stream.write_all(&msg_len).await?;
         log::debug!("msg len sent.");
 
-        // 2. Write msg
+        // 2. Write flags and msg
+        stream.write_all(&msg.flags().0.to_le_bytes()).await?;
         log::debug!("Sending msg...");
         stream.write_all(serialized_msg).await?;
         log::debug!("msg sent.");
@@ -426,25 +1094,61 @@ where
         Ok(())
     }
 
-    pub(crate) async fn send_old_events_to_stream<I: Input>(
-        &mut self,
-        stream: &mut TcpStream,
+    async fn send_old_events_to_stream<I: Input>(
+        old_msgs: &[(Flags, Vec<u8>)],
+        stream: &mut NodeStream,
     ) -> Result<(), Error> {
-        log::debug!("Send old events to new child...");
+        log::debug!("Send old events to new node...");
 
-        for old_msg in &self.old_msgs {
+        for (flags, old_msg) in old_msgs {
             let event_ref: MultiMachineMsg<I> =
-                MultiMachineMsg::llmp_msg(OwnedRef::Ref(old_msg.as_slice()));
+                MultiMachineMsg::llmp_msg(*flags, OwnedRef::Ref(old_msg.as_slice()));
             log::debug!("Sending an old message...");
             Self::write_msg(stream, &event_ref).await?;
             log::debug!("Old message sent.");
         }
 
-        log::debug!("Sent {} old messages.", self.old_msgs.len());
+        log::debug!("Sent {} old messages.", old_msgs.len());
 
         Ok(())
     }
 
+    /// Sends all past messages to a new node.
+    ///
+    /// The backlog is copied under the lock and sent without it, so that the other nodes are not blocked meanwhile.
+    /// Returns the locked state once the node caught up, to add it before any new message arrives.
+    async fn catch_up<'a, I: Input>(
+        self_mutex: &'a RwLock<Self>,
+        node: &mut NodeConnection,
+    ) -> Result<RwLockWriteGuard<'a, Self>, Error> {
+        let mut sent = 0;
+        loop {
+            let backlog = {
+                let state = self_mutex.read().await;
+                state.old_msgs[sent..]
+                    .iter()
+                    .map(|(flags, msg)| {
+                        Ok(node
+                            .transcode(
+                                #[cfg(feature = "llmp_compression")]
+                                &state.compressor,
+                                *flags,
+                                msg,
+                            )?
+                            .unwrap_or_else(|| (*flags, msg.clone())))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?
+            };
+            Self::send_old_events_to_stream::<I>(&backlog, &mut node.stream).await?;
+            sent += backlog.len();
+
+            let state_guard = self_mutex.write().await;
+            if state_guard.old_msgs.len() == sent {
+                return Ok(state_guard);
+            }
+        }
+    }
+
     pub(crate) async fn send_interesting_event_to_nodes<I: Input>(
         &mut self,
         msg: &MultiMachineMsg<'_, I>,
@@ -458,11 +1162,15 @@ where
         {
             if let Some(parent) = &mut self.parent {
                 log::debug!("Sending to parent...");
-                if let Err(e) = Self::write_msg(parent, msg).await {
-                    log::error!(
-                        "The parent disconnected. We won't try to communicate with it again."
-                    );
-                    log::error!("Error: {e:?}");
+                if let Err(e) = Self::write_msg_to_node(
+                    #[cfg(feature = "llmp_compression")]
+                    &self.compressor,
+                    parent,
+                    msg,
+                )
+                .await
+                {
+                    log::error!("The parent disconnected. Error: {e:?}");
                     self.parent.take();
                 }
             }
@@ -476,7 +1184,14 @@ where
             let mut ids_to_remove: Vec<NodeId> = Vec::new();
             for (child_id, child_stream) in &mut self.children {
                 log::debug!("Sending to child {child_id:?}...");
-                if let Err(err) = Self::write_msg(child_stream, msg).await {
+                if let Err(err) = Self::write_msg_to_node(
+                    #[cfg(feature = "llmp_compression")]
+                    &self.compressor,
+                    child_stream,
+                    msg,
+                )
+                .await
+                {
                     // most likely the child disconnected. drop the connection later on and continue.
                     log::debug!(
                         "The child disconnected. We won't try to communicate with it again. Error: {err:?}"
@@ -515,7 +1230,7 @@ where
                 // }
 
                 log::debug!("Receiving from parent...");
-                match Self::read_msg(parent).await {
+                match Self::read_msg(&mut parent.stream).await {
                     Ok(Some(msg)) => {
                         log::debug!("Received event from parent");
                         // The parent has something for us, we store it
@@ -530,10 +1245,8 @@ where
                     }
 
                     Err(Error::OsError(_, _, _)) => {
-                        // most likely the parent disconnected. drop the connection
-                        log::debug!(
-                            "The parent disconnected. We won't try to communicate with it again."
-                        );
+                        // most likely the parent disconnected. drop the connection, it may get reconnected later.
+                        log::debug!("The parent disconnected.");
                         self.parent.take();
                         break;
                     }
@@ -561,7 +1274,7 @@ where
                 //}
 
                 log::debug!("Receiving from child {child_id:?}...");
-                match Self::read_msg(child_stream).await {
+                match Self::read_msg(&mut child_stream.stream).await {
                     Ok(Some(msg)) => {
                         // The parent has something for us, we store it
                         log::debug!("Received event from child!");
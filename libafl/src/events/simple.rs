@@ -4,6 +4,8 @@ use alloc::vec::Vec;
 #[cfg(feature = "std")]
 use core::sync::atomic::{Ordering, compiler_fence};
 use core::{fmt::Debug, marker::PhantomData, time::Duration};
+#[cfg(feature = "std")]
+use std::path::Path;
 
 #[cfg(feature = "std")]
 use hashbrown::HashMap;
@@ -41,6 +43,7 @@ use crate::{
 };
 #[cfg(feature = "std")]
 use crate::{
+    events::{EventCheckpointer, std_checkpoint},
     monitors::{SimplePrintingMonitor, stats::ClientStats},
     state::HasSolutions,
 };
@@ -117,6 +120,16 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<I, MT, S> EventCheckpointer<S> for SimpleEventManager<I, MT, S>
+where
+    S: Serialize,
+{
+    fn checkpoint(&mut self, state: &S, path: &Path) -> Result<(), Error> {
+        std_checkpoint(self, state, path)
+    }
+}
+
 impl<I, MT, S> EventReceiver<I, S> for SimpleEventManager<I, MT, S>
 where
     I: Debug,
@@ -323,6 +336,16 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<I, MT, S, SHM, SP> EventCheckpointer<S> for SimpleRestartingEventManager<I, MT, S, SHM, SP>
+where
+    S: Serialize,
+{
+    fn checkpoint(&mut self, state: &S, path: &Path) -> Result<(), Error> {
+        std_checkpoint(self, state, path)
+    }
+}
+
 #[cfg(feature = "std")]
 impl<I, MT, S, SHM, SP> SendExiting for SimpleRestartingEventManager<I, MT, S, SHM, SP>
 where
@@ -12,6 +12,7 @@ use std::{
     env,
     io::{ErrorKind, Read, Write},
     net::{TcpListener, TcpStream, ToSocketAddrs},
+    path::Path,
 };
 
 #[cfg(feature = "tcp_compression")]
@@ -26,6 +27,7 @@ use libafl_bolts::{
     ClientId,
     core_affinity::CoreId,
     os::CTRL_C_EXIT,
+    serialization::SerializationFormat,
     shmem::{ShMem, ShMemProvider, StdShMem, StdShMemProvider},
     staterestore::StateRestorer,
     tuples::tuple_list,
@@ -44,9 +46,9 @@ use crate::events::EVENTMGR_SIGHANDLER_STATE;
 use crate::{
     Error, HasMetadata,
     events::{
-        BrokerEventResult, Event, EventConfig, EventFirer, EventManagerHooksTuple, EventManagerId,
-        EventReceiver, EventRestarter, EventWithStats, HasEventManagerId, ProgressReporter,
-        std_on_restart,
+        BrokerEventResult, Event, EventCheckpointer, EventConfig, EventFirer,
+        EventManagerHooksTuple, EventManagerId, EventReceiver, EventRestarter, EventWithStats,
+        HasEventManagerId, ProgressReporter, std_checkpoint, std_on_restart,
     },
     inputs::Input,
     monitors::{Monitor, stats::ClientStatsManager},
@@ -64,6 +66,20 @@ fn create_nonblocking_listener<A: ToSocketAddrs>(addr: A) -> Result<TcpListener,
     Ok(listener)
 }
 
+/// Splits an event as sent over TCP into the [`SerializationFormat`] it got serialized with, and the (compressed) event.
+/// The format id is the first byte of each event, so clients may use different formats.
+fn split_format(buf: &[u8]) -> Result<(SerializationFormat, &[u8]), Error> {
+    let (format_id, event_bytes) = buf
+        .split_first()
+        .ok_or_else(|| Error::illegal_state("Received an empty event over TCP"))?;
+    let format = SerializationFormat::from_id(*format_id).ok_or_else(|| {
+        Error::illegal_state(format!(
+            "Received an event in unknown serialization format {format_id} over TCP"
+        ))
+    })?;
+    Ok((format, event_bytes))
+}
+
 /// An TCP-backed event manager for simple multi-processed fuzzing
 #[derive(Debug)]
 pub struct TcpEventBroker<I, MT>
@@ -288,12 +304,12 @@ where
             let client_id = ClientId(u32::from_le_bytes(client_id_buf));
 
             // cut off the ID.
-            let event_bytes = &buf[4..];
+            let (format, event_bytes) = split_format(&buf[4..])?;
 
             #[cfg(feature = "tcp_compression")]
             let event_bytes = &GzipCompressor::new().decompress(event_bytes)?;
 
-            let event: EventWithStats<I> = postcard::from_bytes(event_bytes)?;
+            let event: EventWithStats<I> = format.deserialize(event_bytes)?;
             match Self::handle_in_broker(
                 &mut self.monitor,
                 &mut self.client_stats_manager,
@@ -420,6 +436,8 @@ pub struct TcpEventManager<EMH, I, S> {
     client_id: ClientId,
     #[cfg(feature = "tcp_compression")]
     compressor: GzipCompressor,
+    /// The format we serialize our events with
+    serialization_format: SerializationFormat,
     /// The configuration defines this specific fuzzer.
     /// A node will not re-use the observer values sent over TCP
     /// from nodes with other configurations.
@@ -439,6 +457,7 @@ impl<I, S> TcpEventManager<(), I, S> {
 #[derive(Debug, Copy, Clone)]
 pub struct TcpEventManagerBuilder<EMH, I, S> {
     throttle: Option<Duration>,
+    serialization_format: SerializationFormat,
     hooks: EMH,
     phantom: PhantomData<(I, S)>,
 }
@@ -455,6 +474,7 @@ impl<I, S> TcpEventManagerBuilder<(), I, S> {
     pub fn new() -> Self {
         Self {
             throttle: None,
+            serialization_format: SerializationFormat::default(),
             hooks: (),
             phantom: PhantomData,
         }
@@ -465,6 +485,7 @@ impl<I, S> TcpEventManagerBuilder<(), I, S> {
     pub fn hooks<EMH>(self, hooks: EMH) -> TcpEventManagerBuilder<EMH, I, S> {
         TcpEventManagerBuilder {
             throttle: self.throttle,
+            serialization_format: self.serialization_format,
             hooks,
             phantom: PhantomData,
         }
@@ -479,6 +500,14 @@ impl<EMH, I, S> TcpEventManagerBuilder<EMH, I, S> {
         self
     }
 
+    /// Set the format to serialize events with. Defaults to `postcard`.
+    /// Other formats need their feature, e.g., `serialization_bincode`.
+    #[must_use]
+    pub fn serialization_format(mut self, serialization_format: SerializationFormat) -> Self {
+        self.serialization_format = serialization_format;
+        self
+    }
+
     /// Create a manager from a raw TCP client with hooks
     pub fn build_from_client<A: ToSocketAddrs>(
         self,
@@ -486,6 +515,12 @@ impl<EMH, I, S> TcpEventManagerBuilder<EMH, I, S> {
         client_id: ClientId,
         configuration: EventConfig,
     ) -> Result<TcpEventManager<EMH, I, S>, Error> {
+        if !self.serialization_format.is_available() {
+            return Err(Error::illegal_argument(format!(
+                "Serialization format {} is not available in this build",
+                self.serialization_format
+            )));
+        }
         let mut tcp = TcpStream::connect(addr)?;
 
         let mut our_client_id_buf = client_id.0.to_le_bytes();
@@ -506,6 +541,7 @@ impl<EMH, I, S> TcpEventManagerBuilder<EMH, I, S> {
             client_id,
             #[cfg(feature = "tcp_compression")]
             compressor: GzipCompressor::new(),
+            serialization_format: self.serialization_format,
             configuration,
             phantom: PhantomData,
         })
@@ -547,6 +583,7 @@ impl<EMH, I, S> core::fmt::Debug for TcpEventManager<EMH, I, S> {
         #[cfg(feature = "tcp_compression")]
         let debug = debug.field("compressor", &self.compressor);
         debug
+            .field("serialization_format", &self.serialization_format)
             .field("configuration", &self.configuration)
             .field("phantom", &self.phantom)
             .finish_non_exhaustive()
@@ -601,14 +638,16 @@ where
     }
 
     fn fire(&mut self, _state: &mut S, event: EventWithStats<I>) -> Result<(), Error> {
-        let serialized = postcard::to_allocvec(&event)?;
+        let serialized = self.serialization_format.serialize(&event)?;
 
         #[cfg(feature = "tcp_compression")]
         let serialized = self.compressor.compress(&serialized);
 
-        let size = u32::try_from(serialized.len())?;
+        // The format id goes first, see `split_format`
+        let size = u32::try_from(serialized.len() + 1)?;
         self.tcp.write_all(&size.to_le_bytes())?;
         self.tcp.write_all(&self.client_id.0.to_le_bytes())?;
+        self.tcp.write_all(&[self.serialization_format.id()])?;
         self.tcp.write_all(&serialized)?;
 
         self.last_sent = libafl_bolts::current_time();
@@ -665,12 +704,12 @@ where
                     } else {
                         log::info!("{self_id:?} (from {other_client_id:?}) Received: {buf:?}");
 
-                        let buf = &buf[4..];
+                        let (format, buf) = split_format(&buf[4..])?;
                         #[cfg(feature = "tcp_compression")]
                         let buf = &self.compressor.decompress(buf)?;
 
                         // make decompressed vec and slice compatible
-                        let event: EventWithStats<I> = postcard::from_bytes(buf)?;
+                        let event: EventWithStats<I> = format.deserialize(buf)?;
 
                         if !self.hooks.pre_receive_all(state, other_client_id, &event)? {
                             continue;
@@ -822,6 +861,16 @@ where
     }
 }
 
+impl<EMH, I, S, SHM, SP> EventCheckpointer<S> for TcpRestartingEventManager<EMH, I, S, SHM, SP>
+where
+    S: Serialize,
+    SHM: ShMem,
+{
+    fn checkpoint(&mut self, state: &S, path: &Path) -> Result<(), Error> {
+        std_checkpoint(self, state, path)
+    }
+}
+
 impl<EMH, I, S, SHM, SP> SendExiting for TcpRestartingEventManager<EMH, I, S, SHM, SP>
 where
     SHM: ShMem,
@@ -1032,6 +1081,10 @@ pub struct TcpRestartingMgr<EMH, I, MT, S, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = true)]
     serialize_state: bool,
+    /// The format to serialize events and the state with. Defaults to `postcard`.
+    /// Other formats need their feature, e.g., `serialization_bincode`.
+    #[builder(default)]
+    serialization_format: SerializationFormat,
     /// The hooks for `handle_in_client`
     hooks: EMH,
     #[builder(setter(skip), default = PhantomData)]
@@ -1098,6 +1151,7 @@ where
                             // port was likely already bound
                             let mgr = TcpEventManagerBuilder::new()
                                 .hooks(self.hooks)
+                                .serialization_format(self.serialization_format)
                                 .build_from_client(
                                     &("127.0.0.1", self.broker_port),
                                     UNDEFINED_CLIENT_ID,
@@ -1125,6 +1179,7 @@ where
                     // We are a client
                     let mgr = TcpEventManagerBuilder::new()
                         .hooks(self.hooks)
+                        .serialization_format(self.serialization_format)
                         .build_on_port(self.broker_port, UNDEFINED_CLIENT_ID, self.configuration)?;
 
                     (mgr, cpu_core)
@@ -1147,11 +1202,13 @@ where
             // First, create a channel from the current fuzzer to the next to store state between restarts.
             #[cfg(unix)]
             let staterestorer: StateRestorer<SP::ShMem, SP> =
-                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?);
+                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?)
+                    .with_format(self.serialization_format)?;
 
             #[cfg(not(unix))]
             let staterestorer: StateRestorer<SP::ShMem, SP> =
-                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?);
+                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?)
+                    .with_format(self.serialization_format)?;
 
             // Store the information to a map.
             // # Safety
@@ -1261,6 +1318,7 @@ where
                 TcpRestartingEventManager::with_save_state(
                     TcpEventManagerBuilder::new()
                         .hooks(self.hooks)
+                        .serialization_format(self.serialization_format)
                         .build_on_port(self.broker_port, this_id, self.configuration)?,
                     staterestorer,
                     self.serialize_state,
@@ -1271,6 +1329,7 @@ where
             // Mgr to send and receive msgs from/to all other fuzzer instances
             let mgr = TcpEventManagerBuilder::new()
                 .hooks(self.hooks)
+                .serialization_format(self.serialization_format)
                 .build_existing_from_env(
                     &("127.0.0.1", self.broker_port),
                     _ENV_FUZZER_BROKER_CLIENT_INITIAL,
@@ -679,7 +679,11 @@ where
 
         let last_run_timed_out = self.forkserver.last_run_timed_out_raw();
 
-        let mut input_bytes = self.target_bytes_converter.to_target_bytes(input);
+        let Some(mut input_bytes) = self.target_bytes_converter.try_to_target_bytes(input) else {
+            // Like AFL++, don't run inputs the converter rejected
+            log::debug!("Skipping an input rejected by the target bytes converter");
+            return Ok(ExitKind::Ok);
+        };
         let mut input_size = input_bytes.as_slice().len();
         if input_size > self.max_input_size {
             // Truncate like AFL++ does
@@ -12,12 +12,15 @@ use core::{
 
 #[cfg(all(target_os = "linux", feature = "std"))]
 use libafl_bolts::current_time;
-#[cfg(all(unix, feature = "std"))]
-use libafl_bolts::minibsod::{BsodInfo, generate_minibsod_to_vec};
 #[cfg(all(unix, feature = "std", not(miri)))]
 use libafl_bolts::os::unix_signals::setup_signal_handler;
 #[cfg(all(windows, feature = "std"))]
 use libafl_bolts::os::windows_exceptions::setup_exception_handler;
+#[cfg(all(unix, feature = "std"))]
+use libafl_bolts::{
+    crash_report::CrashReport,
+    minibsod::{BsodInfo, generate_minibsod_to_vec},
+};
 #[cfg(all(windows, feature = "std"))]
 use windows::Win32::System::Threading::{CRITICAL_SECTION, PTP_TIMER};
 
@@ -33,7 +36,9 @@ use crate::{
 #[cfg(all(unix, feature = "std"))]
 use crate::{
     executors::{
-        ExitKind, hooks::unix::unix_signal_handler, inprocess::run_observers_and_save_state,
+        ExitKind,
+        hooks::unix::unix_signal_handler,
+        inprocess::{run_observers_and_save_state, run_observers_and_save_state_with_report},
     },
     state::HasCorpus,
 };
@@ -508,16 +513,31 @@ impl InProcessExecutorHandlerData {
                             log::error!("{r}");
                         }
                     }
-                }
 
-                run_observers_and_save_state::<E, EM, I, OF, S, Z>(
-                    executor,
-                    state,
-                    input,
-                    fuzzer,
-                    event_mgr,
-                    ExitKind::Crash,
-                );
+                    let report = CrashReport::from_signal(
+                        bsod_info.signal,
+                        &bsod_info.siginfo,
+                        bsod_info.ucontext.as_ref(),
+                    );
+                    run_observers_and_save_state_with_report::<E, EM, I, OF, S, Z>(
+                        executor,
+                        state,
+                        input,
+                        fuzzer,
+                        event_mgr,
+                        ExitKind::Crash,
+                        report,
+                    );
+                } else {
+                    run_observers_and_save_state::<E, EM, I, OF, S, Z>(
+                        executor,
+                        state,
+                        input,
+                        fuzzer,
+                        event_mgr,
+                        ExitKind::Crash,
+                    );
+                }
 
                 return true;
             }
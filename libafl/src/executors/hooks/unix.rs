@@ -5,9 +5,12 @@ pub mod unix_signal_handler {
     use core::mem::transmute;
     use std::{io::Write, panic};
 
-    use libafl_bolts::os::{
-        SIGNAL_RECURSION_EXIT,
-        unix_signals::{Signal, SignalHandler, ucontext_t},
+    use libafl_bolts::{
+        crash_report::CrashReport,
+        os::{
+            SIGNAL_RECURSION_EXIT,
+            unix_signals::{Signal, SignalHandler, ucontext_t},
+        },
     };
     use libc::siginfo_t;
 
@@ -16,7 +19,10 @@ pub mod unix_signal_handler {
         executors::{
             Executor, ExitKind, HasObservers, common_signals,
             hooks::inprocess::{GLOBAL_STATE, HasTimeout, InProcessExecutorHandlerData},
-            inprocess::{HasInProcessHooks, run_observers_and_save_state},
+            inprocess::{
+                HasInProcessHooks, run_observers_and_save_state,
+                run_observers_and_save_state_with_report,
+            },
         },
         feedbacks::Feedback,
         fuzzer::HasObjective,
@@ -251,13 +257,15 @@ pub mod unix_signal_handler {
                     }
                 }
 
-                run_observers_and_save_state::<E, EM, I, OF, S, Z>(
+                let report = CrashReport::from_signal(signal, _info, _context.as_deref());
+                run_observers_and_save_state_with_report::<E, EM, I, OF, S, Z>(
                     executor,
                     state,
                     input,
                     fuzzer,
                     event_mgr,
                     ExitKind::Crash,
+                    report,
                 );
             } else {
                 {
@@ -11,7 +11,11 @@ use core::{
     ptr,
     time::Duration,
 };
+#[cfg(feature = "std")]
+use std::path::PathBuf;
 
+#[cfg(feature = "std")]
+use libafl_bolts::crash_report::CrashReport;
 use libafl_bolts::tuples::{RefIndexable, tuple_list};
 
 use crate::{
@@ -330,6 +334,68 @@ pub fn run_observers_and_save_state<E, EM, I, OF, S, Z>(
     S: HasExecutions + HasSolutions<I> + HasCorpus<I> + HasCurrentTestcase<I>,
     Z: HasObjective<Objective = OF>,
     I: Input + Clone,
+{
+    save_state_and_solution::<E, EM, I, OF, S, Z>(
+        executor,
+        state,
+        input,
+        fuzzer,
+        event_mgr,
+        exitkind,
+        #[cfg(feature = "std")]
+        None,
+    );
+}
+
+#[cfg(feature = "std")]
+#[inline]
+/// Save state if it is an objective, like [`run_observers_and_save_state`].
+/// If the input is a solution, the [`CrashReport`] is attached to it as metadata,
+/// with its reproducer set to the file name of the solution, if it is stored on disk.
+pub fn run_observers_and_save_state_with_report<E, EM, I, OF, S, Z>(
+    executor: &mut E,
+    state: &mut S,
+    input: &I,
+    fuzzer: &mut Z,
+    event_mgr: &mut EM,
+    exitkind: ExitKind,
+    report: CrashReport,
+) where
+    E: HasObservers,
+    E::Observers: ObserversTuple<I, S>,
+    EM: EventFirer<I, S> + EventRestarter<S>,
+    OF: Feedback<EM, I, E::Observers, S>,
+    S: HasExecutions + HasSolutions<I> + HasCorpus<I> + HasCurrentTestcase<I>,
+    Z: HasObjective<Objective = OF>,
+    I: Input + Clone,
+{
+    save_state_and_solution::<E, EM, I, OF, S, Z>(
+        executor,
+        state,
+        input,
+        fuzzer,
+        event_mgr,
+        exitkind,
+        Some(report),
+    );
+}
+
+fn save_state_and_solution<E, EM, I, OF, S, Z>(
+    executor: &mut E,
+    state: &mut S,
+    input: &I,
+    fuzzer: &mut Z,
+    event_mgr: &mut EM,
+    exitkind: ExitKind,
+    #[cfg(feature = "std")] report: Option<CrashReport>,
+) where
+    E: HasObservers,
+    E::Observers: ObserversTuple<I, S>,
+    EM: EventFirer<I, S> + EventRestarter<S>,
+    OF: Feedback<EM, I, E::Observers, S>,
+    S: HasExecutions + HasSolutions<I> + HasCorpus<I> + HasCurrentTestcase<I>,
+    Z: HasObjective<Objective = OF>,
+    I: Input + Clone,
 {
     log::info!("in crash handler!");
     let mut observers = executor.observers_mut();
@@ -357,14 +423,45 @@ pub fn run_observers_and_save_state<E, EM, I, OF, S, Z>(
             .objective_mut()
             .append_metadata(state, event_mgr, &*observers, &mut new_testcase)
             .expect("Failed adding metadata");
-        state
+        let mut signature = observers.objective_signature();
+        #[cfg(feature = "std")]
+        if let Some(mut report) = report {
+            // The backtrace starts at the faulting instruction. Module offsets stay the same across runs.
+            signature.add_crash_site(
+                report
+                    .backtrace
+                    .iter()
+                    .map(|frame| frame.module_offset.unwrap_or(frame.address)),
+            );
+            // Name the solution up front, so that the report stored along with it already refers to its file
+            let file_name = new_testcase
+                .filename()
+                .clone()
+                .unwrap_or_else(|| input.generate_name(Some(state.solutions().peek_free_id())));
+            report.reproducer = Some(PathBuf::from(&file_name));
+            *new_testcase.filename_mut() = Some(file_name);
+            new_testcase.add_metadata(report);
+        }
+        #[cfg_attr(not(feature = "std"), expect(unused_variables))]
+        let id = state
             .solutions_mut()
             .add(new_testcase)
             .expect("In run_observers_and_save_state solutions failure.");
+        #[cfg(feature = "std")]
+        if let Ok(testcase) = state.solutions().get(id) {
+            // Solutions kept in memory have no file to reproduce them from
+            let mut testcase = testcase.borrow_mut();
+            if testcase.file_path().is_none() {
+                if let Ok(report) = testcase.metadata_mut::<CrashReport>() {
+                    report.reproducer = None;
+                }
+            }
+        }
 
         let event = Event::Objective {
             input: fuzzer.share_objectives().then_some(input.clone()),
             objective_size: state.solutions().count(),
+            signature,
         };
 
         event_mgr
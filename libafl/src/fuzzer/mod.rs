@@ -23,7 +23,7 @@ use crate::{
     feedbacks::Feedback,
     inputs::Input,
     mark_feature_time,
-    observers::ObserversTuple,
+    observers::{ObjectiveSignature, ObserversTuple},
     schedulers::Scheduler,
     stages::StagesTuple,
     start_timer,
@@ -113,6 +113,7 @@ pub trait ExecutionProcessor<EM, I, OT, S> {
     ) -> Result<(), Error>;
 
     /// send event via manager
+    #[expect(clippy::too_many_arguments)]
     fn dispatch_event(
         &mut self,
         state: &mut S,
@@ -120,6 +121,7 @@ pub trait ExecutionProcessor<EM, I, OT, S> {
         input: &I,
         exec_res: &ExecuteInputResult,
         obs_buf: Option<Vec<u8>>,
+        objective_signature: ObjectiveSignature,
         exit_kind: &ExitKind,
     ) -> Result<(), Error>;
 
@@ -473,7 +475,21 @@ where
             None
         };
 
-        self.dispatch_event(state, manager, input, exec_res, observers_buf, exit_kind)?;
+        let objective_signature = if exec_res.is_solution() {
+            observers.objective_signature()
+        } else {
+            ObjectiveSignature::default()
+        };
+
+        self.dispatch_event(
+            state,
+            manager,
+            input,
+            exec_res,
+            observers_buf,
+            objective_signature,
+            exit_kind,
+        )?;
         Ok(())
     }
 
@@ -484,6 +500,7 @@ where
         input: &I,
         exec_res: &ExecuteInputResult,
         observers_buf: Option<Vec<u8>>,
+        objective_signature: ObjectiveSignature,
         exit_kind: &ExitKind,
     ) -> Result<(), Error> {
         // Now send off the event
@@ -514,6 +531,7 @@ where
                         Event::Objective {
                             input: self.share_objectives.then_some(input.clone()),
                             objective_size: state.solutions().count(),
+                            signature: objective_signature,
                         },
                         *state.executions(),
                     ),
@@ -710,6 +728,7 @@ where
                     Event::Objective {
                         input: self.share_objectives.then_some(input.clone()),
                         objective_size: state.solutions().count(),
+                        signature: observers.objective_signature(),
                     },
                     *state.executions(),
                 ),
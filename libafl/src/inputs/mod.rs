@@ -368,6 +368,12 @@ where
 pub trait TargetBytesConverter<I> {
     /// Create target bytes
     fn to_target_bytes<'a>(&mut self, input: &'a I) -> OwnedSlice<'a, u8>;
+
+    /// Create target bytes, or `None` if the input should be skipped instead of run
+    #[inline]
+    fn try_to_target_bytes<'a>(&mut self, input: &'a I) -> Option<OwnedSlice<'a, u8>> {
+        Some(self.to_target_bytes(input))
+    }
 }
 
 /// Simply gets the target bytes out from a [`HasTargetBytes`] type.
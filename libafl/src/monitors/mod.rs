@@ -29,6 +29,12 @@ pub mod prometheus;
 #[cfg(feature = "statsd_monitor")]
 pub mod statsd;
 
+#[cfg(feature = "web_monitor")]
+pub mod web;
+
+#[cfg(feature = "otel_monitor")]
+pub mod otel;
+
 #[cfg(feature = "std")]
 use alloc::vec::Vec;
 use core::{
@@ -38,10 +44,14 @@ use core::{
 };
 
 use libafl_bolts::ClientId;
+#[cfg(feature = "otel_monitor")]
+pub use otel::{InProcessOtlpCollector, OtelMonitor};
 #[cfg(feature = "prometheus_monitor")]
 pub use prometheus::PrometheusMonitor;
 #[cfg(feature = "statsd_monitor")]
 pub use statsd::StatsdMonitor;
+#[cfg(feature = "web_monitor")]
+pub use web::WebMonitor;
 
 use crate::monitors::stats::ClientStatsManager;
 
@@ -0,0 +1,661 @@
+//! OpenTelemetry monitor, exporting the fuzzer stats to an OTLP collector.
+//!
+//! The [`OtelMonitor`] sends metrics, and optionally spans, using the OTLP/HTTP protocol with JSON encoding,
+//! to the `/v1/metrics` and `/v1/traces` endpoints of a collector, such as the OpenTelemetry Collector.
+//! Only plain `http://` endpoints are supported, so run a collector (agent) next to the fuzzer if the
+//! telemetry has to leave the machine encrypted.
+//!
+//! The requests are sent from a background thread, so a slow or unreachable collector never stalls the broker.
+//! If the collector can't keep up, new exports are dropped.
+//!
+//! For tests, the [`InProcessOtlpCollector`] accepts OTLP requests and keeps them in memory.
+//!
+//! ```rust,no_run
+//! use libafl::monitors::OtelMonitor;
+//!
+//! let mon = OtelMonitor::new("http://127.0.0.1:4318")
+//!     .unwrap()
+//!     .with_resource_attribute("service.instance.id", "fuzzer-1");
+//!
+//! // and finally, like with any other monitor, pass it into the event manager like so:
+//! // let mgr = SimpleEventManager::new(mon);
+//! ```
+
+// Use this since clippy thinks we should use `OpenTelemetry` in backticks.
+#![allow(clippy::doc_markdown)]
+
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+#[cfg(feature = "introspection")]
+use core::fmt::Write as _;
+use core::{net::SocketAddr, time::Duration};
+#[cfg(feature = "introspection")]
+use std::collections::HashMap;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        Mutex,
+        mpsc::{SyncSender, TrySendError, sync_channel},
+    },
+    thread,
+};
+
+use libafl_bolts::{ClientId, Error, current_time};
+#[cfg(feature = "introspection")]
+use libafl_bolts::{
+    current_nanos,
+    rands::{Rand, StdRand},
+};
+use serde_json::{Value, json};
+
+use super::{Monitor, stats::ClientStatsManager};
+
+/// The default interval between two exports
+pub const DEFAULT_OTEL_EXPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The timeout for connecting and sending to the collector
+const OTLP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How many requests may wait for the export thread, before new ones get dropped
+const OTLP_QUEUE_LEN: usize = 16;
+
+/// The instrumentation scope of all metrics and spans
+const OTEL_SCOPE: &str = "libafl";
+
+/// An OTLP/HTTP endpoint
+#[derive(Debug, Clone)]
+struct OtlpEndpoint {
+    /// The `host:port` to connect to
+    host: String,
+    /// The path prefix, without trailing slash
+    prefix: String,
+}
+
+impl OtlpEndpoint {
+    fn parse(endpoint: &str) -> Result<Self, Error> {
+        let rest = endpoint.strip_prefix("http://").ok_or_else(|| {
+            Error::illegal_argument(format!(
+                "Only http:// OTLP endpoints are supported, got {endpoint}"
+            ))
+        })?;
+        let (host, prefix) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        if host.is_empty() {
+            return Err(Error::illegal_argument(format!(
+                "Missing host in OTLP endpoint {endpoint}"
+            )));
+        }
+        let host = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{host}:4318")
+        };
+        Ok(Self {
+            host,
+            prefix: prefix.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Posts the JSON `body` to `path`, returning the HTTP status
+    fn post(&self, path: &str, body: &Value) -> Result<u16, Error> {
+        let addr =
+            self.host.to_socket_addrs()?.next().ok_or_else(|| {
+                Error::illegal_argument(format!("Could not resolve {}", self.host))
+            })?;
+        let mut stream = TcpStream::connect_timeout(&addr, OTLP_TIMEOUT)?;
+        stream.set_read_timeout(Some(OTLP_TIMEOUT))?;
+        stream.set_write_timeout(Some(OTLP_TIMEOUT))?;
+
+        let body = body.to_string();
+        write!(
+            stream,
+            "POST {}{path} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.prefix,
+            self.host,
+            body.len()
+        )?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| Error::unknown(format!("Invalid HTTP response: {status_line}")))
+    }
+}
+
+/// A string attribute, in OTLP JSON encoding
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// A data point, in OTLP JSON encoding. Integers are encoded as strings, as they are 64 bit.
+fn data_point(attributes: &[Value], time: Duration, value: MetricValue) -> Value {
+    let mut point = json!({
+        "attributes": attributes,
+        "timeUnixNano": time.as_nanos().to_string(),
+    });
+    match value {
+        MetricValue::Int(value) => point["asInt"] = Value::String(value.to_string()),
+        MetricValue::Double(value) => point["asDouble"] = json!(value),
+    }
+    point
+}
+
+/// The value of a metric data point
+#[derive(Debug, Clone, Copy)]
+enum MetricValue {
+    Int(u64),
+    Double(f64),
+}
+
+/// The previous cycle counts of a client, to compute the spans of the time in between
+#[cfg(feature = "introspection")]
+#[derive(Debug, Clone, Default)]
+struct PerfSnapshot {
+    elapsed: u64,
+    parts: HashMap<String, u64>,
+}
+
+/// A monitor exporting the stats of each client as OpenTelemetry metrics, see the [module docs](self).
+///
+/// With the `introspection` feature and [`OtelMonitor::with_spans`], the time the clients spent in
+/// the scheduler, the event manager, each stage, and each feedback is exported as spans, too.
+/// These spans are derived from the [`super::stats::ClientPerfStats`] cycle counts since the last export,
+/// so they show the share of each part, laid out one after the other, rather than individual executions.
+#[derive(Debug, Clone)]
+pub struct OtelMonitor {
+    endpoint: OtlpEndpoint,
+    /// The queue of the export thread, started on the first export
+    exporter: Option<SyncSender<(&'static str, Value)>>,
+    resource_attributes: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    export_interval: Duration,
+    last_export: Duration,
+    spans: bool,
+    #[cfg(feature = "introspection")]
+    rand: StdRand,
+    #[cfg(feature = "introspection")]
+    perf_snapshots: HashMap<ClientId, PerfSnapshot>,
+}
+
+impl OtelMonitor {
+    /// Creates a new [`OtelMonitor`] exporting to the OTLP/HTTP collector at `endpoint`, e.g., `http://127.0.0.1:4318`
+    pub fn new(endpoint: &str) -> Result<Self, Error> {
+        Ok(Self {
+            endpoint: OtlpEndpoint::parse(endpoint)?,
+            exporter: None,
+            resource_attributes: vec![("service.name".into(), "libafl".into())],
+            export_interval: DEFAULT_OTEL_EXPORT_INTERVAL,
+            last_export: current_time(),
+            spans: false,
+            #[cfg(feature = "introspection")]
+            rand: StdRand::with_seed(current_nanos()),
+            #[cfg(feature = "introspection")]
+            perf_snapshots: HashMap::new(),
+        })
+    }
+
+    /// Adds a resource attribute, describing this fuzzer, to all exported metrics and spans.
+    /// An attribute with the same key gets replaced.
+    #[must_use]
+    pub fn with_resource_attribute<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<Cow<'static, str>>,
+        V: Into<Cow<'static, str>>,
+    {
+        let key = key.into();
+        self.resource_attributes.retain(|(k, _)| *k != key);
+        self.resource_attributes.push((key, value.into()));
+        self
+    }
+
+    /// Sets the interval between two exports
+    #[must_use]
+    pub fn with_export_interval(mut self, export_interval: Duration) -> Self {
+        self.export_interval = export_interval;
+        self
+    }
+
+    /// Also exports the time spent in the stages as spans. Requires the `introspection` feature.
+    #[must_use]
+    pub fn with_spans(mut self, spans: bool) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    fn resource(&self) -> Value {
+        let attributes: Vec<Value> = self
+            .resource_attributes
+            .iter()
+            .map(|(key, value)| attribute(key, value))
+            .collect();
+        json!({ "attributes": attributes })
+    }
+
+    fn scope() -> Value {
+        json!({ "name": OTEL_SCOPE, "version": env!("CARGO_PKG_VERSION") })
+    }
+
+    /// The OTLP metrics request for the current stats
+    fn metrics_request(
+        &self,
+        client_stats_manager: &mut ClientStatsManager,
+        now: Duration,
+    ) -> Value {
+        let mut corpus = Vec::new();
+        let mut objectives = Vec::new();
+        let mut executions = Vec::new();
+        let mut execs_per_sec = Vec::new();
+        let mut user_stats: Vec<(String, Vec<Value>)> = Vec::new();
+
+        let client_ids: Vec<ClientId> = client_stats_manager
+            .client_stats()
+            .iter()
+            .filter(|(_, client)| client.enabled())
+            .map(|(id, _)| *id)
+            .collect();
+        for client_id in client_ids {
+            let attributes = [attribute("libafl.client.id", &client_id.0.to_string())];
+            let Ok(exec_sec) = client_stats_manager
+                .update_client_stats_for(client_id, |client| client.execs_per_sec(now))
+            else {
+                continue;
+            };
+            let Ok(client) = client_stats_manager.client_stats_for(client_id) else {
+                continue;
+            };
+
+            corpus.push(data_point(
+                &attributes,
+                now,
+                MetricValue::Int(client.corpus_size()),
+            ));
+            objectives.push(data_point(
+                &attributes,
+                now,
+                MetricValue::Int(client.objective_size()),
+            ));
+            executions.push(data_point(
+                &attributes,
+                now,
+                MetricValue::Int(client.executions()),
+            ));
+            execs_per_sec.push(data_point(&attributes, now, MetricValue::Double(exec_sec)));
+
+            for (name, stat) in client.user_stats() {
+                let Some(value) = stat.value().as_f64() else {
+                    continue;
+                };
+                let point = data_point(&attributes, now, MetricValue::Double(value));
+                let metric_name = format!("libafl.user_stats.{name}");
+                match user_stats.iter_mut().find(|(n, _)| *n == metric_name) {
+                    Some((_, points)) => points.push(point),
+                    None => user_stats.push((metric_name, vec![point])),
+                }
+            }
+        }
+
+        let start = client_stats_manager.start_time().as_nanos().to_string();
+        let cumulative = |mut points: Vec<Value>| {
+            for point in &mut points {
+                point["startTimeUnixNano"] = Value::String(start.clone());
+            }
+            json!({ "aggregationTemporality": 2, "isMonotonic": true, "dataPoints": points })
+        };
+
+        let mut metrics = vec![
+            json!({ "name": "libafl.corpus.size", "unit": "{testcase}", "gauge": { "dataPoints": corpus } }),
+            json!({ "name": "libafl.objectives.size", "unit": "{testcase}", "gauge": { "dataPoints": objectives } }),
+            json!({ "name": "libafl.executions", "unit": "{execution}", "sum": cumulative(executions) }),
+            json!({ "name": "libafl.executions.rate", "unit": "{execution}/s", "gauge": { "dataPoints": execs_per_sec } }),
+        ];
+        metrics.extend(
+            user_stats
+                .into_iter()
+                .map(|(name, points)| json!({ "name": name, "gauge": { "dataPoints": points } })),
+        );
+
+        json!({
+            "resourceMetrics": [{
+                "resource": self.resource(),
+                "scopeMetrics": [{ "scope": Self::scope(), "metrics": metrics }],
+            }]
+        })
+    }
+
+    /// A random id of `bytes` bytes, hex encoded
+    #[cfg(feature = "introspection")]
+    fn random_id(&mut self, bytes: usize) -> String {
+        let mut id = String::with_capacity(bytes * 2);
+        for _ in 0..bytes / 8 {
+            write!(id, "{:016x}", self.rand.next()).unwrap();
+        }
+        id
+    }
+
+    /// The OTLP traces request for the time spent since the last export, if any
+    #[cfg(feature = "introspection")]
+    #[expect(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "span timestamps are approximations of the cycle shares"
+    )]
+    fn traces_request(
+        &mut self,
+        client_stats_manager: &ClientStatsManager,
+        now: Duration,
+    ) -> Option<Value> {
+        let window_start = self.last_export;
+        let window = now.saturating_sub(window_start).as_nanos() as f64;
+        let mut spans = Vec::new();
+
+        for (client_id, client) in client_stats_manager.client_stats() {
+            if !client.enabled() {
+                continue;
+            }
+            let perf = &client.introspection_stats;
+            let mut parts: HashMap<String, u64> = HashMap::new();
+            parts.insert("scheduler".into(), perf.scheduler_cycles());
+            parts.insert("manager".into(), perf.manager_cycles());
+            for (stage_index, features) in perf.used_stages() {
+                parts.insert(format!("stage {stage_index}"), features.iter().sum());
+            }
+            for (feedback, cycles) in perf.feedbacks() {
+                parts.insert(format!("feedback {feedback}"), *cycles);
+            }
+
+            let snapshot = PerfSnapshot {
+                elapsed: perf.elapsed_cycles(),
+                parts,
+            };
+            let previous = self
+                .perf_snapshots
+                .insert(*client_id, snapshot.clone())
+                .unwrap_or_default();
+            let elapsed = snapshot.elapsed.saturating_sub(previous.elapsed);
+            if elapsed == 0 {
+                continue;
+            }
+
+            let trace_id = self.random_id(16);
+            let root_id = self.random_id(8);
+            let client_attributes = [attribute("libafl.client.id", &client_id.0.to_string())];
+            spans.push(json!({
+                "traceId": trace_id,
+                "spanId": root_id,
+                "name": "fuzz",
+                "kind": 1,
+                "startTimeUnixNano": window_start.as_nanos().to_string(),
+                "endTimeUnixNano": now.as_nanos().to_string(),
+                "attributes": client_attributes,
+            }));
+
+            let mut names: Vec<&String> = snapshot.parts.keys().collect();
+            names.sort();
+            let mut offset = 0.0;
+            for name in names {
+                let cycles = snapshot.parts[name]
+                    .saturating_sub(previous.parts.get(name).copied().unwrap_or_default());
+                if cycles == 0 {
+                    continue;
+                }
+                let duration = window * (cycles as f64 / elapsed as f64).min(1.0);
+                let start = window_start.as_nanos() as f64 + offset;
+                offset += duration;
+                let span_id = self.random_id(8);
+                spans.push(json!({
+                    "traceId": trace_id,
+                    "spanId": span_id,
+                    "parentSpanId": root_id,
+                    "name": name,
+                    "kind": 1,
+                    "startTimeUnixNano": (start as u128).to_string(),
+                    "endTimeUnixNano": ((start + duration) as u128).to_string(),
+                    "attributes": [attribute("libafl.cycles", &cycles.to_string())],
+                }));
+            }
+        }
+
+        (!spans.is_empty()).then(|| {
+            json!({
+                "resourceSpans": [{
+                    "resource": self.resource(),
+                    "scopeSpans": [{ "scope": Self::scope(), "spans": spans }],
+                }]
+            })
+        })
+    }
+
+    /// Queues `request` to be posted to `path` by the export thread, dropping it if the queue is full.
+    ///
+    /// The thread is started lazily, so that it runs in the process that actually exports,
+    /// e.g., the broker after a fork.
+    fn export(&mut self, path: &'static str, request: Value) {
+        let exporter = self.exporter.get_or_insert_with(|| {
+            let (sender, receiver) = sync_channel::<(&'static str, Value)>(OTLP_QUEUE_LEN);
+            let endpoint = self.endpoint.clone();
+            thread::spawn(move || {
+                for (path, request) in receiver {
+                    Self::post(&endpoint, path, &request);
+                }
+            });
+            sender
+        });
+        match exporter.try_send((path, request)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                log::warn!("OTLP collector can't keep up, dropping the export to {path}");
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                log::warn!("OTLP export thread is gone, restarting it");
+                self.exporter = None;
+            }
+        }
+    }
+
+    /// Posts `request` to `path`, logging failures instead of aborting the campaign
+    fn post(endpoint: &OtlpEndpoint, path: &str, request: &Value) {
+        match endpoint.post(path, request) {
+            Ok(status) if (200..300).contains(&status) => {}
+            Ok(status) => log::warn!("OTLP collector rejected the export to {path}: {status}"),
+            Err(e) => log::warn!("Could not export to the OTLP collector: {e}"),
+        }
+    }
+}
+
+impl Monitor for OtelMonitor {
+    fn display(
+        &mut self,
+        client_stats_manager: &mut ClientStatsManager,
+        _event_msg: &str,
+        _sender_id: ClientId,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        if now.saturating_sub(self.last_export) < self.export_interval {
+            return Ok(());
+        }
+
+        let metrics = self.metrics_request(client_stats_manager, now);
+        self.export("/v1/metrics", metrics);
+
+        #[cfg(feature = "introspection")]
+        if self.spans {
+            if let Some(traces) = self.traces_request(client_stats_manager, now) {
+                self.export("/v1/traces", traces);
+            }
+        }
+        #[cfg(not(feature = "introspection"))]
+        if self.spans {
+            log::warn!("OtelMonitor spans need the `introspection` feature");
+            self.spans = false;
+        }
+
+        self.last_export = now;
+        Ok(())
+    }
+}
+
+/// An OTLP/HTTP collector stand-in, keeping all requests in memory.
+///
+/// Use it to test the telemetry of a fuzzer without running an OpenTelemetry Collector.
+#[derive(Debug, Clone)]
+pub struct InProcessOtlpCollector {
+    local_addr: SocketAddr,
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl InProcessOtlpCollector {
+    /// Starts a new [`InProcessOtlpCollector`] on a random localhost port
+    pub fn new() -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let local_addr = listener.local_addr()?;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let server_requests = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = Self::handle(stream, &server_requests) {
+                    log::warn!("OTLP collector stand-in failed to handle a request: {e}");
+                }
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            requests,
+        })
+    }
+
+    fn handle(stream: TcpStream, requests: &Mutex<Vec<(String, Value)>>) -> Result<(), Error> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let path = request_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or_default()
+            .to_string();
+
+        let mut content_length = 0;
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or_default();
+                }
+            }
+            header.clear();
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        let (status, response) = match serde_json::from_slice(&body) {
+            Ok(body) => {
+                requests.lock().unwrap().push((path, body));
+                ("200 OK", "{}")
+            }
+            Err(_) => ("400 Bad Request", ""),
+        };
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+            response.len()
+        )?;
+        Ok(())
+    }
+
+    /// The endpoint to pass to [`OtelMonitor::new`]
+    #[must_use]
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.local_addr)
+    }
+
+    /// All received requests, as path and JSON body
+    #[must_use]
+    pub fn requests(&self) -> Vec<(String, Value)> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The received metrics requests
+    #[must_use]
+    pub fn metrics(&self) -> Vec<Value> {
+        self.requests_to("/v1/metrics")
+    }
+
+    /// The received traces requests
+    #[must_use]
+    pub fn traces(&self) -> Vec<Value> {
+        self.requests_to("/v1/traces")
+    }
+
+    fn requests_to(&self, path: &str) -> Vec<Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(p, _)| p == path)
+            .map(|(_, body)| body.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::thread;
+
+    use libafl_bolts::ClientId;
+
+    use super::{InProcessOtlpCollector, OtelMonitor};
+    use crate::monitors::{Monitor, stats::ClientStatsManager};
+
+    #[test]
+    fn test_otel_monitor() {
+        let collector = InProcessOtlpCollector::new().unwrap();
+        let mut monitor = OtelMonitor::new(&collector.endpoint())
+            .unwrap()
+            .with_export_interval(Duration::ZERO)
+            .with_resource_attribute("service.name", "test-fuzzer");
+
+        let mut manager = ClientStatsManager::default();
+        manager.client_stats_insert(ClientId(3)).unwrap();
+        manager
+            .update_client_stats_for(ClientId(3), |client| {
+                client.update_corpus_size(42);
+                client.update_executions(1000, libafl_bolts::current_time());
+            })
+            .unwrap();
+        monitor.display(&mut manager, "test", ClientId(3)).unwrap();
+
+        // the export happens in the background
+        let mut metrics = collector.metrics();
+        for _ in 0..100 {
+            if !metrics.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+            metrics = collector.metrics();
+        }
+        assert_eq!(metrics.len(), 1);
+        let resource_metrics = &metrics[0]["resourceMetrics"][0];
+        assert_eq!(
+            resource_metrics["resource"]["attributes"][0]["value"]["stringValue"],
+            "test-fuzzer"
+        );
+        let corpus = &resource_metrics["scopeMetrics"][0]["metrics"][0];
+        assert_eq!(corpus["name"], "libafl.corpus.size");
+        let point = &corpus["gauge"]["dataPoints"][0];
+        assert_eq!(point["asInt"], "42");
+        assert_eq!(point["attributes"][0]["value"]["stringValue"], "3");
+
+        assert!(OtelMonitor::new("https://example.com").is_err());
+    }
+}
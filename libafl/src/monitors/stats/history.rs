@@ -0,0 +1,376 @@
+//! Historical time series of the stats, kept by the [`super::ClientStatsManager`].
+//!
+//! Each stat is sampled at a fixed interval. Once a series is full, it gets down-sampled to half its
+//! resolution, so that the history always covers the whole campaign with a bounded amount of memory.
+//! Besides the global stats, the stats of each client are kept in series of their own.
+//! The history can be exported as CSV, or, with the `stats_parquet` feature, as Parquet file.
+
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::{ops::RangeBounds, time::Duration};
+#[cfg(feature = "std")]
+use std::io::Write;
+
+use hashbrown::HashMap;
+use libafl_bolts::ClientId;
+#[cfg(feature = "std")]
+use libafl_bolts::Error;
+use serde::{Deserialize, Serialize};
+
+/// The default interval between two samples of the [`StatsHistory`]
+pub const DEFAULT_HISTORY_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The default maximum number of samples per [`TimeSeries`], before it gets down-sampled
+pub const DEFAULT_HISTORY_MAX_POINTS: usize = 2048;
+
+/// A single stat over time
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeSeries {
+    /// The samples, as time and value, in chronological order
+    points: Vec<(Duration, f64)>,
+    /// The minimum time between two samples
+    resolution: Duration,
+}
+
+impl TimeSeries {
+    /// Creates a new, empty [`TimeSeries`] with the given `resolution`
+    #[must_use]
+    pub fn new(resolution: Duration) -> Self {
+        Self {
+            points: Vec::new(),
+            resolution,
+        }
+    }
+
+    /// All samples, as time and value, in chronological order
+    #[must_use]
+    pub fn points(&self) -> &[(Duration, f64)] {
+        &self.points
+    }
+
+    /// The samples taken within `range`
+    pub fn range<R>(&self, range: R) -> impl Iterator<Item = &(Duration, f64)>
+    where
+        R: RangeBounds<Duration>,
+    {
+        self.points
+            .iter()
+            .filter(move |(time, _)| range.contains(time))
+    }
+
+    /// The latest sample
+    #[must_use]
+    pub fn latest(&self) -> Option<(Duration, f64)> {
+        self.points.last().copied()
+    }
+
+    /// The minimum time between two samples, which grows as the series gets down-sampled
+    #[must_use]
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    /// Adds a sample.
+    ///
+    /// The latest sample is replaced, until it is at least the resolution away from the sample before.
+    pub fn push(&mut self, time: Duration, value: f64, max_points: usize) {
+        let len = self.points.len();
+        if len >= 2 && time < self.points[len - 2].0 + self.resolution {
+            self.points[len - 1] = (time, value);
+        } else {
+            self.points.push((time, value));
+        }
+
+        if self.points.len() > max_points.max(2) {
+            self.downsample();
+        }
+    }
+
+    /// Halves the resolution, keeping the first and the latest sample
+    fn downsample(&mut self) {
+        let last = self.points.len() - 1;
+        let mut i = 0;
+        self.points.retain(|_| {
+            let keep = i == 0 || (last - i) % 2 == 0;
+            i += 1;
+            keep
+        });
+        self.resolution *= 2;
+    }
+}
+
+/// The series of a set of stats, by name
+type SeriesMap = HashMap<Cow<'static, str>, TimeSeries>;
+
+/// The history of all stats, see the [module docs](self).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsHistory {
+    series: SeriesMap,
+    /// The series of each client
+    #[serde(default)]
+    clients: HashMap<ClientId, SeriesMap>,
+    sample_interval: Duration,
+    max_points: usize,
+    last_sample: Option<Duration>,
+}
+
+impl Default for StatsHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_SAMPLE_INTERVAL, DEFAULT_HISTORY_MAX_POINTS)
+    }
+}
+
+impl StatsHistory {
+    /// Creates a new [`StatsHistory`], sampling every `sample_interval`,
+    /// and keeping at most `max_points` samples per stat
+    #[must_use]
+    pub fn new(sample_interval: Duration, max_points: usize) -> Self {
+        Self {
+            series: HashMap::new(),
+            clients: HashMap::new(),
+            sample_interval,
+            max_points,
+            last_sample: None,
+        }
+    }
+
+    /// The interval between two samples
+    #[must_use]
+    pub fn sample_interval(&self) -> Duration {
+        self.sample_interval
+    }
+
+    /// If a new sample is due at `time`
+    #[must_use]
+    pub fn sample_due(&self, time: Duration) -> bool {
+        self.last_sample
+            .is_none_or(|last| time.saturating_sub(last) >= self.sample_interval)
+    }
+
+    /// Marks that a sample was taken at `time`
+    pub fn mark_sampled(&mut self, time: Duration) {
+        self.last_sample = Some(time);
+    }
+
+    /// Records the `value` of the stat `name` at `time`
+    pub fn record<N>(&mut self, time: Duration, name: N, value: f64)
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let sample_interval = self.sample_interval;
+        self.series
+            .entry(name.into())
+            .or_insert_with(|| TimeSeries::new(sample_interval))
+            .push(time, value, self.max_points);
+    }
+
+    /// Records the `value` of the stat `name` of the client `client_id` at `time`
+    pub fn record_client<N>(&mut self, time: Duration, client_id: ClientId, name: N, value: f64)
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let sample_interval = self.sample_interval;
+        self.clients
+            .entry(client_id)
+            .or_default()
+            .entry(name.into())
+            .or_insert_with(|| TimeSeries::new(sample_interval))
+            .push(time, value, self.max_points);
+    }
+
+    /// The series of the stat `name`
+    #[must_use]
+    pub fn series(&self, name: &str) -> Option<&TimeSeries> {
+        self.series.get(name)
+    }
+
+    /// The names of all recorded stats, sorted
+    #[must_use]
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.series.keys().map(AsRef::as_ref).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Iterates over all recorded stats and their series
+    pub fn iter(&self) -> impl Iterator<Item = (&Cow<'static, str>, &TimeSeries)> {
+        self.series.iter()
+    }
+
+    /// The series of the stat `name` of the client `client_id`
+    #[must_use]
+    pub fn client_series(&self, client_id: ClientId, name: &str) -> Option<&TimeSeries> {
+        self.clients.get(&client_id)?.get(name)
+    }
+
+    /// The clients with recorded stats, sorted
+    #[must_use]
+    pub fn client_ids(&self) -> Vec<ClientId> {
+        let mut client_ids: Vec<ClientId> = self.clients.keys().copied().collect();
+        client_ids.sort_unstable();
+        client_ids
+    }
+
+    /// Iterates over the recorded stats of the client `client_id` and their series
+    pub fn iter_client(
+        &self,
+        client_id: ClientId,
+    ) -> impl Iterator<Item = (&Cow<'static, str>, &TimeSeries)> {
+        self.clients.get(&client_id).into_iter().flatten()
+    }
+
+    /// All samples as `time,stat,value` rows, with the time in seconds since the unix epoch.
+    ///
+    /// Rows are sorted by stat, then by time. The global stats come first,
+    /// then the stats of each client, named `client/<id>/<stat>`.
+    #[must_use]
+    pub fn rows(&self) -> Vec<(f64, String, f64)> {
+        let mut rows = Vec::new();
+        Self::push_rows(&mut rows, &self.series, "");
+        for client_id in self.client_ids() {
+            Self::push_rows(
+                &mut rows,
+                &self.clients[&client_id],
+                &format!("client/{}/", client_id.0),
+            );
+        }
+        rows
+    }
+
+    /// Pushes the rows of all `series`, sorted by stat, with the stat names prefixed by `prefix`
+    fn push_rows(rows: &mut Vec<(f64, String, f64)>, series: &SeriesMap, prefix: &str) {
+        let mut names: Vec<&str> = series.keys().map(AsRef::as_ref).collect();
+        names.sort_unstable();
+        for name in names {
+            let full_name = format!("{prefix}{name}");
+            for (time, value) in series[name].points() {
+                rows.push((time.as_secs_f64(), full_name.clone(), *value));
+            }
+        }
+    }
+
+    /// Writes all series as CSV, with a `time,stat,value` row for every sample, see [`Self::rows`].
+    #[cfg(feature = "std")]
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writeln!(writer, "time,stat,value")?;
+        for (time, name, value) in self.rows() {
+            // Quote names containing separators, as user stats may be named arbitrarily
+            if name.contains([',', '"', '\n']) {
+                let escaped = name.replace('"', "\"\"");
+                writeln!(writer, "{time:.3},\"{escaped}\",{value}")?;
+            } else {
+                writeln!(writer, "{time:.3},{name},{value}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes all series as Parquet file, with the columns `time` (seconds since the unix epoch),
+    /// `stat` and `value`, and a row for every sample, see [`Self::rows`].
+    #[cfg(feature = "stats_parquet")]
+    pub fn write_parquet<W: Write>(&self, writer: W) -> Result<(), Error> {
+        super::parquet::write_rows(writer, &self.rows())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+    use core::time::Duration;
+
+    use libafl_bolts::ClientId;
+
+    use super::StatsHistory;
+
+    #[test]
+    fn test_history_downsampling() {
+        let mut history = StatsHistory::new(Duration::from_secs(1), 8);
+        for secs in 0..64_u32 {
+            let time = Duration::from_secs(secs.into());
+            history.record(time, "corpus", secs.into());
+            // too close to the previous sample, replaces it
+            history.record(time + Duration::from_millis(500), "corpus", secs.into());
+        }
+
+        let series = history.series("corpus").unwrap();
+        assert!(series.points().len() <= 8);
+        assert!(series.resolution() > Duration::from_secs(1));
+        // the history still spans the whole run
+        assert_eq!(series.points()[0].0, Duration::ZERO);
+        assert!((series.latest().unwrap().1 - 63.0).abs() < f64::EPSILON);
+        assert!(
+            series
+                .points()
+                .windows(2)
+                .all(|w| w[0].0 < w[1].0 && w[0].1 <= w[1].1)
+        );
+        assert!(series.range(Duration::from_secs(32)..).count() < series.points().len());
+    }
+
+    #[test]
+    fn test_history_csv() {
+        let mut history = StatsHistory::default();
+        history.record(Duration::from_secs(1), "edges", 0.5);
+        history.record(Duration::from_secs(1), "a,b", 1.0);
+
+        let mut csv = Vec::new();
+        history.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "time,stat,value\n1.000,\"a,b\",1\n1.000,edges,0.5\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "stats_parquet")]
+    fn test_history_parquet() {
+        let mut history = StatsHistory::default();
+        history.record(Duration::from_secs(1), "edges", 0.5);
+        history.record_client(Duration::from_secs(2), ClientId(1), "corpus", 2.0);
+
+        let mut parquet = Vec::new();
+        history.write_parquet(&mut parquet).unwrap();
+        assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+        let footer = &parquet[..parquet.len() - 4];
+        let (rest, meta_len) = footer.split_last_chunk::<4>().unwrap();
+        let meta_len = u32::from_le_bytes(*meta_len) as usize;
+        assert!(meta_len < rest.len());
+        // The `stat` column holds the names, as length and bytes
+        let names = [
+            &5_u32.to_le_bytes()[..],
+            b"edges",
+            &15_u32.to_le_bytes(),
+            b"client/1/corpus",
+        ]
+        .concat();
+        assert!(parquet.windows(names.len()).any(|window| window == names));
+    }
+
+    #[test]
+    fn test_history_per_client() {
+        let mut history = StatsHistory::new(Duration::from_secs(1), 8);
+        for secs in 0..64_u32 {
+            let time = Duration::from_secs(secs.into());
+            history.record_client(time, ClientId(2), "corpus", secs.into());
+            history.record_client(time, ClientId(1), "corpus", 1.0);
+        }
+
+        assert!(history.series("corpus").is_none());
+        assert_eq!(history.client_ids(), [ClientId(1), ClientId(2)]);
+        // every client series is capped like the global ones
+        let series = history.client_series(ClientId(2), "corpus").unwrap();
+        assert!(series.points().len() <= 8);
+        assert!((series.latest().unwrap().1 - 63.0).abs() < f64::EPSILON);
+        assert_eq!(history.iter_client(ClientId(1)).count(), 1);
+        assert_eq!(history.iter_client(ClientId(3)).count(), 0);
+
+        let mut history = StatsHistory::default();
+        history.record(Duration::from_secs(1), "corpus", 2.0);
+        history.record_client(Duration::from_secs(1), ClientId(1), "corpus", 2.0);
+        let mut csv = Vec::new();
+        history.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "time,stat,value\n1.000,corpus,2\n1.000,client/1/corpus,2\n"
+        );
+    }
+}
@@ -10,7 +10,11 @@ use libafl_bolts::{ClientId, Error, current_time, format_duration_hms};
 #[cfg(feature = "std")]
 use serde_json::Value;
 
-use super::{ClientStats, EdgeCoverage, ProcessTiming, user_stats::UserStatsValue};
+#[cfg(feature = "introspection")]
+use super::PerfFeature;
+use super::{
+    ClientStats, EdgeCoverage, ProcessTiming, history::StatsHistory, user_stats::UserStatsValue,
+};
 #[cfg(feature = "std")]
 use super::{
     ItemGeometry,
@@ -30,6 +34,8 @@ pub struct ClientStatsManager {
     /// This will be erased to `None` every time a client is updated with crucial stats.
     cached_global_stats: Option<GlobalStats>,
     start_time: Duration,
+    /// The stats over time
+    history: StatsHistory,
 }
 
 impl ClientStatsManager {
@@ -41,6 +47,7 @@ impl ClientStatsManager {
             cached_aggregated_user_stats: HashMap::new(),
             cached_global_stats: None,
             start_time: current_time(),
+            history: StatsHistory::default(),
         }
     }
 
@@ -98,6 +105,9 @@ impl ClientStatsManager {
             if stat.stats_status.basic_stats_updated {
                 self.cached_global_stats = None;
             }
+            if self.history.sample_due(current_time()) {
+                self.record_history();
+            }
             Ok(res)
         } else {
             Err(Error::key_not_found(format!(
@@ -114,6 +124,111 @@ impl ClientStatsManager {
         self.cached_global_stats = None;
     }
 
+    /// The stats over time.
+    ///
+    /// The global stats, the numeric aggregated user stats and, with the `introspection` feature,
+    /// the share of time spent in the scheduler and the event manager are sampled whenever
+    /// a client gets updated, at most once per [`StatsHistory::sample_interval`].
+    /// The corpus size, objectives, executions and edges of each enabled client are sampled, too.
+    #[must_use]
+    pub fn history(&self) -> &StatsHistory {
+        &self.history
+    }
+
+    /// The stats over time (mutable)
+    pub fn history_mut(&mut self) -> &mut StatsHistory {
+        &mut self.history
+    }
+
+    /// Replaces the [`StatsHistory`], e.g., to change its sample interval
+    pub fn set_history(&mut self, history: StatsHistory) {
+        self.history = history;
+    }
+
+    /// Records the current stats in the [`StatsHistory`], even if no sample is due yet
+    #[expect(clippy::cast_precision_loss)]
+    pub fn record_history(&mut self) {
+        let time = current_time();
+        let edges = self.edges_coverage();
+        let global_stats = self.global_stats();
+        let samples = [
+            ("clients", global_stats.client_stats_count as f64),
+            ("corpus", global_stats.corpus_size as f64),
+            ("objectives", global_stats.objective_size as f64),
+            ("executions", global_stats.total_execs as f64),
+            ("exec_sec", global_stats.execs_per_sec),
+        ];
+
+        let history = &mut self.history;
+        history.mark_sampled(time);
+        for (name, value) in samples {
+            history.record(time, name, value);
+        }
+        if let Some(edges) = edges {
+            history.record(time, "edges_hit", edges.edges_hit as f64);
+            history.record(time, "edges_total", edges.edges_total as f64);
+        }
+        for (name, value) in &self.cached_aggregated_user_stats {
+            if let Some(value) = value.as_f64() {
+                history.record(time, format!("user_stats/{name}"), value);
+            }
+        }
+
+        for (client_id, client) in self.client_stats.iter_mut().filter(|(_, c)| c.enabled) {
+            let samples = [
+                ("corpus", client.corpus_size() as f64),
+                ("objectives", client.objective_size() as f64),
+                ("executions", client.executions() as f64),
+                ("exec_sec", client.execs_per_sec(time)),
+            ];
+            for (name, value) in samples {
+                history.record_client(time, *client_id, name, value);
+            }
+            if let Some(edges) = client.edges_coverage() {
+                history.record_client(time, *client_id, "edges_hit", edges.edges_hit as f64);
+            }
+        }
+
+        #[cfg(feature = "introspection")]
+        {
+            // The cycles of all clients, by the name of their series
+            let mut cycles: HashMap<String, u64> = HashMap::new();
+            let mut elapsed = 0_u64;
+            for client in self.client_stats.values().filter(|client| client.enabled) {
+                let perf = &client.introspection_stats;
+                elapsed += perf.elapsed_cycles();
+                *cycles.entry("perf/scheduler".into()).or_default() += perf.scheduler_cycles();
+                *cycles.entry("perf/manager".into()).or_default() += perf.manager_cycles();
+                for (stage_index, features) in perf.used_stages() {
+                    for (feature_index, feature_cycles) in features.iter().enumerate() {
+                        // Like the `Display` of `ClientPerfStats`, skip the unused features
+                        if *feature_cycles == 0 {
+                            continue;
+                        }
+                        let feature = PerfFeature::from(feature_index);
+                        *cycles
+                            .entry(format!("perf/stage/{stage_index}/{feature:?}"))
+                            .or_default() += feature_cycles;
+                    }
+                }
+                for (name, feedback_cycles) in perf.feedbacks() {
+                    *cycles.entry(format!("perf/feedback/{name}")).or_default() += feedback_cycles;
+                }
+            }
+            if elapsed > 0 {
+                let measured: u64 = cycles.values().sum();
+                for (name, value) in cycles {
+                    history.record(time, name, value as f64 / elapsed as f64);
+                }
+                history.record(
+                    time,
+                    "perf/not_measured",
+                    elapsed.saturating_sub(measured) as f64 / elapsed as f64,
+                );
+            }
+        }
+    }
+
     /// Get immutable reference to client stats
     pub fn client_stats_for(&self, client_id: ClientId) -> Result<&ClientStats, Error> {
         self.client_stats
@@ -1,6 +1,9 @@
 //! Statistics used for Monitors to display.
 
+pub mod history;
 pub mod manager;
+#[cfg(feature = "stats_parquet")]
+mod parquet;
 #[cfg(feature = "introspection")]
 pub mod perf_stats;
 pub mod user_stats;
@@ -12,6 +15,7 @@ use alloc::{
 use core::time::Duration;
 
 use hashbrown::HashMap;
+pub use history::{StatsHistory, TimeSeries};
 use libafl_bolts::current_time;
 pub use manager::ClientStatsManager;
 #[cfg(feature = "introspection")]
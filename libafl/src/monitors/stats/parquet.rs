@@ -0,0 +1,241 @@
+//! A minimal writer for the Parquet files of [`super::StatsHistory::write_parquet`].
+//!
+//! The file holds a single row group, with one uncompressed, `PLAIN` encoded data page per column.
+//! All columns are required, so the pages hold no repetition or definition levels.
+//! The metadata is encoded with the Thrift compact protocol, see
+//! <https://github.com/apache/parquet-format/blob/master/src/main/thrift/parquet.thrift>.
+
+use alloc::{string::String, vec::Vec};
+use std::io::Write;
+
+use libafl_bolts::Error;
+
+const MAGIC: &[u8; 4] = b"PAR1";
+
+/// Parquet physical types
+const TYPE_DOUBLE: i32 = 5;
+const TYPE_BYTE_ARRAY: i32 = 6;
+/// `FieldRepetitionType::REQUIRED`
+const REPETITION_REQUIRED: i32 = 0;
+/// `ConvertedType::UTF8`
+const CONVERTED_UTF8: i32 = 0;
+/// `Encoding::PLAIN` and `Encoding::RLE`
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+/// `CompressionCodec::UNCOMPRESSED`
+const CODEC_UNCOMPRESSED: i32 = 0;
+/// `PageType::DATA_PAGE`
+const PAGE_DATA: i32 = 0;
+
+/// Thrift compact protocol types
+const CT_I32: u8 = 5;
+const CT_I64: u8 = 6;
+const CT_BINARY: u8 = 8;
+const CT_LIST: u8 = 9;
+const CT_STRUCT: u8 = 12;
+
+/// Encodes Thrift structs with the compact protocol
+#[derive(Debug, Default)]
+struct CompactWriter {
+    buf: Vec<u8>,
+    /// The id of the last field of each open struct, as field ids are delta encoded
+    last_ids: Vec<i16>,
+}
+
+impl CompactWriter {
+    #[expect(clippy::cast_possible_truncation)] // 7 bits at a time
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    #[expect(clippy::cast_sign_loss)] // the zigzag encoding is unsigned
+    fn zigzag(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, ty: u8) {
+        let last = self.last_ids.last_mut().unwrap();
+        let delta = id - *last;
+        *last = id;
+        if let Ok(delta @ 1..=15) = u8::try_from(delta) {
+            self.buf.push((delta << 4) | ty);
+        } else {
+            self.buf.push(ty);
+            self.zigzag(id.into());
+        }
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, CT_I32);
+        self.zigzag(value.into());
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, CT_I64);
+        self.zigzag(value);
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, CT_BINARY);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn list(&mut self, id: i16, elem_ty: u8, len: usize) {
+        self.field(id, CT_LIST);
+        if len < 15 {
+            self.buf.push(((len as u8) << 4) | elem_ty);
+        } else {
+            self.buf.push(0xf0 | elem_ty);
+            self.varint(len as u64);
+        }
+    }
+
+    /// Starts a struct, either as field `id`, or as list element
+    fn begin_struct(&mut self, id: Option<i16>) {
+        if let Some(id) = id {
+            self.field(id, CT_STRUCT);
+        }
+        self.last_ids.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.buf.push(0);
+        self.last_ids.pop();
+    }
+}
+
+/// A column of the file
+struct Column<'a> {
+    name: &'a str,
+    physical_type: i32,
+    utf8: bool,
+    data: Vec<u8>,
+}
+
+/// Writes `rows` of `time,stat,value` as Parquet file
+#[expect(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+pub(crate) fn write_rows<W: Write>(
+    mut writer: W,
+    rows: &[(f64, String, f64)],
+) -> Result<(), Error> {
+    let mut columns = [
+        Column {
+            name: "time",
+            physical_type: TYPE_DOUBLE,
+            utf8: false,
+            data: rows.iter().flat_map(|row| row.0.to_le_bytes()).collect(),
+        },
+        Column {
+            name: "stat",
+            physical_type: TYPE_BYTE_ARRAY,
+            utf8: true,
+            data: Vec::new(),
+        },
+        Column {
+            name: "value",
+            physical_type: TYPE_DOUBLE,
+            utf8: false,
+            data: rows.iter().flat_map(|row| row.2.to_le_bytes()).collect(),
+        },
+    ];
+    for (_, stat, _) in rows {
+        let len = u32::try_from(stat.len())
+            .map_err(|_| Error::illegal_argument("Stat name too long for Parquet"))?;
+        columns[1].data.extend_from_slice(&len.to_le_bytes());
+        columns[1].data.extend_from_slice(stat.as_bytes());
+    }
+    let num_rows = rows.len() as i64;
+
+    writer.write_all(MAGIC)?;
+    let mut offset = MAGIC.len() as i64;
+    // (data page offset, total size) of each column chunk
+    let mut chunks = Vec::with_capacity(columns.len());
+    for column in &columns {
+        let page_size = i32::try_from(column.data.len())
+            .map_err(|_| Error::illegal_argument("Stats history too large for a Parquet page"))?;
+        let mut header = CompactWriter::default();
+        header.begin_struct(None);
+        header.i32(1, PAGE_DATA);
+        header.i32(2, page_size);
+        header.i32(3, page_size);
+        header.begin_struct(Some(5));
+        header.i32(1, rows.len() as i32);
+        header.i32(2, ENCODING_PLAIN);
+        header.i32(3, ENCODING_RLE);
+        header.i32(4, ENCODING_RLE);
+        header.end_struct();
+        header.end_struct();
+
+        writer.write_all(&header.buf)?;
+        writer.write_all(&column.data)?;
+        let size = (header.buf.len() + column.data.len()) as i64;
+        chunks.push((offset, size));
+        offset += size;
+    }
+
+    let mut meta = CompactWriter::default();
+    meta.begin_struct(None);
+    meta.i32(1, 1);
+    // The schema, flattened: the root, then the columns
+    meta.list(2, CT_STRUCT, columns.len() + 1);
+    meta.begin_struct(None);
+    meta.binary(4, b"schema");
+    meta.i32(5, columns.len() as i32);
+    meta.end_struct();
+    for column in &columns {
+        meta.begin_struct(None);
+        meta.i32(1, column.physical_type);
+        meta.i32(3, REPETITION_REQUIRED);
+        meta.binary(4, column.name.as_bytes());
+        if column.utf8 {
+            meta.i32(6, CONVERTED_UTF8);
+            // `LogicalType::STRING`
+            meta.begin_struct(Some(10));
+            meta.begin_struct(Some(1));
+            meta.end_struct();
+            meta.end_struct();
+        }
+        meta.end_struct();
+    }
+    meta.i64(3, num_rows);
+    // A single row group
+    meta.list(4, CT_STRUCT, 1);
+    meta.begin_struct(None);
+    meta.list(1, CT_STRUCT, columns.len());
+    for (column, (page_offset, size)) in columns.iter().zip(&chunks) {
+        meta.begin_struct(None);
+        meta.i64(2, *page_offset);
+        meta.begin_struct(Some(3));
+        meta.i32(1, column.physical_type);
+        meta.list(2, CT_I32, 2);
+        meta.zigzag(ENCODING_PLAIN.into());
+        meta.zigzag(ENCODING_RLE.into());
+        meta.list(3, CT_BINARY, 1);
+        meta.varint(column.name.len() as u64);
+        meta.buf.extend_from_slice(column.name.as_bytes());
+        meta.i32(4, CODEC_UNCOMPRESSED);
+        meta.i64(5, num_rows);
+        meta.i64(6, *size);
+        meta.i64(7, *size);
+        meta.i64(9, *page_offset);
+        meta.end_struct();
+        meta.end_struct();
+    }
+    meta.i64(2, chunks.iter().map(|(_, size)| size).sum());
+    meta.i64(3, num_rows);
+    meta.end_struct();
+    meta.binary(6, b"libafl");
+    meta.end_struct();
+
+    let meta_len = u32::try_from(meta.buf.len())
+        .map_err(|_| Error::illegal_argument("Parquet metadata too large"))?;
+    writer.write_all(&meta.buf)?;
+    writer.write_all(&meta_len.to_le_bytes())?;
+    writer.write_all(MAGIC)?;
+    Ok(())
+}
@@ -0,0 +1,375 @@
+//! The [`WebMonitor`] serves the fuzzer progress over HTTP, to be browsed while the campaign is running.
+//!
+//! ## Endpoints
+//!
+//! - `/`: a small dashboard, charting the corpus size, the coverage and the executions per second over time
+//! - `/api/stats`: the current global, aggregated user, and per-client stats, as JSON
+//! - `/api/history`: the global stats over time, as a JSON array
+//! - `/events`: a Server-Sent Events stream, sending the current stats whenever they get updated
+//!
+//! ## How to use it
+//!
+//! ```rust,no_run
+//! use libafl::monitors::WebMonitor;
+//!
+//! // Serves the dashboard on http://127.0.0.1:8080
+//! let mon = WebMonitor::new().unwrap();
+//!
+//! // and finally, like with any other monitor, pass it into the event manager like so:
+//! // let mgr = SimpleEventManager::new(mon);
+//! ```
+//!
+//! The monitor binds to localhost, unless a different address is given using [`WebMonitor::with_addr`].
+//! The endpoints are not authenticated, so only expose them on trusted networks.
+
+use alloc::{
+    collections::VecDeque,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{net::SocketAddr, time::Duration};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        Mutex,
+        mpsc::{Receiver, Sender, channel},
+    },
+    thread,
+};
+
+use libafl_bolts::{ClientId, Error, current_time};
+use serde_json::{Value, json};
+
+use crate::monitors::{Monitor, stats::ClientStatsManager};
+
+/// The address the [`WebMonitor`] listens on by default
+pub const DEFAULT_WEB_MONITOR_ADDR: &str = "127.0.0.1:8080";
+
+/// The number of data points kept for the charts, by default one hour
+pub const DEFAULT_WEB_MONITOR_HISTORY: usize = 3600;
+
+/// The dashboard served at `/`
+const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>LibAFL</title>
+<style>
+body { font-family: sans-serif; margin: 2em; background: #fafafa; }
+#global span { margin-right: 2em; }
+canvas { background: #fff; border: 1px solid #ccc; margin: 1em 1em 0 0; }
+table { border-collapse: collapse; margin-top: 1em; }
+td, th { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: right; }
+</style>
+</head>
+<body>
+<h1>LibAFL</h1>
+<div id="global"></div>
+<div>
+<canvas id="corpus" width="420" height="200"></canvas>
+<canvas id="coverage" width="420" height="200"></canvas>
+<canvas id="exec_sec" width="420" height="200"></canvas>
+</div>
+<table id="clients"></table>
+<script>
+let history = [];
+const charts = [
+  ["corpus", "corpus size", p => p.corpus],
+  ["coverage", "edges hit", p => p.edges_hit],
+  ["exec_sec", "exec/sec", p => p.exec_sec],
+];
+function draw() {
+  for (const [id, label, value] of charts) {
+    const c = document.getElementById(id), ctx = c.getContext("2d");
+    ctx.clearRect(0, 0, c.width, c.height);
+    const points = history.filter(p => value(p) !== null);
+    const max = Math.max(1, ...points.map(value));
+    ctx.fillText(label + " (max " + max.toFixed(0) + ")", 5, 12);
+    if (points.length < 2) continue;
+    const t0 = points[0].time, t1 = points[points.length - 1].time;
+    ctx.beginPath();
+    points.forEach((p, i) => {
+      const x = (p.time - t0) / Math.max(1, t1 - t0) * (c.width - 10) + 5;
+      const y = c.height - 5 - value(p) / max * (c.height - 25);
+      i ? ctx.lineTo(x, y) : ctx.moveTo(x, y);
+    });
+    ctx.stroke();
+  }
+}
+function show(stats) {
+  const g = stats.global;
+  document.getElementById("global").innerHTML =
+    `<span>run time: ${g.run_time}</span><span>clients: ${g.clients}</span>` +
+    `<span>corpus: ${g.corpus}</span><span>objectives: ${g.objectives}</span>` +
+    `<span>executions: ${g.executions}</span><span>exec/sec: ${g.exec_sec.toFixed(1)}</span>`;
+  let rows = "<tr><th>client</th><th>corpus</th><th>objectives</th><th>executions</th></tr>";
+  for (const [id, c] of Object.entries(stats.clients)) {
+    rows += `<tr><td>${id}</td><td>${c.corpus_size}</td><td>${c.objective_size}</td><td>${c.executions}</td></tr>`;
+  }
+  document.getElementById("clients").innerHTML = rows;
+  history.push(stats.point);
+  draw();
+}
+fetch("/api/history").then(r => r.json()).then(h => { history = h; draw(); });
+new EventSource("/events").onmessage = e => show(JSON.parse(e.data));
+</script>
+</body>
+</html>
+"#;
+
+/// The state shared between the [`WebMonitor`] and the HTTP server thread
+#[derive(Debug, Default)]
+struct WebMonitorState {
+    /// The latest stats, as JSON
+    stats: String,
+    /// The global stats over time
+    history: VecDeque<Value>,
+    /// The open Server-Sent Events streams
+    subscribers: Vec<Sender<Arc<String>>>,
+}
+
+/// A monitor serving the fuzzer progress as an HTTP dashboard and JSON API, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct WebMonitor {
+    state: Arc<Mutex<WebMonitorState>>,
+    local_addr: SocketAddr,
+    last_update: Duration,
+    update_interval: Duration,
+    max_history: usize,
+}
+
+impl WebMonitor {
+    /// Creates a new [`WebMonitor`], serving on [`DEFAULT_WEB_MONITOR_ADDR`]
+    pub fn new() -> Result<Self, Error> {
+        Self::with_addr(DEFAULT_WEB_MONITOR_ADDR)
+    }
+
+    /// Creates a new [`WebMonitor`], serving on `addr`
+    pub fn with_addr<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(WebMonitorState::default()));
+
+        let server_state = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let state = server_state.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = handle_connection(stream, &state) {
+                                log::debug!("Web monitor connection failed: {e}");
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!("Web monitor failed to accept a connection: {e}"),
+                }
+            }
+        });
+        log::info!("Serving the web monitor on http://{local_addr}");
+
+        Ok(Self {
+            state,
+            local_addr,
+            last_update: Duration::ZERO,
+            update_interval: Duration::from_secs(1),
+            max_history: DEFAULT_WEB_MONITOR_HISTORY,
+        })
+    }
+
+    /// Sets how often the stats get updated, one second by default
+    #[must_use]
+    pub fn with_update_interval(mut self, update_interval: Duration) -> Self {
+        self.update_interval = update_interval;
+        self
+    }
+
+    /// Sets how many data points are kept for the charts
+    #[must_use]
+    pub fn with_max_history(mut self, max_history: usize) -> Self {
+        self.max_history = max_history;
+        self
+    }
+
+    /// The address the dashboard is served on
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Monitor for WebMonitor {
+    fn display(
+        &mut self,
+        client_stats_manager: &mut ClientStatsManager,
+        event_msg: &str,
+        _sender_id: ClientId,
+    ) -> Result<(), Error> {
+        let cur_time = current_time();
+        if cur_time.saturating_sub(self.last_update) < self.update_interval {
+            return Ok(());
+        }
+        self.last_update = cur_time;
+
+        let edges = client_stats_manager.edges_coverage();
+        let global_stats = client_stats_manager.global_stats();
+        let point = json!({
+            "time": cur_time.as_secs(),
+            "corpus": global_stats.corpus_size,
+            "objectives": global_stats.objective_size,
+            "executions": global_stats.total_execs,
+            "exec_sec": global_stats.execs_per_sec,
+            "edges_hit": edges.as_ref().map(|edges| edges.edges_hit),
+            "edges_total": edges.as_ref().map(|edges| edges.edges_total),
+        });
+        let stats = json!({
+            "event": event_msg,
+            "global": {
+                "run_time": global_stats.run_time_pretty,
+                "run_time_secs": global_stats.run_time.as_secs(),
+                "clients": global_stats.client_stats_count,
+                "corpus": global_stats.corpus_size,
+                "objectives": global_stats.objective_size,
+                "executions": global_stats.total_execs,
+                "exec_sec": global_stats.execs_per_sec,
+            },
+            "user_stats": client_stats_manager.aggregated(),
+            "clients": client_stats_manager.client_stats(),
+            "point": point,
+        })
+        .to_string();
+
+        let mut state = self.state.lock().unwrap();
+        state.history.push_back(point);
+        while state.history.len() > self.max_history {
+            state.history.pop_front();
+        }
+
+        let update = Arc::new(stats.clone());
+        state
+            .subscribers
+            .retain(|subscriber| subscriber.send(update.clone()).is_ok());
+        state.stats = stats;
+
+        Ok(())
+    }
+}
+
+/// Answers a single HTTP request
+fn handle_connection(stream: TcpStream, state: &Mutex<WebMonitorState>) -> Result<(), Error> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return respond(stream, "400 Bad Request", "text/plain", "Bad Request");
+    };
+    if method != "GET" {
+        return respond(
+            stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method Not Allowed",
+        );
+    }
+
+    match path {
+        "/" | "/index.html" => respond(stream, "200 OK", "text/html", DASHBOARD_HTML),
+        "/api/stats" => {
+            let stats = state.lock().unwrap().stats.clone();
+            let stats = if stats.is_empty() { "{}".into() } else { stats };
+            respond(stream, "200 OK", "application/json", &stats)
+        }
+        "/api/history" => {
+            let history = Value::Array(state.lock().unwrap().history.iter().cloned().collect());
+            respond(stream, "200 OK", "application/json", &history.to_string())
+        }
+        "/events" => {
+            let (sender, receiver) = channel();
+            state.lock().unwrap().subscribers.push(sender);
+            stream_events(stream, &receiver)
+        }
+        _ => respond(stream, "404 Not Found", "text/plain", "Not Found"),
+    }
+}
+
+fn respond(
+    mut stream: TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> Result<(), Error> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+/// Sends each update as a Server-Sent Event, until the client disconnects
+fn stream_events(mut stream: TcpStream, updates: &Receiver<Arc<String>>) -> Result<(), Error> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n"
+    )?;
+    stream.flush()?;
+    for update in updates {
+        write!(stream, "data: {update}\n\n")?;
+        stream.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use libafl_bolts::ClientId;
+
+    use super::WebMonitor;
+    use crate::monitors::{Monitor, stats::ClientStatsManager};
+
+    fn get(monitor: &WebMonitor, path: &str) -> String {
+        let mut stream = TcpStream::connect(monitor.local_addr()).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_web_monitor() {
+        let mut monitor = WebMonitor::with_addr("127.0.0.1:0").unwrap();
+        assert!(monitor.local_addr().ip().is_loopback());
+
+        let mut manager = ClientStatsManager::default();
+        manager.client_stats_insert(ClientId(1)).unwrap();
+        manager
+            .update_client_stats_for(ClientId(1), |client| client.update_corpus_size(42))
+            .unwrap();
+        monitor.display(&mut manager, "test", ClientId(1)).unwrap();
+
+        let stats = get(&monitor, "/api/stats");
+        assert!(stats.starts_with("HTTP/1.1 200 OK"));
+        assert!(stats.contains(r#""corpus":42"#));
+
+        let history = get(&monitor, "/api/history");
+        assert!(history.contains(r#""corpus":42"#));
+
+        assert!(get(&monitor, "/").contains("EventSource"));
+        assert!(get(&monitor, "/nope").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,765 @@
+//! Mutators driving AFL++ custom mutator libraries through the AFL++ custom mutator ABI.
+//!
+//! A shared object exporting `afl_custom_init` and (some of) `afl_custom_fuzz`, `afl_custom_havoc_mutation`,
+//! `afl_custom_post_process`, and the `afl_custom_*_trim` functions can be loaded with
+//! [`AflCustomMutatorLibrary::load`], and then used through
+//! - [`AflCustomMutator`] (`afl_custom_fuzz`), as a standalone mutator,
+//! - [`AflCustomHavocMutator`] (`afl_custom_havoc_mutation`), as one more mutation in a havoc stack,
+//! - [`AflCustomTrimMutator`] (`afl_custom_*_trim`), in a [`crate::stages::StdTMinMutationalStage`],
+//! - [`AflCustomPostProcessor`] (`afl_custom_post_process`), as [`TargetBytesConverter`] for executors.
+//!
+//! All of them share the same library instance, just like AFL++ does.
+//! The `afl` pointer passed to `afl_custom_init` is `NULL`, as there is no AFL++ state to pass.
+
+use alloc::{borrow::Cow, rc::Rc, vec::Vec};
+use core::{
+    ffi::{c_uint, c_void},
+    marker::PhantomData,
+    ptr, slice,
+};
+use std::{
+    ffi::{CStr, CString},
+    os::unix::ffi::OsStrExt,
+    path::Path,
+};
+
+use ahash::RandomState;
+use libafl_bolts::{Named, ownedref::OwnedSlice, rands::Rand};
+
+use crate::{
+    Error,
+    corpus::{Corpus, CorpusId},
+    inputs::{HasMutatorBytes, HasTargetBytes, ResizableMutator, TargetBytesConverter},
+    mutators::{MutationResult, Mutator},
+    random_corpus_id_with_disabled,
+    state::{HasCorpus, HasMaxSize, HasRand},
+};
+
+type AflCustomInitFn = unsafe extern "C" fn(afl: *mut c_void, seed: c_uint) -> *mut c_void;
+type AflCustomDeinitFn = unsafe extern "C" fn(data: *mut c_void);
+type AflCustomFuzzFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *mut u8,
+    add_buf: *mut u8,
+    add_buf_size: usize,
+    max_size: usize,
+) -> usize;
+type AflCustomHavocMutationFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *mut u8,
+    max_size: usize,
+) -> usize;
+type AflCustomPostProcessFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *mut u8,
+) -> usize;
+type AflCustomInitTrimFn =
+    unsafe extern "C" fn(data: *mut c_void, buf: *mut u8, buf_size: usize) -> i32;
+type AflCustomTrimFn = unsafe extern "C" fn(data: *mut c_void, out_buf: *mut *mut u8) -> usize;
+type AflCustomPostTrimFn = unsafe extern "C" fn(data: *mut c_void, success: u8) -> i32;
+
+/// A loaded AFL++ custom mutator library, with its initialized `data` instance.
+///
+/// The library gets deinitialized (`afl_custom_deinit`) and unloaded on drop.
+#[derive(Debug)]
+pub struct AflCustomMutatorLibrary {
+    handle: *mut c_void,
+    data: *mut c_void,
+    deinit: Option<AflCustomDeinitFn>,
+    fuzz: Option<AflCustomFuzzFn>,
+    havoc_mutation: Option<AflCustomHavocMutationFn>,
+    post_process: Option<AflCustomPostProcessFn>,
+    init_trim: Option<AflCustomInitTrimFn>,
+    trim: Option<AflCustomTrimFn>,
+    post_trim: Option<AflCustomPostTrimFn>,
+}
+
+/// Looks up a symbol and transmutes it to the given function pointer type
+///
+/// # Safety
+/// `F` must be the correct function pointer type for the symbol.
+unsafe fn lookup<F>(handle: *mut c_void, symbol: &CStr) -> Option<F> {
+    unsafe {
+        let sym = libc::dlsym(handle, symbol.as_ptr());
+        if sym.is_null() {
+            None
+        } else {
+            Some(core::mem::transmute_copy::<*mut c_void, F>(&sym))
+        }
+    }
+}
+
+/// Returns the last `dlerror`, or a generic message
+fn dl_error() -> Cow<'static, str> {
+    // # Safety
+    // `dlerror` returns either null or a valid C string
+    unsafe {
+        let err = libc::dlerror();
+        if err.is_null() {
+            Cow::Borrowed("unknown error")
+        } else {
+            Cow::Owned(CStr::from_ptr(err).to_string_lossy().into_owned())
+        }
+    }
+}
+
+impl AflCustomMutatorLibrary {
+    /// Loads an AFL++ custom mutator shared object and calls its `afl_custom_init` with the given `seed`.
+    pub fn load<P>(path: P, seed: u32) -> Result<Rc<Self>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| Error::illegal_argument("Library path contains a NUL byte"))?;
+
+        // # Safety
+        // Loading a library runs its constructors, the user is responsible for passing a sane custom mutator.
+        unsafe {
+            let handle = libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW);
+            if handle.is_null() {
+                return Err(Error::illegal_argument(format!(
+                    "Could not load custom mutator {}: {}",
+                    path.display(),
+                    dl_error()
+                )));
+            }
+
+            let Some(init) = lookup::<AflCustomInitFn>(handle, c"afl_custom_init") else {
+                libc::dlclose(handle);
+                return Err(Error::illegal_argument(format!(
+                    "Custom mutator {} does not export afl_custom_init",
+                    path.display()
+                )));
+            };
+
+            let data = init(ptr::null_mut(), seed);
+            if data.is_null() {
+                libc::dlclose(handle);
+                return Err(Error::illegal_state(format!(
+                    "afl_custom_init of {} failed",
+                    path.display()
+                )));
+            }
+
+            let library = Self {
+                handle,
+                data,
+                deinit: lookup(handle, c"afl_custom_deinit"),
+                fuzz: lookup(handle, c"afl_custom_fuzz"),
+                havoc_mutation: lookup(handle, c"afl_custom_havoc_mutation"),
+                post_process: lookup(handle, c"afl_custom_post_process"),
+                init_trim: lookup(handle, c"afl_custom_init_trim"),
+                trim: lookup(handle, c"afl_custom_trim"),
+                post_trim: lookup(handle, c"afl_custom_post_trim"),
+            };
+
+            if library.trim.is_some()
+                && (library.init_trim.is_none() || library.post_trim.is_none())
+            {
+                return Err(Error::illegal_argument(format!(
+                    "Custom mutator {} exports afl_custom_trim, but not afl_custom_init_trim and afl_custom_post_trim",
+                    path.display()
+                )));
+            }
+
+            log::info!("Loaded AFL++ custom mutator {}", path.display());
+            Ok(Rc::new(library))
+        }
+    }
+
+    /// If the library exports `afl_custom_fuzz`
+    #[must_use]
+    pub fn has_fuzz(&self) -> bool {
+        self.fuzz.is_some()
+    }
+
+    /// If the library exports `afl_custom_havoc_mutation`
+    #[must_use]
+    pub fn has_havoc_mutation(&self) -> bool {
+        self.havoc_mutation.is_some()
+    }
+
+    /// If the library exports `afl_custom_post_process`
+    #[must_use]
+    pub fn has_post_process(&self) -> bool {
+        self.post_process.is_some()
+    }
+
+    /// If the library exports the `afl_custom_*_trim` functions
+    #[must_use]
+    pub fn has_trim(&self) -> bool {
+        self.trim.is_some()
+    }
+}
+
+impl Drop for AflCustomMutatorLibrary {
+    fn drop(&mut self) {
+        // # Safety
+        // `data` was returned by `afl_custom_init` of this library, and is not used after this.
+        unsafe {
+            if let Some(deinit) = self.deinit {
+                deinit(self.data);
+            }
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+/// Copies `len` bytes the library returned in `out_buf` into the input.
+///
+/// # Safety
+/// `out_buf` must be valid for `len` bytes, if `len` is not `0`.
+unsafe fn copy_out_buf<I>(input: &mut I, out_buf: *const u8, len: usize)
+where
+    I: ResizableMutator<u8> + HasMutatorBytes,
+{
+    // The library may hand back `buf` itself, copy first as resizing can reallocate it.
+    let out = if len == 0 || out_buf.is_null() {
+        Vec::new()
+    } else {
+        unsafe { slice::from_raw_parts(out_buf, len) }.to_vec()
+    };
+    input.resize(out.len(), 0);
+    input.mutator_bytes_mut().copy_from_slice(&out);
+}
+
+/// A [`Mutator`] calling `afl_custom_fuzz` of an AFL++ custom mutator library.
+///
+/// A random other corpus entry is passed as `add_buf`, for splicing.
+#[derive(Debug)]
+pub struct AflCustomMutator {
+    name: Cow<'static, str>,
+    library: Rc<AflCustomMutatorLibrary>,
+}
+
+impl AflCustomMutator {
+    /// Creates a new [`AflCustomMutator`], failing if the library does not export `afl_custom_fuzz`
+    pub fn new(library: Rc<AflCustomMutatorLibrary>) -> Result<Self, Error> {
+        if !library.has_fuzz() {
+            return Err(Error::illegal_argument(
+                "Custom mutator does not export afl_custom_fuzz",
+            ));
+        }
+        Ok(Self {
+            name: Cow::Borrowed("AflCustomMutator"),
+            library,
+        })
+    }
+}
+
+impl Named for AflCustomMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, S> Mutator<I, S> for AflCustomMutator
+where
+    S: HasCorpus<I> + HasRand + HasMaxSize,
+    I: ResizableMutator<u8> + HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let max_size = state.max_size();
+        let fuzz = self.library.fuzz.unwrap();
+
+        let mut add_buf = {
+            let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
+            let mut other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
+            other_testcase
+                .load_input(state.corpus())?
+                .mutator_bytes()
+                .to_vec()
+        };
+
+        let mut out_buf: *mut u8 = ptr::null_mut();
+        // # Safety
+        // All buffers are valid for the given sizes, and `data` belongs to this library.
+        let new_len = unsafe {
+            fuzz(
+                self.library.data,
+                input.mutator_bytes_mut().as_mut_ptr(),
+                input.len(),
+                &raw mut out_buf,
+                add_buf.as_mut_ptr(),
+                add_buf.len(),
+                max_size,
+            )
+        };
+
+        if new_len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        unsafe { copy_out_buf(input, out_buf, new_len.min(max_size)) };
+        Ok(MutationResult::Mutated)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, _state: &mut S, _new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A [`Mutator`] calling `afl_custom_havoc_mutation` of an AFL++ custom mutator library.
+///
+/// Add this to the mutations of a [`crate::mutators::HavocScheduledMutator`] to stack it
+/// with the other havoc mutations, like AFL++ does.
+#[derive(Debug)]
+pub struct AflCustomHavocMutator {
+    name: Cow<'static, str>,
+    library: Rc<AflCustomMutatorLibrary>,
+}
+
+impl AflCustomHavocMutator {
+    /// Creates a new [`AflCustomHavocMutator`], failing if the library does not export `afl_custom_havoc_mutation`
+    pub fn new(library: Rc<AflCustomMutatorLibrary>) -> Result<Self, Error> {
+        if !library.has_havoc_mutation() {
+            return Err(Error::illegal_argument(
+                "Custom mutator does not export afl_custom_havoc_mutation",
+            ));
+        }
+        Ok(Self {
+            name: Cow::Borrowed("AflCustomHavocMutator"),
+            library,
+        })
+    }
+}
+
+impl Named for AflCustomHavocMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, S> Mutator<I, S> for AflCustomHavocMutator
+where
+    S: HasMaxSize,
+    I: ResizableMutator<u8> + HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let max_size = state.max_size();
+        let havoc_mutation = self.library.havoc_mutation.unwrap();
+
+        let mut out_buf: *mut u8 = ptr::null_mut();
+        // # Safety
+        // The input buffer is valid for its length, and `data` belongs to this library.
+        let new_len = unsafe {
+            havoc_mutation(
+                self.library.data,
+                input.mutator_bytes_mut().as_mut_ptr(),
+                input.len(),
+                &raw mut out_buf,
+                max_size,
+            )
+        };
+
+        if new_len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        unsafe { copy_out_buf(input, out_buf, new_len.min(max_size)) };
+        Ok(MutationResult::Mutated)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, _state: &mut S, _new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// An ongoing `afl_custom_trim` session
+#[derive(Debug, Clone, Copy)]
+struct TrimSession {
+    /// The hash of the input the library currently trims
+    input_hash: u64,
+    /// The hash of the last trimmed candidate, which becomes the input once accepted
+    candidate_hash: u64,
+    current_step: i32,
+    total_steps: i32,
+}
+
+/// A trimming [`Mutator`] calling the `afl_custom_init_trim`, `afl_custom_trim` and `afl_custom_post_trim`
+/// functions of an AFL++ custom mutator library.
+///
+/// Use it in a [`crate::stages::StdTMinMutationalStage`], which reports through [`Mutator::post_trim`]
+/// whether each trimmed candidate was kept. Once all trimming steps are done,
+/// the mutator skips until it gets a different input.
+#[derive(Debug)]
+pub struct AflCustomTrimMutator {
+    name: Cow<'static, str>,
+    library: Rc<AflCustomMutatorLibrary>,
+    session: Option<TrimSession>,
+}
+
+impl AflCustomTrimMutator {
+    /// Creates a new [`AflCustomTrimMutator`], failing if the library does not export the trim functions
+    pub fn new(library: Rc<AflCustomMutatorLibrary>) -> Result<Self, Error> {
+        if !library.has_trim() {
+            return Err(Error::illegal_argument(
+                "Custom mutator does not export afl_custom_trim",
+            ));
+        }
+        Ok(Self {
+            name: Cow::Borrowed("AflCustomTrimMutator"),
+            library,
+            session: None,
+        })
+    }
+}
+
+impl Named for AflCustomTrimMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, S> Mutator<I, S> for AflCustomTrimMutator
+where
+    I: ResizableMutator<u8> + HasMutatorBytes,
+{
+    fn mutate(&mut self, _state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let input_hash = RandomState::with_seeds(0, 0, 0, 0).hash_one(input.mutator_bytes());
+
+        let session = match self.session {
+            // Keep trimming the buffer the library holds
+            Some(session)
+                if session.input_hash == input_hash
+                    && session.current_step < session.total_steps =>
+            {
+                session
+            }
+            // Done with this input
+            Some(session) if session.input_hash == input_hash => {
+                return Ok(MutationResult::Skipped);
+            }
+            // A new input, abandon any unfinished session
+            _ => {
+                // # Safety
+                // The input buffer is valid for its length, and `data` belongs to this library.
+                let total_steps = unsafe {
+                    (self.library.init_trim.unwrap())(
+                        self.library.data,
+                        input.mutator_bytes_mut().as_mut_ptr(),
+                        input.len(),
+                    )
+                };
+                if total_steps < 0 {
+                    return Err(Error::illegal_state("afl_custom_init_trim failed"));
+                }
+                let session = TrimSession {
+                    input_hash,
+                    candidate_hash: input_hash,
+                    current_step: 0,
+                    total_steps,
+                };
+                self.session = Some(session);
+                if total_steps == 0 {
+                    return Ok(MutationResult::Skipped);
+                }
+                session
+            }
+        };
+        debug_assert!(session.current_step < session.total_steps);
+
+        let mut out_buf: *mut u8 = ptr::null_mut();
+        // # Safety
+        // `data` belongs to this library, and trimming was initialized.
+        let new_len = unsafe { (self.library.trim.unwrap())(self.library.data, &raw mut out_buf) };
+        unsafe { copy_out_buf(input, out_buf, new_len) };
+        // If the candidate gets accepted, it is the input of the next step
+        let candidate_hash = RandomState::with_seeds(0, 0, 0, 0).hash_one(input.mutator_bytes());
+        if let Some(session) = self.session.as_mut() {
+            session.candidate_hash = candidate_hash;
+        }
+        Ok(MutationResult::Mutated)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, _state: &mut S, _new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn post_trim(&mut self, _state: &mut S, success: bool) -> Result<(), Error> {
+        let Some(session) = self.session.as_mut() else {
+            return Ok(());
+        };
+        if session.current_step >= session.total_steps {
+            return Ok(());
+        }
+        // # Safety
+        // `data` belongs to this library, and trimming was initialized.
+        let next_step =
+            unsafe { (self.library.post_trim.unwrap())(self.library.data, u8::from(success)) };
+        if next_step < 0 {
+            return Err(Error::illegal_state("afl_custom_post_trim failed"));
+        }
+        session.current_step = next_step;
+        if success {
+            session.input_hash = session.candidate_hash;
+        }
+        Ok(())
+    }
+}
+
+/// A [`TargetBytesConverter`] calling `afl_custom_post_process` of an AFL++ custom mutator library,
+/// for example to fix up checksums before the input is handed to the target.
+///
+/// Inputs the library returns no data for are skipped by the [`crate::executors::ForkserverExecutor`].
+#[derive(Debug)]
+pub struct AflCustomPostProcessor<I> {
+    library: Rc<AflCustomMutatorLibrary>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> AflCustomPostProcessor<I> {
+    /// Creates a new [`AflCustomPostProcessor`], failing if the library does not export `afl_custom_post_process`
+    pub fn new(library: Rc<AflCustomMutatorLibrary>) -> Result<Self, Error> {
+        if !library.has_post_process() {
+            return Err(Error::illegal_argument(
+                "Custom mutator does not export afl_custom_post_process",
+            ));
+        }
+        Ok(Self {
+            library,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<I> TargetBytesConverter<I> for AflCustomPostProcessor<I>
+where
+    I: HasTargetBytes,
+{
+    /// Inputs the library rejects become empty, executors that skip them use
+    /// [`TargetBytesConverter::try_to_target_bytes`] instead.
+    fn to_target_bytes<'a>(&mut self, input: &'a I) -> OwnedSlice<'a, u8> {
+        self.try_to_target_bytes(input)
+            .unwrap_or_else(|| OwnedSlice::from(Vec::new()))
+    }
+
+    fn try_to_target_bytes<'a>(&mut self, input: &'a I) -> Option<OwnedSlice<'a, u8>> {
+        let mut buf = input.target_bytes().to_vec();
+        let mut out_buf: *mut u8 = ptr::null_mut();
+        // # Safety
+        // The buffer is valid for its length, and `data` belongs to this library.
+        let new_len = unsafe {
+            (self.library.post_process.unwrap())(
+                self.library.data,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &raw mut out_buf,
+            )
+        };
+        if new_len == 0 || out_buf.is_null() {
+            // AFL++ skips such inputs
+            log::debug!("afl_custom_post_process returned no data, skipping the input");
+            return None;
+        }
+        // # Safety
+        // The library guarantees `out_buf` is valid for `new_len` bytes until the next call.
+        Some(OwnedSlice::from(
+            unsafe { slice::from_raw_parts(out_buf, new_len) }.to_vec(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, rc::Rc, vec::Vec};
+    use core::{ffi::c_void, ptr, slice};
+
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list};
+
+    use super::{AflCustomMutatorLibrary, AflCustomPostProcessor, AflCustomTrimMutator};
+    use crate::{
+        StdFuzzer,
+        corpus::{Corpus, HasCurrentCorpusId, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::{ConstFeedback, CrashFeedback},
+        inputs::{BytesInput, HasMutatorBytes, TargetBytesConverter},
+        mutators::{MutationResult, Mutator},
+        schedulers::QueueScheduler,
+        stages::{Restartable, Stage, StdTMinMutationalStage},
+        state::{HasCorpus, StdState},
+    };
+
+    /// The state of the stub custom mutator: it trims one byte off the end per step.
+    #[derive(Default)]
+    struct StubData {
+        buf: Vec<u8>,
+        step: i32,
+    }
+
+    unsafe fn stub_data<'a>(data: *mut c_void) -> &'a mut StubData {
+        unsafe { &mut *data.cast::<StubData>() }
+    }
+
+    unsafe extern "C" fn stub_deinit(data: *mut c_void) {
+        drop(unsafe { Box::from_raw(data.cast::<StubData>()) });
+    }
+
+    unsafe extern "C" fn stub_init_trim(data: *mut c_void, buf: *mut u8, buf_size: usize) -> i32 {
+        let data = unsafe { stub_data(data) };
+        data.buf = unsafe { slice::from_raw_parts(buf, buf_size) }.to_vec();
+        data.step = 0;
+        i32::try_from(buf_size).unwrap()
+    }
+
+    unsafe extern "C" fn stub_trim(data: *mut c_void, out_buf: *mut *mut u8) -> usize {
+        let data = unsafe { stub_data(data) };
+        unsafe { *out_buf = data.buf.as_mut_ptr() };
+        data.buf.len() - 1
+    }
+
+    unsafe extern "C" fn stub_post_trim(data: *mut c_void, success: u8) -> i32 {
+        let data = unsafe { stub_data(data) };
+        if success != 0 {
+            data.buf.pop();
+        }
+        data.step += 1;
+        data.step
+    }
+
+    /// Uppercases the first byte, and drops inputs starting with `!`
+    unsafe extern "C" fn stub_post_process(
+        _data: *mut c_void,
+        buf: *mut u8,
+        buf_size: usize,
+        out_buf: *mut *mut u8,
+    ) -> usize {
+        let buf_slice = unsafe { slice::from_raw_parts_mut(buf, buf_size) };
+        if buf_slice.first() == Some(&b'!') {
+            return 0;
+        }
+        if let Some(first) = buf_slice.first_mut() {
+            first.make_ascii_uppercase();
+        }
+        unsafe { *out_buf = buf };
+        buf_size
+    }
+
+    fn stub_library() -> Rc<AflCustomMutatorLibrary> {
+        // # Safety
+        // A handle to the main program, which `Drop` may safely `dlclose`.
+        let handle = unsafe { libc::dlopen(ptr::null(), libc::RTLD_NOW) };
+        assert!(!handle.is_null());
+        Rc::new(AflCustomMutatorLibrary {
+            handle,
+            data: Box::into_raw(Box::<StubData>::default()).cast(),
+            deinit: Some(stub_deinit),
+            fuzz: None,
+            havoc_mutation: None,
+            post_process: Some(stub_post_process),
+            init_trim: Some(stub_init_trim),
+            trim: Some(stub_trim),
+            post_trim: Some(stub_post_trim),
+        })
+    }
+
+    #[test]
+    fn test_load_missing_library() {
+        let err = AflCustomMutatorLibrary::load("/nonexistent/custom_mutator.so", 0).unwrap_err();
+        assert!(format!("{err:?}").contains("Could not load custom mutator"));
+    }
+
+    fn trim_step(trim: &mut AflCustomTrimMutator, input: &[u8]) -> (MutationResult, Vec<u8>) {
+        let mut input = BytesInput::new(input.to_vec());
+        let result = Mutator::<BytesInput, ()>::mutate(trim, &mut (), &mut input).unwrap();
+        (result, input.mutator_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_trim_steps() {
+        let mut trim = AflCustomTrimMutator::new(stub_library()).unwrap();
+
+        // The accepted candidate continues the session
+        assert_eq!(
+            trim_step(&mut trim, b"abcd"),
+            (MutationResult::Mutated, b"abc".to_vec())
+        );
+        Mutator::<BytesInput, ()>::post_trim(&mut trim, &mut (), true).unwrap();
+        assert_eq!(
+            trim_step(&mut trim, b"abc"),
+            (MutationResult::Mutated, b"ab".to_vec())
+        );
+        Mutator::<BytesInput, ()>::post_trim(&mut trim, &mut (), false).unwrap();
+
+        // A different input abandons the unfinished session
+        assert_eq!(
+            trim_step(&mut trim, b"xyz"),
+            (MutationResult::Mutated, b"xy".to_vec())
+        );
+        for _ in 0..3 {
+            Mutator::<BytesInput, ()>::post_trim(&mut trim, &mut (), false).unwrap();
+        }
+        // All steps done for this input
+        assert_eq!(trim_step(&mut trim, b"xyz").0, MutationResult::Skipped);
+    }
+
+    #[test]
+    fn test_trim_stage() {
+        let mut corpus = InMemoryCorpus::<BytesInput>::new();
+        let id = corpus
+            .add(Testcase::new(BytesInput::new(b"abcdef".to_vec())))
+            .unwrap();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            corpus,
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        state.set_corpus_id(id).unwrap();
+        let mut manager = NopEventManager::new();
+        let mut fuzzer = StdFuzzer::new(
+            QueueScheduler::new(),
+            ConstFeedback::new(false),
+            ConstFeedback::new(false),
+        );
+        // The "bug" reproduces as long as the input starts with `ab`
+        let mut harness = |input: &BytesInput| {
+            if input.mutator_bytes().starts_with(b"ab") {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(),
+            &mut fuzzer,
+            &mut state,
+            &mut manager,
+        )
+        .unwrap();
+
+        let trim = AflCustomTrimMutator::new(stub_library()).unwrap();
+        let mut stage = StdTMinMutationalStage::new(trim, |(): &()| CrashFeedback::new(), 16);
+        assert!(stage.should_restart(&mut state).unwrap());
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut manager)
+            .unwrap();
+
+        // Each accepted candidate is trimmed further, down to the smallest crashing input
+        let testcase = state.corpus().get(id).unwrap().borrow();
+        assert_eq!(testcase.input().as_ref().unwrap().mutator_bytes(), b"ab");
+    }
+
+    #[test]
+    fn test_post_process() {
+        let mut post_process = AflCustomPostProcessor::new(stub_library()).unwrap();
+        let input = BytesInput::new(b"abc".to_vec());
+        assert_eq!(&*post_process.to_target_bytes(&input), b"Abc");
+        // No data from the library, the input is skipped
+        let input = BytesInput::new(b"!abc".to_vec());
+        assert!(post_process.try_to_target_bytes(&input).is_none());
+        assert!(post_process.to_target_bytes(&input).is_empty());
+    }
+}
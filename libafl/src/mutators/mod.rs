@@ -27,12 +27,19 @@ pub mod mapping;
 pub use mapping::*;
 pub mod tuneable;
 pub use tuneable::*;
+pub mod repair;
+pub use repair::*;
 
 #[cfg(feature = "std")]
 pub mod hash;
 #[cfg(feature = "std")]
 pub use hash::*;
 
+#[cfg(all(feature = "std", unix))]
+pub mod afl_custom;
+#[cfg(all(feature = "std", unix))]
+pub use afl_custom::*;
+
 #[cfg(feature = "unicode")]
 pub mod unicode;
 #[cfg(feature = "unicode")]
@@ -112,6 +119,13 @@ pub trait Mutator<I, S>: Named {
     /// Post-process given the outcome of the execution
     /// `new_corpus_id` will be `Some` if a new [`crate::corpus::Testcase`] was created this execution.
     fn post_exec(&mut self, _state: &mut S, _new_corpus_id: Option<CorpusId>) -> Result<(), Error>;
+
+    /// Called by minimizing stages after each trimmed candidate was executed,
+    /// with `success` set if the candidate was kept.
+    #[inline]
+    fn post_trim(&mut self, _state: &mut S, _success: bool) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// A mutator that takes input, and returns a vector of mutated inputs.
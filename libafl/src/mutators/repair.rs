@@ -0,0 +1,356 @@
+//! Input repair: recomputing checksum and length fields after mutation, before the input gets executed.
+//!
+//! The [`InputFixup`]s of a [`crate::corpus::Testcase`] are usually inferred by the
+//! [`crate::stages::ChecksumInferenceStage`], and applied by the [`InputRepairMutator`].
+
+use alloc::{borrow::Cow, vec::Vec};
+
+use libafl_bolts::{Named, impl_serdeany};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, HasMetadata,
+    corpus::CorpusId,
+    inputs::HasMutatorBytes,
+    mutators::{MutationResult, Mutator},
+    state::HasCurrentTestcase,
+};
+
+/// The checksum algorithms an [`InputFixup::Checksum`] can recompute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChecksumKind {
+    /// CRC-32 (IEEE 802.3), as used by zlib, PNG, ZIP, ...
+    Crc32,
+    /// Adler-32, as used by zlib streams
+    Adler32,
+}
+
+impl ChecksumKind {
+    /// Computes this checksum over `data`
+    #[must_use]
+    pub fn compute(self, data: &[u8]) -> u32 {
+        match self {
+            Self::Crc32 => crc32(data),
+            Self::Adler32 => adler32(data),
+        }
+    }
+}
+
+/// An unsigned integer field at a fixed offset of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IntegerField {
+    /// The offset of the field in the input
+    pub offset: usize,
+    /// The width of the field in bytes, one of `1`, `2`, `4` or `8`
+    pub width: usize,
+    /// If the field is stored big endian
+    pub big_endian: bool,
+}
+
+impl IntegerField {
+    /// The end offset of this field
+    #[must_use]
+    pub fn end(&self) -> usize {
+        self.offset + self.width
+    }
+
+    /// Reads this field from `bytes`, if it is in bounds
+    #[must_use]
+    pub fn read(&self, bytes: &[u8]) -> Option<u64> {
+        let field = bytes.get(self.offset..self.end())?;
+        let mut buf = [0_u8; 8];
+        if self.big_endian {
+            buf[8 - self.width..].copy_from_slice(field);
+            Some(u64::from_be_bytes(buf))
+        } else {
+            buf[..self.width].copy_from_slice(field);
+            Some(u64::from_le_bytes(buf))
+        }
+    }
+
+    /// Writes the lower bytes of `value` to this field in `bytes`.
+    /// Returns `false` if the field is out of bounds.
+    pub fn write(&self, bytes: &mut [u8], value: u64) -> bool {
+        let Some(field) = bytes.get_mut(self.offset..self.offset + self.width) else {
+            return false;
+        };
+        if self.big_endian {
+            field.copy_from_slice(&value.to_be_bytes()[8 - self.width..]);
+        } else {
+            field.copy_from_slice(&value.to_le_bytes()[..self.width]);
+        }
+        true
+    }
+}
+
+/// A relationship between input bytes that has to hold for the target to accept the input
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputFixup {
+    /// The field holds the length of the input, minus `subtract` bytes
+    Length {
+        /// The length field
+        field: IntegerField,
+        /// The number of bytes not counted in the length, e.g., the bytes up to the end of the length field
+        subtract: usize,
+    },
+    /// The field holds a checksum over the bytes `start..end`
+    Checksum {
+        /// The checksum field
+        field: IntegerField,
+        /// The checksum algorithm
+        kind: ChecksumKind,
+        /// The first byte covered by the checksum
+        start: usize,
+        /// The end of the covered bytes (exclusive), or `None` if the checksum covers everything up to the end of the input
+        end: Option<usize>,
+    },
+}
+
+impl InputFixup {
+    /// Re-establishes this relationship in `bytes`.
+    /// Returns `false` if the fixup does not fit the (possibly resized) input anymore.
+    pub fn apply(&self, bytes: &mut [u8]) -> bool {
+        match self {
+            Self::Length { field, subtract } => {
+                let Some(len) = bytes.len().checked_sub(*subtract) else {
+                    return false;
+                };
+                field.write(bytes, len as u64)
+            }
+            Self::Checksum {
+                field,
+                kind,
+                start,
+                end,
+            } => {
+                let end = end.unwrap_or(bytes.len());
+                if *start >= end || end > bytes.len() {
+                    return false;
+                }
+                let checksum = kind.compute(&bytes[*start..end]);
+                field.write(bytes, u64::from(checksum))
+            }
+        }
+    }
+}
+
+/// The [`InputFixup`]s inferred for a [`crate::corpus::Testcase`], in the order they need to be applied
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    expect(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct InputFixupsMetadata {
+    fixups: Vec<InputFixup>,
+}
+
+impl_serdeany!(InputFixupsMetadata);
+
+impl InputFixupsMetadata {
+    /// Creates a new [`InputFixupsMetadata`].
+    ///
+    /// The fixups get ordered so that length fields are fixed before checksums,
+    /// and checksums over smaller ranges before checksums that may cover them.
+    #[must_use]
+    pub fn new(mut fixups: Vec<InputFixup>) -> Self {
+        fixups.sort_by_key(|fixup| match fixup {
+            InputFixup::Length { .. } => (0, 0),
+            InputFixup::Checksum { start, end, .. } => {
+                (1, end.map_or(usize::MAX, |end| end - start))
+            }
+        });
+        Self { fixups }
+    }
+
+    /// The fixups, in the order they are applied
+    #[must_use]
+    pub fn fixups(&self) -> &[InputFixup] {
+        &self.fixups
+    }
+
+    /// If no fixups were inferred
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fixups.is_empty()
+    }
+
+    /// Applies all fixups to `bytes`, returning how many of them could be applied
+    pub fn apply(&self, bytes: &mut [u8]) -> usize {
+        self.fixups
+            .iter()
+            .filter(|fixup| fixup.apply(bytes))
+            .count()
+    }
+}
+
+/// A [`Mutator`] wrapping another mutator, repairing checksum and length fields of each mutated input
+/// using the [`InputFixupsMetadata`] of the current [`crate::corpus::Testcase`].
+#[derive(Debug)]
+pub struct InputRepairMutator<M> {
+    name: Cow<'static, str>,
+    inner: M,
+}
+
+impl<M> InputRepairMutator<M>
+where
+    M: Named,
+{
+    /// Creates a new [`InputRepairMutator`], repairing the inputs mutated by `inner`
+    pub fn new(inner: M) -> Self {
+        Self {
+            name: Cow::from(format!("InputRepairMutator[{}]", inner.name())),
+            inner,
+        }
+    }
+}
+
+impl<M> InputRepairMutator<M> {
+    /// The wrapped mutator
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// The wrapped mutator (mutable)
+    pub fn inner_mut(&mut self) -> &mut M {
+        &mut self.inner
+    }
+}
+
+impl<M> Named for InputRepairMutator<M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for InputRepairMutator<M>
+where
+    M: Mutator<I, S>,
+    S: HasCurrentTestcase<I>,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let result = self.inner.mutate(state, input)?;
+        if result == MutationResult::Mutated {
+            if let Ok(testcase) = state.current_testcase() {
+                if let Ok(meta) = testcase.metadata::<InputFixupsMetadata>() {
+                    meta.apply(input.mutator_bytes_mut());
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.inner.post_exec(state, new_corpus_id)
+    }
+
+    #[inline]
+    fn post_trim(&mut self, state: &mut S, success: bool) -> Result<(), Error> {
+        self.inner.post_trim(state, success)
+    }
+}
+
+/// The CRC-32 (IEEE) lookup table
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0_u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Computes the CRC-32 (IEEE) of `data`
+#[must_use]
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0_u32, |crc, byte| {
+        CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Computes the Adler-32 of `data`
+#[must_use]
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // The largest n such that 255n(n+1)/2 + (n+1)(MOD_ADLER-1) fits a u32
+    const NMAX: usize = 5552;
+
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for chunk in data.chunks(NMAX) {
+        for byte in chunk {
+            a += u32::from(*byte);
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChecksumKind, InputFixup, InputFixupsMetadata, IntegerField, adler32, crc32};
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(b""), 1);
+    }
+
+    #[test]
+    fn test_apply_fixups() {
+        // [len: u16 be][crc32 le][payload]
+        let mut bytes = b"\0\0\0\0\0\0hello world".to_vec();
+        let len = IntegerField {
+            offset: 0,
+            width: 2,
+            big_endian: true,
+        };
+        let crc = IntegerField {
+            offset: 2,
+            width: 4,
+            big_endian: false,
+        };
+        let meta = InputFixupsMetadata::new(vec![
+            InputFixup::Checksum {
+                field: crc,
+                kind: ChecksumKind::Crc32,
+                start: 6,
+                end: None,
+            },
+            InputFixup::Length {
+                field: len,
+                subtract: 2,
+            },
+        ]);
+        assert!(matches!(meta.fixups()[0], InputFixup::Length { .. }));
+
+        assert_eq!(meta.apply(&mut bytes), 2);
+        assert_eq!(len.read(&bytes), Some(15));
+        assert_eq!(crc.read(&bytes), Some(u64::from(crc32(b"hello world"))));
+
+        // the fixups follow the input when it grows
+        bytes.extend_from_slice(b"!");
+        assert_eq!(meta.apply(&mut bytes), 2);
+        assert_eq!(len.read(&bytes), Some(16));
+        assert_eq!(crc.read(&bytes), Some(u64::from(crc32(b"hello world!"))));
+
+        // and are skipped if they do not fit anymore
+        let mut short = vec![0_u8; 3];
+        assert_eq!(meta.apply(&mut short), 1);
+    }
+}
@@ -7,10 +7,11 @@ use core::{
     ops::{Deref, DerefMut},
 };
 
+use hashbrown::HashMap;
 use libafl_bolts::{
     Named,
     rands::Rand,
-    tuples::{HasConstLen, NamedTuple, tuple_list, tuple_list_type},
+    tuples::{NamedTuple, tuple_list, tuple_list_type},
 };
 use serde::{Deserialize, Serialize};
 
@@ -22,8 +23,7 @@ use crate::{
         MutationResult, Mutator, MutatorsTuple,
         token_mutations::{TokenInsert, TokenReplace},
     },
-    nonzero,
-    state::{HasCorpus, HasRand},
+    state::{HasCorpus, HasRand, HasSolutions},
 };
 
 /// The metadata placed in a [`crate::corpus::Testcase`] by a [`LoggerScheduledMutator`].
@@ -59,6 +59,188 @@ impl LogMutationMetadata {
     }
 }
 
+/// Success counters of a single mutation, collected by a [`LoggerScheduledMutator`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MutationStats {
+    /// The number of executions this mutation was (at least once) part of
+    pub applied: u64,
+    /// The number of new corpus entries this mutation was part of
+    pub corpus_finds: u64,
+    /// The number of objectives this mutation was part of
+    pub objective_finds: u64,
+}
+
+impl MutationStats {
+    /// The total number of finds (corpus entries and objectives)
+    #[must_use]
+    pub fn finds(&self) -> u64 {
+        self.corpus_finds + self.objective_finds
+    }
+
+    /// Sums up two stats
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            applied: self.applied + other.applied,
+            corpus_finds: self.corpus_finds + other.corpus_finds,
+            objective_finds: self.objective_finds + other.objective_finds,
+        }
+    }
+}
+
+/// The success counters of the mutations of one scheduled mutator, in [`MutationId`] order.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MutatorStats {
+    names: Vec<Cow<'static, str>>,
+    stats: Vec<MutationStats>,
+}
+
+impl MutatorStats {
+    /// Creates new [`MutatorStats`] for the mutations with the given names,
+    /// in [`MutationId`] order.
+    #[must_use]
+    pub fn new(names: Vec<Cow<'static, str>>) -> Self {
+        let stats = vec![MutationStats::default(); names.len()];
+        Self { names, stats }
+    }
+
+    /// The stats of the mutation with the given [`MutationId`]
+    #[must_use]
+    pub fn get(&self, id: MutationId) -> Option<&MutationStats> {
+        self.stats.get(id.0)
+    }
+
+    /// The stats of all mutations with the given name, summed up
+    #[must_use]
+    pub fn get_by_name(&self, name: &str) -> Option<MutationStats> {
+        self.iter()
+            .filter(|(_, n, _)| *n == name)
+            .map(|(_, _, stats)| *stats)
+            .reduce(MutationStats::merge)
+    }
+
+    /// Iterates over all mutations, with their [`MutationId`], name and stats
+    pub fn iter(&self) -> impl Iterator<Item = (MutationId, &Cow<'static, str>, &MutationStats)> {
+        self.names
+            .iter()
+            .zip(self.stats.iter())
+            .enumerate()
+            .map(|(idx, (name, stats))| (MutationId(idx), name, stats))
+    }
+
+    /// The number of mutations tracked
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    /// Returns `true` if no mutations are tracked
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    /// Records one execution, given the (deduplicated) mutations applied to its input.
+    /// Unknown [`MutationId`]s are ignored.
+    pub fn record(&mut self, applied: &[MutationId], corpus_find: bool, objective_find: bool) {
+        for id in applied {
+            let Some(stats) = self.stats.get_mut(id.0) else {
+                continue;
+            };
+            stats.applied += 1;
+            if corpus_find {
+                stats.corpus_finds += 1;
+            }
+            if objective_find {
+                stats.objective_finds += 1;
+            }
+        }
+    }
+
+    /// The probability of each mutation, proportional to its (smoothed) success rate, in [`MutationId`] order.
+    /// Can be fed to [`crate::mutators::TuneableScheduledMutator::set_mutation_probabilities`].
+    #[expect(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn success_probabilities(&self) -> Vec<f32> {
+        let rates: Vec<f64> = self
+            .stats
+            .iter()
+            .map(|s| (s.finds() + 1) as f64 / (s.applied + 1) as f64)
+            .collect();
+        let sum: f64 = rates.iter().sum();
+        rates.iter().map(|r| (r / sum) as f32).collect()
+    }
+}
+
+/// The metadata placed in the state by [`LoggerScheduledMutator`]s,
+/// counting how often each mutation contributed to a new corpus entry or objective.
+///
+/// The stats are kept per scheduled mutator, keyed by its name,
+/// so that multiple logging mutators with different mutations can share one state.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    expect(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct MutationStatsMetadata {
+    mutators: HashMap<Cow<'static, str>, MutatorStats>,
+}
+
+libafl_bolts::impl_serdeany!(MutationStatsMetadata);
+
+impl MutationStatsMetadata {
+    /// Creates new, empty [`MutationStatsMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The stats of the scheduled mutator with the given name
+    #[must_use]
+    pub fn mutator(&self, name: &str) -> Option<&MutatorStats> {
+        self.mutators.get(name)
+    }
+
+    /// The stats of the scheduled mutator with the given name, created for the given mutation names if missing.
+    pub fn mutator_or_insert<F>(&mut self, name: &str, mutation_names: F) -> &mut MutatorStats
+    where
+        F: FnOnce() -> Vec<Cow<'static, str>>,
+    {
+        if !self.mutators.contains_key(name) {
+            self.mutators
+                .insert(Cow::Owned(name.into()), MutatorStats::new(mutation_names()));
+        }
+        self.mutators.get_mut(name).unwrap()
+    }
+
+    /// Iterates over all scheduled mutators, with their name and stats
+    pub fn iter(&self) -> impl Iterator<Item = (&Cow<'static, str>, &MutatorStats)> {
+        self.mutators.iter()
+    }
+
+    /// The stats of all mutations with the given name, summed up over all scheduled mutators
+    #[must_use]
+    pub fn get_by_name(&self, name: &str) -> Option<MutationStats> {
+        self.mutators
+            .values()
+            .filter_map(|stats| stats.get_by_name(name))
+            .reduce(MutationStats::merge)
+    }
+
+    /// The names of all mutations tracked, over all scheduled mutators, sorted and deduplicated
+    #[must_use]
+    pub fn mutation_names(&self) -> Vec<&Cow<'static, str>> {
+        let mut names: Vec<&Cow<'static, str>> = self
+            .mutators
+            .values()
+            .flat_map(|stats| stats.iter().map(|(_, name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+}
+
 /// A [`Mutator`] that composes multiple mutations into one.
 pub trait ComposedByMutations {
     /// The mutations of this
@@ -280,12 +462,16 @@ pub fn tokens_mutations() -> tuple_list_type!(TokenInsert, TokenReplace) {
     tuple_list!(TokenInsert::new(), TokenReplace::new())
 }
 
-/// A logging [`Mutator`] that wraps around a [`HavocScheduledMutator`].
+/// A logging [`Mutator`] that wraps around a [`ScheduledMutator`], such as the [`HavocScheduledMutator`].
+///
+/// It adds a [`LogMutationMetadata`] to each new [`crate::corpus::Testcase`], and keeps per-mutation
+/// success counters in the [`MutationStatsMetadata`] of the state, keyed by the name of the wrapped mutator.
 #[derive(Debug)]
 pub struct LoggerScheduledMutator<SM> {
     name: Cow<'static, str>,
     scheduled: SM,
     mutation_log: Vec<MutationId>,
+    solutions_before: usize,
 }
 
 impl<SM> Named for LoggerScheduledMutator<SM> {
@@ -296,15 +482,26 @@ impl<SM> Named for LoggerScheduledMutator<SM> {
 
 impl<I, S, SM> Mutator<I, S> for LoggerScheduledMutator<SM>
 where
-    S: HasRand + HasCorpus<I>,
+    S: HasRand + HasCorpus<I> + HasSolutions<I> + HasMetadata,
     SM: ScheduledMutator<I, S>,
     SM::Mutations: MutatorsTuple<I, S> + NamedTuple,
 {
     fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        self.solutions_before = state.solutions().count();
         self.scheduled_mutate(state, input)
     }
 
     fn post_exec(&mut self, state: &mut S, corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        let objective_find = state.solutions().count() > self.solutions_before;
+        let mut applied = self.mutation_log.clone();
+        applied.sort_unstable();
+        applied.dedup();
+        let mutations = self.scheduled.mutations();
+        state
+            .metadata_or_insert_with(MutationStatsMetadata::new)
+            .mutator_or_insert(self.scheduled.name(), || mutations.names())
+            .record(&applied, corpus_id.is_some(), objective_find);
+
         if let Some(id) = corpus_id {
             let mut testcase = (*state.corpus_mut().get(id)?).borrow_mut();
             let mut log = Vec::<Cow<'static, str>>::new();
@@ -339,24 +536,18 @@ where
 
 impl<I, S, SM> ScheduledMutator<I, S> for LoggerScheduledMutator<SM>
 where
-    S: HasRand + HasCorpus<I>,
+    S: HasRand + HasCorpus<I> + HasSolutions<I> + HasMetadata,
     SM: ScheduledMutator<I, S>,
     SM::Mutations: MutatorsTuple<I, S> + NamedTuple,
 {
-    /// Compute the number of iterations used to apply stacked mutations
-    fn iterations(&self, state: &mut S, _: &I) -> u64 {
-        1 << (1 + state.rand_mut().below(nonzero!(7)))
+    /// Compute the number of iterations used to apply stacked mutations, as the wrapped mutator would
+    fn iterations(&self, state: &mut S, input: &I) -> u64 {
+        self.scheduled.iterations(state, input)
     }
 
-    /// Get the next mutation to apply
-    fn schedule(&self, state: &mut S, _: &I) -> MutationId {
-        debug_assert!(<SM::Mutations as HasConstLen>::LEN != 0);
-        // # Safety
-        // In debug we check the length. Worst case we end up with an illegal MutationId and fail later.
-        state
-            .rand_mut()
-            .below(unsafe { NonZero::new(<SM::Mutations as HasConstLen>::LEN).unwrap_unchecked() })
-            .into()
+    /// Get the next mutation to apply, as the wrapped mutator would
+    fn schedule(&self, state: &mut S, input: &I) -> MutationId {
+        self.scheduled.schedule(state, input)
     }
 
     fn scheduled_mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
@@ -386,15 +577,20 @@ where
             name: Cow::from(format!("LoggerScheduledMutator[{}]", scheduled.name())),
             scheduled,
             mutation_log: vec![],
+            solutions_before: 0,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use libafl_bolts::rands::{StdRand, XkcdRand};
+    use libafl_bolts::{
+        HasLen, Named,
+        rands::{StdRand, XkcdRand},
+    };
 
     use crate::{
+        HasMetadata,
         corpus::{Corpus, InMemoryCorpus, Testcase},
         feedbacks::ConstFeedback,
         inputs::{BytesInput, HasMutatorBytes},
@@ -402,9 +598,12 @@ mod tests {
             Mutator,
             havoc_mutations::havoc_mutations,
             mutations::SpliceMutator,
-            scheduled::{HavocScheduledMutator, SingleChoiceScheduledMutator},
+            scheduled::{
+                HavocScheduledMutator, LogMutationMetadata, LoggerScheduledMutator,
+                MutationStatsMetadata, SingleChoiceScheduledMutator, tokens_mutations,
+            },
         },
-        state::StdState,
+        state::{HasCorpus, StdState},
     };
 
     #[test]
@@ -522,4 +721,76 @@ mod tests {
             assert_ne!(equal_in_a_row, 20);
         }
     }
+
+    #[test]
+    fn test_logger_stats() {
+        let mut corpus: InMemoryCorpus<BytesInput> = InMemoryCorpus::new();
+        corpus.add(Testcase::new(b"abc".to_vec().into())).unwrap();
+        let mut input = corpus.cloned_input_for_id(corpus.first().unwrap()).unwrap();
+
+        let mut state = StdState::new(
+            StdRand::with_seed(0x1337),
+            corpus,
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+
+        let mut mutator =
+            LoggerScheduledMutator::new(HavocScheduledMutator::new(havoc_mutations()));
+
+        mutator.mutate(&mut state, &mut input).unwrap();
+        mutator.post_exec(&mut state, None).unwrap();
+
+        mutator.mutate(&mut state, &mut input).unwrap();
+        let id = state
+            .corpus_mut()
+            .add(Testcase::new(input.clone()))
+            .unwrap();
+        mutator.post_exec(&mut state, Some(id)).unwrap();
+
+        let log = state
+            .corpus()
+            .get(id)
+            .unwrap()
+            .borrow()
+            .metadata::<LogMutationMetadata>()
+            .unwrap()
+            .list
+            .clone();
+        let meta = state.metadata::<MutationStatsMetadata>().unwrap();
+        let stats = meta.mutator(mutator.scheduled.name()).unwrap();
+
+        assert_eq!(stats.len(), havoc_mutations().len());
+        let applied: u64 = stats.iter().map(|(_, _, s)| s.applied).sum();
+        assert!(applied >= 2);
+        for name in &log {
+            assert!(stats.get_by_name(name).unwrap().corpus_finds >= 1);
+        }
+        assert_eq!(
+            stats.iter().map(|(_, _, s)| s.objective_finds).sum::<u64>(),
+            0
+        );
+
+        let probabilities = stats.success_probabilities();
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 0.001);
+
+        // A second logging mutator with fewer mutations keeps its own stats
+        let mut tokens_mutator =
+            LoggerScheduledMutator::new(HavocScheduledMutator::new(tokens_mutations()));
+        for _ in 0..8 {
+            tokens_mutator.mutate(&mut state, &mut input).unwrap();
+            tokens_mutator.post_exec(&mut state, None).unwrap();
+        }
+        let meta = state.metadata::<MutationStatsMetadata>().unwrap();
+        assert_eq!(meta.iter().count(), 2);
+        let token_stats = meta.mutator(tokens_mutator.scheduled.name()).unwrap();
+        assert_eq!(token_stats.len(), 2);
+        assert!(token_stats.iter().map(|(_, _, s)| s.applied).sum::<u64>() >= 8);
+        assert_eq!(
+            meta.mutator(mutator.scheduled.name()).unwrap().len(),
+            havoc_mutations().len()
+        );
+    }
 }
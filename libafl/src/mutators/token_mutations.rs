@@ -13,7 +13,7 @@ use core::{
 #[cfg(feature = "std")]
 use std::{
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, BufWriter, Write},
     path::Path,
 };
 
@@ -156,6 +156,40 @@ impl Tokens {
         true
     }
 
+    /// Removes a token from the dictionary.
+    /// Returns `false` if the token was not present.
+    pub fn remove_token(&mut self, token: &[u8]) -> bool {
+        if !self.tokens_set.remove(token) {
+            return false;
+        }
+        self.tokens_vec.retain(|t| t != token);
+        true
+    }
+
+    /// Writes all tokens to a file, in the AFL dictionary format.
+    /// Non-printable bytes, quotes and backslashes are `\`-escaped, so the file can be
+    /// read back by [`Tokens::from_file`] or passed to AFL/AFL++ via `-x`.
+    #[cfg(feature = "std")]
+    pub fn to_file<P>(&self, file: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut writer = BufWriter::new(File::create(file)?);
+        for (idx, token) in self.tokens_vec.iter().enumerate() {
+            write!(writer, "token_{idx}=\"")?;
+            for &b in token {
+                match b {
+                    b'"' | b'\\' => write!(writer, "\\{}", b as char)?,
+                    0x20..=0x7e => write!(writer, "{}", b as char)?,
+                    _ => write!(writer, "\\x{b:02x}")?,
+                }
+            }
+            writeln!(writer, "\"")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
     /// Reads a tokens file, returning the count of new entries read
     #[cfg(feature = "std")]
     pub fn add_from_file<P>(&mut self, file: P) -> Result<&mut Self, Error>
@@ -2131,6 +2165,23 @@ token2="B"
         let _res = fs::remove_file("test.tkns");
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_tokens() {
+        let mut tokens = Tokens::new();
+        tokens.add_token(&b"plain".to_vec());
+        tokens.add_token(&b"\"quoted\\\"".to_vec());
+        tokens.add_token(&vec![0, 0xff, b'A', b'\n']);
+        tokens.add_token(&b"removed".to_vec());
+        assert!(tokens.remove_token(b"removed"));
+        assert!(!tokens.remove_token(b"removed"));
+
+        tokens.to_file("test_write.tkns").unwrap();
+        let read = Tokens::from_file("test_write.tkns").unwrap();
+        let _res = fs::remove_file("test_write.tkns");
+        assert_eq!(read.tokens(), tokens.tokens());
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_token_mutations() {
@@ -7,7 +7,7 @@ use alloc::{borrow::Cow, vec::Vec};
 use core::{fmt::Debug, num::NonZero};
 
 use libafl_bolts::{
-    Named, impl_serdeany, math::calculate_cumulative_distribution_in_place, rands::Rand,
+    HasLen, Named, impl_serdeany, math::calculate_cumulative_distribution_in_place, rands::Rand,
     tuples::NamedTuple,
 };
 use serde::{Deserialize, Serialize};
@@ -16,7 +16,8 @@ pub use crate::mutators::{mutations::*, token_mutations::*};
 use crate::{
     Error, HasMetadata,
     mutators::{
-        ComposedByMutations, MutationId, MutationResult, Mutator, MutatorsTuple, ScheduledMutator,
+        ComposedByMutations, MutationId, MutationResult, MutationStatsMetadata, Mutator,
+        MutatorsTuple, ScheduledMutator,
     },
     state::HasRand,
 };
@@ -314,6 +315,32 @@ impl<MT> TuneableScheduledMutator<MT> {
         Ok(())
     }
 
+    /// Sets the mutation probabilities according to the success rate of each mutation,
+    /// as collected in the [`MutationStatsMetadata`] by a [`crate::mutators::LoggerScheduledMutator`]
+    /// wrapping this mutator.
+    /// Call this periodically to auto-tune the mutations towards the ones that find new entries for this target.
+    pub fn set_mutation_probabilities_from_stats<S>(&self, state: &mut S) -> Result<(), Error>
+    where
+        S: HasMetadata,
+        MT: HasLen,
+    {
+        let probabilities = state
+            .metadata::<MutationStatsMetadata>()?
+            .mutator(self.name())
+            .ok_or_else(|| {
+                Error::key_not_found(format!("No mutation stats recorded for {}", self.name()))
+            })?
+            .success_probabilities();
+        if probabilities.len() != self.mutations.len() {
+            return Err(Error::illegal_argument(format!(
+                "MutationStatsMetadata tracks {} mutations, but this mutator has {}",
+                probabilities.len(),
+                self.mutations.len()
+            )));
+        }
+        self.set_mutation_probabilities(state, probabilities)
+    }
+
     /// mutation ids and iterations
     pub fn set_mutation_ids_and_iters<S>(
         &self,
@@ -0,0 +1,350 @@
+//! Category-preserving mutators for strings in UTF-16 and legacy encodings, such as Shift-JIS.
+//!
+//! The encoding of each testcase is detected by the [`crate::stages::EncodedStringIdentificationStage`].
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::{num::NonZero, ops::Range};
+
+use libafl_bolts::{Error, HasLen, Named, rands::Rand};
+
+use super::{MAX_CHARS, choose_category_range_in, choose_start, choose_subcategory_range_in};
+use crate::{
+    HasMetadata,
+    corpus::{CorpusId, HasTestcase, Testcase},
+    inputs::{BytesInput, HasMutatorBytes, ResizableMutator},
+    mutators::{MutationResult, Mutator, rand_range},
+    nonzero,
+    stages::{
+        EncodedStringsMetadata, TextEncoding, extract_encoded_metadata,
+        mutational::{MutatedTransform, MutatedTransformPost},
+    },
+    state::{HasCorpus, HasMaxSize, HasRand},
+};
+
+/// How often we try to generate a char that the encoding can represent, before giving up
+const MAX_ENCODE_ATTEMPTS: usize = 8;
+
+/// Input which contains the context necessary to perform mutations on strings in any [`TextEncoding`]
+pub type EncodedUnicodeInput = (BytesInput, EncodedStringsMetadata);
+
+impl<S> MutatedTransform<BytesInput, S> for EncodedUnicodeInput
+where
+    S: HasCorpus<BytesInput> + HasTestcase<BytesInput>,
+{
+    type Post = EncodedStringsMetadata;
+
+    fn try_transform_from(base: &mut Testcase<BytesInput>, state: &S) -> Result<Self, Error> {
+        let input = base.load_input(state.corpus())?.clone();
+        let metadata = base.metadata::<EncodedStringsMetadata>().cloned()?;
+        Ok((input, metadata))
+    }
+
+    fn try_transform_into(self, _state: &S) -> Result<(BytesInput, Self::Post), Error> {
+        Ok(self)
+    }
+}
+
+impl<S> MutatedTransformPost<S> for EncodedStringsMetadata
+where
+    S: HasTestcase<BytesInput>,
+{
+    fn post_exec(self, state: &mut S, corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        if let Some(corpus_id) = corpus_id {
+            let mut tc = state.testcase_mut(corpus_id)?;
+            tc.add_metadata(self);
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the chars of a string range, returning their absolute offsets in the input
+fn decode_range(
+    encoding: TextEncoding,
+    bytes: &[u8],
+    base: usize,
+    len: usize,
+) -> Vec<(usize, char)> {
+    let mut chars = Vec::new();
+    let mut offset = base;
+    while offset < base + len {
+        let Some((c, c_len)) = encoding.decode_char(&bytes[offset..base + len]) else {
+            break;
+        };
+        chars.push((offset, c));
+        offset += c_len;
+    }
+    chars
+}
+
+/// The chars of a string with their offsets, and the end offset of the string
+type DecodedString = (Vec<(usize, char)>, usize);
+
+/// Chooses a random string in the input, and decodes it
+fn choose_string<S: HasRand>(state: &mut S, input: &EncodedUnicodeInput) -> Option<DecodedString> {
+    let bytes = input.0.mutator_bytes();
+    let (base, len) = choose_start(state.rand_mut(), bytes, input.1.ranges())?;
+    let chars = decode_range(input.1.encoding(), bytes, base, len);
+    (!chars.is_empty()).then_some((chars, base + len))
+}
+
+fn rand_replace_encoded_range<S: HasRand + HasMaxSize, F: Fn(&mut S) -> char>(
+    state: &mut S,
+    input: &mut EncodedUnicodeInput,
+    chars: &[(usize, char)],
+    range: Range<usize>,
+    char_gen: F,
+) -> MutationResult {
+    let first = chars.iter().position(|&(i, _)| i >= range.start);
+    let count = chars.iter().filter(|&&(i, _)| range.contains(&i)).count();
+    let (Some(first), Some(_)) = (first, NonZero::new(count)) else {
+        return MutationResult::Skipped;
+    };
+
+    let temp_range = rand_range(state, count, nonzero!(MAX_CHARS));
+    let start = chars[first + temp_range.start].0;
+    let end = chars
+        .get(first + temp_range.end)
+        .map_or(range.end, |&(i, _)| i)
+        .min(range.end);
+    if start >= end {
+        return MutationResult::Skipped;
+    }
+
+    let encoding = input.1.encoding();
+    let replace_chars = state.rand_mut().below(nonzero!(MAX_CHARS));
+    let mut replacement = Vec::new();
+    for _ in 0..replace_chars {
+        for _ in 0..MAX_ENCODE_ATTEMPTS {
+            let new_c = char_gen(state);
+            if encoding.encode_char(new_c, &mut replacement) {
+                break;
+            }
+        }
+    }
+
+    if input.0.len() - (end - start) + replacement.len() > state.max_size() {
+        return MutationResult::Skipped;
+    }
+
+    input.0.splice(start..end, replacement);
+    input.1 = extract_encoded_metadata(input.0.mutator_bytes(), encoding);
+
+    MutationResult::Mutated
+}
+
+/// Mutator which randomly replaces a randomly selected range of chars in a string of the detected
+/// [`TextEncoding`] with chars that preserve the range's category
+#[derive(Debug, Default)]
+pub struct EncodedCategoryRandMutator;
+
+impl Named for EncodedCategoryRandMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("encoded-string-category-rand");
+        &NAME
+    }
+}
+
+impl<S> Mutator<EncodedUnicodeInput, S> for EncodedCategoryRandMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedUnicodeInput,
+    ) -> Result<MutationResult, Error> {
+        let Some((chars, end)) = choose_string(state, input) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let (range, category) = choose_category_range_in(state.rand_mut(), &chars, end);
+
+        let Some(options) = NonZero::new(
+            category
+                .iter()
+                .map(|&(start, end)| end as usize - start as usize + 1)
+                .sum(),
+        ) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let char_gen = |state: &mut S| loop {
+            let mut selected = state.rand_mut().below(options);
+            for &(min, max) in category {
+                if let Some(next_selected) = selected.checked_sub(max as usize - min as usize + 1) {
+                    selected = next_selected;
+                } else if let Some(new_c) = char::from_u32(selected as u32 + min) {
+                    return new_c;
+                } else {
+                    break;
+                }
+            }
+        };
+
+        Ok(rand_replace_encoded_range(
+            state, input, &chars, range, char_gen,
+        ))
+    }
+
+    #[inline]
+    fn post_exec(&mut self, _state: &mut S, _new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Mutator which randomly replaces a randomly selected range of chars in a string of the detected
+/// [`TextEncoding`] with chars that preserve the range's subcategory
+#[derive(Debug, Default)]
+pub struct EncodedSubcategoryRandMutator;
+
+impl Named for EncodedSubcategoryRandMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("encoded-string-subcategory-rand");
+        &NAME
+    }
+}
+
+impl<S> Mutator<EncodedUnicodeInput, S> for EncodedSubcategoryRandMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedUnicodeInput,
+    ) -> Result<MutationResult, Error> {
+        let Some((chars, end)) = choose_string(state, input) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let (range, subcategory) = choose_subcategory_range_in(state.rand_mut(), &chars, end);
+
+        let Some(options) = NonZero::new(subcategory.1 as usize - subcategory.0 as usize + 1)
+        else {
+            return Ok(MutationResult::Skipped);
+        };
+        let char_gen = |state: &mut S| loop {
+            let selected = state.rand_mut().below(options);
+            if let Some(new_c) = char::from_u32(selected as u32 + subcategory.0) {
+                return new_c;
+            }
+        };
+
+        Ok(rand_replace_encoded_range(
+            state, input, &chars, range, char_gen,
+        ))
+    }
+
+    #[inline]
+    fn post_exec(&mut self, _state: &mut S, _new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use libafl_bolts::{Error, rands::StdRand};
+
+    use crate::{
+        corpus::NopCorpus,
+        inputs::{BytesInput, HasMutatorBytes},
+        mutators::{EncodedCategoryRandMutator, EncodedSubcategoryRandMutator, Mutator},
+        stages::{TextEncoding, extract_encoded_metadata, identify_encoding},
+        state::StdState,
+    };
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn detect_encodings() -> Result<(), Error> {
+        let bytes = [&b"\0\0"[..], &utf16le("hello world"), &b"\xff"[..]].concat();
+
+        let utf16 = extract_encoded_metadata(&bytes, TextEncoding::Utf16Le);
+        assert_eq!(utf16.ranges().len(), 1);
+        assert_eq!(utf16.ranges()[0].0, 2);
+        assert_eq!(utf16.covered_bytes(), 22);
+        assert_eq!(utf16.ranges()[0].1.count_ones(), 11);
+
+        // as UTF-8, the string is full of NUL bytes
+        let utf8 = extract_encoded_metadata(&bytes, TextEncoding::Utf8);
+        assert!(utf8.covered_bytes() < utf16.covered_bytes());
+
+        let sjis = TextEncoding::legacy("shift_jis")?;
+        assert_eq!(sjis, TextEncoding::shift_jis());
+        assert_eq!(TextEncoding::legacy("utf-16le")?, TextEncoding::Utf16Le);
+        assert!(TextEncoding::legacy("no-such-encoding").is_err());
+
+        // "こんにちは" in Shift-JIS
+        let bytes = b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd";
+        let meta = extract_encoded_metadata(bytes, sjis);
+        assert_eq!(meta.covered_bytes(), bytes.len());
+        assert_eq!(meta.ranges()[0].1.count_ones(), 5);
+        assert_eq!(sjis.decode_char(bytes), Some(('こ', 2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn identify_encodings() {
+        let encodings = [
+            TextEncoding::Utf8,
+            TextEncoding::Utf16Le,
+            TextEncoding::Utf16Be,
+        ];
+        let identify = |bytes: &[u8]| identify_encoding(bytes, &encodings).unwrap().encoding();
+
+        // UTF-16 decodes more of these bytes, but to unlikely chars
+        assert_eq!(identify(b"\x01\x02hello world!"), TextEncoding::Utf8);
+        assert_eq!(identify(b"hello world"), TextEncoding::Utf8);
+        assert_eq!(
+            identify(&[&b"\x01\x02"[..], &utf16le("hello world")].concat()),
+            TextEncoding::Utf16Le
+        );
+        let utf16be: Vec<u8> = "hello world"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        assert_eq!(identify(&utf16be), TextEncoding::Utf16Be);
+    }
+
+    #[test]
+    fn mutate_encoded() -> Result<(), Error> {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            NopCorpus::<BytesInput>::new(),
+            NopCorpus::new(),
+            &mut (),
+            &mut (),
+        )?;
+
+        for encoding in [
+            TextEncoding::Utf16Le,
+            TextEncoding::Utf16Be,
+            TextEncoding::shift_jis(),
+        ] {
+            let mut seed = Vec::new();
+            for c in "abcdefghijklmnopqrstuvwxyzひらがな0123456789".chars() {
+                assert!(encoding.encode_char(c, &mut seed));
+            }
+            let mut bytes = BytesInput::from(seed);
+
+            for i in 0..(1 << 10) {
+                let metadata = extract_encoded_metadata(bytes.mutator_bytes(), encoding);
+                let mut input = (bytes, metadata);
+                if i % 2 == 0 {
+                    EncodedCategoryRandMutator.mutate(&mut state, &mut input)?;
+                } else {
+                    EncodedSubcategoryRandMutator.mutate(&mut state, &mut input)?;
+                }
+                bytes = input.0;
+            }
+
+            // the input still holds strings in its encoding
+            let meta = extract_encoded_metadata(bytes.mutator_bytes(), encoding);
+            assert!(meta.covered_bytes() > 0);
+        }
+
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@ use core::{
     ops::Range,
 };
 
+use bitvec::vec::BitVec;
 use libafl_bolts::{Error, HasLen, Named, rands::Rand};
 
 use crate::{
@@ -26,6 +27,9 @@ use crate::{
 #[expect(missing_docs, clippy::redundant_static_lifetimes)]
 pub mod unicode_categories;
 
+pub mod encoded;
+pub use encoded::*;
+
 /// Input which contains the context necessary to perform unicode mutations
 pub type UnicodeInput = (BytesInput, UnicodeIdentificationMetadata);
 
@@ -64,13 +68,13 @@ const MAX_CHARS: usize = 16;
 fn choose_start<R: Rand>(
     rand: &mut R,
     bytes: &[u8],
-    meta: &UnicodeIdentificationMetadata,
+    ranges: &[(usize, BitVec)],
 ) -> Option<(usize, usize)> {
     let bytes_len = NonZero::new(bytes.len())?;
 
     let idx = rand.below(bytes_len);
     let mut options = Vec::new();
-    for (start, range) in meta.ranges() {
+    for (start, range) in ranges {
         if idx
             .checked_sub(*start) // idx adjusted to start
             .and_then(|idx| (idx < range.len()).then(|| range[idx])) // idx in range
@@ -112,6 +116,7 @@ fn get_subcategory<T: Ord + Copy>(needle: T, haystack: &[(T, T)]) -> Option<(T,
 fn find_range<F: Fn(char) -> bool>(
     chars: &[(usize, char)],
     idx: usize,
+    end: usize,
     predicate: F,
 ) -> Range<usize> {
     // walk backwards and discover
@@ -122,13 +127,12 @@ fn find_range<F: Fn(char) -> bool>(
         .last()
         .map_or(chars[idx].0, |&(i, _)| i);
     // walk forwards
-    let end = chars[(idx + 1)..]
-        .iter()
-        .take_while(|&&(_, c)| predicate(c))
-        .last()
-        .map_or(chars[idx].0 + chars[idx].1.len_utf8(), |&(i, c)| {
-            i + c.len_utf8()
-        });
+    let last = idx
+        + chars[(idx + 1)..]
+            .iter()
+            .take_while(|&&(_, c)| predicate(c))
+            .count();
+    let end = chars.get(last + 1).map_or(end, |&(i, _)| i);
 
     start..end
 }
@@ -138,6 +142,15 @@ fn choose_category_range<R: Rand>(
     string: &str,
 ) -> (Range<usize>, &'static [(u32, u32)]) {
     let chars = string.char_indices().collect::<Vec<_>>();
+    choose_category_range_in(rand, &chars, string.len())
+}
+
+/// Chooses a category-contiguous range in `chars`, given as `(offset, char)` pairs ending at offset `end`
+fn choose_category_range_in<R: Rand>(
+    rand: &mut R,
+    chars: &[(usize, char)],
+    end: usize,
+) -> (Range<usize>, &'static [(u32, u32)]) {
     let chars_len = NonZero::new(chars.len()).expect("Got empty string in choose_category_range");
     let idx = rand.below(chars_len);
     let c = chars[idx].1;
@@ -175,7 +188,7 @@ fn choose_category_range<R: Rand>(
     println!("category for `{c}' ({}): {}", c as u32, names[selected_idx]);
 
     (
-        find_range(&chars, idx, |c| {
+        find_range(chars, idx, end, |c| {
             get_subcategory(c as u32, selected).is_some()
         }),
         selected,
@@ -184,6 +197,15 @@ fn choose_category_range<R: Rand>(
 
 fn choose_subcategory_range<R: Rand>(rand: &mut R, string: &str) -> (Range<usize>, (u32, u32)) {
     let chars = string.char_indices().collect::<Vec<_>>();
+    choose_subcategory_range_in(rand, &chars, string.len())
+}
+
+/// Chooses a subcategory-contiguous range in `chars`, given as `(offset, char)` pairs ending at offset `end`
+fn choose_subcategory_range_in<R: Rand>(
+    rand: &mut R,
+    chars: &[(usize, char)],
+    end: usize,
+) -> (Range<usize>, (u32, u32)) {
     let idx =
         rand.below(NonZero::new(chars.len()).expect("Empty string in choose_subcategory_range"));
     let c = chars[idx].1;
@@ -216,7 +238,7 @@ fn choose_subcategory_range<R: Rand>(rand: &mut R, string: &str) -> (Range<usize
     );
 
     (
-        find_range(&chars, idx, |c| {
+        find_range(chars, idx, end, |c| {
             let expanded = c as u32;
             selected.0 <= expanded && expanded <= selected.1
         }),
@@ -297,7 +319,7 @@ where
 
         let bytes = input.0.mutator_bytes();
         let meta = &input.1;
-        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta) {
+        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta.ranges()) {
             let substring = core::str::from_utf8(&bytes[base..][..len])?;
             let (range, category) = choose_category_range(state.rand_mut(), substring);
             #[cfg(test)]
@@ -363,7 +385,7 @@ where
 
         let bytes = input.0.mutator_bytes();
         let meta = &input.1;
-        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta) {
+        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta.ranges()) {
             let substring = core::str::from_utf8(&bytes[base..][..len])?;
             let (range, subcategory) = choose_subcategory_range(state.rand_mut(), substring);
             #[cfg(test)]
@@ -427,7 +449,7 @@ where
 
         let bytes = input.0.mutator_bytes();
         let meta = &input.1;
-        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta) {
+        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta.ranges()) {
             let substring = core::str::from_utf8(&bytes[base..][..len])?;
             let (range, _) = choose_category_range(state.rand_mut(), substring);
 
@@ -490,7 +512,7 @@ where
 
         let bytes = input.0.mutator_bytes();
         let meta = &input.1;
-        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta) {
+        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta.ranges()) {
             let substring = core::str::from_utf8(&bytes[base..][..len])?;
             let (range, _) = choose_subcategory_range(state.rand_mut(), substring);
 
@@ -13,7 +13,10 @@ use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::{
     Error,
-    observers::{ConstLenMapObserver, Observer, map::MapObserver},
+    observers::{
+        ConstLenMapObserver, ObjectiveSignature, Observer,
+        map::{MapObserver, coverage_hash},
+    },
 };
 
 /// Use a const size to speedup `Feedback::is_interesting` when the user can
@@ -34,6 +37,11 @@ where
     fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
         self.reset_map()
     }
+
+    #[inline]
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        signature.add_coverage_hash(coverage_hash(self));
+    }
 }
 
 impl<T, const N: usize> Named for ConstMapObserver<'_, T, N> {
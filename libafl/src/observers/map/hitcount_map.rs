@@ -15,7 +15,8 @@ use crate::{
     Error,
     executors::ExitKind,
     observers::{
-        ConstLenMapObserver, DifferentialObserver, Observer, VarLenMapObserver, map::MapObserver,
+        ConstLenMapObserver, DifferentialObserver, ObjectiveSignature, Observer, VarLenMapObserver,
+        map::{MapObserver, coverage_hash},
     },
 };
 
@@ -143,6 +144,11 @@ where
         classify_counts(&mut self.as_slice_mut());
         self.base.post_exec(state, input, exit_kind)
     }
+
+    #[inline]
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        signature.add_coverage_hash(coverage_hash(&self.base));
+    }
 }
 
 impl<M> Named for HitcountsMapObserver<M>
@@ -360,6 +366,11 @@ where
 
         self.base.post_exec(state, input, exit_kind)
     }
+
+    #[inline]
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        self.base.update_objective_signature(signature);
+    }
 }
 
 impl<M> Named for HitcountsIterableMapObserver<M>
@@ -7,13 +7,15 @@ use core::{
     ops::{Deref, DerefMut},
 };
 
-use libafl_bolts::{AsSlice, AsSliceMut, HasLen, Named, Truncate, ownedref::OwnedMutSlice};
+use libafl_bolts::{
+    AsSlice, AsSliceMut, HasLen, Named, Truncate, hasher_std, ownedref::OwnedMutSlice,
+};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::{
     Error,
     executors::ExitKind,
-    observers::{DifferentialObserver, Observer},
+    observers::{DifferentialObserver, ObjectiveSignature, Observer},
 };
 
 pub mod const_map;
@@ -163,6 +165,10 @@ where
     ) -> Result<(), Error> {
         self.0.post_exec_child(state, input, exit_kind)
     }
+
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        self.0.update_objective_signature(signature);
+    }
 }
 
 impl<T, OTA, OTB, I, S, const ITH: bool, const NTH: bool> DifferentialObserver<OTA, OTB, I, S>
@@ -385,6 +391,23 @@ pub trait MapObserver:
     fn how_many_set(&self, indexes: &[usize]) -> usize;
 }
 
+/// The hash of the indices of all entries of `map` differing from the initial value,
+/// used as the coverage hash of an [`ObjectiveSignature`]
+#[must_use]
+pub fn coverage_hash<M>(map: &M) -> u64
+where
+    M: MapObserver,
+{
+    let initial = map.initial();
+    let mut hasher = hasher_std();
+    for idx in 0..map.usable_count() {
+        if map.get(idx) != initial {
+            idx.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
 /// The "real" length of the underlying map could change at any point in time.
 /// Thus, the size of the map should be fetched each time it is used.
 pub trait VarLenMapObserver: MapObserver {
@@ -452,6 +475,11 @@ where
     fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
         self.reset_map()
     }
+
+    #[inline]
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        signature.add_coverage_hash(coverage_hash(self));
+    }
 }
 
 impl<I, S, T> Observer<I, S> for StdMapObserver<'_, T, true> {}
@@ -16,7 +16,10 @@ use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::{
     Error,
-    observers::{DifferentialObserver, Observer, map::MapObserver},
+    observers::{
+        DifferentialObserver, ObjectiveSignature, Observer,
+        map::{MapObserver, coverage_hash},
+    },
 };
 
 /// The Multi Map Observer merge different maps into one observer
@@ -38,6 +41,11 @@ where
     fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
         self.reset_map()
     }
+
+    #[inline]
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        signature.add_coverage_hash(coverage_hash(self));
+    }
 }
 
 impl<I, S, T> Observer<I, S> for MultiMapObserver<'_, T, true> {
@@ -12,7 +12,10 @@ use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::{
     Error,
-    observers::{Observer, map::MapObserver},
+    observers::{
+        ObjectiveSignature, Observer,
+        map::{MapObserver, coverage_hash},
+    },
 };
 
 /// Exact copy of `StdMapObserver` that owns its map
@@ -31,6 +34,11 @@ where
     fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
         self.reset_map()
     }
+
+    #[inline]
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        signature.add_coverage_hash(coverage_hash(self));
+    }
 }
 
 impl<T> Named for OwnedMapObserver<T> {
@@ -15,7 +15,10 @@ use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::{
     Error,
-    observers::{Observer, VarLenMapObserver, map::MapObserver},
+    observers::{
+        ObjectiveSignature, Observer, VarLenMapObserver,
+        map::{MapObserver, coverage_hash},
+    },
 };
 
 /// Overlooking a variable bitmap
@@ -36,6 +39,11 @@ where
     fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
         self.reset_map()
     }
+
+    #[inline]
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        signature.add_coverage_hash(coverage_hash(self));
+    }
 }
 
 impl<T> Named for VariableMapObserver<'_, T> {
@@ -22,19 +22,66 @@ pub mod value;
 
 /// List observer
 pub mod list;
-use core::{fmt::Debug, time::Duration};
+use core::{fmt::Debug, hash::Hasher, time::Duration};
 #[cfg(feature = "std")]
 use std::time::Instant;
 
 #[cfg(not(feature = "std"))]
 use libafl_bolts::current_time;
-use libafl_bolts::{Named, tuples::MatchName};
+use libafl_bolts::{Named, hasher_std, tuples::MatchName};
 pub use list::*;
 use serde::{Deserialize, Serialize};
 pub use value::*;
 
 use crate::{Error, executors::ExitKind};
 
+/// How many of the innermost frames of a stack trace make up the crash site of an [`ObjectiveSignature`]
+pub const CRASH_SITE_FRAMES: usize = 3;
+
+/// The signature of an objective, sent along with [`crate::events::Event::Objective`],
+/// so that the broker can deduplicate and triage crashes found by different clients.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ObjectiveSignature {
+    /// The hash of the stack trace of the crash, e.g., from a [`BacktraceObserver`]
+    pub stack_hash: Option<u64>,
+    /// The hash of the crash site, i.e., the faulting address or the [`CRASH_SITE_FRAMES`] innermost frames.
+    /// Unlike the whole stack trace, it stays the same for a bug reached along different paths.
+    pub crash_site_hash: Option<u64>,
+    /// The hash of the coverage of the execution, e.g., from a map observer.
+    /// Only used to bucket objectives that come without a crash site or stack trace.
+    pub coverage_hash: Option<u64>,
+}
+
+impl ObjectiveSignature {
+    /// Adds the hash of a stack trace, combining it with any stack hash added before
+    pub fn add_stack_hash(&mut self, hash: u64) {
+        self.stack_hash = Some(Self::combine(self.stack_hash, hash));
+    }
+
+    /// Adds the crash site, given as the addresses of the innermost frames, innermost first.
+    /// Only the first [`CRASH_SITE_FRAMES`] addresses are taken into account.
+    /// The hash gets combined with any crash site added before.
+    pub fn add_crash_site<A>(&mut self, addresses: A)
+    where
+        A: IntoIterator<Item = u64>,
+    {
+        let mut hasher = hasher_std();
+        for address in addresses.into_iter().take(CRASH_SITE_FRAMES) {
+            hasher.write_u64(address);
+        }
+        self.crash_site_hash = Some(Self::combine(self.crash_site_hash, hasher.finish()));
+    }
+
+    /// Adds the hash of a coverage map, combining it with any coverage hash added before
+    pub fn add_coverage_hash(&mut self, hash: u64) {
+        self.coverage_hash = Some(Self::combine(self.coverage_hash, hash));
+    }
+
+    fn combine(previous: Option<u64>, hash: u64) -> u64 {
+        previous.map_or(hash, |previous| previous.rotate_left(17) ^ hash)
+    }
+}
+
 /// Observers observe different information about the target.
 /// They can then be used by various sorts of feedback.
 pub trait Observer<I, S>: Named {
@@ -78,6 +125,10 @@ pub trait Observer<I, S>: Named {
     ) -> Result<(), Error> {
         Ok(())
     }
+
+    /// Adds what this observer knows about the last execution to the signature of an objective.
+    #[inline]
+    fn update_objective_signature(&self, _signature: &mut ObjectiveSignature) {}
 }
 
 /// A haskell-style tuple of observers
@@ -103,6 +154,18 @@ pub trait ObserversTuple<I, S>: MatchName {
         input: &I,
         exit_kind: &ExitKind,
     ) -> Result<(), Error>;
+
+    /// Adds what the observers know about the last execution to the signature of an objective.
+    #[inline]
+    fn update_objective_signature_all(&self, _signature: &mut ObjectiveSignature) {}
+
+    /// The signature of the last execution, if it is an objective
+    #[inline]
+    fn objective_signature(&self) -> ObjectiveSignature {
+        let mut signature = ObjectiveSignature::default();
+        self.update_objective_signature_all(&mut signature);
+        signature
+    }
 }
 
 impl<I, S> ObserversTuple<I, S> for () {
@@ -167,6 +230,11 @@ where
         self.0.post_exec_child(state, input, exit_kind)?;
         self.1.post_exec_child_all(state, input, exit_kind)
     }
+
+    fn update_objective_signature_all(&self, signature: &mut ObjectiveSignature) {
+        self.0.update_objective_signature(signature);
+        self.1.update_objective_signature_all(signature);
+    }
 }
 
 /// A trait for [`Observer`]`s` with a hash field
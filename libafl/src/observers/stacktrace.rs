@@ -38,7 +38,7 @@ use libcasr::{
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use super::ObserverWithHashField;
+use super::{CRASH_SITE_FRAMES, ObjectiveSignature, ObserverWithHashField};
 use crate::{Error, executors::ExitKind, observers::Observer};
 
 #[cfg(not(feature = "casr"))]
@@ -214,6 +214,12 @@ impl<I, S> Observer<I, S> for BacktraceObserver<'_> {
     ) -> Result<(), Error> {
         self.post_exec(state, input, exit_kind)
     }
+
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        if let Some(hash) = *self.hash.as_ref() {
+            signature.add_stack_hash(hash);
+        }
+    }
 }
 
 impl Named for BacktraceObserver<'_> {
@@ -255,6 +261,9 @@ pub fn get_asan_runtime_flags() -> String {
 pub struct AsanBacktraceObserver {
     observer_name: Cow<'static, str>,
     hash: Option<u64>,
+    /// The addresses of the innermost frames of the last report
+    #[serde(default)]
+    crash_site: Vec<u64>,
 }
 
 impl AsanBacktraceObserver {
@@ -268,6 +277,7 @@ impl AsanBacktraceObserver {
         Self {
             observer_name: observer_name.into(),
             hash: None,
+            crash_site: Vec::new(),
         }
     }
 
@@ -282,6 +292,7 @@ impl AsanBacktraceObserver {
         Self {
             observer_name: observer_name.into(),
             hash: None,
+            crash_site: Vec::new(),
         }
     }
 
@@ -313,10 +324,15 @@ impl AsanBacktraceObserver {
     /// parse ASAN error output emited by the target command and compute the hash
     pub fn parse_asan_output(&mut self, output: &str) {
         let mut hash = 0;
+        self.crash_site.clear();
         let matcher = Regex::new("\\s*#[0-9]*\\s0x([0-9a-f]*)\\s.*").unwrap();
         matcher.captures_iter(output).for_each(|m| {
             let g = m.get(1).unwrap();
-            hash ^= u64::from_str_radix(g.as_str(), 16).unwrap();
+            let address = u64::from_str_radix(g.as_str(), 16).unwrap();
+            hash ^= address;
+            if self.crash_site.len() < CRASH_SITE_FRAMES {
+                self.crash_site.push(address);
+            }
         });
         self.update_hash(hash);
     }
@@ -325,12 +341,19 @@ impl AsanBacktraceObserver {
     /// parse ASAN error output emited by the target command and compute the hash
     pub fn parse_asan_output(&mut self, output: &str) {
         let mut hash = 0;
+        self.crash_site.clear();
         if let Ok(st_vec) = AsanStacktrace::extract_stacktrace(output) {
             if let Ok(mut stacktrace) = AsanStacktrace::parse_stacktrace(&st_vec) {
                 stacktrace.filter();
                 let mut s = DefaultHasher::new();
                 stacktrace.hash(&mut s);
                 hash = s.finish();
+                self.crash_site.extend(
+                    stacktrace
+                        .iter()
+                        .take(CRASH_SITE_FRAMES)
+                        .map(|entry| entry.address),
+                );
             }
         }
         self.update_hash(hash);
@@ -355,7 +378,16 @@ impl Default for AsanBacktraceObserver {
     }
 }
 
-impl<I, S> Observer<I, S> for AsanBacktraceObserver {}
+impl<I, S> Observer<I, S> for AsanBacktraceObserver {
+    fn update_objective_signature(&self, signature: &mut ObjectiveSignature) {
+        if let Some(hash) = self.hash {
+            signature.add_stack_hash(hash);
+        }
+        if !self.crash_site.is_empty() {
+            signature.add_crash_site(self.crash_site.iter().copied());
+        }
+    }
+}
 
 impl Named for AsanBacktraceObserver {
     fn name(&self) -> &Cow<'static, str> {
@@ -0,0 +1,391 @@
+//! The [`ChecksumInferenceStage`] finds checksum and length fields in corpus entries, so that the
+//! [`crate::mutators::InputRepairMutator`] can fix them up after mutation, similar to the checksum handling of Redqueen.
+
+use alloc::vec::Vec;
+use core::{marker::PhantomData, ops::Range};
+
+use crate::{
+    Error, HasMetadata,
+    inputs::HasMutatorBytes,
+    mutators::{ChecksumKind, InputFixup, InputFixupsMetadata, IntegerField},
+    observers::{AFLppCmpValuesMetadata, CmpValues, CmpValuesMetadata},
+    stages::{Restartable, Stage, TaintMetadata},
+    state::HasCurrentTestcase,
+};
+
+/// Inputs longer than this are not searched for fixups
+const MAX_INFERENCE_LEN: usize = 1 << 16;
+/// The maximum number of occurrences of a single comparison operand we check
+const MAX_OCCURRENCES: usize = 8;
+/// The maximum number of candidate fields we check per input
+const MAX_CANDIDATES: usize = 32;
+/// The maximum number of taint range and field boundaries considered as start or end of a checksummed range
+const MAX_BOUNDARIES: usize = 12;
+
+/// A stage that infers checksum and length fields of the current [`crate::corpus::Testcase`],
+/// and stores them as [`InputFixupsMetadata`].
+///
+/// It relies on the comparison operands logged for the current input, so it needs to run right after a
+/// [`crate::stages::TracingStage`] with a cmplog observer that adds [`CmpValuesMetadata`], or the
+/// `AFLppCmplogTracingStage` of `libafl_targets`. If a [`crate::stages::ColorizationStage`] ran before,
+/// its [`TaintMetadata`] is used to rule out bytes that do not influence the execution, and to find
+/// the boundaries of checksummed ranges.
+///
+/// Each testcase is only analyzed once.
+#[derive(Debug, Clone, Copy)]
+pub struct ChecksumInferenceStage<I> {
+    phantom: PhantomData<I>,
+}
+
+impl<I> Default for ChecksumInferenceStage<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> ChecksumInferenceStage<I> {
+    /// Creates a new [`ChecksumInferenceStage`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, EM, I, S, Z> Stage<E, EM, S, Z> for ChecksumInferenceStage<I>
+where
+    S: HasCurrentTestcase<I> + HasMetadata,
+    I: HasMutatorBytes + Clone,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut S,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        if state
+            .current_testcase()?
+            .has_metadata::<InputFixupsMetadata>()
+        {
+            return Ok(());
+        }
+
+        let input = state.current_input_cloned()?;
+        let bytes = input.mutator_bytes();
+
+        let mut cmps: Vec<CmpValues> = Vec::new();
+        if let Ok(meta) = state.metadata::<CmpValuesMetadata>() {
+            cmps.extend(meta.list.iter().cloned());
+        }
+        if let Ok(meta) = state.metadata::<AFLppCmpValuesMetadata>() {
+            cmps.extend(meta.orig_cmpvals().values().flatten().cloned());
+        }
+
+        // The taint is only meaningful if it belongs to this input
+        let taint = state
+            .metadata::<TaintMetadata>()
+            .ok()
+            .filter(|taint| taint.input_vec().len() == bytes.len())
+            .map(|taint| taint.ranges().clone())
+            .unwrap_or_default();
+
+        let fixups = infer_fixups(bytes, &cmps, &taint);
+        if !fixups.is_empty() {
+            log::debug!("Inferred input fixups: {fixups:?}");
+        }
+        state
+            .current_testcase_mut()?
+            .add_metadata(InputFixupsMetadata::new(fixups));
+
+        Ok(())
+    }
+}
+
+impl<I, S> Restartable<S> for ChecksumInferenceStage<I> {
+    #[inline]
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
+
+/// Infers the checksum and length fields of `bytes`.
+///
+/// A field is a value that a successful comparison in `cmps` checked, and that is stored in the input.
+/// It is a length field if it equals the input length minus the bytes before or up to the end of the field,
+/// and a checksum field if it equals the [`ChecksumKind::Crc32`] or [`ChecksumKind::Adler32`] over other input bytes.
+/// Fields overlapping a `taint` range, i.e., bytes that do not influence the execution, are ignored.
+#[must_use]
+pub fn infer_fixups(bytes: &[u8], cmps: &[CmpValues], taint: &[Range<usize>]) -> Vec<InputFixup> {
+    let mut fixups = Vec::new();
+    if bytes.is_empty() || bytes.len() > MAX_INFERENCE_LEN {
+        return fixups;
+    }
+
+    // A target only accepts an input if its checksum and length checks pass,
+    // so we are interested in comparisons with equal operands.
+    let mut values: Vec<(u64, usize)> = cmps
+        .iter()
+        .filter_map(|cmp| {
+            let width = match cmp {
+                CmpValues::U8(_) => 1,
+                CmpValues::U16(_) => 2,
+                CmpValues::U32(_) => 4,
+                CmpValues::U64(_) => 8,
+                CmpValues::Bytes(_) => return None,
+            };
+            let (v0, v1, _) = cmp.to_u64_tuple()?;
+            (v0 == v1 && v0 != 0).then_some((v0, width))
+        })
+        .collect();
+    values.sort_unstable();
+    values.dedup();
+
+    // Candidates with their confidence: matching the compared width, wider, and fewer occurrences are
+    // less likely to be coincidental matches.
+    let mut ranked: Vec<((bool, usize, usize), IntegerField, u64)> = Vec::new();
+    for (value, cmp_width) in values {
+        // Prefer the widest encoding, a narrower one matches the lower bytes as well
+        for width in [8, 4, 2, 1] {
+            if width > cmp_width || (width < 8 && value >> (width * 8) != 0) {
+                continue;
+            }
+            for big_endian in [false, true] {
+                if width == 1 && big_endian {
+                    continue;
+                }
+                let field_bytes = if big_endian {
+                    value.to_be_bytes()[8 - width..].to_vec()
+                } else {
+                    value.to_le_bytes()[..width].to_vec()
+                };
+
+                let matches: Vec<IntegerField> = bytes
+                    .windows(width)
+                    .enumerate()
+                    .filter(|(_, window)| *window == field_bytes.as_slice())
+                    .map(|(offset, _)| IntegerField {
+                        offset,
+                        width,
+                        big_endian,
+                    })
+                    .filter(|field| !overlaps(taint, &(field.offset..field.end())))
+                    .take(MAX_OCCURRENCES)
+                    .collect();
+                let confidence = (width == cmp_width, width, MAX_OCCURRENCES - matches.len());
+                ranked.extend(matches.into_iter().map(|field| (confidence, field, value)));
+            }
+        }
+    }
+    // Most confident first, stable for equal confidence
+    ranked.sort_by(|(a, _, _), (b, _, _)| b.cmp(a));
+    let fields: Vec<(IntegerField, u64)> = ranked
+        .into_iter()
+        .take(MAX_CANDIDATES)
+        .map(|(_, field, value)| (field, value))
+        .collect();
+
+    // Length fields first, their bounds are likely bounds of checksummed ranges as well
+    let mut claimed: Vec<Range<usize>> = Vec::new();
+    for (field, value) in &fields {
+        let range = field.offset..field.end();
+        if overlaps(&claimed, &range) {
+            continue;
+        }
+        if let Some(fixup) = infer_length(bytes.len(), *field, *value) {
+            claimed.push(range);
+            fixups.push(fixup);
+        }
+    }
+
+    for (field, value) in &fields {
+        let range = field.offset..field.end();
+        if overlaps(&claimed, &range) {
+            continue;
+        }
+        let Ok(checksum) = u32::try_from(*value) else {
+            continue;
+        };
+        if field.width != 4 {
+            continue;
+        }
+        let boundaries: Vec<usize> = taint
+            .iter()
+            .chain(&claimed)
+            .flat_map(|r| [r.start, r.end])
+            .collect();
+        if let Some(fixup) = infer_checksum(bytes, *field, checksum, &boundaries) {
+            claimed.push(range);
+            fixups.push(fixup);
+        }
+    }
+
+    fixups
+}
+
+/// Checks if `field`, holding `value`, is a length field
+fn infer_length(len: usize, field: IntegerField, value: u64) -> Option<InputFixup> {
+    // single bytes equal to some length are too common to be meaningful
+    if field.width == 1 && value < 4 {
+        return None;
+    }
+    [field.end(), field.offset, 0]
+        .into_iter()
+        .find(|subtract| len.checked_sub(*subtract).map(|l| l as u64) == Some(value))
+        .map(|subtract| InputFixup::Length { field, subtract })
+}
+
+/// Checks if `field`, holding `checksum`, is a checksum over other bytes of the input
+fn infer_checksum(
+    bytes: &[u8],
+    field: IntegerField,
+    checksum: u32,
+    boundaries: &[usize],
+) -> Option<InputFixup> {
+    let len = bytes.len();
+
+    // The most common layouts first: a checksum over everything after, or everything before the field
+    let mut ranges = vec![field.end()..len, 0..field.offset];
+
+    let mut boundaries = boundaries.to_vec();
+    boundaries.sort_unstable_by_key(|b| b.abs_diff(field.offset));
+    boundaries.truncate(MAX_BOUNDARIES);
+    boundaries.extend([0, field.offset, field.end(), len]);
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    for (i, start) in boundaries.iter().enumerate() {
+        for end in &boundaries[i + 1..] {
+            ranges.push(*start..*end);
+        }
+    }
+
+    ranges
+        .into_iter()
+        .filter(|range| {
+            !range.is_empty()
+                && range.end <= len
+                && (range.end <= field.offset || range.start >= field.end())
+        })
+        .find_map(|range| {
+            [ChecksumKind::Crc32, ChecksumKind::Adler32]
+                .into_iter()
+                .find(|kind| kind.compute(&bytes[range.clone()]) == checksum)
+                .map(|kind| InputFixup::Checksum {
+                    field,
+                    kind,
+                    start: range.start,
+                    end: (range.end != len).then_some(range.end),
+                })
+        })
+}
+
+/// If `range` overlaps any of `ranges`
+fn overlaps(ranges: &[Range<usize>], range: &Range<usize>) -> bool {
+    ranges
+        .iter()
+        .any(|r| r.start < range.end && range.start < r.end && !r.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::infer_fixups;
+    use crate::{
+        mutators::{ChecksumKind, InputFixup, InputFixupsMetadata, IntegerField, adler32, crc32},
+        observers::CmpValues,
+    };
+
+    #[test]
+    fn test_infer_fixups() {
+        // [magic][len: u16 be][payload][crc32 le over payload][adler32 be over everything before]
+        let payload = b"some payload bytes";
+        let mut bytes = b"MGC".to_vec();
+        bytes.extend_from_slice(&(payload.len() as u16 + 8).to_be_bytes());
+        bytes.extend_from_slice(payload);
+        let crc = crc32(payload);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        let adler = adler32(&bytes);
+        bytes.extend_from_slice(&adler.to_be_bytes());
+
+        let len_value = payload.len() as u16 + 8;
+        let cmps = [
+            CmpValues::U8((b'M', b'M', true)),
+            CmpValues::U16((len_value, len_value, false)),
+            CmpValues::U32((crc, crc, false)),
+            CmpValues::U32((adler, adler, false)),
+            // a failed comparison is no evidence
+            CmpValues::U32((crc, 1, false)),
+        ];
+
+        let fixups = infer_fixups(&bytes, &cmps, &[]);
+        let field = |offset, width, big_endian| IntegerField {
+            offset,
+            width,
+            big_endian,
+        };
+        assert!(fixups.contains(&InputFixup::Length {
+            field: field(3, 2, true),
+            subtract: 5,
+        }));
+        assert!(fixups.contains(&InputFixup::Checksum {
+            field: field(5 + payload.len(), 4, false),
+            kind: ChecksumKind::Crc32,
+            start: 5,
+            end: Some(5 + payload.len()),
+        }));
+        assert!(fixups.contains(&InputFixup::Checksum {
+            field: field(9 + payload.len(), 4, true),
+            kind: ChecksumKind::Adler32,
+            start: 0,
+            end: Some(9 + payload.len()),
+        }));
+
+        // Mutating the payload and repairing it yields the original input again
+        let meta = InputFixupsMetadata::new(fixups);
+        let mut mutated = bytes.clone();
+        mutated[3..5].fill(0);
+        mutated[5 + payload.len()..].fill(0xff);
+        meta.apply(&mut mutated);
+        assert_eq!(mutated, bytes);
+
+        // Tainted bytes do not influence the execution, so they can not be checked fields
+        let tainted = infer_fixups(&bytes, &cmps, &[0..5, 5..bytes.len()]);
+        assert!(tainted.is_empty());
+    }
+
+    #[test]
+    fn test_infer_fixups_many_operands() {
+        // [payload][crc32 le over payload], with many small, single-byte comparison operands
+        let payload: Vec<u8> = (0x20..0x60).collect();
+        let crc = crc32(&payload);
+        let mut bytes = payload.clone();
+        bytes.extend_from_slice(&crc.to_le_bytes());
+
+        let mut cmps: Vec<CmpValues> = payload
+            .iter()
+            .map(|b| CmpValues::U8((*b, *b, false)))
+            .collect();
+        cmps.push(CmpValues::U32((crc, crc, false)));
+
+        let fixups = infer_fixups(&bytes, &cmps, &[]);
+        assert!(fixups.contains(&InputFixup::Checksum {
+            field: IntegerField {
+                offset: payload.len(),
+                width: 4,
+                big_endian: false,
+            },
+            kind: ChecksumKind::Crc32,
+            start: 0,
+            end: Some(payload.len()),
+        }));
+    }
+}
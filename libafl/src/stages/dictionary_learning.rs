@@ -0,0 +1,439 @@
+//! The [`DictionaryLearningStage`] mines recurring n-grams and delimited words from the corpus
+//! and keeps the most productive ones in the [`Tokens`] dictionary.
+//!
+//! This is useful for targets without autotokens (e.g. binary-only targets), where the dictionary
+//! would otherwise only be filled by cmplog or by hand.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+use hashbrown::{HashMap, HashSet};
+use libafl_bolts::impl_serdeany;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, HasMetadata,
+    corpus::{Corpus, CorpusId},
+    feedbacks::map::MapNoveltiesMetadata,
+    inputs::HasTargetBytes,
+    mutators::Tokens,
+    stages::{Restartable, Stage},
+    state::HasCorpus,
+};
+
+/// Only the first bytes of each input are mined for n-grams, to bound the cost for large inputs.
+const MAX_SCANNED_LEN: usize = 4096;
+
+/// The longest delimited word that is considered as a token candidate.
+const MAX_WORD_LEN: usize = 32;
+
+/// Default delimiters used to split inputs into words.
+const DEFAULT_DELIMITERS: &[u8] = b" \t\r\n\0,;:=&|/\\\"'<>()[]{}";
+
+/// Statistics about one token candidate
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenCandidateStats {
+    /// The number of corpus entries containing this candidate
+    pub occurrences: u64,
+    /// The summed coverage contribution of all corpus entries containing this candidate
+    pub score: u64,
+}
+
+/// State metadata of the [`DictionaryLearningStage`]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    expect(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DictionaryLearningMetadata {
+    last_corpus: Option<CorpusId>,
+    candidates: HashMap<Vec<u8>, TokenCandidateStats>,
+    learned: HashSet<Vec<u8>>,
+}
+
+impl_serdeany!(DictionaryLearningMetadata);
+
+impl DictionaryLearningMetadata {
+    /// The statistics of all token candidates seen so far
+    #[must_use]
+    pub fn candidates(&self) -> &HashMap<Vec<u8>, TokenCandidateStats> {
+        &self.candidates
+    }
+
+    /// The tokens this stage added to [`Tokens`].
+    /// Tokens that were already present (e.g. from a user dictionary) are never listed here,
+    /// and are never removed by the stage.
+    #[must_use]
+    pub fn learned(&self) -> &HashSet<Vec<u8>> {
+        &self.learned
+    }
+}
+
+/// A stage that learns a dictionary from n-gram and delimiter statistics over the corpus.
+///
+/// Every corpus entry is mined once. Each candidate is scored by the coverage its corpus entries
+/// contributed (using [`MapNoveltiesMetadata`] if the feedback tracks novelties, else `1` per entry).
+/// The best `max_tokens` candidates that occur in at least `min_occurrences` entries are kept in the
+/// [`Tokens`] metadata; learned tokens that drop out of the top are removed again.
+#[derive(Debug, Clone)]
+pub struct DictionaryLearningStage<I, S> {
+    min_len: usize,
+    max_len: usize,
+    min_occurrences: u64,
+    max_tokens: usize,
+    max_candidates: usize,
+    delimiters: Vec<u8>,
+    #[cfg(feature = "std")]
+    export_path: Option<PathBuf>,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, S> Default for DictionaryLearningStage<I, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, S> DictionaryLearningStage<I, S> {
+    /// Create a new [`DictionaryLearningStage`] with default settings:
+    /// n-grams of 3 to 8 bytes, at least 3 occurrences and at most 256 learned tokens.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            min_len: 3,
+            max_len: 8,
+            min_occurrences: 3,
+            max_tokens: 256,
+            max_candidates: 1 << 16,
+            delimiters: DEFAULT_DELIMITERS.to_vec(),
+            #[cfg(feature = "std")]
+            export_path: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the (inclusive) range of n-gram lengths to mine.
+    /// `min_len` is also the minimum length of delimited words.
+    #[must_use]
+    pub fn with_ngram_len(mut self, min_len: usize, max_len: usize) -> Self {
+        assert!(
+            min_len > 0 && min_len <= max_len,
+            "Illegal n-gram length range {min_len}..={max_len}"
+        );
+        self.min_len = min_len;
+        self.max_len = max_len;
+        self
+    }
+
+    /// Sets the number of corpus entries a candidate has to occur in before it is learned.
+    #[must_use]
+    pub fn with_min_occurrences(mut self, min_occurrences: u64) -> Self {
+        self.min_occurrences = min_occurrences;
+        self
+    }
+
+    /// Sets the maximum number of tokens this stage keeps in the dictionary.
+    #[must_use]
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Sets the maximum number of candidates to keep statistics for.
+    /// If exceeded, the lower-scoring half of the candidates is dropped.
+    #[must_use]
+    pub fn with_max_candidates(mut self, max_candidates: usize) -> Self {
+        self.max_candidates = max_candidates;
+        self
+    }
+
+    /// Sets the delimiter bytes used to split inputs into words.
+    #[must_use]
+    pub fn with_delimiters(mut self, delimiters: &[u8]) -> Self {
+        self.delimiters = delimiters.to_vec();
+        self
+    }
+
+    /// Writes the whole dictionary to `path`, in the AFL format, whenever the learned tokens change.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn with_export_path<P>(mut self, path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.export_path = Some(path.into());
+        self
+    }
+
+    /// Collects all distinct token candidates of one input
+    fn extract_candidates(&self, bytes: &[u8]) -> HashSet<Vec<u8>> {
+        let bytes = &bytes[..bytes.len().min(MAX_SCANNED_LEN)];
+        let mut found = HashSet::new();
+
+        for len in self.min_len..=self.max_len {
+            for window in bytes.windows(len) {
+                // Runs of a single byte (padding, zeroes) are no useful tokens
+                if window.iter().all(|b| *b == window[0]) {
+                    continue;
+                }
+                found.insert(window.to_vec());
+            }
+        }
+
+        for word in bytes.split(|b| self.delimiters.contains(b)) {
+            if word.len() >= self.min_len && word.len() <= MAX_WORD_LEN {
+                found.insert(word.to_vec());
+            }
+        }
+
+        found
+    }
+
+    /// Drops the lower-scoring half of the candidates, if there are too many
+    fn prune_candidates(&self, meta: &mut DictionaryLearningMetadata) {
+        if meta.candidates.len() <= self.max_candidates {
+            return;
+        }
+        // Learned tokens are always kept, the best half of the others survives.
+        // Ties are broken like in `select_tokens`, so exactly `keep` candidates remain, deterministically.
+        let mut ranked: Vec<(&Vec<u8>, u64)> = meta
+            .candidates
+            .iter()
+            .filter(|(token, _)| !meta.learned.contains(*token))
+            .map(|(token, s)| (token, s.score))
+            .collect();
+        ranked.sort_unstable_by(|(ta, sa), (tb, sb)| {
+            sb.cmp(sa).then(tb.len().cmp(&ta.len())).then(ta.cmp(tb))
+        });
+        let keep = self.max_candidates / 2;
+        let dropped: Vec<Vec<u8>> = ranked
+            .into_iter()
+            .skip(keep)
+            .map(|(token, _)| token.clone())
+            .collect();
+        for token in dropped {
+            meta.candidates.remove(&token);
+        }
+    }
+
+    /// Ranks the candidates and returns the tokens that should be in the dictionary
+    fn select_tokens(&self, meta: &DictionaryLearningMetadata) -> Vec<Vec<u8>> {
+        let mut ranked: Vec<(&Vec<u8>, &TokenCandidateStats)> = meta
+            .candidates
+            .iter()
+            .filter(|(_, s)| s.occurrences >= self.min_occurrences)
+            .collect();
+        // Best score first, longer tokens before their substrings, then lexicographically for determinism
+        ranked.sort_unstable_by(|(ta, sa), (tb, sb)| {
+            sb.score
+                .cmp(&sa.score)
+                .then(tb.len().cmp(&ta.len()))
+                .then(ta.cmp(tb))
+        });
+
+        let mut selected: Vec<(Vec<u8>, u64)> = Vec::new();
+        for (token, stats) in ranked {
+            if selected.len() >= self.max_tokens {
+                break;
+            }
+            // A substring that never occurs without its longer token adds nothing new
+            let redundant = selected.iter().any(|(sel, occurrences)| {
+                *occurrences >= stats.occurrences
+                    && sel.windows(token.len()).any(|w| w == token.as_slice())
+            });
+            if !redundant {
+                selected.push((token.clone(), stats.occurrences));
+            }
+        }
+        selected.into_iter().map(|(token, _)| token).collect()
+    }
+
+    /// Mines all corpus entries added since the last run and updates the [`Tokens`] metadata.
+    /// Returns `true` if the set of learned tokens changed.
+    pub fn learn_dictionary(&mut self, state: &mut S) -> Result<bool, Error>
+    where
+        S: HasCorpus<I> + HasMetadata,
+        I: HasTargetBytes,
+    {
+        let mut meta = state
+            .metadata_map_mut()
+            .remove::<DictionaryLearningMetadata>()
+            .map(|m| *m)
+            .unwrap_or_default();
+
+        let mut corpus_id = match meta.last_corpus {
+            Some(last) => state.corpus().next(last),
+            None => state.corpus().first(),
+        };
+        if corpus_id.is_none() {
+            state.add_metadata(meta);
+            return Ok(false);
+        }
+
+        while let Some(id) = corpus_id {
+            let mut testcase = state.corpus().get(id)?.borrow_mut();
+            let weight = 1 + testcase
+                .metadata_map()
+                .get::<MapNoveltiesMetadata>()
+                .map_or(0, |novelties| novelties.list.len() as u64);
+            let candidates = {
+                let input = testcase.load_input(state.corpus())?;
+                self.extract_candidates(&input.target_bytes())
+            };
+            drop(testcase);
+
+            for candidate in candidates {
+                let stats = meta.candidates.entry(candidate).or_default();
+                stats.occurrences += 1;
+                stats.score += weight;
+            }
+
+            meta.last_corpus = Some(id);
+            corpus_id = state.corpus().next(id);
+        }
+
+        self.prune_candidates(&mut meta);
+        let selected = self.select_tokens(&meta);
+
+        let tokens = state.metadata_or_insert_with(Tokens::new);
+        let mut changed = false;
+        let selected_set: HashSet<Vec<u8>> = selected.iter().cloned().collect();
+        for stale in meta.learned.difference(&selected_set) {
+            changed |= tokens.remove_token(stale);
+        }
+        meta.learned.retain(|token| selected_set.contains(token));
+        for token in selected {
+            if !meta.learned.contains(&token) && tokens.add_token(&token) {
+                meta.learned.insert(token);
+                changed = true;
+            }
+        }
+
+        #[cfg(feature = "std")]
+        if changed {
+            if let Some(path) = &self.export_path {
+                tokens.to_file(path)?;
+            }
+        }
+
+        state.add_metadata(meta);
+        Ok(changed)
+    }
+}
+
+impl<E, EM, I, S, Z> Stage<E, EM, S, Z> for DictionaryLearningStage<I, S>
+where
+    S: HasCorpus<I> + HasMetadata,
+    I: HasTargetBytes,
+{
+    #[inline]
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut S,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        self.learn_dictionary(state)?;
+        Ok(())
+    }
+}
+
+impl<I, S> Restartable<S> for DictionaryLearningStage<I, S> {
+    #[inline]
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::{DictionaryLearningMetadata, DictionaryLearningStage, TokenCandidateStats};
+    use crate::{
+        HasMetadata,
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        inputs::BytesInput,
+        mutators::Tokens,
+        state::{HasCorpus, StdState},
+    };
+
+    #[test]
+    fn test_dictionary_learning() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+
+        let mut user_tokens = Tokens::new();
+        user_tokens.add_token(&b"MAGIC".to_vec());
+        state.add_metadata(user_tokens);
+
+        let mut stage = DictionaryLearningStage::new()
+            .with_ngram_len(4, 6)
+            .with_min_occurrences(2)
+            .with_max_tokens(4);
+
+        for input in [
+            &b"xx MAGIC yy"[..],
+            b"key=MAGIC&header",
+            b"aa header bb MAGIC",
+        ] {
+            state
+                .corpus_mut()
+                .add(Testcase::new(BytesInput::new(input.to_vec())))
+                .unwrap();
+        }
+
+        assert!(stage.learn_dictionary(&mut state).unwrap());
+        // Nothing new to mine
+        assert!(!stage.learn_dictionary(&mut state).unwrap());
+
+        let tokens = state.metadata::<Tokens>().unwrap();
+        assert!(tokens.tokens().contains(&b"header".to_vec()));
+        assert!(tokens.len() <= 5);
+
+        let meta = state.metadata::<DictionaryLearningMetadata>().unwrap();
+        assert!(meta.learned().contains(&b"header".to_vec()));
+        // The user-supplied token is never owned by the stage
+        assert!(!meta.learned().contains(&b"MAGIC".to_vec()));
+        assert_eq!(meta.candidates()[&b"MAGIC".to_vec()].occurrences, 3);
+    }
+
+    #[test]
+    fn test_prune_candidates_keeps_ties() {
+        let stage = DictionaryLearningStage::<BytesInput, ()>::new().with_max_candidates(8);
+        let mut meta = DictionaryLearningMetadata::default();
+        // All candidates tie, as is typical early on
+        for i in 0..10_u8 {
+            meta.candidates.insert(
+                vec![b'a' + i; 4],
+                TokenCandidateStats {
+                    occurrences: 1,
+                    score: 1,
+                },
+            );
+        }
+        meta.learned.insert(vec![b'a' + 9; 4]);
+
+        stage.prune_candidates(&mut meta);
+        // Half of the unlearned candidates, plus the learned one
+        assert_eq!(meta.candidates().len(), 5);
+        assert!(meta.candidates().contains_key(&vec![b'a' + 9; 4]));
+        assert!(meta.candidates().contains_key(&b"aaaa".to_vec()));
+    }
+}
@@ -15,11 +15,13 @@ use core::{fmt, marker::PhantomData};
 #[cfg(feature = "std")]
 pub use afl_stats::{AflStatsStage, CalibrationTime, FuzzTime, SyncTime};
 pub use calibrate::CalibrationStage;
+pub use checksum::ChecksumInferenceStage;
 pub use colorization::*;
 #[cfg(all(feature = "std", unix))]
 pub use concolic::ConcolicTracingStage;
 #[cfg(all(feature = "std", feature = "concolic_mutation", unix))]
 pub use concolic::SimpleConcolicMutationalStage;
+pub use dictionary_learning::{DictionaryLearningMetadata, DictionaryLearningStage};
 #[cfg(feature = "std")]
 pub use dump::*;
 pub use generalization::GeneralizationStage;
@@ -29,6 +31,7 @@ use libafl_bolts::{
     tuples::{HasConstLen, IntoVec},
 };
 pub use logics::*;
+pub use mutation_stats::MutationStatsStage;
 pub use mutational::{MutationalStage, StdMutationalStage};
 pub use power::{PowerMutationalStage, StdPowerMutationalStage};
 use serde::{Deserialize, Serialize};
@@ -66,15 +69,18 @@ pub use replay::*;
 #[cfg(feature = "std")]
 pub mod afl_stats;
 pub mod calibrate;
+pub mod checksum;
 pub mod colorization;
 #[cfg(all(feature = "std", unix))]
 pub mod concolic;
+pub mod dictionary_learning;
 #[cfg(feature = "std")]
 pub mod dump;
 pub mod dynamic;
 pub mod generalization;
 pub mod generation;
 pub mod logics;
+pub mod mutation_stats;
 pub mod nop;
 pub mod power;
 #[cfg(feature = "std")]
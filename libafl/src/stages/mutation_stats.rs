@@ -0,0 +1,133 @@
+//! The [`MutationStatsStage`] reports the per-mutation success counters collected by a
+//! [`crate::mutators::LoggerScheduledMutator`] to the monitors, as [`UserStats`].
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+
+use libafl_bolts::current_time;
+
+use crate::{
+    Error, HasMetadata,
+    events::{Event, EventFirer, EventWithStats},
+    monitors::stats::{AggregatorOps, UserStats, UserStatsValue},
+    mutators::MutationStatsMetadata,
+    stages::{Restartable, Stage},
+    state::HasExecutions,
+};
+
+/// The default interval between two reports
+const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A stage that periodically sends the [`MutationStatsMetadata`] of the state to all monitors.
+///
+/// For each mutation name, it sends `mutation_finds_<name>`, the ratio of executions that found a new
+/// corpus entry or objective to all executions the mutation was part of, and `mutation_objectives_<name>`,
+/// the number of objectives the mutation was part of.
+#[derive(Debug, Clone)]
+pub struct MutationStatsStage<I> {
+    interval: Duration,
+    last_report: Duration,
+    phantom: PhantomData<I>,
+}
+
+impl<I> Default for MutationStatsStage<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> MutationStatsStage<I> {
+    /// Create a new [`MutationStatsStage`], reporting every 15 seconds
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_interval(DEFAULT_REPORT_INTERVAL)
+    }
+
+    /// Create a new [`MutationStatsStage`], reporting at most once per `interval`
+    #[must_use]
+    pub fn with_interval(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_report: Duration::ZERO,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, EM, I, S, Z> Stage<E, EM, S, Z> for MutationStatsStage<I>
+where
+    EM: EventFirer<I, S>,
+    S: HasMetadata + HasExecutions,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        if now.saturating_sub(self.last_report) < self.interval {
+            return Ok(());
+        }
+        self.last_report = now;
+
+        let Some(meta) = state.metadata_map().get::<MutationStatsMetadata>() else {
+            return Ok(());
+        };
+
+        let reports: Vec<_> = meta
+            .mutation_names()
+            .into_iter()
+            .filter_map(|name| meta.get_by_name(name).map(|stats| (name.clone(), stats)))
+            .filter(|(_, stats)| stats.applied > 0)
+            .collect();
+
+        for (name, stats) in reports {
+            for (stat_name, value) in [
+                (
+                    format!("mutation_finds_{name}"),
+                    UserStats::new(
+                        UserStatsValue::Ratio(stats.finds(), stats.applied),
+                        AggregatorOps::Avg,
+                    ),
+                ),
+                (
+                    format!("mutation_objectives_{name}"),
+                    UserStats::new(
+                        UserStatsValue::Number(stats.objective_finds),
+                        AggregatorOps::Sum,
+                    ),
+                ),
+            ] {
+                manager.fire(
+                    state,
+                    EventWithStats::with_current_time(
+                        Event::UpdateUserStats {
+                            name: Cow::Owned(stat_name),
+                            value,
+                            phantom: PhantomData,
+                        },
+                        *state.executions(),
+                    ),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<I, S> Restartable<S> for MutationStatsStage<I> {
+    #[inline]
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
@@ -191,7 +191,8 @@ where
         }
 
         start_timer!(state);
-        let transformed = I::try_transform_from(state.current_testcase_mut()?.borrow_mut(), state)?;
+        let mut transformed =
+            I::try_transform_from(state.current_testcase_mut()?.borrow_mut(), state)?;
         let mut base = state.current_input_cloned()?;
         // potential post operation if base is replaced by a shorter input
         let mut base_post = None;
@@ -221,10 +222,12 @@ where
             mark_feature_time!(state, PerfFeature::Mutate);
 
             if mutated == MutationResult::Skipped {
+                i = next_i;
                 continue;
             }
 
             let (input, post) = input_transformed.try_transform_into(state)?;
+            let mut accepted = false;
             let corpus_id = if input.len() < before_len {
                 // run the input
                 let exit_kind = fuzzer.execute_input(state, executor, manager, &input)?;
@@ -253,6 +256,10 @@ where
                         // we found a reduced corpus entry! use the smaller base
                         base = input;
                         base_post = Some(post);
+                        accepted = true;
+                        // and mutate the smaller base from now on
+                        transformed =
+                            I::try_transform_from(&mut Testcase::from(base.clone()), state)?;
 
                         // do more runs! maybe we can minify further
                         next_i = 0;
@@ -268,6 +275,7 @@ where
 
             start_timer!(state);
             self.mutator_mut().post_exec(state, corpus_id)?;
+            self.mutator_mut().post_trim(state, accepted)?;
             post.post_exec(state, corpus_id)?;
             mark_feature_time!(state, PerfFeature::MutatePostExec);
 
@@ -1,9 +1,10 @@
 //! Stages which analysis common to Unicode-style mutations
 
 use alloc::{collections::VecDeque, rc::Rc, vec::Vec};
-use core::marker::PhantomData;
+use core::{char, marker::PhantomData};
 
 use bitvec::{bitvec, vec::BitVec};
+use encoding_rs::Encoding;
 use libafl_bolts::{Error, impl_serdeany};
 use serde::{Deserialize, Serialize};
 
@@ -68,6 +69,414 @@ pub(crate) fn extract_metadata(bytes: &[u8]) -> UnicodeIdentificationMetadata {
     }
 }
 
+/// The minimum number of printable chars for a run of bytes to be considered a string by [`extract_encoded_metadata`]
+const MIN_ENCODED_STRING_CHARS: usize = 4;
+
+/// A text encoding strings in an input may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TextEncoding {
+    /// UTF-8
+    Utf8,
+    /// UTF-16, little endian, as used by Windows and many JavaScript engines
+    Utf16Le,
+    /// UTF-16, big endian
+    Utf16Be,
+    /// A legacy encoding supported by [`encoding_rs`], serialized by its canonical name
+    Legacy(#[serde(with = "legacy_encoding")] &'static Encoding),
+}
+
+/// (De)serializes a legacy [`Encoding`] by its name, resolving it once on deserialization
+mod legacy_encoding {
+    use alloc::string::String;
+
+    use encoding_rs::Encoding;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error as _};
+
+    #[expect(clippy::trivially_copy_pass_by_ref)] // the signature required by `serde(with)`
+    pub(super) fn serialize<S>(
+        encoding: &&'static Encoding,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(encoding.name())
+    }
+
+    pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<&'static Encoding, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Encoding::for_label(name.as_bytes())
+            .ok_or_else(|| D::Error::custom(format!("Unknown text encoding {name}")))
+    }
+}
+
+/// A [`TextEncoding`], resolved for en- and decoding
+#[derive(Debug, Clone, Copy)]
+enum Codec {
+    Utf8,
+    Utf16 { big_endian: bool },
+    Legacy(&'static Encoding),
+}
+
+impl Codec {
+    fn decode_char(self, bytes: &[u8]) -> Option<(char, usize)> {
+        match self {
+            Self::Utf8 => {
+                let len = bytes.len().min(4);
+                let valid = match core::str::from_utf8(&bytes[..len]) {
+                    Ok(s) => s,
+                    Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+                };
+                valid.chars().next().map(|c| (c, c.len_utf8()))
+            }
+            Self::Utf16 { big_endian } => {
+                let unit = |i: usize| {
+                    let pair = [*bytes.get(i)?, *bytes.get(i + 1)?];
+                    Some(if big_endian {
+                        u16::from_be_bytes(pair)
+                    } else {
+                        u16::from_le_bytes(pair)
+                    })
+                };
+                let first = unit(0)?;
+                if let Some(c) = char::from_u32(u32::from(first)) {
+                    return Some((c, 2));
+                }
+                let c = char::decode_utf16([first, unit(2)?]).next()?.ok()?;
+                Some((c, 4))
+            }
+            Self::Legacy(encoding) => (1..=bytes.len().min(4)).find_map(|len| {
+                let decoded =
+                    encoding.decode_without_bom_handling_and_without_replacement(&bytes[..len])?;
+                let mut chars = decoded.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some((c, len)),
+                    _ => None,
+                }
+            }),
+        }
+    }
+
+    fn encode_char(self, c: char, out: &mut Vec<u8>) -> bool {
+        match self {
+            Self::Utf8 => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            Self::Utf16 { big_endian } => {
+                let mut buf = [0; 2];
+                for unit in c.encode_utf16(&mut buf) {
+                    if big_endian {
+                        out.extend_from_slice(&unit.to_be_bytes());
+                    } else {
+                        out.extend_from_slice(&unit.to_le_bytes());
+                    }
+                }
+            }
+            Self::Legacy(encoding) => {
+                let mut buf = [0; 4];
+                let (encoded, _, unmappable) = encoding.encode(c.encode_utf8(&mut buf));
+                if unmappable {
+                    return false;
+                }
+                out.extend_from_slice(&encoded);
+            }
+        }
+        true
+    }
+}
+
+impl TextEncoding {
+    /// A legacy encoding by its WHATWG label, for example `"shift_jis"`, `"euc-kr"` or `"windows-1252"`
+    pub fn legacy(label: &str) -> Result<Self, Error> {
+        let encoding = Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| Error::illegal_argument(format!("Unknown text encoding {label}")))?;
+        if encoding == encoding_rs::UTF_8 {
+            Ok(Self::Utf8)
+        } else if encoding == encoding_rs::UTF_16LE {
+            Ok(Self::Utf16Le)
+        } else if encoding == encoding_rs::UTF_16BE {
+            Ok(Self::Utf16Be)
+        } else if encoding.output_encoding() != encoding {
+            // e.g. `replacement`, or `x-user-defined`
+            Err(Error::illegal_argument(format!(
+                "Text encoding {label} can not be used to encode strings"
+            )))
+        } else {
+            Ok(Self::Legacy(encoding))
+        }
+    }
+
+    /// Shift-JIS
+    #[must_use]
+    pub fn shift_jis() -> Self {
+        Self::Legacy(encoding_rs::SHIFT_JIS)
+    }
+
+    /// The name of this encoding
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Legacy(encoding) => encoding.name(),
+        }
+    }
+
+    fn codec(self) -> Codec {
+        match self {
+            Self::Utf8 => Codec::Utf8,
+            Self::Utf16Le => Codec::Utf16 { big_endian: false },
+            Self::Utf16Be => Codec::Utf16 { big_endian: true },
+            Self::Legacy(encoding) => Codec::Legacy(encoding),
+        }
+    }
+
+    /// Decodes the char at the start of `bytes`, returning it and its encoded length
+    #[must_use]
+    pub fn decode_char(self, bytes: &[u8]) -> Option<(char, usize)> {
+        self.codec().decode_char(bytes)
+    }
+
+    /// Encodes `c` into `out`. Returns `false` if `c` can not be represented in this encoding.
+    pub fn encode_char(self, c: char, out: &mut Vec<u8>) -> bool {
+        self.codec().encode_char(c, out)
+    }
+}
+
+/// Metadata which stores the encoding detected for an input, and the string-like ranges in that encoding
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncodedStringsMetadata {
+    encoding: TextEncoding,
+    ranges: Rc<Vec<(usize, BitVec)>>,
+}
+
+impl_serdeany!(EncodedStringsMetadata);
+
+impl EncodedStringsMetadata {
+    /// The detected encoding of the strings in the input
+    #[must_use]
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    /// The list of string-like ranges in the input, with the set bits marking the start of each char
+    #[must_use]
+    pub fn ranges(&self) -> &Vec<(usize, BitVec)> {
+        self.ranges.as_ref()
+    }
+
+    /// The number of bytes covered by strings
+    #[must_use]
+    pub fn covered_bytes(&self) -> usize {
+        self.ranges.iter().map(|(_, entries)| entries.len()).sum()
+    }
+}
+
+/// A run of printable chars, see [`printable_run`]
+struct PrintableRun {
+    /// The char offsets, relative to the start of the run
+    starts: Vec<usize>,
+    /// The end offset of the run
+    end: usize,
+    /// The number of ASCII chars in the run
+    ascii: usize,
+}
+
+/// Decodes printable chars starting at `start`, as far as possible
+fn printable_run(codec: Codec, bytes: &[u8], start: usize) -> PrintableRun {
+    let mut run = PrintableRun {
+        starts: Vec::new(),
+        end: start,
+        ascii: 0,
+    };
+    while let Some((c, len)) = bytes.get(run.end..).and_then(|b| codec.decode_char(b)) {
+        if c.is_control() && !matches!(c, '\t' | '\n' | '\r') {
+            break;
+        }
+        run.starts.push(run.end - start);
+        run.ascii += usize::from(c.is_ascii());
+        run.end += len;
+    }
+    run
+}
+
+/// Finds runs of at least four printable chars of the given `encoding` in `bytes`.
+#[must_use]
+pub fn extract_encoded_metadata(bytes: &[u8], encoding: TextEncoding) -> EncodedStringsMetadata {
+    let codec = encoding.codec();
+
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let mut run = printable_run(codec, bytes, i);
+        if let Codec::Utf16 { .. } = codec {
+            // Text in the wrong alignment decodes to CJK ideographs, prefer the alignment with more ASCII
+            let shifted = printable_run(codec, bytes, i + 1);
+            if shifted.ascii > run.ascii {
+                i += 1;
+                run = shifted;
+            }
+        }
+
+        if run.starts.len() >= MIN_ENCODED_STRING_CHARS {
+            let mut entries = bitvec![0; run.end - i];
+            for start in run.starts {
+                entries.set(start, true);
+            }
+            ranges.push((i, entries));
+            i = run.end;
+        } else {
+            i += 1;
+        }
+    }
+
+    EncodedStringsMetadata {
+        encoding,
+        ranges: Rc::new(ranges),
+    }
+}
+
+/// How many of the bytes covered by strings plausibly are text in the metadata's encoding, doubled.
+///
+/// Almost any pair of bytes decodes to a printable UTF-16 char, so only ASCII chars count fully,
+/// any other char counts half.
+fn plausibility(bytes: &[u8], metadata: &EncodedStringsMetadata) -> usize {
+    let mut score = 0;
+    for (offset, entries) in metadata.ranges() {
+        for start in entries.iter_ones() {
+            if let Some((c, len)) = metadata.encoding.decode_char(&bytes[offset + start..]) {
+                score += if c.is_ascii() { 2 * len } else { len };
+            }
+        }
+    }
+    score
+}
+
+/// Detects the most likely of the given `encodings` of `bytes`, returning the strings found in it.
+///
+/// The encoding under which the strings most plausibly are text wins, ASCII counting more than other chars.
+/// On ties, the first encoding wins.
+#[must_use]
+pub fn identify_encoding(
+    bytes: &[u8],
+    encodings: &[TextEncoding],
+) -> Option<EncodedStringsMetadata> {
+    let mut best: Option<(usize, EncodedStringsMetadata)> = None;
+    for encoding in encodings {
+        let metadata = extract_encoded_metadata(bytes, *encoding);
+        let score = plausibility(bytes, &metadata);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, metadata));
+        }
+    }
+    best.map(|(_, metadata)| metadata)
+}
+
+/// Stage which detects the most likely [`TextEncoding`] of the current testcase, out of a list of candidates,
+/// and stores the strings found in that encoding as [`EncodedStringsMetadata`].
+///
+/// Use it together with the [`crate::mutators::EncodedCategoryRandMutator`] and
+/// [`crate::mutators::EncodedSubcategoryRandMutator`].
+#[derive(Debug)]
+pub struct EncodedStringIdentificationStage<I, S> {
+    encodings: Vec<TextEncoding>,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, S> Default for EncodedStringIdentificationStage<I, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, S> EncodedStringIdentificationStage<I, S> {
+    /// Create a new instance, detecting UTF-8, UTF-16LE and UTF-16BE
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            encodings: vec![
+                TextEncoding::Utf8,
+                TextEncoding::Utf16Le,
+                TextEncoding::Utf16Be,
+            ],
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create a new instance, detecting the given `encodings`.
+    /// If several encodings are equally plausible, the first one wins.
+    pub fn with_encodings(encodings: Vec<TextEncoding>) -> Result<Self, Error> {
+        if encodings.is_empty() {
+            return Err(Error::illegal_argument("No text encodings to detect"));
+        }
+        Ok(Self {
+            encodings,
+            phantom: PhantomData,
+        })
+    }
+
+    fn identify_encoding_in_current_testcase(&self, state: &mut S) -> Result<(), Error>
+    where
+        S: HasCurrentTestcase<I>,
+        I: HasTargetBytes,
+    {
+        let mut tc = state.current_testcase_mut()?;
+        if tc.has_metadata::<EncodedStringsMetadata>() {
+            return Ok(()); // skip recompute
+        }
+
+        let input = tc.load_input(state.corpus())?;
+        let bytes = input.target_bytes();
+
+        let best = identify_encoding(&bytes, &self.encodings);
+        drop(bytes);
+
+        if let Some(metadata) = best {
+            log::trace!(
+                "Detected {} strings in testcase",
+                metadata.encoding().name()
+            );
+            tc.add_metadata(metadata);
+        }
+
+        Ok(())
+    }
+}
+
+impl<E, EM, S, Z> Stage<E, EM, S, Z> for EncodedStringIdentificationStage<BytesInput, S>
+where
+    S: HasCorpus<BytesInput> + HasCurrentTestcase<BytesInput>,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut S,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        self.identify_encoding_in_current_testcase(state)
+    }
+}
+
+impl<S> Restartable<S> for EncodedStringIdentificationStage<BytesInput, S> {
+    #[inline]
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // Stage does not run the target. No reset helper needed.
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        // Stage does not run the target. No reset helper needed.
+        Ok(())
+    }
+}
+
 /// Stage which identifies potential strings in the provided input
 #[derive(Debug)]
 pub struct UnicodeIdentificationStage<I, S> {
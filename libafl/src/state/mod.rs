@@ -11,7 +11,8 @@ use core::{
 };
 #[cfg(feature = "std")]
 use std::{
-    fs,
+    fs::{self, File},
+    io::Write,
     path::{Path, PathBuf},
 };
 
@@ -1112,6 +1113,9 @@ where
     }
 
     /// Creates a new `State`, taking ownership of all of the individual components during fuzzing.
+    ///
+    /// In a deterministic campaign, the `rand` continues with the client's
+    /// [`client_rand_stream`](crate::events::client_rand_stream) instead of its own seed.
     pub fn new<F, O>(
         rand: R,
         corpus: C,
@@ -1150,6 +1154,11 @@ where
             #[cfg(feature = "std")]
             multicore_inputs_processed: None,
         };
+        // In a deterministic campaign, each client fuzzes with its own stream of the master seed
+        #[cfg(feature = "std")]
+        if let Some((master_seed, stream, restart)) = crate::events::client_rand_stream() {
+            state.rand.set_stream(master_seed, stream, restart);
+        }
         feedback.init_state(&mut state)?;
         objective.init_state(&mut state)?;
         Ok(state)
@@ -1173,6 +1182,81 @@ impl StdState<InMemoryCorpus<NopInput>, NopInput, StdRand, InMemoryCorpus<NopInp
     }
 }
 
+/// Magic bytes at the start of every checkpoint written by [`write_checkpoint`]
+#[cfg(feature = "std")]
+pub const CHECKPOINT_MAGIC: &[u8; 8] = b"LAFLCKP1";
+
+/// Serializes `state` to a checkpoint file at `path`, from which the campaign can be resumed
+/// after a full restart of the fuzzer using [`read_checkpoint`].
+///
+/// The state is written as it is, without the side effects of a restart, so the fuzzer can keep going.
+/// The checkpoint is first written and synced to a file next to `path` and then renamed over it,
+/// so an interrupted write or a crash of the machine never destroys the previous checkpoint.
+#[cfg(feature = "std")]
+pub fn write_checkpoint<S>(state: &S, path: &Path) -> Result<(), Error>
+where
+    S: Serialize,
+{
+    let mut buf = CHECKPOINT_MAGIC.to_vec();
+    buf.extend(postcard::to_allocvec(state)?);
+
+    let mut tmp = path.as_os_str().to_os_string();
+    tmp.push(".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(&buf)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Deserializes a state from a checkpoint file previously written by [`write_checkpoint`]
+#[cfg(feature = "std")]
+pub fn read_checkpoint<S>(path: &Path) -> Result<S, Error>
+where
+    S: DeserializeOwned,
+{
+    let buf = fs::read(path)?;
+    let Some(payload) = buf.strip_prefix(CHECKPOINT_MAGIC.as_slice()) else {
+        return Err(Error::illegal_argument(format!(
+            "{} is not a fuzzer checkpoint",
+            path.display()
+        )));
+    };
+    Ok(postcard::from_bytes(payload)?)
+}
+
+#[cfg(feature = "std")]
+impl<C, I, R, SC> StdState<C, I, R, SC>
+where
+    C: Serialize + DeserializeOwned,
+    R: Serialize + DeserializeOwned,
+    SC: Serialize + DeserializeOwned,
+{
+    /// Writes everything needed to resume this campaign to `path`:
+    /// the corpora, all (named) metadata such as feedback history and scheduler metadata,
+    /// the RNG state, and the execution counters.
+    ///
+    /// When fuzzing with a restarting event manager, prefer
+    /// [`EventCheckpointer::checkpoint`](crate::events::EventCheckpointer::checkpoint),
+    /// which also makes sure all events sent so far reached the broker.
+    pub fn checkpoint<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        write_checkpoint(self, path.as_ref())
+    }
+
+    /// Loads a state from a checkpoint written by [`StdState::checkpoint`],
+    /// continuing the campaign exactly where it stopped.
+    pub fn from_checkpoint<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        read_checkpoint(path.as_ref())
+    }
+}
+
 #[cfg(feature = "introspection")]
 impl<C, I, R, SC> HasClientPerfMonitor for StdState<C, I, R, SC> {
     fn introspection_stats(&self) -> &ClientPerfStats {
@@ -1339,10 +1423,50 @@ impl<I> HasClientPerfMonitor for NopState<I> {
 
 #[cfg(test)]
 mod test {
+    #[cfg(feature = "std")]
+    use libafl_bolts::rands::{Rand, StdRand};
+
     use crate::state::StdState;
+    #[cfg(feature = "std")]
+    use crate::{
+        corpus::InMemoryCorpus,
+        inputs::NopInput,
+        stages::StageId,
+        state::{HasCurrentStageId, HasExecutions, HasNestedStage, HasRand},
+    };
 
     #[test]
     fn test_std_state() {
         StdState::nop().expect("couldn't instantiate the test state");
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_std_state_checkpoint() {
+        let path =
+            std::env::temp_dir().join(format!("libafl_state_checkpoint_{}", std::process::id()));
+
+        let mut state = StdState::nop().unwrap();
+        *state.executions_mut() = 1337;
+        state.rand_mut().next();
+        state.set_current_stage_id(StageId(1)).unwrap();
+        state.enter_inner_stage().unwrap();
+        state.checkpoint(&path).unwrap();
+        // checkpointing leaves the running state alone
+        state.exit_inner_stage().unwrap();
+        state.clear_stage_id().unwrap();
+
+        let mut resumed: StdState<
+            InMemoryCorpus<NopInput>,
+            NopInput,
+            StdRand,
+            InMemoryCorpus<NopInput>,
+        > = StdState::from_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(*resumed.executions(), 1337);
+        assert_eq!(resumed.rand_mut().next(), state.rand_mut().next());
+        // the resumed state re-enters the stage it was in
+        assert_eq!(resumed.current_stage_id().unwrap(), Some(StageId(1)));
+    }
 }
@@ -13,7 +13,10 @@ use crate::{
 pub struct StageStack {
     /// The stage indexes for each nesting of stages
     stage_idx_stack: Vec<StageId>,
-    /// The current stage depth
+    /// The current stage depth.
+    /// A deserialized stack always starts at the outermost stage, as after [`HasCurrentStageId::on_restart`],
+    /// so that a state serialized while running, e.g., for a checkpoint, resumes the stages it was in.
+    #[serde(skip)]
     stage_depth: usize,
 }
 
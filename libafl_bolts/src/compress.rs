@@ -1,8 +1,13 @@
 //! Compression of events passed between a broker and clients.
-//! Currently we use the gzip compression algorithm for its fast decompression performance.
+//! Gzip is always available, `zstd` and `lz4` can be enabled through the features of the same name.
+//! The [`CodecCompressor`] compresses with one of them, and decompresses any codec this build knows.
 
-use alloc::vec::Vec;
-use core::fmt::Debug;
+use alloc::{borrow::ToOwned, format, vec::Vec};
+use core::fmt::{self, Debug, Display};
+#[cfg(feature = "zstd")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
 
 use miniz_oxide::{
     deflate::{CompressionLevel, compress_to_vec},
@@ -11,6 +16,158 @@ use miniz_oxide::{
 
 use crate::Error;
 
+/// The default size of a trained dictionary, in bytes
+pub const DEFAULT_DICTIONARY_SIZE: usize = 16 * 1024;
+
+/// The amount of samples a [`DictionaryTrainer`] keeps around by default
+pub const DEFAULT_DICTIONARY_SAMPLES: usize = 1024;
+
+/// lz4 only ever looks at the last 64KiB of a dictionary.
+#[cfg(feature = "lz4")]
+const LZ4_MAX_DICTIONARY_SIZE: usize = 64 * 1024;
+
+/// The dictionary installed for this process, see [`install_dictionary`].
+#[cfg(feature = "std")]
+static INSTALLED_DICTIONARY: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// A compression algorithm.
+/// The id of each codec is part of the wire format and must never change.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CompressionCodec {
+    /// Deflate, via `miniz_oxide`. Always available.
+    Gzip = 0,
+    /// Zstandard, needs the `zstd` feature.
+    Zstd = 1,
+    /// lz4 block compression, needs the `lz4` feature.
+    Lz4 = 2,
+}
+
+impl CompressionCodec {
+    /// The id of this codec on the wire
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Get the codec for an id received on the wire, if it is known
+    #[must_use]
+    pub const fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Gzip),
+            1 => Some(Self::Zstd),
+            2 => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+
+    /// The human-readable name of this codec
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Lz4 => "lz4",
+        }
+    }
+
+    /// If this build can compress and decompress this codec
+    #[must_use]
+    pub const fn is_available(self) -> bool {
+        match self {
+            Self::Gzip => true,
+            Self::Zstd => cfg!(feature = "zstd"),
+            Self::Lz4 => cfg!(feature = "lz4"),
+        }
+    }
+
+    /// All codecs this build supports, the most preferable first.
+    /// zstd gives the best ratio for cross-machine links, lz4 is the cheapest on the CPU.
+    #[must_use]
+    pub fn available() -> Vec<Self> {
+        [Self::Zstd, Self::Lz4, Self::Gzip]
+            .into_iter()
+            .filter(|codec| codec.is_available())
+            .collect()
+    }
+
+    /// The codec this build prefers to send with
+    #[must_use]
+    pub fn preferred() -> Self {
+        Self::available()[0]
+    }
+
+    /// Pick the codec to send with to a peer that can decode `theirs`.
+    /// Uses `preferred` if both sides support it, else the best codec both sides know, else gzip.
+    #[must_use]
+    pub fn negotiate(preferred: Self, theirs: &[Self]) -> Self {
+        if preferred.is_available() && theirs.contains(&preferred) {
+            return preferred;
+        }
+        Self::available()
+            .into_iter()
+            .find(|codec| theirs.contains(codec))
+            .unwrap_or(Self::Gzip)
+    }
+}
+
+impl Display for CompressionCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Install a compression dictionary for this process.
+///
+/// Every [`CodecCompressor`] created afterwards will use it for zstd and lz4.
+/// Install it before forking clients, and make sure every node of a fuzzing campaign uses the same dictionary,
+/// else payloads compressed with it cannot be decompressed.
+#[cfg(feature = "std")]
+pub fn install_dictionary(dictionary: Vec<u8>) -> Result<(), Error> {
+    if dictionary.is_empty() {
+        return Err(Error::illegal_argument(
+            "Cannot install an empty compression dictionary",
+        ));
+    }
+    INSTALLED_DICTIONARY
+        .set(dictionary)
+        .map_err(|_| Error::illegal_state("A compression dictionary was already installed"))
+}
+
+/// The dictionary installed with [`install_dictionary`], if any
+#[cfg(feature = "std")]
+#[must_use]
+pub fn installed_dictionary() -> Option<&'static [u8]> {
+    INSTALLED_DICTIONARY.get().map(Vec::as_slice)
+}
+
+/// A compression algorithm that can be used for LLMP payloads.
+pub trait Compressor: Debug {
+    /// The codec this compressor implements
+    fn codec(&self) -> CompressionCodec;
+
+    /// Payloads smaller than this will not get compressed by [`Compressor::maybe_compress`].
+    fn threshold(&self) -> usize;
+
+    /// Force compression.
+    /// Will ignore the preset threshold, and always compress.
+    fn compress(&self, buf: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Decompression.
+    fn decompress(&self, buf: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Compression.
+    /// If the buffer is smaller than the threshold of this compressor, `None` will be returned.
+    /// Else, the buffer is compressed.
+    fn maybe_compress(&self, buf: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if buf.len() >= self.threshold() {
+            self.compress(buf).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
 /// Compression for your stream compression needs.
 #[derive(Debug)]
 pub struct GzipCompressor {
@@ -71,9 +228,502 @@ impl GzipCompressor {
     }
 }
 
+impl Compressor for GzipCompressor {
+    fn codec(&self) -> CompressionCodec {
+        CompressionCodec::Gzip
+    }
+
+    fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    fn compress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(GzipCompressor::compress(self, buf))
+    }
+
+    fn decompress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        GzipCompressor::decompress(self, buf)
+    }
+}
+
+/// The zstd contexts of a [`ZstdCompressor`], created on first use and reused for every message.
+#[cfg(feature = "zstd")]
+#[derive(Default)]
+struct ZstdContexts {
+    compressor: Option<zstd::bulk::Compressor<'static>>,
+    decompressor: Option<zstd::bulk::Decompressor<'static>>,
+}
+
+/// Zstandard compression, optionally using a dictionary.
+/// The compression and decompression contexts are kept around, so a dictionary is only loaded once.
+#[cfg(feature = "zstd")]
+pub struct ZstdCompressor {
+    threshold: usize,
+    level: i32,
+    dictionary: Option<Vec<u8>>,
+    contexts: Mutex<ZstdContexts>,
+}
+
+#[cfg(feature = "zstd")]
+impl Debug for ZstdCompressor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZstdCompressor")
+            .field("threshold", &self.threshold)
+            .field("level", &self.level)
+            .field("dictionary_len", &self.dictionary.as_ref().map(Vec::len))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdCompressor {
+    /// The default compression level. Low levels keep the broker cheap, and still beat gzip on size.
+    pub const DEFAULT_LEVEL: i32 = 3;
+
+    /// Create a [`ZstdCompressor`] that compresses buffers of at least `threshold` bytes
+    #[must_use]
+    pub fn with_threshold(threshold: usize) -> Self {
+        Self {
+            threshold,
+            level: Self::DEFAULT_LEVEL,
+            dictionary: None,
+            contexts: Mutex::new(ZstdContexts::default()),
+        }
+    }
+
+    /// Create a [`ZstdCompressor`] that will always compress
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_threshold(0)
+    }
+
+    /// Set the zstd compression level
+    #[must_use]
+    pub fn with_level(mut self, level: i32) -> Self {
+        self.level = level;
+        self.contexts = Mutex::new(ZstdContexts::default());
+        self
+    }
+
+    /// Compress and decompress with the given dictionary.
+    /// Both sides need to use the same dictionary.
+    #[must_use]
+    pub fn with_dictionary(mut self, dictionary: &[u8]) -> Self {
+        self.dictionary = Some(dictionary.to_vec());
+        self.contexts = Mutex::new(ZstdContexts::default());
+        self
+    }
+
+    /// The zstd compression level
+    #[must_use]
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+
+    /// Lock the cached contexts. A panic while compressing cannot leave them half-updated,
+    /// so a poisoned lock is fine to reuse.
+    fn contexts(&self) -> std::sync::MutexGuard<'_, ZstdContexts> {
+        self.contexts
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Default for ZstdCompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Compressor for ZstdCompressor {
+    fn codec(&self) -> CompressionCodec {
+        CompressionCodec::Zstd
+    }
+
+    fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    fn compress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut contexts = self.contexts();
+        if contexts.compressor.is_none() {
+            contexts.compressor = Some(match &self.dictionary {
+                Some(dictionary) => {
+                    zstd::bulk::Compressor::with_dictionary(self.level, dictionary)?
+                }
+                None => zstd::bulk::Compressor::new(self.level)?,
+            });
+        }
+        let compressor = contexts.compressor.as_mut().unwrap();
+        Ok(compressor.compress(buf)?)
+    }
+
+    fn decompress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        // Our frames always carry their content size, anything else did not come from a peer.
+        let capacity = match zstd::zstd_safe::get_frame_content_size(buf) {
+            Ok(Some(size)) => usize::try_from(size).map_err(|_| Error::compression())?,
+            _ => return Err(Error::compression()),
+        };
+
+        let mut contexts = self.contexts();
+        if contexts.decompressor.is_none() {
+            contexts.decompressor = Some(match &self.dictionary {
+                Some(dictionary) => zstd::bulk::Decompressor::with_dictionary(dictionary)?,
+                None => zstd::bulk::Decompressor::new()?,
+            });
+        }
+        let decompressor = contexts.decompressor.as_mut().unwrap();
+        decompressor
+            .decompress(buf, capacity)
+            .map_err(|_| Error::compression())
+    }
+}
+
+/// lz4 block compression, optionally using a dictionary.
+/// The uncompressed size is prepended to each compressed buffer.
+#[cfg(feature = "lz4")]
+#[derive(Debug)]
+pub struct Lz4Compressor {
+    threshold: usize,
+    dictionary: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "lz4")]
+impl Lz4Compressor {
+    /// Create a [`Lz4Compressor`] that compresses buffers of at least `threshold` bytes
+    #[must_use]
+    pub fn with_threshold(threshold: usize) -> Self {
+        Self {
+            threshold,
+            dictionary: None,
+        }
+    }
+
+    /// Create a [`Lz4Compressor`] that will always compress
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_threshold(0)
+    }
+
+    /// Compress and decompress with the given dictionary.
+    /// Only the last 64KiB are used. Both sides need to use the same dictionary.
+    #[must_use]
+    pub fn with_dictionary(mut self, dictionary: &[u8]) -> Self {
+        let start = dictionary.len().saturating_sub(LZ4_MAX_DICTIONARY_SIZE);
+        self.dictionary = Some(dictionary[start..].to_vec());
+        self
+    }
+}
+
+#[cfg(feature = "lz4")]
+impl Default for Lz4Compressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "lz4")]
+impl Compressor for Lz4Compressor {
+    fn codec(&self) -> CompressionCodec {
+        CompressionCodec::Lz4
+    }
+
+    fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    fn compress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(match &self.dictionary {
+            Some(dictionary) => lz4_flex::block::compress_prepend_size_with_dict(buf, dictionary),
+            None => lz4_flex::block::compress_prepend_size(buf),
+        })
+    }
+
+    fn decompress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        match &self.dictionary {
+            Some(dictionary) => {
+                lz4_flex::block::decompress_size_prepended_with_dict(buf, dictionary)
+            }
+            None => lz4_flex::block::decompress_size_prepended(buf),
+        }
+        .map_err(|_| Error::compression())
+    }
+}
+
+/// Collects observed testcases and trains a compression dictionary from them.
+///
+/// With the `zstd` feature, the dictionary is trained with zstd's dictionary builder.
+/// Otherwise, or if there are too few samples for training, the most recent samples are used as raw content dictionary,
+/// which both zstd and lz4 understand.
+#[derive(Debug, Clone)]
+pub struct DictionaryTrainer {
+    samples: Vec<Vec<u8>>,
+    max_samples: usize,
+    dictionary_size: usize,
+    observed: usize,
+}
+
+impl DictionaryTrainer {
+    /// Create a new [`DictionaryTrainer`] for a dictionary of (at most) `dictionary_size` bytes
+    #[must_use]
+    pub fn new(dictionary_size: usize) -> Self {
+        Self {
+            samples: Vec::new(),
+            max_samples: DEFAULT_DICTIONARY_SAMPLES,
+            dictionary_size,
+            observed: 0,
+        }
+    }
+
+    /// Keep at most `max_samples` samples. Once full, the oldest samples are replaced.
+    #[must_use]
+    pub fn with_max_samples(mut self, max_samples: usize) -> Self {
+        self.max_samples = max_samples.max(1);
+        self.samples.truncate(self.max_samples);
+        self
+    }
+
+    /// Observe a new sample, usually the bytes of a testcase or a serialized event
+    pub fn observe(&mut self, sample: &[u8]) {
+        if sample.is_empty() {
+            return;
+        }
+        if self.samples.len() < self.max_samples {
+            self.samples.push(sample.to_vec());
+        } else {
+            let idx = self.observed % self.max_samples;
+            sample.clone_into(&mut self.samples[idx]);
+        }
+        self.observed += 1;
+    }
+
+    /// The amount of samples currently kept
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// If no samples have been observed yet
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// If the trainer keeps as many samples as it may, so that training will not improve by waiting
+    #[must_use]
+    pub fn is_full(&self) -> bool {
+        self.samples.len() >= self.max_samples
+    }
+
+    /// Train a dictionary from the observed samples
+    pub fn train(&self) -> Result<Vec<u8>, Error> {
+        if self.samples.is_empty() {
+            return Err(Error::illegal_state(
+                "Cannot train a compression dictionary without samples",
+            ));
+        }
+
+        #[cfg(feature = "zstd")]
+        match zstd::dict::from_samples(&self.samples, self.dictionary_size) {
+            Ok(dictionary) => return Ok(dictionary),
+            Err(err) => log::info!(
+                "Could not train zstd dictionary from {} samples ({err}), using raw content",
+                self.samples.len()
+            ),
+        }
+
+        Ok(self.raw_content())
+    }
+
+    /// The newest samples, concatenated, with the newest sample at the end.
+    fn raw_content(&self) -> Vec<u8> {
+        let mut dictionary = Vec::with_capacity(self.dictionary_size);
+        let newest = self.observed % self.samples.len();
+        let (older, newer) = self.samples.split_at(newest);
+        for sample in newer.iter().chain(older) {
+            dictionary.extend_from_slice(sample);
+        }
+        let start = dictionary.len().saturating_sub(self.dictionary_size);
+        dictionary.split_off(start)
+    }
+}
+
+/// Compresses with a single (negotiated) codec, and decompresses all codecs supported by this build.
+#[derive(Debug)]
+pub struct CodecCompressor {
+    codec: CompressionCodec,
+    threshold: usize,
+    gzip: GzipCompressor,
+    #[cfg(feature = "zstd")]
+    zstd: ZstdCompressor,
+    #[cfg(feature = "zstd")]
+    zstd_dict: Option<ZstdCompressor>,
+    #[cfg(feature = "lz4")]
+    lz4: Lz4Compressor,
+    #[cfg(feature = "lz4")]
+    lz4_dict: Option<Lz4Compressor>,
+}
+
+impl CodecCompressor {
+    /// Create a [`CodecCompressor`] for the preferred codec of this build,
+    /// compressing buffers of at least `threshold` bytes.
+    /// If a dictionary was installed with [`install_dictionary`], it will be used.
+    #[must_use]
+    pub fn with_threshold(threshold: usize) -> Self {
+        let ret = Self {
+            codec: CompressionCodec::preferred(),
+            threshold,
+            gzip: GzipCompressor::with_threshold(threshold),
+            #[cfg(feature = "zstd")]
+            zstd: ZstdCompressor::with_threshold(threshold),
+            #[cfg(feature = "zstd")]
+            zstd_dict: None,
+            #[cfg(feature = "lz4")]
+            lz4: Lz4Compressor::with_threshold(threshold),
+            #[cfg(feature = "lz4")]
+            lz4_dict: None,
+        };
+
+        #[cfg(feature = "std")]
+        if let Some(dictionary) = installed_dictionary() {
+            return ret.with_dictionary(dictionary);
+        }
+
+        ret
+    }
+
+    /// Create a [`CodecCompressor`] that will always compress
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_threshold(0)
+    }
+
+    /// Compress with the given codec.
+    /// Errors if this build does not support it.
+    pub fn with_codec(mut self, codec: CompressionCodec) -> Result<Self, Error> {
+        if !codec.is_available() {
+            return Err(Error::illegal_argument(format!(
+                "Compression codec {codec} is not supported by this build, enable the `{codec}` feature"
+            )));
+        }
+        self.codec = codec;
+        Ok(self)
+    }
+
+    /// Use the given dictionary for the codecs that support one (zstd and lz4).
+    #[must_use]
+    #[cfg_attr(
+        not(any(feature = "zstd", feature = "lz4")),
+        expect(unused_mut, unused_variables)
+    )]
+    pub fn with_dictionary(mut self, dictionary: &[u8]) -> Self {
+        #[cfg(feature = "zstd")]
+        {
+            self.zstd_dict =
+                Some(ZstdCompressor::with_threshold(self.threshold).with_dictionary(dictionary));
+        }
+        #[cfg(feature = "lz4")]
+        {
+            self.lz4_dict =
+                Some(Lz4Compressor::with_threshold(self.threshold).with_dictionary(dictionary));
+        }
+        self
+    }
+
+    /// If this compressor has a dictionary for the given codec
+    #[must_use]
+    pub fn has_dictionary(&self, codec: CompressionCodec) -> bool {
+        match codec {
+            CompressionCodec::Gzip => false,
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd => self.zstd_dict.is_some(),
+            #[cfg(feature = "lz4")]
+            CompressionCodec::Lz4 => self.lz4_dict.is_some(),
+            #[cfg(not(all(feature = "zstd", feature = "lz4")))]
+            _ => false,
+        }
+    }
+
+    /// If payloads compressed by this compressor use a dictionary
+    #[must_use]
+    pub fn uses_dictionary(&self) -> bool {
+        self.has_dictionary(self.codec)
+    }
+
+    /// Decompress a payload compressed with the given codec, and with or without dictionary.
+    pub fn decompress_with(
+        &self,
+        codec: CompressionCodec,
+        dictionary: bool,
+        buf: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        self.compressor_for(codec, dictionary)?.decompress(buf)
+    }
+
+    fn compressor_for(
+        &self,
+        codec: CompressionCodec,
+        dictionary: bool,
+    ) -> Result<&dyn Compressor, Error> {
+        if dictionary && !self.has_dictionary(codec) {
+            return Err(Error::illegal_state(format!(
+                "Payload was compressed with a {codec} dictionary, but no dictionary is installed"
+            )));
+        }
+        match codec {
+            CompressionCodec::Gzip => Ok(&self.gzip),
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd => Ok(match &self.zstd_dict {
+                Some(zstd_dict) if dictionary => zstd_dict,
+                _ => &self.zstd,
+            }),
+            #[cfg(feature = "lz4")]
+            CompressionCodec::Lz4 => Ok(match &self.lz4_dict {
+                Some(lz4_dict) if dictionary => lz4_dict,
+                _ => &self.lz4,
+            }),
+            #[cfg(not(all(feature = "zstd", feature = "lz4")))]
+            _ => Err(Error::illegal_argument(format!(
+                "Compression codec {codec} is not supported by this build"
+            ))),
+        }
+    }
+}
+
+impl Default for CodecCompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compressor for CodecCompressor {
+    fn codec(&self) -> CompressionCodec {
+        self.codec
+    }
+
+    fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    fn compress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        self.compressor_for(self.codec, self.uses_dictionary())?
+            .compress(buf)
+    }
+
+    fn decompress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        self.decompress_with(self.codec, self.uses_dictionary(), buf)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::compress::GzipCompressor;
+    use alloc::vec::Vec;
+
+    use crate::compress::{
+        CodecCompressor, CompressionCodec, Compressor, DictionaryTrainer, GzipCompressor,
+    };
 
     #[test]
     fn test_compression() {
@@ -92,4 +742,73 @@ mod tests {
         assert!(compressor.maybe_compress(&[1u8; 1023]).is_none());
         assert!(compressor.maybe_compress(&[1u8; 1024]).is_some());
     }
+
+    #[test]
+    fn test_codecs_roundtrip() {
+        let payload: Vec<u8> = (0..4096_u32).map(|i| (i % 7) as u8).collect();
+        for codec in CompressionCodec::available() {
+            let compressor = CodecCompressor::new().with_codec(codec).unwrap();
+            let compressed = compressor.maybe_compress(&payload).unwrap().unwrap();
+            assert!(compressed.len() < payload.len());
+            assert_eq!(
+                compressor
+                    .decompress_with(codec, false, &compressed)
+                    .unwrap(),
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let mut trainer = DictionaryTrainer::new(1024).with_max_samples(4);
+        assert!(trainer.train().is_err());
+        for i in 0..8_u8 {
+            trainer.observe(&[b'A', b'B', b'C', i]);
+        }
+        assert_eq!(trainer.len(), 4);
+        let dictionary = trainer.train().unwrap();
+        assert!(!dictionary.is_empty());
+
+        let payload = b"ABC\x07ABC\x06ABC\x05";
+        for codec in CompressionCodec::available() {
+            let compressor = CodecCompressor::new()
+                .with_codec(codec)
+                .unwrap()
+                .with_dictionary(&dictionary);
+            let compressed = compressor.compress(payload).unwrap();
+            let dictionary_used = compressor.uses_dictionary();
+            assert_eq!(dictionary_used, codec != CompressionCodec::Gzip);
+            assert_eq!(
+                compressor
+                    .decompress_with(codec, dictionary_used, &compressed)
+                    .unwrap(),
+                payload
+            );
+            if dictionary_used {
+                assert!(
+                    CodecCompressor::new()
+                        .decompress_with(codec, true, &compressed)
+                        .is_err()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_negotiate() {
+        use CompressionCodec::{Gzip, Lz4, Zstd};
+
+        assert_eq!(CompressionCodec::negotiate(Gzip, &[Zstd, Gzip]), Gzip);
+        assert_eq!(CompressionCodec::negotiate(Zstd, &[Gzip]), Gzip);
+        assert_eq!(CompressionCodec::negotiate(Lz4, &[]), Gzip);
+        assert_eq!(
+            CompressionCodec::negotiate(Lz4, &[Lz4, Gzip]),
+            if cfg!(feature = "lz4") { Lz4 } else { Gzip }
+        );
+        for codec in [Gzip, Zstd, Lz4] {
+            assert_eq!(CompressionCodec::from_id(codec.id()), Some(codec));
+        }
+        assert_eq!(CompressionCodec::from_id(0xff), None);
+    }
 }
@@ -37,6 +37,7 @@ use alloc::{
     string::{String, ToString},
     vec::Vec,
 };
+use core::fmt::{self, Display, Formatter};
 
 use serde::{Deserialize, Serialize};
 
@@ -73,6 +74,20 @@ impl CoreId {
     pub fn set_affinity_forced(&self) -> Result<(), Error> {
         set_for_current_helper(*self)
     }
+
+    /// The [`CoreId`] the current thread is running on right now
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        linux::current()
+    }
+
+    /// The NUMA node this [`CoreId`] belongs to, or `None` if the system does not report NUMA nodes
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn numa_node(&self) -> Option<usize> {
+        linux::numa_node(*self)
+    }
 }
 
 impl From<usize> for CoreId {
@@ -117,20 +132,38 @@ impl Cores {
         Ok(())
     }
 
+    /// Pick one core per physical core, skipping SMT siblings, within the cgroup cpu quota.
+    /// See [`order_physical_first`] for the order the cores are picked in.
+    pub fn physical() -> Result<Self, Error> {
+        Self::from_cmdline("physical")
+    }
+
+    /// Pick all usable cores, physical cores before their SMT siblings, within the cgroup cpu quota
+    pub fn usable() -> Result<Self, Error> {
+        Self::from_cmdline("usable")
+    }
+
     /// Parses core binding args from user input.
     /// Returns a Vec of CPU IDs.
     /// * `./fuzzer --cores 1,2-4,6`: clients run in cores `1,2,3,4,6`
     /// * `./fuzzer --cores all`: one client runs on each available core
+    /// * `./fuzzer --cores physical`: one client runs on each usable physical core, see [`Cores::physical`]
+    /// * `./fuzzer --cores usable`: one client runs on each usable core, see [`Cores::usable`]
     pub fn from_cmdline(args: &str) -> Result<Self, Error> {
         let mut cores: Vec<CoreId> = vec![];
 
         // ./fuzzer --cores all -> one client runs in each available core
         if args == "all" {
-            // TODO: is this really correct? Core ID != core number?
-            let num_cores = get_core_ids()?.len();
-            for x in 0..num_cores {
-                cores.push(x.into());
+            cores = get_core_ids()?;
+        } else if args == "physical" || args == "usable" {
+            let mut topology = order_physical_first(get_core_topology()?);
+            if args == "physical" {
+                topology.retain(|core| !core.is_smt_sibling());
+            }
+            if let Some(quota) = cpu_quota() {
+                topology.truncate(quota);
             }
+            cores = topology.iter().map(|core| core.id).collect();
         } else {
             let core_args: Vec<&str> = args.split(',').collect();
 
@@ -165,6 +198,31 @@ impl Cores {
         self.ids.contains(&core_id)
     }
 
+    /// The topology of the chosen cores, to report the layout a campaign runs on
+    pub fn layout(&self) -> Result<CoreLayout, Error> {
+        let topology = get_core_topology()?;
+        let chosen = self
+            .ids
+            .iter()
+            .map(|id| {
+                topology
+                    .iter()
+                    .find(|core| core.id == *id)
+                    .copied()
+                    .unwrap_or_else(|| CoreTopology::standalone(*id))
+            })
+            .collect();
+        Ok(CoreLayout {
+            chosen,
+            usable: topology.len(),
+            physical: topology
+                .iter()
+                .filter(|core| !core.is_smt_sibling())
+                .count(),
+            cpu_quota: cpu_quota(),
+        })
+    }
+
     /// Returns the index/position of the given [`CoreId`] in this cores.ids list.
     /// Will return `None`, if [`CoreId`] wasn't found.
     #[must_use]
@@ -204,6 +262,119 @@ impl TryFrom<&str> for Cores {
     }
 }
 
+/// Where a logical core sits in the cpu topology
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub struct CoreTopology {
+    /// The logical core
+    pub id: CoreId,
+    /// The physical package (socket) of this core
+    pub package: usize,
+    /// The physical core within the package, shared by all its SMT siblings
+    pub core: usize,
+    /// The position among the SMT siblings of the physical core, `0` for the first one
+    pub smt_index: usize,
+}
+
+impl CoreTopology {
+    /// A core without known siblings, as reported on platforms without topology information
+    #[must_use]
+    pub fn standalone(id: CoreId) -> Self {
+        Self {
+            id,
+            package: 0,
+            core: id.0,
+            smt_index: 0,
+        }
+    }
+
+    /// If this core is a hyperthread sharing its physical core with a core that has a lower id
+    #[must_use]
+    pub fn is_smt_sibling(&self) -> bool {
+        self.smt_index > 0
+    }
+}
+
+/// Returns the topology of all cores this process may run on, ordered by [`CoreId`].
+///
+/// On Linux, cores outside the cpuset of the process's cgroup are left out, and SMT siblings are
+/// read from sysfs. On other platforms, each core is reported as its own physical core.
+pub fn get_core_topology() -> Result<Vec<CoreTopology>, Error> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::get_core_topology()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Ok(get_core_ids()?
+            .into_iter()
+            .map(CoreTopology::standalone)
+            .collect())
+    }
+}
+
+/// Orders cores so that every physical core comes before any SMT sibling,
+/// spreading the physical cores over the packages.
+#[must_use]
+pub fn order_physical_first(mut topology: Vec<CoreTopology>) -> Vec<CoreTopology> {
+    topology.sort_by_key(|core| (core.smt_index, core.core, core.package, core.id.0));
+    topology
+}
+
+/// The number of cores the cpu quota of this process's cgroup allows fully, but at least one.
+/// A fractional core is not counted, as a fuzzer on it would be throttled all the time.
+///
+/// Returns `None` if there is no quota, or if the platform has no cgroups.
+#[must_use]
+pub fn cpu_quota() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::cpu_quota()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// The cores chosen for a campaign, together with the limits they were chosen under
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreLayout {
+    /// The chosen cores, in order
+    pub chosen: Vec<CoreTopology>,
+    /// The number of cores this process may run on
+    pub usable: usize,
+    /// The number of physical cores among the usable ones
+    pub physical: usize,
+    /// The number of cores allowed by the cgroup cpu quota, if any
+    pub cpu_quota: Option<usize>,
+}
+
+impl Display for CoreLayout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} cores chosen of {} usable ({} physical)",
+            self.chosen.len(),
+            self.usable,
+            self.physical
+        )?;
+        if let Some(quota) = self.cpu_quota {
+            write!(f, ", cpu quota of {quota} cores")?;
+        }
+        for core in &self.chosen {
+            write!(
+                f,
+                "\n  core {}: package {}, physical core {}",
+                core.id.0, core.package, core.core
+            )?;
+            if core.is_smt_sibling() {
+                write!(f, ", SMT sibling {}", core.smt_index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 // Linux Section
 
 #[cfg(any(
@@ -237,6 +408,8 @@ fn set_for_current_helper(core_id: CoreId) -> Result<(), Error> {
 mod linux {
     use alloc::{string::ToString, vec::Vec};
     use core::mem::{size_of, zeroed};
+    #[cfg(target_os = "linux")]
+    use std::path::{Path, PathBuf};
 
     #[cfg(not(target_os = "freebsd"))]
     use libc::cpu_set_t;
@@ -250,8 +423,14 @@ mod linux {
     const CPU_SETSIZE: libc::c_int = 256;
 
     use super::CoreId;
+    #[cfg(target_os = "linux")]
+    use super::{CoreTopology, Cores};
     use crate::Error;
 
+    /// Where the cgroup hierarchies are mounted
+    #[cfg(target_os = "linux")]
+    const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
     #[allow(trivial_numeric_casts)]
     pub fn get_core_ids() -> Result<Vec<CoreId>, Error> {
         let full_set = get_affinity_mask()?;
@@ -289,6 +468,151 @@ mod linux {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    pub fn current() -> Option<CoreId> {
+        usize::try_from(unsafe { libc::sched_getcpu() })
+            .ok()
+            .map(CoreId)
+    }
+
+    /// Each cpu in sysfs links the NUMA node it belongs to as `nodeN`.
+    #[cfg(target_os = "linux")]
+    pub fn numa_node(core_id: CoreId) -> Option<usize> {
+        std::fs::read_dir(format!("/sys/devices/system/cpu/cpu{}", core_id.0))
+            .ok()?
+            .filter_map(Result::ok)
+            .find_map(|entry| {
+                entry
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("node")?
+                    .parse()
+                    .ok()
+            })
+    }
+
+    /// The usable cores, with their package and physical core from sysfs
+    #[cfg(target_os = "linux")]
+    pub fn get_core_topology() -> Result<Vec<CoreTopology>, Error> {
+        let mut core_ids = get_core_ids()?;
+        if let Some(cpuset) = cgroup_cpuset() {
+            core_ids.retain(|id| cpuset.contains(id));
+        }
+
+        let mut topology: Vec<CoreTopology> = Vec::with_capacity(core_ids.len());
+        for id in core_ids {
+            let read_id = |name: &str| {
+                std::fs::read_to_string(format!(
+                    "/sys/devices/system/cpu/cpu{}/topology/{name}",
+                    id.0
+                ))
+                .ok()
+                .and_then(|content| content.trim().parse().ok())
+            };
+            let package = read_id("physical_package_id").unwrap_or(0);
+            let core = read_id("core_id").unwrap_or(id.0);
+            let smt_index = topology
+                .iter()
+                .filter(|other| other.package == package && other.core == core)
+                .count();
+            topology.push(CoreTopology {
+                id,
+                package,
+                core,
+                smt_index,
+            });
+        }
+        Ok(topology)
+    }
+
+    /// The directory of the given cgroup controller for this process, or of the unified
+    /// hierarchy for `None`
+    #[cfg(target_os = "linux")]
+    fn cgroup_dir(controller: Option<&str>) -> Option<PathBuf> {
+        let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+        for line in cgroups.lines() {
+            let mut fields = line.splitn(3, ':');
+            let (Some(hierarchy), Some(controllers), Some(path)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let mount = match controller {
+                None if hierarchy == "0" && controllers.is_empty() => {
+                    let root = Path::new(CGROUP_ROOT);
+                    // Hybrid setups mount the unified hierarchy next to the v1 controllers
+                    if root.join("cgroup.controllers").exists() {
+                        root.to_path_buf()
+                    } else {
+                        root.join("unified")
+                    }
+                }
+                Some(controller) if controllers.split(',').any(|c| c == controller) => {
+                    Path::new(CGROUP_ROOT).join(controllers)
+                }
+                _ => continue,
+            };
+            let dir = mount.join(path.trim_start_matches('/'));
+            // Inside a cgroup namespace, our own cgroup is mounted as the root
+            return Some(if dir.exists() { dir } else { mount });
+        }
+        None
+    }
+
+    /// The cores in the cpuset of this process's cgroup
+    #[cfg(target_os = "linux")]
+    fn cgroup_cpuset() -> Option<Vec<CoreId>> {
+        let cpus = cgroup_dir(None)
+            .and_then(|dir| std::fs::read_to_string(dir.join("cpuset.cpus.effective")).ok())
+            .or_else(|| {
+                let dir = cgroup_dir(Some("cpuset"))?;
+                std::fs::read_to_string(dir.join("cpuset.effective_cpus"))
+                    .or_else(|_| std::fs::read_to_string(dir.join("cpuset.cpus")))
+                    .ok()
+            })?;
+        Cores::from_cmdline(cpus.trim()).ok().map(|cores| cores.ids)
+    }
+
+    /// The cpu quota of this process's cgroup and all its parents, in cores
+    #[cfg(target_os = "linux")]
+    pub fn cpu_quota() -> Option<usize> {
+        let unified = cgroup_dir(None).and_then(|dir| {
+            dir.ancestors()
+                .take_while(|dir| dir.starts_with(CGROUP_ROOT))
+                .filter_map(|dir| std::fs::read_to_string(dir.join("cpu.max")).ok())
+                .filter_map(|max| parse_cpu_max(&max))
+                .min()
+        });
+        unified.or_else(|| {
+            let dir = cgroup_dir(Some("cpu"))?;
+            let read = |name: &str| -> Option<i64> {
+                std::fs::read_to_string(dir.join(name))
+                    .ok()?
+                    .trim()
+                    .parse()
+                    .ok()
+            };
+            quota_to_cores(read("cpu.cfs_quota_us")?, read("cpu.cfs_period_us")?)
+        })
+    }
+
+    /// Parses the `cpu.max` file of cgroup v2, `<quota|max> <period>`
+    #[cfg(target_os = "linux")]
+    pub(super) fn parse_cpu_max(max: &str) -> Option<usize> {
+        let mut fields = max.split_whitespace();
+        let quota = fields.next()?.parse().ok()?;
+        let period = fields.next()?.parse().ok()?;
+        quota_to_cores(quota, period)
+    }
+
+    /// Converts a cpu quota per period to cores, rounded down, but at least one. Negative quotas mean no limit.
+    #[cfg(target_os = "linux")]
+    fn quota_to_cores(quota: i64, period: i64) -> Option<usize> {
+        let quota = u64::try_from(quota).ok().filter(|quota| *quota > 0)?;
+        let period = u64::try_from(period).ok().filter(|period| *period > 0)?;
+        usize::try_from((quota / period).max(1)).ok()
+    }
+
     fn get_affinity_mask() -> Result<cpu_set_t, Error> {
         let mut set = new_cpu_set();
 
@@ -829,4 +1153,44 @@ mod tests {
 
         ids[0].set_affinity().unwrap();
     }
+
+    #[test]
+    fn test_order_physical_first() {
+        // Two packages with two physical cores each, numbered like Linux does with SMT
+        let topology = (0..8)
+            .map(|id| CoreTopology {
+                id: CoreId(id),
+                package: (id / 2) % 2,
+                core: id % 2,
+                smt_index: id / 4,
+            })
+            .collect();
+        let order: Vec<usize> = order_physical_first(topology)
+            .iter()
+            .map(|core| core.id.0)
+            .collect();
+        assert_eq!(order, [0, 2, 1, 3, 4, 6, 5, 7]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_physical_cores() {
+        let usable = Cores::usable().unwrap();
+        let physical = Cores::physical().unwrap();
+        assert!(physical.ids.len() <= usable.ids.len());
+        assert!(physical.ids.iter().all(|id| usable.contains(*id)));
+
+        let layout = physical.layout().unwrap();
+        assert_eq!(layout.chosen.len(), physical.ids.len());
+        assert!(layout.chosen.iter().all(|core| !core.is_smt_sibling()));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_parse_cpu_max() {
+        assert_eq!(linux::parse_cpu_max("max 100000\n"), None);
+        assert_eq!(linux::parse_cpu_max("200000 100000\n"), Some(2));
+        assert_eq!(linux::parse_cpu_max("150000 100000"), Some(1));
+        assert_eq!(linux::parse_cpu_max("50000 100000"), Some(1));
+    }
 }
@@ -0,0 +1,1036 @@
+//! A structured crash report, collecting everything known about a crash in one place.
+//!
+//! A [`CrashReport`] holds the signal, the registers, the backtrace, the sanitizer
+//! classification and the path of the reproducer. It can be attached to a testcase as metadata
+//! and exported as JSON or as [SARIF](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html),
+//! so that bug trackers and code scanning tools can ingest it.
+//!
+//! Reports created in a signal handler only hold the raw frames of the backtrace, with their module offsets.
+//! Symbolize them later, outside of the handler, with [`CrashReport::symbolize`].
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    ffi::c_void,
+    fmt::{self, Write},
+};
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use libc::siginfo_t;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::Error;
+#[cfg(unix)]
+use crate::os::unix_signals::{Signal, ucontext_t};
+
+/// Faults on addresses below this are reported as null pointer dereferences.
+const NULL_PAGE_SIZE: u64 = 0x1000;
+
+/// The maximum number of frames [`capture_raw_backtrace`] unwinds
+const MAX_RAW_FRAMES: usize = 128;
+
+/// A single, possibly symbolized, frame of a backtrace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackFrame {
+    /// The instruction address of this frame
+    pub address: u64,
+    /// The path of the module (executable or shared library) containing the address, if known
+    #[serde(default)]
+    pub module: Option<String>,
+    /// The offset of the address in its module, which stays the same across runs of the binary
+    #[serde(default)]
+    pub module_offset: Option<u64>,
+    /// The demangled name of the function, if known
+    pub symbol: Option<String>,
+    /// The source file, if debug info is available
+    pub file: Option<String>,
+    /// The source line, if debug info is available
+    pub line: Option<u32>,
+}
+
+impl StackFrame {
+    /// Symbolizes an address of the current process, using the debug info of the loaded modules.
+    ///
+    /// Inlined functions at this address result in multiple frames, innermost first.
+    #[must_use]
+    pub fn symbolize(address: u64) -> Vec<Self> {
+        let mut frames = Vec::new();
+        backtrace::resolve(address as usize as *mut c_void, |symbol| {
+            frames.push(Self::from_symbol(address, symbol));
+        });
+        if frames.is_empty() {
+            frames.push(Self {
+                address,
+                ..Self::default()
+            });
+        }
+        frames
+    }
+
+    /// A frame of an address of the current process, with its module but without symbols
+    #[must_use]
+    pub fn unsymbolized(address: u64) -> Self {
+        let module = module_of(address);
+        Self {
+            address,
+            module_offset: module.as_ref().map(|(_, base)| address.wrapping_sub(*base)),
+            module: module.map(|(path, _)| path),
+            ..Self::default()
+        }
+    }
+
+    fn from_symbol(address: u64, symbol: &backtrace::Symbol) -> Self {
+        Self {
+            address,
+            symbol: symbol.name().map(|name| format!("{name:#}")),
+            file: symbol.filename().map(|file| file.display().to_string()),
+            line: symbol.lineno(),
+            ..Self::default()
+        }
+    }
+
+    /// The address of this frame in the current process.
+    ///
+    /// Frames captured by an earlier run of the same binary are rebased to where their module is loaded now.
+    fn current_address(&self) -> Option<u64> {
+        let (Some(module), Some(offset)) = (&self.module, self.module_offset) else {
+            return Some(self.address);
+        };
+        if module_of(self.address).is_some_and(|(path, base)| {
+            path == *module && self.address.wrapping_sub(base) == offset
+        }) {
+            return Some(self.address);
+        }
+        module_base(module).map(|base| base.wrapping_add(offset))
+    }
+}
+
+impl fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#018x}", self.address)?;
+        if let Some(symbol) = &self.symbol {
+            write!(f, " in {symbol}")?;
+        } else if let (Some(module), Some(offset)) = (&self.module, self.module_offset) {
+            write!(f, " in {module}+{offset:#x}")?;
+        }
+        if let Some(file) = &self.file {
+            write!(f, " at {file}")?;
+            if let Some(line) = self.line {
+                write!(f, ":{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Captures and symbolizes the backtrace of the current thread.
+///
+/// Symbolizing takes locks and reads debug info, so don't call this from a signal handler,
+/// see [`capture_raw_backtrace`] instead.
+#[must_use]
+pub fn capture_backtrace() -> Vec<StackFrame> {
+    let backtrace = backtrace::Backtrace::new();
+    let mut frames = Vec::new();
+    for frame in backtrace.frames() {
+        let address = frame.ip() as usize as u64;
+        if frame.symbols().is_empty() {
+            frames.push(StackFrame {
+                address,
+                ..StackFrame::default()
+            });
+        }
+        for symbol in frame.symbols() {
+            frames.push(StackFrame {
+                address,
+                symbol: symbol.name().map(|name| format!("{name:#}")),
+                file: symbol.filename().map(|file| file.display().to_string()),
+                line: symbol.lineno(),
+                ..StackFrame::default()
+            });
+        }
+    }
+    frames
+}
+
+/// Captures the backtrace of the current thread without symbolizing it, starting at the frame of `pc`.
+///
+/// In a signal handler, `pc` is the program counter of the interrupted context: the frames of the handler
+/// itself are dropped. If `pc` is not found on the stack, the backtrace only holds the frame of `pc`.
+/// Frames only get their module and module offset, see [`CrashReport::symbolize`] to resolve them later.
+#[must_use]
+pub fn capture_raw_backtrace(pc: Option<u64>) -> Vec<StackFrame> {
+    let mut addresses = Vec::with_capacity(MAX_RAW_FRAMES);
+    // # Safety
+    // Unwinding without the lock of the `backtrace` crate, which the crashing thread might hold.
+    unsafe {
+        backtrace::trace_unsynchronized(|frame| {
+            addresses.push(frame.ip() as usize as u64);
+            addresses.len() < MAX_RAW_FRAMES
+        });
+    }
+
+    let addresses = match pc {
+        Some(pc) => match addresses.iter().position(|&address| address == pc) {
+            Some(start) => &addresses[start..],
+            None => &[pc][..],
+        },
+        None => &addresses[..],
+    };
+    addresses
+        .iter()
+        .map(|&address| StackFrame::unsymbolized(address))
+        .collect()
+}
+
+/// The path and the base address of the module containing `address` in the current process
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_vendor = "apple"
+))]
+fn module_of(address: u64) -> Option<(String, u64)> {
+    let mut info: libc::Dl_info = unsafe { core::mem::zeroed() };
+    if unsafe { libc::dladdr(address as usize as *const c_void, &raw mut info) } == 0
+        || info.dli_fname.is_null()
+    {
+        return None;
+    }
+    let path = unsafe { core::ffi::CStr::from_ptr(info.dli_fname) };
+    Some((
+        path.to_string_lossy().into_owned(),
+        info.dli_fbase as usize as u64,
+    ))
+}
+
+/// The path and the base address of the module containing `address` in the current process
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_vendor = "apple"
+)))]
+fn module_of(_address: u64) -> Option<(String, u64)> {
+    None
+}
+
+/// The base address the module at `path` is loaded at in the current process
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn module_base(path: &str) -> Option<u64> {
+    use std::{ffi::CStr, fs};
+
+    /// The canonical path of the module to find, and where it was found
+    struct Search {
+        path: PathBuf,
+        base: Option<u64>,
+    }
+
+    #[cfg_attr(
+        target_pointer_width = "64",
+        expect(
+            clippy::useless_conversion,
+            reason = "addresses are only narrower than u64 on 32-bit targets"
+        )
+    )]
+    unsafe extern "C" fn callback(
+        info: *mut libc::dl_phdr_info,
+        _size: libc::size_t,
+        data: *mut c_void,
+    ) -> libc::c_int {
+        let (info, search) = unsafe { (&*info, &mut *data.cast::<Search>()) };
+        let name = if info.dlpi_name.is_null() {
+            ""
+        } else {
+            unsafe { CStr::from_ptr(info.dlpi_name) }
+                .to_str()
+                .unwrap_or_default()
+        };
+        // The main executable has no name
+        let module = if name.is_empty() {
+            std::env::current_exe().ok()
+        } else {
+            Some(PathBuf::from(name))
+        };
+        if module.and_then(|module| fs::canonicalize(module).ok()) != Some(search.path.clone()) {
+            return 0;
+        }
+
+        // The module's base is where its first segment is mapped, like `dladdr` reports it
+        let phdrs = if info.dlpi_phdr.is_null() {
+            &[][..]
+        } else {
+            unsafe { core::slice::from_raw_parts(info.dlpi_phdr, usize::from(info.dlpi_phnum)) }
+        };
+        let first_vaddr = phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type == libc::PT_LOAD)
+            .map(|phdr| u64::from(phdr.p_vaddr))
+            .min()
+            .unwrap_or_default();
+        search.base = Some(u64::from(info.dlpi_addr).wrapping_add(first_vaddr));
+        1
+    }
+
+    let mut search = Search {
+        path: fs::canonicalize(path).ok()?,
+        base: None,
+    };
+    unsafe {
+        libc::dl_iterate_phdr(Some(callback), (&raw mut search).cast());
+    }
+    search.base
+}
+
+/// The base address the module at `path` is loaded at in the current process
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn module_base(_path: &str) -> Option<u64> {
+    None
+}
+
+/// The registers of a `ucontext`, as `(name, value)` pairs
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    target_arch = "x86_64"
+))]
+#[expect(clippy::cast_sign_loss)]
+fn ucontext_registers(ucontext: &ucontext_t) -> Vec<(String, u64)> {
+    use libc::{
+        REG_EFL, REG_R8, REG_R9, REG_R10, REG_R11, REG_R12, REG_R13, REG_R14, REG_R15, REG_RAX,
+        REG_RBP, REG_RBX, REG_RCX, REG_RDI, REG_RDX, REG_RIP, REG_RSI, REG_RSP,
+    };
+
+    let gregs = &ucontext.uc_mcontext.gregs;
+    [
+        ("rax", REG_RAX),
+        ("rbx", REG_RBX),
+        ("rcx", REG_RCX),
+        ("rdx", REG_RDX),
+        ("rsi", REG_RSI),
+        ("rdi", REG_RDI),
+        ("rbp", REG_RBP),
+        ("rsp", REG_RSP),
+        ("r8", REG_R8),
+        ("r9", REG_R9),
+        ("r10", REG_R10),
+        ("r11", REG_R11),
+        ("r12", REG_R12),
+        ("r13", REG_R13),
+        ("r14", REG_R14),
+        ("r15", REG_R15),
+        ("rip", REG_RIP),
+        ("efl", REG_EFL),
+    ]
+    .into_iter()
+    .map(|(name, reg)| (name.into(), gregs[reg as usize] as u64))
+    .collect()
+}
+
+/// The registers of a `ucontext`, as `(name, value)` pairs
+#[cfg(all(any(target_os = "linux", target_os = "android"), target_arch = "x86"))]
+#[expect(clippy::cast_sign_loss)]
+fn ucontext_registers(ucontext: &ucontext_t) -> Vec<(String, u64)> {
+    use libc::{
+        REG_EAX, REG_EBP, REG_EBX, REG_ECX, REG_EDI, REG_EDX, REG_EFL, REG_EIP, REG_ESI, REG_ESP,
+    };
+
+    let gregs = &ucontext.uc_mcontext.gregs;
+    [
+        ("eax", REG_EAX),
+        ("ebx", REG_EBX),
+        ("ecx", REG_ECX),
+        ("edx", REG_EDX),
+        ("esi", REG_ESI),
+        ("edi", REG_EDI),
+        ("ebp", REG_EBP),
+        ("esp", REG_ESP),
+        ("eip", REG_EIP),
+        ("efl", REG_EFL),
+    ]
+    .into_iter()
+    .map(|(name, reg)| (name.into(), u64::from(gregs[reg as usize] as u32)))
+    .collect()
+}
+
+/// The registers of a `ucontext`, as `(name, value)` pairs
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    target_arch = "aarch64"
+))]
+fn ucontext_registers(ucontext: &ucontext_t) -> Vec<(String, u64)> {
+    let mcontext = &ucontext.uc_mcontext;
+    let mut registers: Vec<(String, u64)> = mcontext
+        .regs
+        .iter()
+        .enumerate()
+        .map(|(reg, value)| (format!("x{reg}"), *value))
+        .collect();
+    registers.push(("sp".into(), mcontext.sp));
+    registers.push(("pc".into(), mcontext.pc));
+    registers.push(("pstate".into(), mcontext.pstate));
+    registers
+}
+
+/// The registers of a `ucontext`, as `(name, value)` pairs
+#[cfg(all(target_os = "linux", target_arch = "arm"))]
+fn ucontext_registers(ucontext: &ucontext_t) -> Vec<(String, u64)> {
+    let mcontext = &ucontext.uc_mcontext;
+    [
+        ("r0", mcontext.arm_r0),
+        ("r1", mcontext.arm_r1),
+        ("r2", mcontext.arm_r2),
+        ("r3", mcontext.arm_r3),
+        ("r4", mcontext.arm_r4),
+        ("r5", mcontext.arm_r5),
+        ("r6", mcontext.arm_r6),
+        ("r7", mcontext.arm_r7),
+        ("r8", mcontext.arm_r8),
+        ("r9", mcontext.arm_r9),
+        ("r10", mcontext.arm_r10),
+        ("fp", mcontext.arm_fp),
+        ("ip", mcontext.arm_ip),
+        ("sp", mcontext.arm_sp),
+        ("lr", mcontext.arm_lr),
+        ("pc", mcontext.arm_pc),
+        ("cpsr", mcontext.arm_cpsr),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.into(), u64::from(value)))
+    .collect()
+}
+
+/// The registers of a `ucontext`, as `(name, value)` pairs
+#[cfg(all(target_vendor = "apple", target_arch = "aarch64"))]
+fn ucontext_registers(ucontext: &ucontext_t) -> Vec<(String, u64)> {
+    let ss = unsafe { &(*ucontext.uc_mcontext).__ss };
+    let mut registers: Vec<(String, u64)> = ss
+        .__x
+        .iter()
+        .enumerate()
+        .map(|(reg, value)| (format!("x{reg}"), *value))
+        .collect();
+    registers.push(("fp".into(), ss.__fp));
+    registers.push(("lr".into(), ss.__lr));
+    registers.push(("sp".into(), ss.__sp));
+    registers.push(("pc".into(), ss.__pc));
+    registers.push(("cpsr".into(), u64::from(ss.__cpsr)));
+    registers
+}
+
+/// The registers of a `ucontext`, as `(name, value)` pairs
+#[cfg(all(target_vendor = "apple", target_arch = "x86_64"))]
+fn ucontext_registers(ucontext: &ucontext_t) -> Vec<(String, u64)> {
+    let ss = unsafe { &(*ucontext.uc_mcontext).__ss };
+    [
+        ("rax", ss.__rax),
+        ("rbx", ss.__rbx),
+        ("rcx", ss.__rcx),
+        ("rdx", ss.__rdx),
+        ("rsi", ss.__rsi),
+        ("rdi", ss.__rdi),
+        ("rbp", ss.__rbp),
+        ("rsp", ss.__rsp),
+        ("r8", ss.__r8),
+        ("r9", ss.__r9),
+        ("r10", ss.__r10),
+        ("r11", ss.__r11),
+        ("r12", ss.__r12),
+        ("r13", ss.__r13),
+        ("r14", ss.__r14),
+        ("r15", ss.__r15),
+        ("rip", ss.__rip),
+        ("efl", ss.__rflags),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.into(), value))
+    .collect()
+}
+
+/// The registers of a `ucontext`, as `(name, value)` pairs
+#[cfg(all(
+    any(target_os = "freebsd", target_os = "dragonfly"),
+    target_arch = "x86_64"
+))]
+#[expect(clippy::cast_sign_loss)]
+fn ucontext_registers(ucontext: &ucontext_t) -> Vec<(String, u64)> {
+    let mcontext = &ucontext.uc_mcontext;
+    [
+        ("rax", mcontext.mc_rax),
+        ("rbx", mcontext.mc_rbx),
+        ("rcx", mcontext.mc_rcx),
+        ("rdx", mcontext.mc_rdx),
+        ("rsi", mcontext.mc_rsi),
+        ("rdi", mcontext.mc_rdi),
+        ("rbp", mcontext.mc_rbp),
+        ("rsp", mcontext.mc_rsp),
+        ("r8", mcontext.mc_r8),
+        ("r9", mcontext.mc_r9),
+        ("r10", mcontext.mc_r10),
+        ("r11", mcontext.mc_r11),
+        ("r12", mcontext.mc_r12),
+        ("r13", mcontext.mc_r13),
+        ("r14", mcontext.mc_r14),
+        ("r15", mcontext.mc_r15),
+        ("rip", mcontext.mc_rip),
+        ("efl", mcontext.mc_rflags),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.into(), value as u64))
+    .collect()
+}
+
+/// The registers of a `ucontext`, as `(name, value)` pairs; not supported on this platform
+#[cfg(all(
+    unix,
+    not(any(
+        all(
+            any(target_os = "linux", target_os = "android"),
+            any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64")
+        ),
+        all(target_os = "linux", target_arch = "arm"),
+        all(
+            target_vendor = "apple",
+            any(target_arch = "aarch64", target_arch = "x86_64")
+        ),
+        all(
+            any(target_os = "freebsd", target_os = "dragonfly"),
+            target_arch = "x86_64"
+        ),
+    ))
+))]
+fn ucontext_registers(_ucontext: &ucontext_t) -> Vec<(String, u64)> {
+    Vec::new()
+}
+
+/// The kind of bug behind a crash, as reported by a sanitizer or derived from the signal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CrashClass {
+    /// Out-of-bounds access on the heap
+    HeapBufferOverflow,
+    /// Out-of-bounds access on the stack
+    StackBufferOverflow,
+    /// Out-of-bounds access of a global
+    GlobalBufferOverflow,
+    /// Access to freed or out-of-scope memory
+    UseAfterFree,
+    /// Memory was freed twice
+    DoubleFree,
+    /// A pointer not returned by the allocator was freed
+    InvalidFree,
+    /// Memory was allocated but never freed
+    MemoryLeak,
+    /// Read of uninitialized memory
+    UninitializedRead,
+    /// Undefined behavior, such as an integer overflow
+    UndefinedBehavior,
+    /// The stack was exhausted
+    StackOverflow,
+    /// Access to the null page
+    NullDereference,
+    /// Any other invalid memory access
+    Segv,
+    /// The target aborted
+    Abort,
+    /// Division by zero or another arithmetic fault
+    FloatingPointException,
+    /// An illegal instruction was executed
+    IllegalInstruction,
+    /// A misaligned or nonexistent physical address was accessed
+    BusError,
+    /// The crash could not be classified
+    #[default]
+    Unknown,
+}
+
+impl CrashClass {
+    /// The identifier of this class, as used by the sanitizers and as SARIF rule id
+    #[must_use]
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::HeapBufferOverflow => "heap-buffer-overflow",
+            Self::StackBufferOverflow => "stack-buffer-overflow",
+            Self::GlobalBufferOverflow => "global-buffer-overflow",
+            Self::UseAfterFree => "use-after-free",
+            Self::DoubleFree => "double-free",
+            Self::InvalidFree => "invalid-free",
+            Self::MemoryLeak => "memory-leak",
+            Self::UninitializedRead => "use-of-uninitialized-value",
+            Self::UndefinedBehavior => "undefined-behavior",
+            Self::StackOverflow => "stack-overflow",
+            Self::NullDereference => "null-dereference",
+            Self::Segv => "segv",
+            Self::Abort => "abort",
+            Self::FloatingPointException => "floating-point-exception",
+            Self::IllegalInstruction => "illegal-instruction",
+            Self::BusError => "bus-error",
+            Self::Unknown => "unknown-crash",
+        }
+    }
+
+    /// A short, human readable description of this class
+    #[must_use]
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::HeapBufferOverflow => "Out-of-bounds access on the heap",
+            Self::StackBufferOverflow => "Out-of-bounds access on the stack",
+            Self::GlobalBufferOverflow => "Out-of-bounds access of a global variable",
+            Self::UseAfterFree => "Use of memory after it was freed",
+            Self::DoubleFree => "Memory freed twice",
+            Self::InvalidFree => "Free of memory not owned by the allocator",
+            Self::MemoryLeak => "Allocated memory was never freed",
+            Self::UninitializedRead => "Read of uninitialized memory",
+            Self::UndefinedBehavior => "Undefined behavior",
+            Self::StackOverflow => "Stack exhaustion",
+            Self::NullDereference => "Null pointer dereference",
+            Self::Segv => "Invalid memory access",
+            Self::Abort => "The target aborted",
+            Self::FloatingPointException => "Arithmetic fault",
+            Self::IllegalInstruction => "Illegal instruction",
+            Self::BusError => "Bus error",
+            Self::Unknown => "Unclassified crash",
+        }
+    }
+
+    /// Classifies a crash from the report printed by `ASan`, `MSan`, `UBSan` or `LSan`.
+    ///
+    /// Returns the name of the sanitizer and the class, or [`None`] if the output holds no report.
+    #[must_use]
+    pub fn from_sanitizer_output(output: &str) -> Option<(String, Self)> {
+        for line in output.lines() {
+            if let Some(pos) = line.find("runtime error:") {
+                let details = &line[pos..];
+                let class = if details.contains("null pointer") {
+                    Self::NullDereference
+                } else {
+                    Self::UndefinedBehavior
+                };
+                return Some(("UndefinedBehaviorSanitizer".into(), class));
+            }
+
+            let Some(report) = line.split("ERROR: ").nth(1) else {
+                continue;
+            };
+            let Some((sanitizer, details)) = report.split_once(": ") else {
+                continue;
+            };
+            if !sanitizer.ends_with("Sanitizer") {
+                continue;
+            }
+            let kind = details.split_whitespace().next().unwrap_or_default();
+            let class = match kind {
+                "heap-buffer-overflow" | "container-overflow" => Self::HeapBufferOverflow,
+                "stack-buffer-overflow"
+                | "stack-buffer-underflow"
+                | "dynamic-stack-buffer-overflow" => Self::StackBufferOverflow,
+                "global-buffer-overflow" => Self::GlobalBufferOverflow,
+                "heap-use-after-free" | "stack-use-after-return" | "stack-use-after-scope" => {
+                    Self::UseAfterFree
+                }
+                "attempting" if details.contains("double-free") => Self::DoubleFree,
+                "attempting" | "alloc-dealloc-mismatch" | "new-delete-type-mismatch" => {
+                    Self::InvalidFree
+                }
+                "detected" if details.contains("memory leaks") => Self::MemoryLeak,
+                "use-of-uninitialized-value" => Self::UninitializedRead,
+                "stack-overflow" => Self::StackOverflow,
+                "SEGV" if output.contains("address points to the zero page") => {
+                    Self::NullDereference
+                }
+                "SEGV" => Self::Segv,
+                "ABRT" => Self::Abort,
+                "FPE" => Self::FloatingPointException,
+                "ILL" => Self::IllegalInstruction,
+                "BUS" => Self::BusError,
+                _ => Self::Unknown,
+            };
+            return Some((sanitizer.to_string(), class));
+        }
+        None
+    }
+
+    /// Classifies a crash from the signal it raised and the faulting address, if any.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn from_signal(signal: Signal, fault_address: Option<u64>) -> Self {
+        match signal {
+            Signal::SigSegmentationFault
+                if fault_address.is_some_and(|address| address < NULL_PAGE_SIZE) =>
+            {
+                Self::NullDereference
+            }
+            Signal::SigSegmentationFault => Self::Segv,
+            Signal::SigAbort => Self::Abort,
+            Signal::SigFloatingPointException => Self::FloatingPointException,
+            Signal::SigIllegalInstruction => Self::IllegalInstruction,
+            Signal::SigBus => Self::BusError,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for CrashClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Everything known about a single crash.
+///
+/// Build it with [`CrashReport::from_signal`] from a signal handler, or start from
+/// [`CrashReport::default`] and fill in what is known for crashes observed out of process.
+/// Call [`CrashReport::symbolize`] before exporting a report created in a signal handler.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// The name of the signal that killed the target, such as `SIGSEGV`
+    pub signal: Option<String>,
+    /// The faulting address, for memory access faults
+    pub fault_address: Option<u64>,
+    /// The registers at the time of the crash, as `(name, value)` pairs
+    pub registers: Vec<(String, u64)>,
+    /// The backtrace, innermost frame first
+    pub backtrace: Vec<StackFrame>,
+    /// The sanitizer that reported the crash, such as `AddressSanitizer`
+    pub sanitizer: Option<String>,
+    /// The full sanitizer report, if any
+    pub sanitizer_output: Option<String>,
+    /// The kind of bug
+    pub classification: CrashClass,
+    /// The path of the input reproducing this crash.
+    ///
+    /// For solutions of the in-process executors, this is the file name in the solutions directory.
+    pub reproducer: Option<PathBuf>,
+}
+
+// `impl_serdeany` expects its `register` function to be unused, which it is not for a public type in this crate
+#[allow(unfulfilled_lint_expectations)]
+mod serdeany_impl {
+    use super::CrashReport;
+
+    crate::impl_serdeany!(CrashReport);
+}
+
+impl CrashReport {
+    /// Creates a report from a signal handler, capturing the registers and the raw backtrace.
+    ///
+    /// The backtrace starts at the program counter of the `ucontext`, and is not symbolized,
+    /// see [`CrashReport::symbolize`].
+    #[cfg(unix)]
+    #[must_use]
+    pub fn from_signal(signal: Signal, siginfo: &siginfo_t, ucontext: Option<&ucontext_t>) -> Self {
+        let fault_address = match signal {
+            Signal::SigSegmentationFault | Signal::SigBus => Some(fault_address(siginfo)),
+            _ => None,
+        };
+        let mut report = Self {
+            signal: Some(signal.to_string()),
+            fault_address,
+            registers: ucontext.map(ucontext_registers).unwrap_or_default(),
+            classification: CrashClass::from_signal(signal, fault_address),
+            ..Self::default()
+        };
+        report.backtrace = capture_raw_backtrace(report.pc());
+        report
+    }
+
+    /// Symbolizes the frames of the backtrace that have no symbol yet.
+    ///
+    /// This uses the debug info of the modules loaded in the current process, so frames captured by an
+    /// earlier run of the same binary are rebased by their module offset first.
+    /// Frames of modules that aren't loaded stay unsymbolized.
+    /// Don't call this from a signal handler.
+    pub fn symbolize(&mut self) {
+        let backtrace = core::mem::take(&mut self.backtrace);
+        for frame in backtrace {
+            let Some(address) = frame.current_address().filter(|_| frame.symbol.is_none()) else {
+                self.backtrace.push(frame);
+                continue;
+            };
+            let symbolized = StackFrame::symbolize(address);
+            if symbolized.iter().all(|symbol| symbol.symbol.is_none()) {
+                self.backtrace.push(frame);
+                continue;
+            }
+            self.backtrace
+                .extend(symbolized.into_iter().map(|symbol| StackFrame {
+                    address: frame.address,
+                    module: frame.module.clone(),
+                    module_offset: frame.module_offset,
+                    ..symbol
+                }));
+        }
+    }
+
+    /// Sets the path of the input reproducing this crash
+    #[must_use]
+    pub fn with_reproducer<P: AsRef<Path>>(mut self, reproducer: P) -> Self {
+        self.reproducer = Some(reproducer.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the backtrace, symbolizing the given addresses of the current process
+    #[must_use]
+    pub fn with_backtrace_addresses<A: IntoIterator<Item = u64>>(mut self, addresses: A) -> Self {
+        self.backtrace = addresses
+            .into_iter()
+            .flat_map(StackFrame::symbolize)
+            .collect();
+        self
+    }
+
+    /// Attaches a sanitizer report.
+    ///
+    /// If the report can be classified, its classification takes precedence over the one derived
+    /// from the signal.
+    #[must_use]
+    pub fn with_sanitizer_output(mut self, output: &str) -> Self {
+        if let Some((sanitizer, class)) = CrashClass::from_sanitizer_output(output) {
+            self.sanitizer = Some(sanitizer);
+            self.classification = class;
+        }
+        self.sanitizer_output = Some(output.into());
+        self
+    }
+
+    /// The program counter at the time of the crash, if the registers were captured
+    #[must_use]
+    pub fn pc(&self) -> Option<u64> {
+        self.registers
+            .iter()
+            .find(|(name, _)| matches!(name.as_str(), "pc" | "rip" | "eip"))
+            .map(|(_, value)| *value)
+    }
+
+    /// A one-line summary of this crash
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut summary = self.classification.description().to_string();
+        if let Some(signal) = &self.signal {
+            let _ = write!(summary, " ({signal})");
+        }
+        if let Some(address) = self.fault_address {
+            let _ = write!(summary, " on address {address:#x}");
+        }
+        if let Some(frame) = self.backtrace.first() {
+            if let Some(symbol) = &frame.symbol {
+                let _ = write!(summary, " in {symbol}");
+            } else if let (Some(module), Some(offset)) = (&frame.module, frame.module_offset) {
+                let _ = write!(summary, " in {module}+{offset:#x}");
+            }
+        }
+        summary
+    }
+
+    /// Serializes this report to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| Error::serialize(format!("Failed to serialize crash report: {err}")))
+    }
+
+    /// Deserializes a report written by [`CrashReport::to_json`]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|err| Error::serialize(format!("Failed to parse crash report: {err}")))
+    }
+
+    /// Builds a SARIF 2.1.0 log containing this report as its single result
+    #[must_use]
+    pub fn to_sarif_value(&self) -> Value {
+        let frames: Vec<Value> = self
+            .backtrace
+            .iter()
+            .map(|frame| json!({ "location": sarif_location(frame) }))
+            .collect();
+        let mut result = json!({
+            "ruleId": self.classification.id(),
+            "level": "error",
+            "message": { "text": self.summary() },
+            "properties": {
+                "signal": self.signal,
+                "faultAddress": self.fault_address,
+                "registers": self
+                    .registers
+                    .iter()
+                    .map(|(name, value)| (name.clone(), json!(value)))
+                    .collect::<serde_json::Map<String, Value>>(),
+                "sanitizer": self.sanitizer,
+                "sanitizerOutput": self.sanitizer_output,
+            },
+        });
+        if let Some(frame) = self.backtrace.iter().find(|frame| frame.file.is_some()) {
+            result["locations"] = json!([sarif_location(frame)]);
+        }
+        if !frames.is_empty() {
+            result["stacks"] = json!([{ "frames": frames }]);
+        }
+        if let Some(reproducer) = &self.reproducer {
+            result["attachments"] = json!([{
+                "description": { "text": "Reproducer" },
+                "artifactLocation": { "uri": reproducer.display().to_string() },
+            }]);
+        }
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "LibAFL",
+                        "informationUri": "https://github.com/AFLplusplus/LibAFL",
+                        "rules": [{
+                            "id": self.classification.id(),
+                            "shortDescription": { "text": self.classification.description() },
+                        }],
+                    },
+                },
+                "results": [result],
+            }],
+        })
+    }
+
+    /// Serializes this report to a pretty-printed SARIF 2.1.0 log
+    pub fn to_sarif(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(&self.to_sarif_value())
+            .map_err(|err| Error::serialize(format!("Failed to serialize SARIF log: {err}")))
+    }
+}
+
+/// The SARIF `location` object of a stack frame
+fn sarif_location(frame: &StackFrame) -> Value {
+    let mut address = json!({ "absoluteAddress": frame.address });
+    if let (Some(module), Some(offset)) = (&frame.module, frame.module_offset) {
+        address["relativeAddress"] = json!(offset);
+        address["name"] = json!(module);
+    }
+    let mut physical = json!({ "address": address });
+    if let Some(file) = &frame.file {
+        physical["artifactLocation"] = json!({ "uri": file });
+        if let Some(line) = frame.line {
+            physical["region"] = json!({ "startLine": line });
+        }
+    }
+    let mut location = json!({ "physicalLocation": physical });
+    if let Some(symbol) = &frame.symbol {
+        location["logicalLocations"] =
+            json!([{ "fullyQualifiedName": symbol, "kind": "function" }]);
+    }
+    location
+}
+
+/// The faulting address of a memory access signal
+#[cfg(unix)]
+fn fault_address(siginfo: &siginfo_t) -> u64 {
+    #[cfg(target_os = "android")]
+    #[expect(clippy::cast_sign_loss, reason = "si_addr is split into two i32s")]
+    let address = u64::from(siginfo._pad[0] as u32) | (u64::from(siginfo._pad[1] as u32) << 32);
+    #[cfg(not(target_os = "android"))]
+    let address = unsafe { siginfo.si_addr() } as usize as u64;
+    address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrashClass, CrashReport, StackFrame};
+
+    const ASAN_OUTPUT: &str = "==4242==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000011 at pc 0x55d8 bp 0x7ffd sp 0x7ffd
+READ of size 1 at 0x602000000011 thread T0";
+
+    #[test]
+    fn test_sanitizer_classification() {
+        assert_eq!(
+            CrashClass::from_sanitizer_output(ASAN_OUTPUT),
+            Some(("AddressSanitizer".into(), CrashClass::HeapBufferOverflow))
+        );
+        assert_eq!(
+            CrashClass::from_sanitizer_output(
+                "==1==ERROR: AddressSanitizer: attempting double-free on 0x6020 in thread T0:"
+            )
+            .map(|(_, class)| class),
+            Some(CrashClass::DoubleFree)
+        );
+        assert_eq!(
+            CrashClass::from_sanitizer_output("==1==ERROR: LeakSanitizer: detected memory leaks"),
+            Some(("LeakSanitizer".into(), CrashClass::MemoryLeak))
+        );
+        assert_eq!(
+            CrashClass::from_sanitizer_output(
+                "main.c:3:5: runtime error: signed integer overflow: 2147483647 + 1"
+            )
+            .map(|(_, class)| class),
+            Some(CrashClass::UndefinedBehavior)
+        );
+        assert_eq!(CrashClass::from_sanitizer_output("all fine"), None);
+    }
+
+    #[test]
+    fn test_crash_report_export() {
+        let report = CrashReport {
+            signal: Some("SIGSEGV".into()),
+            fault_address: Some(0x6020_0000_0011),
+            backtrace: vec![StackFrame {
+                address: 0x55d8,
+                symbol: Some("parse_header".into()),
+                file: Some("src/parser.c".into()),
+                line: Some(42),
+                ..StackFrame::default()
+            }],
+            ..CrashReport::default()
+        }
+        .with_sanitizer_output(ASAN_OUTPUT)
+        .with_reproducer("crashes/id_0");
+
+        let json = report.to_json().unwrap();
+        assert_eq!(CrashReport::from_json(&json).unwrap(), report);
+
+        let sarif = report.to_sarif_value();
+        assert_eq!(sarif["version"], "2.1.0");
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "heap-buffer-overflow");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"]["startLine"],
+            42
+        );
+        assert_eq!(
+            result["stacks"][0]["frames"][0]["location"]["logicalLocations"][0]["fullyQualifiedName"],
+            "parse_header"
+        );
+        assert_eq!(
+            result["attachments"][0]["artifactLocation"]["uri"],
+            "crashes/id_0"
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_capture_backtrace() {
+        let backtrace = super::capture_backtrace();
+        let frame = backtrace
+            .iter()
+            .find(|frame| frame.symbol.is_some())
+            .unwrap();
+        assert_eq!(StackFrame::symbolize(frame.address)[0].symbol, frame.symbol);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_symbolize_raw_backtrace() {
+        let mut report = CrashReport {
+            backtrace: super::capture_raw_backtrace(None),
+            ..CrashReport::default()
+        };
+        assert!(report.backtrace.iter().all(|frame| frame.symbol.is_none()));
+        let pc = report.backtrace[0].address;
+        assert_eq!(super::capture_raw_backtrace(Some(pc))[0].address, pc);
+
+        report.symbolize();
+        assert!(report.backtrace.iter().any(|frame| {
+            frame
+                .symbol
+                .as_ref()
+                .is_some_and(|symbol| symbol.contains("test_symbolize_raw_backtrace"))
+        }));
+    }
+}
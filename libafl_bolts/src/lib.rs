@@ -91,6 +91,8 @@ pub mod compress;
 pub mod core_affinity;
 pub mod cpu;
 #[cfg(feature = "std")]
+pub mod crash_report;
+#[cfg(feature = "std")]
 pub mod fs;
 #[cfg(feature = "alloc")]
 pub mod llmp;
@@ -103,6 +105,8 @@ pub mod ownedref;
 pub mod rands;
 #[cfg(feature = "alloc")]
 pub mod serdeany;
+#[cfg(feature = "alloc")]
+pub mod serialization;
 pub mod shmem;
 #[cfg(feature = "std")]
 pub mod staterestore;
@@ -139,6 +143,8 @@ pub mod bolts_prelude {
     #[cfg(feature = "std")]
     pub use super::core_affinity::*;
     #[cfg(feature = "std")]
+    pub use super::crash_report::*;
+    #[cfg(feature = "std")]
     pub use super::fs::*;
     #[cfg(all(feature = "std", unix))]
     pub use super::minibsod::*;
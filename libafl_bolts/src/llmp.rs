@@ -61,30 +61,34 @@ Check out the `llmp_test` example in ./examples, or build it with `cargo run --e
 use alloc::boxed::Box;
 #[cfg(feature = "std")]
 use alloc::string::ToString;
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
 use alloc::{string::String, vec::Vec};
 #[cfg(feature = "std")]
-use core::net::SocketAddr;
-#[cfg(not(target_pointer_width = "64"))]
-use core::sync::atomic::AtomicU32;
+use core::net::{IpAddr, SocketAddr};
 #[cfg(target_pointer_width = "64")]
 use core::sync::atomic::AtomicU64;
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+use core::sync::atomic::{AtomicBool, AtomicUsize};
 use core::{
     cmp::max,
     fmt::Debug,
     hint,
-    mem::size_of,
+    mem::{self, size_of},
     num::NonZeroUsize,
     ops::{BitAnd, BitOr, Not},
     ptr, slice,
-    sync::atomic::{AtomicU16, Ordering, fence},
+    sync::atomic::{AtomicU16, AtomicU32, Ordering, fence},
     time::Duration,
 };
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+use std::sync::Mutex;
 #[cfg(feature = "std")]
 use std::{
     env,
     io::{ErrorKind, Read, Write},
     net::{TcpListener, TcpStream, ToSocketAddrs},
-    sync::mpsc::channel,
+    sync::{RwLock, mpsc::channel},
     thread,
 };
 
@@ -93,10 +97,16 @@ use backtrace::Backtrace;
 #[cfg(all(unix, feature = "std"))]
 #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
 use nix::sys::socket::{self, sockopt::ReusePort};
+#[cfg(feature = "std")]
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use tuple_list::tuple_list;
 
+#[cfg(feature = "llmp_compression")]
+use crate::compress::{CodecCompressor, CompressionCodec, Compressor};
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+use crate::os::notify::{EventFd, futex_wait, futex_wake};
 #[cfg(all(unix, not(miri)))]
 use crate::os::unix_signals::setup_signal_handler;
 #[cfg(unix)]
@@ -105,6 +115,7 @@ use crate::os::unix_signals::{Signal, SignalHandler, siginfo_t, ucontext_t};
 use crate::os::windows_exceptions::{CtrlHandler, setup_ctrl_handler};
 use crate::{
     ClientId, Error,
+    serialization::SerializationFormat,
     shmem::{ShMem, ShMemDescription, ShMemId, ShMemProvider},
 };
 #[cfg(feature = "std")]
@@ -148,11 +159,36 @@ pub const LLMP_FLAG_COMPRESSED: Flags = Flags(0x1);
 pub const LLMP_FLAG_FROM_B2B: Flags = Flags(0x2);
 /// From another machine (with the `multi_machine` mode)
 pub const LLMP_FLAG_FROM_MM: Flags = Flags(0x4);
+/// The bits holding the id of the compression codec, for [`LLMP_FLAG_COMPRESSED`] messages.
+/// Gzip has id `0`, so messages of older senders decode as gzip.
+pub const LLMP_FLAG_CODEC_MASK: Flags = Flags(0xF00);
+/// The payload was compressed using the installed compression dictionary.
+pub const LLMP_FLAG_DICTIONARY: Flags = Flags(0x1000);
+/// The shift of the codec id inside [`LLMP_FLAG_CODEC_MASK`]
+const LLMP_FLAG_CODEC_SHIFT: u32 = 8;
+/// The bits holding the id of the [`SerializationFormat`] of the payload.
+/// `postcard` has id `0`, so messages of older senders decode as `postcard`.
+pub const LLMP_FLAG_FORMAT_MASK: Flags = Flags(0xE000);
+/// The shift of the format id inside [`LLMP_FLAG_FORMAT_MASK`]
+const LLMP_FLAG_FORMAT_SHIFT: u32 = 13;
 
 /// Timt the broker 2 broker connection waits for incoming data,
 /// before checking for own data to forward again.
 const _LLMP_B2B_BLOCK_TIME: Duration = Duration::from_millis(3_000);
 
+/// Time a new tcp connection may take to say hello, including the Noise handshake of remote brokers.
+const _LLMP_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The Noise protocol pattern of broker 2 broker connections, authenticated by a pre-shared key
+#[cfg(feature = "llmp_noise")]
+const LLMP_NOISE_PATTERN: &str = "Noise_NNpsk0_25519_ChaChaPoly_BLAKE2s";
+/// The maximum length of a single Noise message
+#[cfg(feature = "llmp_noise")]
+const LLMP_NOISE_MAX_MSG_LEN: usize = 65535;
+/// The length of the authentication tag of each encrypted Noise message
+#[cfg(feature = "llmp_noise")]
+const LLMP_NOISE_TAG_LEN: usize = 16;
+
 /// If broker2broker is enabled, bind to public IP
 #[cfg(feature = "llmp_bind_public")]
 const _LLMP_BIND_ADDR: &str = "0.0.0.0";
@@ -164,8 +200,9 @@ const _LLMP_BIND_ADDR: &str = "127.0.0.1";
 /// An env var of this value indicates that the set value was a NULL PTR
 const _NULL_ENV_STR: &str = "_NULL";
 
-/// Magic indicating that a got initialized correctly
-const PAGE_INITIALIZED_MAGIC: u64 = 0x1A1A1A1A1A1A1AF1;
+/// Magic indicating that a got initialized correctly.
+/// Bumped whenever the layout of [`LlmpPage`] changes, so peers of different versions refuse each other's pages.
+const PAGE_INITIALIZED_MAGIC: u64 = 0x1A1A1A1A1A1A1AF2;
 
 /// Magic indicating that a got deinitialized correctly, after use
 const PAGE_DEINITIALIZED_MAGIC: u64 = 0xDEADC0FEAF1BEEF1;
@@ -210,14 +247,101 @@ impl Debug for Flags {
         // Initialized is the default value, no need to print.
         if *self & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
             f.write_str("COMPRESSED")?;
+            let codec_id = (self.0 & LLMP_FLAG_CODEC_MASK.0) >> LLMP_FLAG_CODEC_SHIFT;
+            if codec_id != 0 {
+                f.write_fmt(format_args!("({codec_id})"))?;
+            }
+        }
+        if *self & LLMP_FLAG_DICTIONARY == LLMP_FLAG_DICTIONARY {
+            f.write_str("DICTIONARY")?;
         }
         if *self & LLMP_FLAG_FROM_B2B == LLMP_FLAG_FROM_B2B {
             f.write_str("FROM_B2B")?;
         }
+        let format_id = (self.0 & LLMP_FLAG_FORMAT_MASK.0) >> LLMP_FLAG_FORMAT_SHIFT;
+        if format_id != 0 {
+            f.write_fmt(format_args!("FORMAT({format_id})"))?;
+        }
         f.write_str(" )")
     }
 }
 
+impl Flags {
+    /// The flags marking a payload as serialized with the given format.
+    #[must_use]
+    pub fn serialized_with(format: SerializationFormat) -> Self {
+        Flags(u32::from(format.id()) << LLMP_FLAG_FORMAT_SHIFT)
+    }
+
+    /// The format the payload was serialized with
+    pub fn format(self) -> Result<SerializationFormat, Error> {
+        let format_id = (self.0 & LLMP_FLAG_FORMAT_MASK.0) >> LLMP_FLAG_FORMAT_SHIFT;
+        u8::try_from(format_id)
+            .ok()
+            .and_then(SerializationFormat::from_id)
+            .ok_or_else(|| {
+                Error::illegal_argument(format!("Unknown serialization format id {format_id}"))
+            })
+    }
+}
+
+#[cfg(feature = "llmp_compression")]
+impl Flags {
+    /// The flags marking a payload as compressed by the given compressor.
+    /// Records the codec and whether a dictionary was used.
+    #[must_use]
+    pub fn compressed_by(compressor: &CodecCompressor) -> Self {
+        let mut flags = LLMP_FLAG_COMPRESSED
+            | Flags(u32::from(compressor.codec().id()) << LLMP_FLAG_CODEC_SHIFT);
+        if compressor.uses_dictionary() {
+            flags = flags | LLMP_FLAG_DICTIONARY;
+        }
+        flags
+    }
+
+    /// The codec a compressed payload was compressed with
+    pub fn codec(self) -> Result<CompressionCodec, Error> {
+        let codec_id = (self.0 & LLMP_FLAG_CODEC_MASK.0) >> LLMP_FLAG_CODEC_SHIFT;
+        u8::try_from(codec_id)
+            .ok()
+            .and_then(CompressionCodec::from_id)
+            .ok_or_else(|| {
+                Error::illegal_argument(format!("Unknown compression codec id {codec_id}"))
+            })
+    }
+
+    /// If a compressed payload was compressed with a dictionary
+    #[must_use]
+    pub fn uses_dictionary(self) -> bool {
+        self & LLMP_FLAG_DICTIONARY == LLMP_FLAG_DICTIONARY
+    }
+}
+
+/// The ids of the compression codecs this build can decompress, announced during the tcp handshake.
+#[cfg(feature = "std")]
+fn supported_codec_ids() -> Vec<u8> {
+    #[cfg(feature = "llmp_compression")]
+    {
+        CompressionCodec::available()
+            .into_iter()
+            .map(CompressionCodec::id)
+            .collect()
+    }
+    #[cfg(not(feature = "llmp_compression"))]
+    {
+        Vec::new()
+    }
+}
+
+/// The known codecs of a list of codec ids, received during the tcp handshake.
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+fn codecs_from_ids(codec_ids: &[u8]) -> Vec<CompressionCodec> {
+    codec_ids
+        .iter()
+        .filter_map(|id| CompressionCodec::from_id(*id))
+        .collect()
+}
+
 impl BitAnd for Flags {
     type Output = Self;
 
@@ -274,6 +398,10 @@ pub enum TcpRequest {
         /// Tell the broker that remove the client with this `client_id`. `client_id` is equal to the one of event restarter
         client_id: ClientId,
     },
+    /// We would like to establish an encrypted b2b connection, authenticated by the pre-shared key.
+    /// The Noise handshake follows, then the broker repeats its hello and we send our
+    /// [`TcpRequest::RemoteBrokerHello`], both on the encrypted channel.
+    NoiseHandshake,
 }
 
 impl TryFrom<&Vec<u8>> for TcpRequest {
@@ -365,6 +493,75 @@ impl TryFrom<Vec<u8>> for TcpResponse {
     }
 }
 
+/// The version of the tcp handshake, announced in the [`TcpHelloExtension`]
+pub const LLMP_TCP_HELLO_VERSION: u32 = 1;
+
+/// Capabilities appended to a [`TcpResponse::BrokerConnectHello`] or a [`TcpRequest::RemoteBrokerHello`].
+///
+/// postcard ignores trailing bytes, so peers from before this extension still understand the hello.
+/// A hello without an extension comes from such a peer, which only knows gzip.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TcpHelloExtension {
+    /// The handshake version of the peer
+    pub version: u32,
+    /// The ids of the compression codecs the peer can decompress
+    pub codecs: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl TcpHelloExtension {
+    /// The extension this build announces
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            version: LLMP_TCP_HELLO_VERSION,
+            codecs: supported_codec_ids(),
+        }
+    }
+
+    /// The capabilities of a peer from before the extension, which compressed with gzip only.
+    /// Gzip has id `0`, see [`LLMP_FLAG_CODEC_MASK`].
+    #[must_use]
+    pub fn legacy() -> Self {
+        Self {
+            version: 0,
+            codecs: vec![0],
+        }
+    }
+
+    /// Serialize a hello, followed by the extension of this build
+    pub fn encode_hello<T>(hello: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize,
+    {
+        let mut bytes = postcard::to_allocvec(hello)?;
+        bytes.extend(postcard::to_allocvec(&Self::new())?);
+        Ok(bytes)
+    }
+
+    /// Deserialize a hello, and the extension following it.
+    /// Falls back to [`TcpHelloExtension::legacy`] if the peer did not append one.
+    pub fn decode_hello<T>(bytes: &[u8]) -> Result<(T, Self), Error>
+    where
+        T: DeserializeOwned,
+    {
+        let (hello, rest) = postcard::take_from_bytes(bytes)?;
+        let extension = if rest.is_empty() {
+            Self::legacy()
+        } else {
+            postcard::from_bytes(rest)?
+        };
+        Ok((hello, extension))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for TcpHelloExtension {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Abstraction for listeners
 #[cfg(feature = "std")]
 #[derive(Debug)]
@@ -412,6 +609,17 @@ unsafe fn shmem2page<SHM: ShMem>(afl_shmem: &SHM) -> *const LlmpPage {
     afl_shmem.as_ptr() as *const LlmpPage
 }
 
+/// Get the [`LlmpDoorbell`] from its map
+#[inline]
+fn shmem2doorbell<SHM: ShMem>(doorbell_shmem: &SHM) -> Result<&LlmpDoorbell, Error> {
+    let doorbell = doorbell_shmem
+        .as_ptr_of::<LlmpDoorbell>()
+        .ok_or_else(|| Error::illegal_state("The doorbell map is too small"))?;
+    // # Safety
+    // The map is large enough, and the doorbell consists of atomics only.
+    Ok(unsafe { &*doorbell })
+}
+
 /// Return, if a msg is contained in the current page
 #[inline]
 unsafe fn llmp_msg_in_page(page: *const LlmpPage, msg: *const LlmpMsg) -> bool {
@@ -465,13 +673,103 @@ fn tcp_bind(port: u16) -> Result<TcpListener, Error> {
     Ok(listener)
 }
 
+/// If a connection from `addr` may be accepted, given the `allowlist` of remote addresses
+#[cfg(feature = "std")]
+fn is_allowed_tcp_peer(allowlist: &RwLock<Option<Vec<IpAddr>>>, addr: &SocketAddr) -> bool {
+    let ip = addr.ip().to_canonical();
+    ip.is_loopback()
+        || allowlist
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(|allowed| allowed.to_canonical() == ip))
+}
+
+/// Greets a new tcp connection, and receives its first request.
+/// Runs in its own thread, so that slow peers do not keep others from connecting.
+///
+/// If a pre-shared key is set, remote brokers have to authenticate with it,
+/// and only peers on the loopback interface may send plaintext requests.
+#[cfg(feature = "std")]
+fn accept_tcp_peer(
+    mut stream: TcpStream,
+    addr: &SocketAddr,
+    broker_hello: &[u8],
+    psk: Option<[u8; 32]>,
+) -> Result<(TcpChannel, TcpRequest, TcpHelloExtension), Error> {
+    stream.set_read_timeout(Some(_LLMP_HANDSHAKE_TIMEOUT))?;
+
+    // Send initial information, without anyone asking.
+    // This makes it a tiny bit easier to map the broker map for new Clients.
+    send_tcp_bytes(&mut stream, broker_hello)?;
+    let (request, extension) = TcpHelloExtension::decode_hello(&recv_tcp_msg(&mut stream)?)?;
+
+    let (stream, request, extension) = match request {
+        TcpRequest::NoiseHandshake => accept_noise_peer(stream, broker_hello, psk)?,
+        TcpRequest::RemoteBrokerHello { .. } if psk.is_some() => {
+            return Err(Error::illegal_state(format!(
+                "Refusing remote broker {addr}, it did not authenticate with the pre-shared key"
+            )));
+        }
+        _ if psk.is_some() && !addr.ip().to_canonical().is_loopback() => {
+            return Err(Error::illegal_state(format!(
+                "Refusing plaintext request from remote address {addr}"
+            )));
+        }
+        request => (TcpChannel::Plain(stream), request, extension),
+    };
+
+    stream.stream().set_read_timeout(None)?;
+    Ok((stream, request, extension))
+}
+
+/// Runs the Noise handshake with a remote broker, then repeats the `broker_hello`
+/// and receives the [`TcpRequest::RemoteBrokerHello`] on the encrypted channel.
+#[cfg(feature = "llmp_noise")]
+fn accept_noise_peer(
+    stream: TcpStream,
+    broker_hello: &[u8],
+    psk: Option<[u8; 32]>,
+) -> Result<(TcpChannel, TcpRequest, TcpHelloExtension), Error> {
+    let psk = psk.ok_or_else(|| {
+        Error::illegal_state("A remote broker wants to authenticate, but no pre-shared key is set")
+    })?;
+    let mut stream = TcpChannel::Noise(Box::new(NoiseTcpStream::handshake(stream, &psk, false)?));
+    stream.send_bytes(broker_hello)?;
+    match TcpHelloExtension::decode_hello(&stream.recv_msg()?)? {
+        (request @ TcpRequest::RemoteBrokerHello { .. }, extension) => {
+            Ok((stream, request, extension))
+        }
+        _ => Err(Error::illegal_state(
+            "Unexpected request received on an encrypted b2b connection",
+        )),
+    }
+}
+
+/// Without the `llmp_noise` feature, remote brokers can not authenticate.
+#[cfg(all(feature = "std", not(feature = "llmp_noise")))]
+fn accept_noise_peer(
+    _stream: TcpStream,
+    _broker_hello: &[u8],
+    _psk: Option<[u8; 32]>,
+) -> Result<(TcpChannel, TcpRequest, TcpHelloExtension), Error> {
+    Err(Error::illegal_state(
+        "A remote broker wants to authenticate, but this build lacks the `llmp_noise` feature",
+    ))
+}
+
 /// Send one message as `u32` len and `[u8;len]` bytes
 #[cfg(feature = "std")]
 pub fn send_tcp_msg<T>(stream: &mut TcpStream, msg: &T) -> Result<(), Error>
 where
     T: Serialize,
 {
-    let msg = postcard::to_allocvec(msg)?;
+    send_tcp_bytes(stream, &postcard::to_allocvec(msg)?)
+}
+
+/// Send already serialized bytes as one message, see [`send_tcp_msg`]
+#[cfg(feature = "std")]
+fn send_tcp_bytes(stream: &mut TcpStream, msg: &[u8]) -> Result<(), Error> {
     if msg.len() > u32::MAX as usize {
         return Err(Error::illegal_state(format!(
             "Trying to send message a tcp message > u32! (size: {})",
@@ -484,7 +782,7 @@ where
 
     let size_bytes = (msg.len() as u32).to_be_bytes();
     stream.write_all(&size_bytes)?;
-    stream.write_all(&msg)?;
+    stream.write_all(msg)?;
 
     #[cfg(feature = "llmp_debug")]
     log::trace!("LLMP TCP: Sending {} bytes finished.", msg.len());
@@ -517,6 +815,222 @@ pub fn recv_tcp_msg(stream: &mut TcpStream) -> Result<Vec<u8>, Error> {
     Ok(bytes)
 }
 
+/// A tcp connection of the broker to a client or a remote broker
+#[cfg(feature = "std")]
+#[derive(Debug)]
+enum TcpChannel {
+    /// A plaintext connection
+    Plain(TcpStream),
+    /// A connection encrypted with the Noise protocol
+    #[cfg(feature = "llmp_noise")]
+    Noise(Box<NoiseTcpStream>),
+}
+
+#[cfg(feature = "std")]
+impl TcpChannel {
+    /// The underlying tcp stream
+    fn stream(&self) -> &TcpStream {
+        match self {
+            Self::Plain(stream) => stream,
+            #[cfg(feature = "llmp_noise")]
+            Self::Noise(noise) => &noise.stream,
+        }
+    }
+
+    /// Send one message, see [`send_tcp_msg`]
+    fn send_msg<T>(&mut self, msg: &T) -> Result<(), Error>
+    where
+        T: Serialize,
+    {
+        self.send_bytes(&postcard::to_allocvec(msg)?)
+    }
+
+    /// Send already serialized bytes as one message
+    fn send_bytes(&mut self, msg: &[u8]) -> Result<(), Error> {
+        match self {
+            Self::Plain(stream) => send_tcp_bytes(stream, msg),
+            #[cfg(feature = "llmp_noise")]
+            Self::Noise(noise) => noise.send(msg),
+        }
+    }
+
+    /// Receive one message, see [`recv_tcp_msg`]
+    fn recv_msg(&mut self) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Plain(stream) => recv_tcp_msg(stream),
+            #[cfg(feature = "llmp_noise")]
+            Self::Noise(noise) => noise.recv(),
+        }
+    }
+}
+
+/// A tcp connection encrypted with the Noise protocol, authenticated by a pre-shared key.
+///
+/// Each message is sent as its `u32` length, followed by the message, split into encrypted Noise messages.
+/// Each Noise message is prefixed by its `u16` length.
+#[cfg(feature = "llmp_noise")]
+struct NoiseTcpStream {
+    stream: TcpStream,
+    transport: snow::TransportState,
+    /// Received bytes that do not make up a full Noise message yet
+    encrypted: Vec<u8>,
+    /// Decrypted bytes that do not make up a full message yet
+    decrypted: Vec<u8>,
+}
+
+#[cfg(feature = "llmp_noise")]
+impl Debug for NoiseTcpStream {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NoiseTcpStream")
+            .field("stream", &self.stream)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "llmp_noise")]
+#[expect(clippy::needless_pass_by_value)] // for `map_err`
+fn noise_error(e: snow::Error) -> Error {
+    Error::illegal_state(format!("Noise protocol error: {e:?}"))
+}
+
+#[cfg(feature = "llmp_noise")]
+impl NoiseTcpStream {
+    /// Runs the Noise `NNpsk0` handshake. It fails, unless both sides know the same `psk`.
+    fn handshake(mut stream: TcpStream, psk: &[u8; 32], initiator: bool) -> Result<Self, Error> {
+        let builder =
+            snow::Builder::new(LLMP_NOISE_PATTERN.parse().map_err(noise_error)?).psk(0, psk);
+        let mut handshake = if initiator {
+            builder.build_initiator()
+        } else {
+            builder.build_responder()
+        }
+        .map_err(noise_error)?;
+        let mut buf = vec![0; LLMP_NOISE_MAX_MSG_LEN];
+
+        while !handshake.is_handshake_finished() {
+            if handshake.is_my_turn() {
+                let len = handshake
+                    .write_message(&[], &mut buf)
+                    .map_err(noise_error)?;
+                Self::send_frame(&mut stream, &buf[..len])?;
+            } else {
+                let frame = Self::recv_frame(&mut stream)?;
+                handshake
+                    .read_message(&frame, &mut buf)
+                    .map_err(noise_error)?;
+            }
+        }
+
+        Ok(Self {
+            stream,
+            transport: handshake.into_transport_mode().map_err(noise_error)?,
+            encrypted: Vec::new(),
+            decrypted: Vec::new(),
+        })
+    }
+
+    fn send_frame(stream: &mut TcpStream, frame: &[u8]) -> Result<(), Error> {
+        let len = u16::try_from(frame.len())
+            .map_err(|_| Error::illegal_argument("Noise message too long"))?;
+        stream.write_all(&len.to_be_bytes())?;
+        stream.write_all(frame)?;
+        Ok(())
+    }
+
+    fn recv_frame(stream: &mut TcpStream) -> Result<Vec<u8>, Error> {
+        let mut len = [0; 2];
+        stream.read_exact(&mut len)?;
+        let mut frame = vec![0; u16::from_be_bytes(len).into()];
+        stream.read_exact(&mut frame)?;
+        Ok(frame)
+    }
+
+    fn send(&mut self, msg: &[u8]) -> Result<(), Error> {
+        let msg_len = u32::try_from(msg.len())
+            .map_err(|_| Error::illegal_argument("Trying to send a tcp message > u32!"))?
+            .to_le_bytes();
+        let mut buf = vec![0; LLMP_NOISE_MAX_MSG_LEN];
+        let plaintext = msg_len.iter().chain(msg).copied().collect::<Vec<_>>();
+        for chunk in plaintext.chunks(LLMP_NOISE_MAX_MSG_LEN - LLMP_NOISE_TAG_LEN) {
+            let len = self
+                .transport
+                .write_message(chunk, &mut buf)
+                .map_err(noise_error)?;
+            Self::send_frame(&mut self.stream, &buf[..len])?;
+        }
+        Ok(())
+    }
+
+    /// Receives the next message.
+    ///
+    /// Partially received messages are kept if the read times out, so the next call continues where this one stopped.
+    /// Failing to decrypt is returned as [`ErrorKind::InvalidData`], the connection can not be used anymore.
+    fn recv(&mut self) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![0; LLMP_NOISE_MAX_MSG_LEN];
+        loop {
+            if let Some(len) = self.decrypted.first_chunk::<4>() {
+                let len = u32::from_le_bytes(*len) as usize;
+                if self.decrypted.len() >= 4 + len {
+                    let msg = self.decrypted[4..4 + len].to_vec();
+                    self.decrypted.drain(..4 + len);
+                    return Ok(msg);
+                }
+            }
+
+            if let Some(len) = self.encrypted.first_chunk::<2>() {
+                let len = usize::from(u16::from_be_bytes(*len));
+                if self.encrypted.len() >= 2 + len {
+                    let decrypted_len = self
+                        .transport
+                        .read_message(&self.encrypted[2..2 + len], &mut buf)
+                        .map_err(|e| {
+                            Error::os_error(
+                                std::io::Error::new(ErrorKind::InvalidData, format!("{e:?}")),
+                                "Failed to decrypt message from remote broker",
+                            )
+                        })?;
+                    self.decrypted.extend_from_slice(&buf[..decrypted_len]);
+                    self.encrypted.drain(..2 + len);
+                    continue;
+                }
+            }
+
+            match self.stream.read(&mut buf)? {
+                0 => {
+                    return Err(Error::os_error(
+                        ErrorKind::UnexpectedEof.into(),
+                        "Remote broker closed the connection",
+                    ));
+                }
+                n => self.encrypted.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+}
+
+/// Recompresses a compressed payload with the `compressor`, if the peer cannot decompress its codec.
+/// Returns `None` if the payload can be forwarded as-is.
+#[cfg(feature = "llmp_compression")]
+pub fn transcode_for_peer(
+    compressor: &CodecCompressor,
+    peer_codecs: &[CompressionCodec],
+    flags: Flags,
+    payload: &[u8],
+) -> Result<Option<(Flags, Vec<u8>)>, Error> {
+    if flags & LLMP_FLAG_COMPRESSED != LLMP_FLAG_COMPRESSED {
+        return Ok(None);
+    }
+    let codec = flags.codec()?;
+    // A peer without any codecs was built without compression, transcoding will not help.
+    if peer_codecs.is_empty() || peer_codecs.contains(&codec) {
+        return Ok(None);
+    }
+    let decompressed = compressor.decompress_with(codec, flags.uses_dictionary(), payload)?;
+    let flags =
+        (flags & !(LLMP_FLAG_CODEC_MASK | LLMP_FLAG_DICTIONARY)) | Flags::compressed_by(compressor);
+    Ok(Some((flags, compressor.compress(&decompressed)?)))
+}
+
 /// In case we don't have enough space, make sure the next page will be large
 /// enough. For now, we want to have at least enough space to store 2 of the
 /// largest messages we encountered (plus message one `new_page` message).
@@ -554,6 +1068,8 @@ unsafe fn llmp_page_init<SHM: ShMem>(shmem: &mut SHM, sender_id: ClientId, allow
         (*page).sender_id = sender_id;
         (*page).current_msg_id.store(0, Ordering::Relaxed);
         (*page).max_alloc_size = 0;
+        (*page).notify_sleepers.store(0, Ordering::Relaxed);
+        (*page).doorbell_shmem = None;
         // Don't forget to subtract our own header size
         (*page).size_total = map_size - LLMP_PAGE_HEADER_LEN;
         (*page).size_used = 0;
@@ -861,6 +1377,13 @@ pub struct LlmpPage {
     /// The maximum amount of bytes that ever got allocated on this page in one go.
     /// An inidactor of what to use as size for future pages
     pub max_alloc_size: usize,
+    /// Bumped by the sender after each message, receivers can sleep on this futex word until it changes.
+    pub notify_seq: AtomicU32,
+    /// The amount of receivers sleeping on `notify_seq`. The sender only wakes them up if this is not `0`.
+    pub notify_sleepers: AtomicU32,
+    /// The map holding the [`LlmpDoorbell`] of the broker owning this (broadcast) page, `None` for client pages.
+    /// It's the same map for all pages of a broker, so it doesn't move when the clients switch pages.
+    pub doorbell_shmem: Option<ShMemDescription>,
     /// Pointer to the messages, from here on.
     pub messages: [LlmpMsg; 0],
 }
@@ -879,6 +1402,57 @@ impl LlmpPage {
         //receivers_joined_count.fetch_add(1, Ordering::Relaxed);
         receivers_left_count.store(1, Ordering::Relaxed);
     }
+
+    /// Wake up all receivers sleeping until a new message arrives on this page.
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    #[inline]
+    fn notify_receivers(&self) {
+        self.notify_seq.fetch_add(1, Ordering::SeqCst);
+        if self.notify_sleepers.load(Ordering::SeqCst) > 0 {
+            futex_wake(&self.notify_seq, u32::MAX);
+        }
+    }
+}
+
+/// The doorbell of a broker, in a map of its own.
+/// Clients ring it after they sent a message to the broker, the broker sleeps on it while idle.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub struct LlmpDoorbell {
+    /// Bumped each time a client rings, the broker sleeps on this futex word.
+    pub rings: AtomicU32,
+    /// The amount of brokers sleeping on `rings`.
+    pub sleepers: AtomicU32,
+}
+
+impl LlmpDoorbell {
+    /// Wake up the broker, if it sleeps until a client sends a new message.
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    #[inline]
+    fn ring(&self) {
+        self.rings.fetch_add(1, Ordering::SeqCst);
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            futex_wake(&self.rings, u32::MAX);
+        }
+    }
+}
+
+/// Sleep on the futex `word` of a page, as long as it still holds `expected`, for at most `timeout`.
+/// While asleep, we are counted in `sleepers`, so that the other side knows to wake us up.
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+fn llmp_futex_sleep(
+    word: &AtomicU32,
+    sleepers: &AtomicU32,
+    expected: u32,
+    timeout: Option<Duration>,
+) -> Result<bool, Error> {
+    sleepers.fetch_add(1, Ordering::SeqCst);
+    let ret = futex_wait(word, expected, timeout);
+    // The sender may have reinitialized the page in the meantime, never underflow.
+    let _ = sleepers.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+        count.checked_sub(1)
+    });
+    ret
 }
 
 /// Message payload when a client got added */
@@ -1173,6 +1747,8 @@ where
             // Allocations may never shrink:
             // keep track of the max message size we allocated across maps.
             (*new_map).max_alloc_size = (*old_map).max_alloc_size;
+            // The doorbell stays the same for all pages.
+            (*new_map).doorbell_shmem = (*old_map).doorbell_shmem;
 
             /* On the old map, place a last message linking to the new map for the clients
              * to consume */
@@ -1615,6 +2191,9 @@ where
                 .current_msg_id
                 .store((*msg).message_id.0, Ordering::Release);
 
+            #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+            (*page).notify_receivers();
+
             self.last_msg_sent = msg;
             self.has_unsent_message = false;
 
@@ -1656,12 +2235,161 @@ pub struct LlmpReceiver<SHM, SP> {
     last_msg_time: Duration,
     /// The shmem provider
     shmem_provider: SP,
+    /// The doorbell of the broker we receive from, mapped the first time we ring it
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    doorbell_shmem: Option<SHM>,
+    /// Signals an [`EventFd`] on new messages, started by [`LlmpReceiver::notification_fd`].
+    /// Declared before `current_recv_shmem`, so it's dropped while the page it watches is still mapped.
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    notifier: Option<LlmpNotifier<SHM>>,
     /// current page. After EOP, this gets replaced with the new one
     current_recv_shmem: LlmpSharedMap<SHM>,
     /// Caches the highest msg id we've seen so far
     highest_msg_id: MessageId,
 }
 
+/// The default time the broker loops sleep between iterations, if no client wakes them up.
+/// On Linux and Android, clients wake up the broker, so it can sleep a lot longer without slowing things down.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub const LLMP_DEFAULT_BROKER_SLEEP_TIME: Duration = Duration::from_millis(100);
+/// The default time the broker loops sleep between iterations, if no client wakes them up.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub const LLMP_DEFAULT_BROKER_SLEEP_TIME: Duration = Duration::from_millis(5);
+
+/// How long the [`Brokers`] loop sleeps at most, if it runs more than one broker.
+/// It can only wait for the doorbell of a single broker, so it polls all of them at this interval instead.
+#[cfg(feature = "std")]
+const LLMP_MULTI_BROKER_SLEEP_TIME: Duration = Duration::from_millis(5);
+
+/// How long a sleeping receiver waits at most, before it checks for new messages again.
+/// Senders of other builds (for example `no_std`) don't wake sleeping receivers up.
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+const LLMP_NOTIFY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often [`LlmpReceiver::recv_blocking`] spins before it goes to sleep.
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+const LLMP_BLOCKING_SPIN_COUNT: usize = 1 << 12;
+
+/// A background thread, signalling an [`EventFd`] whenever a new message arrives on a receiver's page.
+///
+/// The thread watches the receiver's own mapping of the page, so no fd-backed mapping is ever opened twice.
+/// Pages the receiver moved on from stay mapped until the thread moved on, too.
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+#[derive(Debug)]
+struct LlmpNotifier<SHM> {
+    /// The fd we signal
+    eventfd: Arc<EventFd>,
+    /// The generation and the address of the page the thread watches, bumped each time the receiver moves on
+    page: Arc<Mutex<(usize, usize)>>,
+    /// The latest generation the thread picked up, it no longer touches pages of older generations
+    acked: Arc<AtomicUsize>,
+    /// Pages the receiver moved on from, with their generation, until the thread acked a newer one
+    retired: Vec<(usize, LlmpSharedMap<SHM>)>,
+    /// Tells the thread to quit
+    stop: Arc<AtomicBool>,
+    /// The thread, joined on drop, before the receiver unmaps its page
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+impl<SHM> LlmpNotifier<SHM>
+where
+    SHM: ShMem,
+{
+    /// Spawn the notifier thread, watching the receiver's current `page`
+    fn spawn(page: *const LlmpPage) -> Result<Self, Error> {
+        let mut notifier = Self {
+            eventfd: Arc::new(EventFd::new()?),
+            page: Arc::new(Mutex::new((0, page as usize))),
+            acked: Arc::new(AtomicUsize::new(0)),
+            retired: vec![],
+            stop: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        };
+        let eventfd = notifier.eventfd.clone();
+        let page = notifier.page.clone();
+        let acked = notifier.acked.clone();
+        let stop = notifier.stop.clone();
+        notifier.thread = Some(thread::spawn(move || {
+            if let Err(e) = Self::watch(&eventfd, &page, &acked, &stop) {
+                log::error!("LLMP notifier thread failed: {e}");
+            }
+        }));
+        Ok(notifier)
+    }
+
+    /// The loop of the notifier thread.
+    /// Signals the fd each time the sender bumped the current page's `notify_seq`.
+    fn watch(
+        eventfd: &EventFd,
+        page: &Mutex<(usize, usize)>,
+        acked: &AtomicUsize,
+        stop: &AtomicBool,
+    ) -> Result<(), Error> {
+        let mut current_generation = None;
+        // Signal once for each new page, in case messages were already waiting.
+        let mut last_seq = None;
+
+        while !stop.load(Ordering::SeqCst) {
+            let (generation, addr) = *page.lock().unwrap();
+            if current_generation != Some(generation) {
+                current_generation = Some(generation);
+                last_seq = None;
+                acked.store(generation, Ordering::SeqCst);
+            }
+
+            // # Safety
+            // The receiver keeps the page of the generation we acked mapped, and joins us before it unmaps its page.
+            let page = unsafe { &*(addr as *const LlmpPage) };
+            let seq = page.notify_seq.load(Ordering::SeqCst);
+            if last_seq == Some(seq) {
+                llmp_futex_sleep(
+                    &page.notify_seq,
+                    &page.notify_sleepers,
+                    seq,
+                    Some(LLMP_NOTIFY_POLL_INTERVAL),
+                )?;
+            } else {
+                last_seq = Some(seq);
+                eventfd.notify()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The receiver moved on from `old_shmem` to `next_page`, make the thread follow.
+    /// `old_shmem` stays mapped until the thread no longer watches it.
+    fn follow(&mut self, old_shmem: LlmpSharedMap<SHM>, next_page: *const LlmpPage) {
+        let generation = {
+            let mut page = self.page.lock().unwrap();
+            let generation = page.0;
+            *page = (generation.wrapping_add(1), next_page as usize);
+            generation
+        };
+        // # Safety
+        // We still hold the old page, wake up the thread in case it sleeps on it.
+        unsafe { (*old_shmem.page()).notify_receivers() };
+        self.retired.push((generation, old_shmem));
+
+        let acked = self.acked.load(Ordering::SeqCst);
+        self.retired.retain(|(generation, _)| *generation >= acked);
+    }
+}
+
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+impl<SHM> Drop for LlmpNotifier<SHM> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let addr = self.page.lock().unwrap().1;
+        // # Safety
+        // The receiver drops us before its current page, so it's still mapped.
+        unsafe { (*(addr as *const LlmpPage)).notify_receivers() };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
 /// Receiving end of an llmp channel
 impl<SHM, SP> LlmpReceiver<SHM, SP>
 where
@@ -1701,6 +2429,10 @@ where
             // We don't know the last received time, just assume the current time.
             #[cfg(feature = "std")]
             last_msg_time: current_time(),
+            #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+            doorbell_shmem: None,
+            #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+            notifier: None,
         })
     }
 
@@ -1804,11 +2536,26 @@ where
                         (*page).receiver_left();
 
                         // Map the new page. The old one should be unmapped by Drop
-                        self.current_recv_shmem =
-                            LlmpSharedMap::existing(self.shmem_provider.shmem_from_id_and_size(
-                                ShMemId::from_array(&pageinfo_cpy.shm_str),
-                                pageinfo_cpy.map_size,
-                            )?);
+                        let new_shmem = self.shmem_provider.shmem_from_id_and_size(
+                            ShMemId::from_array(&pageinfo_cpy.shm_str),
+                            pageinfo_cpy.map_size,
+                        )?;
+                        let old_shmem = mem::replace(
+                            &mut self.current_recv_shmem,
+                            LlmpSharedMap::existing(new_shmem),
+                        );
+                        #[cfg(all(
+                            feature = "std",
+                            any(target_os = "linux", target_os = "android")
+                        ))]
+                        if let Some(notifier) = &mut self.notifier {
+                            notifier.follow(old_shmem, self.current_recv_shmem.page());
+                        }
+                        #[cfg(not(all(
+                            feature = "std",
+                            any(target_os = "linux", target_os = "android")
+                        )))]
+                        drop(old_shmem);
                         let new_page = self.current_recv_shmem.page_mut();
 
                         // Mark the old page as save to remap (it's mapped by us, the receiver, now)
@@ -1850,6 +2597,8 @@ where
 
                 current_msg_id = (*last_msg).message_id;
             }
+            #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+            let mut spins = 0;
             loop {
                 if (*page).current_msg_id.load(Ordering::Relaxed) != current_msg_id.0 {
                     return match self.recv()? {
@@ -1857,11 +2606,106 @@ where
                         None => panic!("BUG: blocking llmp message should never be NULL"),
                     };
                 }
+                // Spin for a bit, then go to sleep until the sender notifies us.
+                #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+                if spins < LLMP_BLOCKING_SPIN_COUNT {
+                    spins += 1;
+                } else {
+                    self.wait_for_message(Some(LLMP_NOTIFY_POLL_INTERVAL))?;
+                    continue;
+                }
                 hint::spin_loop();
             }
         }
     }
 
+    /// Returns `true` if the sender posted a message (or an end of page) we did not receive yet.
+    #[cfg(feature = "std")]
+    fn has_pending_message(&self) -> bool {
+        // # Safety
+        // The current page and our last message stay mapped for as long as we hold them.
+        unsafe {
+            let current_msg_id = (*self.current_recv_shmem.page())
+                .current_msg_id
+                .load(Ordering::Relaxed);
+            let last_msg_id = if self.last_msg_recvd.is_null() {
+                0
+            } else {
+                (*self.last_msg_recvd).message_id.0
+            };
+            current_msg_id != last_msg_id
+        }
+    }
+
+    /// Sleeps until the sender posts a new message, or until `timeout` passed.
+    /// Returns `false` on timeout, and `true` if a new message may be available.
+    ///
+    /// Wakeups can be spurious, so [`Self::recv_buf`] may still return `None` afterwards.
+    /// On Linux and Android, this uses a futex on the shared page and does not burn any cpu while waiting,
+    /// on other platforms, it polls.
+    #[cfg(feature = "std")]
+    pub fn wait_for_message(&mut self, timeout: Option<Duration>) -> Result<bool, Error> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            // # Safety
+            // The current page stays mapped for as long as we hold it.
+            let page = unsafe { &*self.current_recv_shmem.page() };
+            // Read the sequence before we check for messages, so we can't miss a wakeup in between.
+            let seq = page.notify_seq.load(Ordering::SeqCst);
+            if self.has_pending_message() {
+                return Ok(true);
+            }
+            llmp_futex_sleep(&page.notify_seq, &page.notify_sleepers, seq, timeout)
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        {
+            let start = current_time();
+            loop {
+                if self.has_pending_message() {
+                    return Ok(true);
+                }
+                if timeout.is_some_and(|timeout| current_time() - start >= timeout) {
+                    return Ok(false);
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    /// Returns an [`EventFd`] that becomes readable whenever new messages arrive on this receiver.
+    /// The fd can be registered with `epoll`, `poll`, or `select`, to integrate `LLMP` with other event loops.
+    ///
+    /// Once readable, [`EventFd::clear`] it, then receive until [`Self::recv_buf`] returns `None`.
+    /// The first call spawns a small background thread waiting for new messages.
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    pub fn notification_fd(&mut self) -> Result<&EventFd, Error> {
+        if self.notifier.is_none() {
+            // # Safety
+            // The notifier only watches the page while we keep it mapped.
+            let page = unsafe { self.current_recv_shmem.page() };
+            self.notifier = Some(LlmpNotifier::spawn(page)?);
+        }
+        Ok(&self.notifier.as_ref().unwrap().eventfd)
+    }
+
+    /// Wake up the broker owning the page we receive from, if it waits for new messages.
+    /// Clients call this after they sent a message to the broker.
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    fn ring_doorbell(&mut self) -> Result<(), Error> {
+        if self.doorbell_shmem.is_none() {
+            // # Safety
+            // The current page stays mapped for as long as we hold it.
+            let Some(description) = (unsafe { (*self.current_recv_shmem.page()).doorbell_shmem })
+            else {
+                // We don't receive from a broker, nobody to wake up.
+                return Ok(());
+            };
+            self.doorbell_shmem = Some(self.shmem_provider.shmem_from_description(description)?);
+        }
+        shmem2doorbell(self.doorbell_shmem.as_ref().unwrap())?.ring();
+        Ok(())
+    }
+
     /// Returns the next message, tag, buf, if available, else None
     #[expect(clippy::type_complexity)]
     #[inline]
@@ -2149,6 +2993,14 @@ pub struct LlmpBrokerInner<SHM, SP> {
     clients_to_remove: Vec<ClientId>,
     /// The `ShMemProvider` to use
     shmem_provider: SP,
+    /// The map of our [`LlmpDoorbell`], clients find its description on each of our out pages
+    doorbell_shmem: SHM,
+    /// If set, the listeners refuse remote connections from addresses not on this list
+    #[cfg(feature = "std")]
+    tcp_allowlist: Arc<RwLock<Option<Vec<IpAddr>>>>,
+    /// If set, remote brokers have to authenticate with this pre-shared key, and the b2b traffic gets encrypted
+    #[cfg(feature = "std")]
+    b2b_psk: Arc<RwLock<Option<[u8; 32]>>>,
 }
 
 /// The broker (node 0)
@@ -2188,6 +3040,19 @@ pub trait Broker {
 
     /// Getter to `nb_listeners`
     fn nb_listeners(&self) -> usize;
+
+    /// The current value of the doorbell, see [`LlmpBrokerInner::doorbell`]
+    fn doorbell(&self) -> u32 {
+        0
+    }
+
+    /// Sleeps until a client rang the doorbell, or `timeout` passed, see [`LlmpBrokerInner::wait_for_doorbell`]
+    #[cfg(feature = "std")]
+    fn wait_for_doorbell(&self, doorbell: u32, timeout: Duration) -> Result<(), Error> {
+        let _ = doorbell;
+        thread::sleep(timeout);
+        Ok(())
+    }
 }
 
 impl<HT, SHM, SP> Broker for LlmpBroker<HT, SHM, SP>
@@ -2230,6 +3095,15 @@ where
     fn nb_listeners(&self) -> usize {
         self.inner.listeners.len()
     }
+
+    fn doorbell(&self) -> u32 {
+        self.inner.doorbell()
+    }
+
+    #[cfg(feature = "std")]
+    fn wait_for_doorbell(&self, doorbell: u32, timeout: Duration) -> Result<(), Error> {
+        self.inner.wait_for_doorbell(doorbell, timeout)
+    }
 }
 
 /// A set of brokers.
@@ -2305,6 +3179,16 @@ pub trait LlmpHook<SHM, SP> {
     fn on_timeout(&mut self) -> Result<(), Error> {
         Ok(())
     }
+
+    /// Hook called once per [`LlmpBroker::broker_once`], whether messages arrived or not.
+    /// Messages pushed to `new_msgs` get broadcast, e.g., messages held back by [`Self::on_new_message`] earlier.
+    fn on_tick(
+        &mut self,
+        _broker_inner: &mut LlmpBrokerInner<SHM, SP>,
+        _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// A tuple of Llmp hooks. They are evaluated sequentially, and returns if one decides to filter out the evaluated message.
@@ -2322,6 +3206,13 @@ pub trait LlmpHookTuple<SHM, SP> {
 
     /// Call all hook callbacks on timeout.
     fn on_timeout_all(&mut self) -> Result<(), Error>;
+
+    /// Call all hook callbacks once per broker iteration.
+    fn on_tick_all(
+        &mut self,
+        inner: &mut LlmpBrokerInner<SHM, SP>,
+        new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<(), Error>;
 }
 
 impl<SHM, SP> LlmpHookTuple<SHM, SP> for () {
@@ -2340,6 +3231,14 @@ impl<SHM, SP> LlmpHookTuple<SHM, SP> for () {
     fn on_timeout_all(&mut self) -> Result<(), Error> {
         Ok(())
     }
+
+    fn on_tick_all(
+        &mut self,
+        _inner: &mut LlmpBrokerInner<SHM, SP>,
+        _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 impl<Head, Tail, SHM, SP> LlmpHookTuple<SHM, SP> for (Head, Tail)
@@ -2376,6 +3275,15 @@ where
         self.0.on_timeout()?;
         self.1.on_timeout_all()
     }
+
+    fn on_tick_all(
+        &mut self,
+        inner: &mut LlmpBrokerInner<SHM, SP>,
+        new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        self.0.on_tick(inner, new_msgs)?;
+        self.1.on_tick_all(inner, new_msgs)
+    }
 }
 
 impl<SHM, SP> LlmpBroker<(), SHM, SP> {
@@ -2433,7 +3341,8 @@ impl Brokers {
     /// forwarding and handling all incoming messages from clients for each broker.
     /// Will call `on_timeout` roughly after `timeout`
     /// Panics on error.
-    /// 5 millis of sleep can't hurt to keep busywait not at 100%
+    /// Between iterations, sleeps for up to `sleep_time`, or until a client sends a new message.
+    /// See [`LLMP_DEFAULT_BROKER_SLEEP_TIME`] for a sane default.
     #[cfg(feature = "std")]
     pub fn loop_with_timeouts(&mut self, timeout: Duration, sleep_time: Option<Duration>) {
         use super::current_milliseconds;
@@ -2445,6 +3354,12 @@ impl Brokers {
         let mut end_time = current_milliseconds() + timeout;
 
         loop {
+            // Read the doorbell before brokering, so we don't sleep through messages arriving in between.
+            // We can only wait for one doorbell, so only do so if there's a single broker.
+            let doorbell = match self.llmp_brokers.as_slice() {
+                [broker] => Some(broker.doorbell()),
+                _ => None,
+            };
             self.llmp_brokers.retain_mut(|broker| {
                 if broker.is_shutting_down() {
                     broker.send_buf(LLMP_TAG_EXITING, &[]).expect(
@@ -2493,9 +3408,17 @@ impl Brokers {
                 break;
             }
 
+            // Sleep until a client sends a message.
+            // With several brokers, poll them all at a short interval instead.
             #[cfg(feature = "std")]
             if let Some(time) = sleep_time {
-                thread::sleep(time);
+                if let Some(doorbell) = doorbell {
+                    self.llmp_brokers[0]
+                        .wait_for_doorbell(doorbell, time)
+                        .expect("An error occurred while waiting for new messages. Exiting.");
+                } else {
+                    thread::sleep(time.min(LLMP_MULTI_BROKER_SLEEP_TIME));
+                }
             }
 
             #[cfg(not(feature = "std"))]
@@ -2568,13 +3491,18 @@ where
 
     /// Loops unitl the last client quit,
     /// forwarding and handling all incoming messages from clients.
-    /// 5 millis of sleep can't hurt to keep busywait not at 100%
+    /// Between iterations, sleeps for up to `sleep_time`, or until a client sends a new message.
+    /// See [`LLMP_DEFAULT_BROKER_SLEEP_TIME`] for a sane default.
     /// On std, if you need to run code even if no update got sent, use `Self::loop_with_timeout` (needs the `std` feature).
     pub fn loop_forever(&mut self, sleep_time: Option<Duration>) {
         #[cfg(any(all(unix, not(miri)), all(windows, feature = "std")))]
         Self::setup_handlers();
 
         while !self.inner.is_shutting_down() {
+            // Read the doorbell before brokering, so we don't sleep through messages arriving in between.
+            #[cfg(feature = "std")]
+            let doorbell = self.inner.doorbell();
+
             self.broker_once()
                 .expect("An error occurred when brokering. Exiting.");
 
@@ -2589,9 +3517,12 @@ where
                 }
             }
 
+            // Sleep until a client sends a message, for `sleep_time` at most
             #[cfg(feature = "std")]
             if let Some(time) = sleep_time {
-                thread::sleep(time);
+                self.inner
+                    .wait_for_doorbell(doorbell, time)
+                    .expect("An error occurred while waiting for new messages. Exiting.");
             }
 
             #[cfg(not(feature = "std"))]
@@ -2609,7 +3540,8 @@ where
     /// forwarding and handling all incoming messages from clients.
     /// Will call `on_timeout` roughly after `timeout`
     /// Panics on error.
-    /// 5 millis of sleep can't hurt to keep busywait not at 100%
+    /// Between iterations, sleeps for up to `sleep_time`, or until a client sends a new message.
+    /// See [`LLMP_DEFAULT_BROKER_SLEEP_TIME`] for a sane default.
     #[cfg(feature = "std")]
     pub fn loop_with_timeouts(&mut self, timeout: Duration, sleep_time: Option<Duration>) {
         use super::current_milliseconds;
@@ -2621,6 +3553,9 @@ where
         let mut end_time = current_milliseconds() + timeout;
 
         while !self.inner.is_shutting_down() {
+            // Read the doorbell before brokering, so we don't sleep through messages arriving in between.
+            let doorbell = self.inner.doorbell();
+
             if current_milliseconds() > end_time {
                 self.hooks
                     .on_timeout_all()
@@ -2653,9 +3588,12 @@ where
                 }
             }
 
+            // Sleep until a client sends a message, for `sleep_time` at most
             #[cfg(feature = "std")]
             if let Some(time) = sleep_time {
-                thread::sleep(time);
+                self.inner
+                    .wait_for_doorbell(doorbell, time)
+                    .expect("An error occurred while waiting for new messages. Exiting.");
             }
 
             #[cfg(not(feature = "std"))]
@@ -2705,6 +3643,16 @@ where
         }
 
         self.inner.clients_to_remove.clear();
+
+        let mut new_msgs = Vec::new();
+        self.hooks.on_tick_all(&mut self.inner, &mut new_msgs)?;
+        for (new_msg_tag, new_msg_flag, new_msg) in new_msgs {
+            self.inner
+                .llmp_out
+                .send_buf_with_flags(new_msg_tag, new_msg_flag, &new_msg)?;
+            new_messages = true;
+        }
+
         Ok(new_messages)
     }
 
@@ -2820,6 +3768,16 @@ where
                                     // We don't know the last received time, just assume the current time.
                                     #[cfg(feature = "std")]
                                     last_msg_time: current_time(),
+                                    #[cfg(all(
+                                        feature = "std",
+                                        any(target_os = "linux", target_os = "android")
+                                    ))]
+                                    doorbell_shmem: None,
+                                    #[cfg(all(
+                                        feature = "std",
+                                        any(target_os = "linux", target_os = "android")
+                                    ))]
+                                    notifier: None,
                                 });
                             }
                             Err(e) => {
@@ -2915,14 +3873,18 @@ where
         mut shmem_provider: SP,
         keep_pages_forever: bool,
     ) -> Result<Self, Error> {
+        let doorbell_shmem = shmem_provider.new_on_shmem(LlmpDoorbell::default())?;
+        let mut out_shmem =
+            LlmpSharedMap::new(ClientId(0), shmem_provider.new_shmem(next_shmem_size(0))?);
+        // # Safety
+        // We just created and initialized the page.
+        unsafe { (*out_shmem.page_mut()).doorbell_shmem = Some(doorbell_shmem.description()) };
+
         Ok(LlmpBrokerInner {
             llmp_out: LlmpSender {
                 id: ClientId(0),
                 last_msg_sent: ptr::null_mut(),
-                out_shmems: vec![LlmpSharedMap::new(
-                    ClientId(0),
-                    shmem_provider.new_shmem(next_shmem_size(0))?,
-                )],
+                out_shmems: vec![out_shmem],
                 keep_pages_forever,
                 has_unsent_message: false,
                 shmem_provider: shmem_provider.clone(),
@@ -2934,9 +3896,39 @@ where
             exit_cleanly_after: None,
             num_clients_seen: 0,
             shmem_provider,
+            doorbell_shmem,
+            #[cfg(feature = "std")]
+            tcp_allowlist: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "std")]
+            b2b_psk: Arc::new(RwLock::new(None)),
         })
     }
 
+    /// The current value of the doorbell clients ring after they sent a message.
+    /// Pass it to [`Self::wait_for_doorbell`] to sleep until the next message arrives.
+    #[must_use]
+    pub fn doorbell(&self) -> u32 {
+        shmem2doorbell(&self.doorbell_shmem)
+            .map_or(0, |doorbell| doorbell.rings.load(Ordering::SeqCst))
+    }
+
+    /// Sleeps for at most `timeout`, or until a client rang the doorbell after we read `doorbell`.
+    /// Only clients on Linux and Android ring the doorbell, elsewhere this sleeps for the whole `timeout`.
+    #[cfg(feature = "std")]
+    pub fn wait_for_doorbell(&self, doorbell: u32, timeout: Duration) -> Result<(), Error> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            let bell = shmem2doorbell(&self.doorbell_shmem)?;
+            llmp_futex_sleep(&bell.rings, &bell.sleepers, doorbell, Some(timeout))?;
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        {
+            let _ = doorbell;
+            thread::sleep(timeout);
+        }
+        Ok(())
+    }
+
     /// Gets the [`ClientId`] the next client attaching to this broker will get.
     /// In its current implementation, the inner value of the next [`ClientId`]
     /// is equal to `self.num_clients_seen`.
@@ -2984,6 +3976,27 @@ where
         self.exit_cleanly_after = Some(n_clients);
     }
 
+    /// Only accept remote brokers connecting from one of the `allowed` addresses, or from anywhere if `None`.
+    ///
+    /// Connections from the loopback interface are always accepted, as local clients attach through them.
+    /// This applies to listeners that are already running as well.
+    /// To authenticate and encrypt the broker-to-broker traffic itself, use `set_b2b_psk` (feature `llmp_noise`).
+    #[cfg(feature = "std")]
+    pub fn set_tcp_allowlist(&mut self, allowed: Option<Vec<IpAddr>>) {
+        *self.tcp_allowlist.write().unwrap() = allowed;
+    }
+
+    /// Authenticate remote brokers with a pre-shared key, and encrypt all broker-to-broker traffic.
+    ///
+    /// All brokers of the campaign need the same key: it is used for the connections we accept,
+    /// and for those we open with [`Self::connect_b2b`].
+    /// Once set, remote brokers without the key, and plaintext requests from remote addresses, are refused.
+    /// Local clients keep connecting in plaintext over the loopback interface.
+    #[cfg(feature = "llmp_noise")]
+    pub fn set_b2b_psk(&mut self, psk: Option<[u8; 32]>) {
+        *self.b2b_psk.write().unwrap() = psk;
+    }
+
     /// Add a client to this broker.
     /// Will set an appropriate [`ClientId`] before pushing the client to the internal vec.
     /// Will increase `num_clients_seen`.
@@ -3018,6 +4031,10 @@ where
             // We don't know the last received time, just assume the current time.
             #[cfg(feature = "std")]
             last_msg_time: current_time(),
+            #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+            doorbell_shmem: None,
+            #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+            notifier: None,
         })
     }
 
@@ -3031,27 +4048,54 @@ where
     {
         let mut stream = TcpStream::connect(addr)?;
         log::info!("B2B: Connected to {stream:?}");
-
-        match recv_tcp_msg(&mut stream)?.try_into()? {
-            TcpResponse::BrokerConnectHello {
-                broker_shmem_description: _,
-                hostname,
-            } => log::info!("B2B: Connected to {hostname}"),
+        stream.set_read_timeout(Some(_LLMP_HANDSHAKE_TIMEOUT))?;
+
+        let hello = recv_tcp_msg(&mut stream)?;
+        #[cfg(feature = "llmp_noise")]
+        let (mut stream, hello) = if let Some(psk) = *self.b2b_psk.read().unwrap() {
+            send_tcp_msg(&mut stream, &TcpRequest::NoiseHandshake)?;
+            let mut stream =
+                TcpChannel::Noise(Box::new(NoiseTcpStream::handshake(stream, &psk, true)?));
+            // Only trust the hello repeated on the authenticated channel
+            let hello = stream.recv_msg()?;
+            (stream, hello)
+        } else {
+            (TcpChannel::Plain(stream), hello)
+        };
+        #[cfg(not(feature = "llmp_noise"))]
+        let (mut stream, hello) = (TcpChannel::Plain(stream), hello);
+
+        let peer_codecs = match TcpHelloExtension::decode_hello(&hello)? {
+            (
+                TcpResponse::BrokerConnectHello {
+                    broker_shmem_description: _,
+                    hostname,
+                },
+                extension,
+            ) => {
+                log::info!(
+                    "B2B: Connected to {hostname} (handshake version {})",
+                    extension.version
+                );
+                extension.codecs
+            }
             _ => {
                 return Err(Error::illegal_state(
                     "Unexpected response from B2B server received.".to_string(),
                 ));
             }
-        }
+        };
 
         let hostname = hostname::get()
             .unwrap_or_else(|_| "<unknown>".into())
             .to_string_lossy()
             .into();
 
-        send_tcp_msg(&mut stream, &TcpRequest::RemoteBrokerHello { hostname })?;
+        stream.send_bytes(&TcpHelloExtension::encode_hello(
+            &TcpRequest::RemoteBrokerHello { hostname },
+        )?)?;
 
-        let broker_id = match recv_tcp_msg(&mut stream)?.try_into()? {
+        let broker_id = match stream.recv_msg()?.try_into()? {
             TcpResponse::RemoteBrokerAccepted { broker_id } => {
                 log::info!("B2B: Got Connection Ack, broker_id {broker_id:?}");
                 broker_id
@@ -3077,6 +4121,7 @@ where
                 .unwrap()
                 .shmem
                 .description(),
+            &peer_codecs,
         )?;
 
         let new_shmem = LlmpSharedMap::existing(
@@ -3149,6 +4194,37 @@ where
         self.llmp_out.send_buf_with_flags(tag, flags, buf)
     }
 
+    /// Broadcasts a `buf` with the given `flags` on behalf of the client `sender`,
+    /// just like a forwarded message. Clients skip their own messages, so `sender` does not receive it.
+    pub fn send_buf_with_flags_from(
+        &mut self,
+        sender: ClientId,
+        tag: Tag,
+        flags: Flags,
+        buf: &[u8],
+    ) -> Result<(), Error> {
+        // Make sure we don't reuse already allocated tags
+        if tag == LLMP_TAG_NEW_SHM_CLIENT
+            || tag == LLMP_TAG_END_OF_PAGE
+            || tag == LLMP_TAG_UNINITIALIZED
+            || tag == LLMP_TAG_UNSET
+        {
+            return Err(Error::unknown(format!(
+                "Reserved tag supplied to send_buf ({tag:?})"
+            )));
+        }
+
+        unsafe {
+            let msg = self.llmp_out.alloc_next(buf.len())?;
+            (*msg).tag = tag;
+            (*msg).flags = flags;
+            (*msg).sender = sender;
+            buf.as_ptr()
+                .copy_to_nonoverlapping((*msg).buf.as_mut_ptr(), buf.len());
+            self.llmp_out.send(msg, false)
+        }
+    }
+
     /// Launches a thread using a tcp listener socket, on which new clients may connect to this broker.
     /// Does so on the given port.
     #[cfg(feature = "std")]
@@ -3201,15 +4277,20 @@ where
     /// Launches a proxy thread.
     /// It will read outgoing messages from the given broker map (and handle EOP by mapping a new page).
     /// This function returns the [`ShMemDescription`] the client uses to place incoming messages.
+    /// Compressed messages in a codec the remote broker cannot decompress (see `peer_codecs`) get transcoded.
     /// The thread exits, when the remote broker disconnects.
     #[cfg(feature = "std")]
     #[expect(clippy::too_many_lines)]
+    #[cfg_attr(not(feature = "llmp_compression"), expect(unused_variables))]
     fn b2b_thread_on(
-        mut stream: TcpStream,
+        mut stream: TcpChannel,
         b2b_client_id: ClientId,
         broker_shmem_description: &ShMemDescription,
+        peer_codecs: &[u8],
     ) -> Result<ShMemDescription, Error> {
         let broker_shmem_description = *broker_shmem_description;
+        #[cfg(feature = "llmp_compression")]
+        let peer_codecs = codecs_from_ids(peer_codecs);
 
         // A channel to get the new "client's" sharedmap id from
         let (send, recv) = channel();
@@ -3224,6 +4305,7 @@ where
 
             // The background thread blocks on the incoming connection for 15 seconds (if no data is available), then checks if it should forward own messages, then blocks some more.
             stream
+                .stream()
                 .set_read_timeout(Some(_LLMP_B2B_BLOCK_TIME))
                 .expect("Failed to set tcp stream timeout");
 
@@ -3251,7 +4333,17 @@ where
             #[cfg(feature = "llmp_debug")]
             log::info!("B2B: Starting proxy loop :)");
 
-            let peer_address = stream.peer_addr().unwrap();
+            let peer_address = stream.stream().peer_addr().unwrap();
+
+            #[cfg(feature = "llmp_compression")]
+            let transcoder = {
+                let link_codec =
+                    CompressionCodec::negotiate(CompressionCodec::preferred(), &peer_codecs);
+                log::info!("B2B: Compressing messages to {peer_address} with {link_codec}");
+                CodecCompressor::new()
+                    .with_codec(link_codec)
+                    .expect("Negotiated an unsupported compression codec")
+            };
 
             loop {
                 // first, forward all data we have.
@@ -3271,16 +4363,33 @@ where
                                 "Fowarding message ({} bytes) via broker2broker connection",
                                 payload.len()
                             );
-                            // We got a new message! Forward...
-                            if let Err(e) = send_tcp_msg(
-                                &mut stream,
-                                &TcpRemoteNewMessage {
-                                    client_id,
-                                    tag,
-                                    flags,
-                                    payload: payload.to_vec(),
-                                },
+
+                            #[cfg(feature = "llmp_compression")]
+                            let (flags, payload) = match transcode_for_peer(
+                                &transcoder,
+                                &peer_codecs,
+                                flags,
+                                payload,
                             ) {
+                                Ok(Some((flags, transcoded))) => (flags, transcoded),
+                                Ok(None) => (flags, payload.to_vec()),
+                                Err(e) => {
+                                    log::warn!(
+                                        "Could not transcode message for broker {peer_address}, dropping it: {e}"
+                                    );
+                                    continue;
+                                }
+                            };
+                            #[cfg(not(feature = "llmp_compression"))]
+                            let payload = payload.to_vec();
+
+                            // We got a new message! Forward...
+                            if let Err(e) = stream.send_msg(&TcpRemoteNewMessage {
+                                client_id,
+                                tag,
+                                flags,
+                                payload,
+                            }) {
                                 log::info!(
                                     "Got error {e} while trying to forward a message to broker {peer_address}, exiting thread"
                                 );
@@ -3301,7 +4410,7 @@ where
                 // Forwarding happens between each recv, too, as simplification.
                 // We ignore errors completely as they may be timeout, or stream closings.
                 // Instead, we catch stream close when/if we next try to send.
-                match recv_tcp_msg(&mut stream) {
+                match stream.recv_msg() {
                     Ok(val) => {
                         let msg: TcpRemoteNewMessage = val.try_into().expect(
                             "Illegal message received from broker 2 broker connection - shutting down.",
@@ -3322,10 +4431,16 @@ where
                                 &msg.payload,
                             )
                             .expect("B2B: Error forwarding message. Exiting.");
+                        #[cfg(any(target_os = "linux", target_os = "android"))]
+                        if let Err(e) = local_receiver.ring_doorbell() {
+                            log::warn!("B2B: Could not ring the local broker's doorbell: {e}");
+                        }
                     }
                     Err(e) => {
                         if let Error::OsError(e, ..) = e {
-                            if e.kind() == ErrorKind::UnexpectedEof {
+                            // A message that fails to decrypt leaves the channel unusable
+                            if matches!(e.kind(), ErrorKind::UnexpectedEof | ErrorKind::InvalidData)
+                            {
                                 log::info!(
                                     "Broker {peer_address} seems to have disconnected, exiting"
                                 );
@@ -3353,8 +4468,9 @@ where
     /// handles a single tcp request in the current context.
     #[cfg(feature = "std")]
     fn handle_tcp_request(
-        mut stream: TcpStream,
+        mut stream: TcpChannel,
         request: &TcpRequest,
+        extension: &TcpHelloExtension,
         current_client_id: &mut ClientId,
         sender: &mut LlmpSender<SHM, SP>,
         broker_shmem_description: &ShMemDescription,
@@ -3373,12 +4489,9 @@ where
                     Err(e) => log::info!("Error forwarding client on map: {e:?}"),
                 }
 
-                if let Err(e) = send_tcp_msg(
-                    &mut stream,
-                    &TcpResponse::LocalClientAccepted {
-                        client_id: *current_client_id,
-                    },
-                ) {
+                if let Err(e) = stream.send_msg(&TcpResponse::LocalClientAccepted {
+                    client_id: *current_client_id,
+                }) {
                     log::info!("An error occurred sending via tcp {e}");
                 }
                 current_client_id.0 += 1;
@@ -3387,27 +4500,31 @@ where
                 log::info!("B2B new client: {hostname}");
 
                 // TODO: Clean up broker ids.
-                if send_tcp_msg(
-                    &mut stream,
-                    &TcpResponse::RemoteBrokerAccepted {
+                if stream
+                    .send_msg(&TcpResponse::RemoteBrokerAccepted {
                         broker_id: BrokerId(current_client_id.0),
-                    },
-                )
-                .is_err()
+                    })
+                    .is_err()
                 {
                     log::info!("Error accepting broker, ignoring.");
                     return;
                 }
 
-                if let Ok(shmem_description) =
-                    Self::b2b_thread_on(stream, *current_client_id, broker_shmem_description)
-                {
+                if let Ok(shmem_description) = Self::b2b_thread_on(
+                    stream,
+                    *current_client_id,
+                    broker_shmem_description,
+                    &extension.codecs,
+                ) {
                     if Self::announce_new_client(sender, &shmem_description).is_err() {
                         log::info!("B2B: Error announcing client {shmem_description:?}");
                     }
                     current_client_id.0 += 1;
                 }
             }
+            TcpRequest::NoiseHandshake => {
+                log::info!("Ignoring a Noise handshake request on an established connection");
+            }
         }
     }
 
@@ -3425,12 +4542,14 @@ where
             .unwrap_or_else(|_| "<unknown>".into())
             .to_string_lossy()
             .into();
-        let broker_hello = TcpResponse::BrokerConnectHello {
+        let broker_hello = TcpHelloExtension::encode_hello(&TcpResponse::BrokerConnectHello {
             broker_shmem_description,
             hostname,
-        };
+        })?;
 
         let llmp_tcp_id = self.peek_next_client_id();
+        let tcp_allowlist = self.tcp_allowlist.clone();
+        let b2b_psk = self.b2b_psk.clone();
 
         // Tcp out map sends messages from background thread tcp server to foreground client
         let tcp_out_shmem = LlmpSharedMap::new(
@@ -3440,6 +4559,41 @@ where
         let tcp_out_shmem_description = tcp_out_shmem.shmem.description();
         let listener_id = self.register_client(tcp_out_shmem);
 
+        // Peers that said hello, and their first request
+        let (request_send, request_recv) = channel();
+
+        // The accept loop hands each new connection to its own thread, which greets it.
+        thread::spawn(move || {
+            loop {
+                match listener.accept() {
+                    ListenerStream::Tcp(stream, addr) => {
+                        if !is_allowed_tcp_peer(&tcp_allowlist, &addr) {
+                            log::warn!(
+                                "Refusing connection from {addr}, it is not on the allowlist"
+                            );
+                            continue;
+                        }
+
+                        log::info!("New connection: {addr:?}");
+
+                        let broker_hello = broker_hello.clone();
+                        let psk = *b2b_psk.read().unwrap();
+                        let request_send = request_send.clone();
+                        thread::spawn(move || {
+                            match accept_tcp_peer(stream, &addr, &broker_hello, psk) {
+                                Ok(peer) => {
+                                    // The broker only goes away when the process exits
+                                    let _ = request_send.send(peer);
+                                }
+                                Err(e) => log::warn!("Refusing connection from {addr}: {e}"),
+                            }
+                        });
+                    }
+                    ListenerStream::Empty() => {}
+                }
+            }
+        });
+
         let ret = thread::spawn(move || {
             // Create a new ShMemProvider for this background thread.
             let mut shmem_provider_bg = SP::new().unwrap();
@@ -3461,52 +4615,15 @@ where
                 unused_shmem_cache: vec![],
             };
 
-            loop {
-                match listener.accept() {
-                    ListenerStream::Tcp(mut stream, addr) => {
-                        log::info!(
-                            "New connection: {:?}/{:?}",
-                            addr,
-                            stream.peer_addr().unwrap()
-                        );
-
-                        // Send initial information, without anyone asking.
-                        // This makes it a tiny bit easier to map the broker map for new Clients.
-                        match send_tcp_msg(&mut stream, &broker_hello) {
-                            Ok(()) => {}
-                            Err(e) => {
-                                log::error!("Error sending initial hello: {e:?}");
-                                continue;
-                            }
-                        }
-
-                        let buf = match recv_tcp_msg(&mut stream) {
-                            Ok(buf) => buf,
-                            Err(e) => {
-                                log::error!("Error receving from tcp: {e:?}");
-                                continue;
-                            }
-                        };
-
-                        // log::info!("{:#?}", buf);
-                        let req = match buf.try_into() {
-                            Ok(req) => req,
-                            Err(e) => {
-                                log::error!("Could not deserialize tcp message: {e:?}");
-                                continue;
-                            }
-                        };
-
-                        Self::handle_tcp_request(
-                            stream,
-                            &req,
-                            &mut current_client_id,
-                            &mut tcp_incoming_sender,
-                            &broker_shmem_description,
-                        );
-                    }
-                    ListenerStream::Empty() => {}
-                }
+            for (stream, request, extension) in request_recv {
+                Self::handle_tcp_request(
+                    stream,
+                    &request,
+                    &extension,
+                    &mut current_client_id,
+                    &mut tcp_incoming_sender,
+                    &broker_shmem_description,
+                );
             }
         });
 
@@ -3532,6 +4649,10 @@ pub struct LlmpClient<SHM, SP> {
     sender: LlmpSender<SHM, SP>,
     /// Incoming (broker) broadcast map
     receiver: LlmpReceiver<SHM, SP>,
+    /// The compression codecs the broker can decompress.
+    /// Unless we learned otherwise during the tcp handshake, the broker is assumed to be this build.
+    #[cfg(feature = "llmp_compression")]
+    broker_codecs: Vec<CompressionCodec>,
 }
 
 /// `n` clients connect to a broker. They share an outgoing map with the broker,
@@ -3570,19 +4691,28 @@ where
                 // We don't know the last received time, just assume the current time.
                 #[cfg(feature = "std")]
                 last_msg_time: current_time(),
+                #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+                doorbell_shmem: None,
+                #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+                notifier: None,
             },
+            #[cfg(feature = "llmp_compression")]
+            broker_codecs: CompressionCodec::available(),
         })
     }
 
     /// Create a point-to-point channel instead of using a broker-client channel
-    pub fn new_p2p(shmem_provider: SP, sender_id: ClientId) -> Result<Self, Error> {
+    pub fn new_p2p(mut shmem_provider: SP, sender_id: ClientId) -> Result<Self, Error> {
         let sender = LlmpSender::new(shmem_provider.clone(), sender_id, false)?;
-        let receiver = LlmpReceiver::on_existing_shmem(
-            shmem_provider,
-            sender.out_shmems[0].shmem.clone(),
-            None,
-        )?;
-        Ok(Self { sender, receiver })
+        // Map the page on our own, the sender unmaps its mapping when it's dropped.
+        let recv_shmem = shmem_provider.clone_ref(&sender.out_shmems[0].shmem)?;
+        let receiver = LlmpReceiver::on_existing_shmem(shmem_provider, recv_shmem, None)?;
+        Ok(Self {
+            sender,
+            receiver,
+            #[cfg(feature = "llmp_compression")]
+            broker_codecs: CompressionCodec::available(),
+        })
     }
 
     /// Reattach to a vacant client map.
@@ -3607,6 +4737,8 @@ where
                 current_broker_shmem,
                 last_msg_recvd_offset,
             )?,
+            #[cfg(feature = "llmp_compression")]
+            broker_codecs: CompressionCodec::available(),
         })
     }
 
@@ -3622,6 +4754,8 @@ where
                 shmem_provider,
                 &format!("{env_name}_RECEIVER"),
             )?,
+            #[cfg(feature = "llmp_compression")]
+            broker_codecs: CompressionCodec::available(),
         })
     }
 
@@ -3639,17 +4773,35 @@ where
                 shmem_provider,
                 &description.receiver,
             )?,
+            #[cfg(feature = "llmp_compression")]
+            broker_codecs: CompressionCodec::available(),
         })
     }
 
+    /// Commits a msg to the client's out map
+    /// # Safety
+    /// Needs to be called with a proper msg pointer
+    pub unsafe fn send(&mut self, msg: *mut LlmpMsg) -> Result<(), Error> {
+        unsafe { self.sender.send(msg, true) }?;
+        #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+        self.receiver.ring_doorbell()?;
+        Ok(())
+    }
+
     /// Allocates a message of the given size, tags it, and sends it off.
     pub fn send_buf(&mut self, tag: Tag, buf: &[u8]) -> Result<(), Error> {
-        self.sender.send_buf(tag, buf)
+        self.sender.send_buf(tag, buf)?;
+        #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+        self.receiver.ring_doorbell()?;
+        Ok(())
     }
 
     /// Send a `buf` with the given `flags`.
     pub fn send_buf_with_flags(&mut self, tag: Tag, flags: Flags, buf: &[u8]) -> Result<(), Error> {
-        self.sender.send_buf_with_flags(tag, flags, buf)
+        self.sender.send_buf_with_flags(tag, flags, buf)?;
+        #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+        self.receiver.ring_doorbell()?;
+        Ok(())
     }
 
     /// A client receives a broadcast message.
@@ -3692,6 +4844,22 @@ where
         self.receiver.recv_buf_blocking()
     }
 
+    /// Sleeps until the broker posts a new message, or until `timeout` passed.
+    /// See [`LlmpReceiver::wait_for_message`].
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn wait_for_message(&mut self, timeout: Option<Duration>) -> Result<bool, Error> {
+        self.receiver.wait_for_message(timeout)
+    }
+
+    /// An [`EventFd`] that becomes readable whenever the broker posts new messages, for use with `epoll`.
+    /// See [`LlmpReceiver::notification_fd`].
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    #[inline]
+    pub fn notification_fd(&mut self) -> Result<&EventFd, Error> {
+        self.receiver.notification_fd()
+    }
+
     /// Receive a `buf` from the broker, including the `flags` used during transmission.
     #[expect(clippy::type_complexity)]
     pub fn recv_buf_with_flags(&mut self) -> Result<Option<(ClientId, Tag, Flags, &[u8])>, Error> {
@@ -3738,10 +4906,13 @@ where
         };
         log::info!("Connected to port {port}");
 
-        let TcpResponse::BrokerConnectHello {
-            broker_shmem_description,
-            hostname: _,
-        } = recv_tcp_msg(&mut stream)?.try_into()?
+        let (
+            TcpResponse::BrokerConnectHello {
+                broker_shmem_description,
+                hostname: _,
+            },
+            extension,
+        ) = TcpHelloExtension::decode_hello(&recv_tcp_msg(&mut stream)?)?
         else {
             return Err(Error::illegal_state(
                 "Received unexpected Broker Hello".to_string(),
@@ -3754,6 +4925,12 @@ where
 
         // We'll set `sender_id` later
         let mut ret = Self::new(shmem_provider, map, ClientId(0))?;
+        #[cfg(feature = "llmp_compression")]
+        {
+            ret.broker_codecs = codecs_from_ids(&extension.codecs);
+        }
+        #[cfg(not(feature = "llmp_compression"))]
+        let _ = extension;
 
         // Now sender contains 1 shmem, that must be shared back with the broker.
         let client_hello_req = TcpRequest::LocalClientHello {
@@ -3810,13 +4987,6 @@ where
         }
     }
 
-    /// Commits a msg to the client's out map
-    /// # Safety
-    /// Needs to be called with a proper msg pointer
-    pub unsafe fn send(&mut self, msg: *mut LlmpMsg) -> Result<(), Error> {
-        unsafe { self.sender.send(msg, true) }
-    }
-
     /// Write the current state to env.
     /// A new client can attach to exactly the same state by calling [`LlmpClient::on_existing_shmem()`].
     ///
@@ -3863,6 +5033,20 @@ impl<SHM, SP> LlmpClient<SHM, SP> {
     pub fn receiver_mut(&mut self) -> &mut LlmpReceiver<SHM, SP> {
         &mut self.receiver
     }
+
+    /// The compression codecs the broker can decompress
+    #[cfg(feature = "llmp_compression")]
+    #[must_use]
+    pub fn broker_codecs(&self) -> &[CompressionCodec] {
+        &self.broker_codecs
+    }
+
+    /// The codec to compress messages to the broker with, given the `preferred` codec.
+    #[cfg(feature = "llmp_compression")]
+    #[must_use]
+    pub fn negotiate_codec(&self, preferred: CompressionCodec) -> CompressionCodec {
+        CompressionCodec::negotiate(preferred, &self.broker_codecs)
+    }
 }
 
 #[cfg(test)]
@@ -3879,7 +5063,10 @@ mod tests {
         LlmpConnection::{self, IsBroker, IsClient},
         Tag,
     };
-    use crate::shmem::{ShMemProvider, StdShMemProvider};
+    use crate::{
+        ClientId,
+        shmem::{ShMemProvider, StdShMemProvider},
+    };
 
     #[test]
     #[serial]
@@ -3932,4 +5119,166 @@ mod tests {
         // We want at least the tcp and sender clients.
         assert_eq!(broker.inner.llmp_clients.len(), 2);
     }
+
+    #[test]
+    #[serial]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[cfg_attr(miri, ignore)]
+    fn test_llmp_notification() {
+        use std::os::fd::AsRawFd;
+
+        let shmem_provider = StdShMemProvider::new().unwrap();
+        let mut client = LlmpClient::new_p2p(shmem_provider, ClientId(0)).unwrap();
+        assert!(
+            !client
+                .wait_for_message(Some(Duration::from_millis(1)))
+                .unwrap()
+        );
+
+        let fd = client.notification_fd().unwrap().as_raw_fd();
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // # Safety
+        // Polls a single, valid fd.
+        let poll = |pollfd: &mut libc::pollfd| unsafe { libc::poll(pollfd, 1, 5000) };
+
+        // The notifier signals once on startup, in case messages were already waiting.
+        assert_eq!(poll(&mut pollfd), 1);
+        client.notification_fd().unwrap().clear().unwrap();
+
+        let tag = Tag(0x1337);
+        client.send_buf(tag, &[1]).unwrap();
+        assert!(client.wait_for_message(None).unwrap());
+        assert_eq!(poll(&mut pollfd), 1);
+        client.notification_fd().unwrap().clear().unwrap();
+
+        let (_sender_id, tag2, buf) = client.recv_buf().unwrap().unwrap();
+        assert_eq!(tag, tag2);
+        assert_eq!(buf, [1]);
+
+        // The notifier follows the receiver to the next page.
+        // # Safety
+        // Test only, the receiver maps the next page on end of page.
+        unsafe { client.sender.handle_out_eop().unwrap() };
+        client.send_buf(tag, &[2]).unwrap();
+        assert_eq!(client.recv_buf_blocking().unwrap().2, [2]);
+        client.notification_fd().unwrap().clear().unwrap();
+        client.send_buf(tag, &[3]).unwrap();
+        assert_eq!(poll(&mut pollfd), 1);
+        assert_eq!(client.recv_buf().unwrap().unwrap().2, [3]);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[cfg_attr(miri, ignore)]
+    fn test_llmp_doorbell() {
+        use std::time::Instant;
+
+        use super::{LlmpBrokerInner, LlmpSharedMap};
+        use crate::shmem::ShMem;
+
+        let mut shmem_provider = StdShMemProvider::new().unwrap();
+        let mut broker = LlmpBrokerInner::new(shmem_provider.clone()).unwrap();
+        let broker_shmem = shmem_provider
+            .shmem_from_description(broker.llmp_out.out_shmems[0].shmem.description())
+            .unwrap();
+        let mut client = LlmpClient::new(
+            shmem_provider,
+            LlmpSharedMap::existing(broker_shmem),
+            ClientId(1),
+        )
+        .unwrap();
+
+        // The doorbell stays the same, even after the broker moved on to the next page.
+        // # Safety
+        // Test only, the broker keeps its pages mapped.
+        unsafe {
+            broker.llmp_out.handle_out_eop().unwrap();
+            let first = (*broker.llmp_out.out_shmems[0].page()).doorbell_shmem;
+            let last = (*broker.llmp_out.out_shmems.last().unwrap().page()).doorbell_shmem;
+            assert_eq!(first.unwrap().id, last.unwrap().id);
+        }
+
+        let doorbell = broker.doorbell();
+        client.send_buf(Tag(0x1337), &[1]).unwrap();
+        assert_ne!(broker.doorbell(), doorbell);
+
+        // The client rang since we read the doorbell, so we don't sleep.
+        let start = Instant::now();
+        broker
+            .wait_for_doorbell(doorbell, Duration::from_secs(5))
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    #[cfg(feature = "llmp_noise")]
+    #[cfg_attr(miri, ignore)]
+    fn test_llmp_noise_channel() {
+        use alloc::boxed::Box;
+        use std::{
+            net::{TcpListener, TcpStream},
+            thread,
+        };
+
+        use super::{NoiseTcpStream, TcpChannel, TcpRequest};
+
+        let connect = |server_psk: [u8; 32], client_psk: [u8; 32]| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                NoiseTcpStream::handshake(stream, &server_psk, false).map(|noise| {
+                    let mut channel = TcpChannel::Noise(Box::new(noise));
+                    channel.recv_msg().unwrap()
+                })
+            });
+            let stream = TcpStream::connect(addr).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let client = NoiseTcpStream::handshake(stream, &client_psk, true).map(|noise| {
+                let mut channel = TcpChannel::Noise(Box::new(noise));
+                channel.send_msg(&TcpRequest::NoiseHandshake).unwrap();
+            });
+            (client, server.join().unwrap())
+        };
+
+        let (client, server) = connect([1; 32], [1; 32]);
+        client.unwrap();
+        let request: TcpRequest = server.unwrap().try_into().unwrap();
+        assert!(matches!(request, TcpRequest::NoiseHandshake));
+
+        // A peer without the key can not connect
+        let (_client, server) = connect([1; 32], [2; 32]);
+        assert!(server.is_err());
+    }
+
+    #[test]
+    fn test_tcp_hello_extension() {
+        use super::{TcpHelloExtension, TcpRequest};
+
+        let hello = TcpRequest::RemoteBrokerHello {
+            hostname: "peer".into(),
+        };
+
+        // Older peers parse the hello, and ignore the extension
+        let extended = TcpHelloExtension::encode_hello(&hello).unwrap();
+        let request: TcpRequest = extended.clone().try_into().unwrap();
+        assert!(
+            matches!(request, TcpRequest::RemoteBrokerHello { hostname } if hostname == "peer")
+        );
+
+        let (_, extension) = TcpHelloExtension::decode_hello::<TcpRequest>(&extended).unwrap();
+        assert_eq!(extension, TcpHelloExtension::new());
+
+        // A hello from an older peer falls back to gzip
+        let legacy = postcard::to_allocvec(&hello).unwrap();
+        let (_, extension) = TcpHelloExtension::decode_hello::<TcpRequest>(&legacy).unwrap();
+        assert_eq!(extension, TcpHelloExtension::legacy());
+    }
 }
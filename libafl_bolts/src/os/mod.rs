@@ -14,6 +14,9 @@ pub use unix_signals::CTRL_C_EXIT;
 #[cfg(all(unix, feature = "alloc"))]
 pub mod pipes;
 
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+pub mod notify;
+
 #[cfg(all(unix, feature = "std"))]
 use alloc::{borrow::Cow, ffi::CString};
 #[cfg(all(unix, feature = "std"))]
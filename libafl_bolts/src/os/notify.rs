@@ -0,0 +1,170 @@
+//! Cross-process wakeups, used by `LLMP` to sleep until a new message arrives.
+//!
+//! Futexes work on shared memory across processes, as long as the word lives in a shared mapping.
+//! An [`EventFd`] can be registered with `epoll`, `poll`, or `select`, to integrate with other event loops.
+
+use core::{ptr, sync::atomic::AtomicU32, time::Duration};
+use std::{
+    io,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+};
+
+use crate::Error;
+
+/// Sleep until the futex `word` gets woken up, or `timeout` passed.
+/// Returns immediately if `word` no longer holds `expected`.
+///
+/// Returns `Ok(false)` on timeout, `Ok(true)` otherwise. Spurious wakeups are possible.
+/// The word is not marked as process-private, so wakeups work across processes sharing the mapping.
+pub fn futex_wait(
+    word: &AtomicU32,
+    expected: u32,
+    timeout: Option<Duration>,
+) -> Result<bool, Error> {
+    let timeout = timeout.map(|timeout| libc::timespec {
+        tv_sec: timeout.as_secs().try_into().unwrap_or(libc::time_t::MAX),
+        tv_nsec: i32::try_from(timeout.subsec_nanos())
+            .unwrap_or_default()
+            .into(),
+    });
+    let timeout_ptr = timeout.as_ref().map_or(ptr::null(), ptr::from_ref);
+
+    // # Safety
+    // The kernel only reads the word we hold a reference to, and the optional timeout on our stack.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAIT,
+            expected,
+            timeout_ptr,
+            ptr::null::<u32>(),
+            0,
+        )
+    };
+    if ret == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        // The value changed before we went to sleep, or a signal interrupted us.
+        Some(libc::EAGAIN | libc::EINTR) => Ok(true),
+        Some(libc::ETIMEDOUT) => Ok(false),
+        _ => Err(Error::os_error(err, "futex wait failed")),
+    }
+}
+
+/// Wake up to `count` waiters sleeping on the futex `word`.
+/// Returns the number of woken waiters.
+pub fn futex_wake(word: &AtomicU32, count: u32) -> usize {
+    let count = i32::try_from(count).unwrap_or(i32::MAX);
+    // # Safety
+    // `FUTEX_WAKE` does not access the memory behind the word.
+    let ret = unsafe { libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE, count) };
+    // Waking can only fail for invalid addresses, which a reference cannot be.
+    usize::try_from(ret).unwrap_or(0)
+}
+
+/// A non-blocking `eventfd`, readable whenever it got notified since it was last cleared.
+#[derive(Debug)]
+pub struct EventFd {
+    fd: OwnedFd,
+}
+
+impl EventFd {
+    /// Create a new, non-blocking, close-on-exec `eventfd`
+    pub fn new() -> Result<Self, Error> {
+        // # Safety
+        // Plain syscall, the result is checked below.
+        let fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(Error::last_os_error("Could not create eventfd"));
+        }
+        // # Safety
+        // We just created the fd and are the only owner.
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// Make the `eventfd` readable
+    pub fn notify(&self) -> Result<(), Error> {
+        let one = 1_u64.to_ne_bytes();
+        // # Safety
+        // Writes exactly the 8 bytes of our buffer.
+        let ret = unsafe { libc::write(self.fd.as_raw_fd(), one.as_ptr().cast(), one.len()) };
+        // `EAGAIN` means the counter is about to overflow, so the fd is readable either way.
+        if ret < 0 && io::Error::last_os_error().raw_os_error() != Some(libc::EAGAIN) {
+            return Err(Error::last_os_error("Could not write to eventfd"));
+        }
+        Ok(())
+    }
+
+    /// Reset the `eventfd`, returning how often it got notified since it was last cleared.
+    pub fn clear(&self) -> Result<u64, Error> {
+        let mut buf = [0_u8; 8];
+        // # Safety
+        // Reads at most the 8 bytes of our buffer.
+        let ret = unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        if ret < 0 {
+            if io::Error::last_os_error().raw_os_error() == Some(libc::EAGAIN) {
+                return Ok(0);
+            }
+            return Err(Error::last_os_error("Could not read from eventfd"));
+        }
+        Ok(u64::from_ne_bytes(buf))
+    }
+}
+
+impl AsFd for EventFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for EventFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use core::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+    use std::thread;
+
+    use super::{EventFd, futex_wait, futex_wake};
+
+    #[test]
+    fn test_futex() {
+        let word = Arc::new(AtomicU32::new(0));
+        // Value changed already, returns right away.
+        assert!(futex_wait(&word, 1, None).unwrap());
+        assert!(!futex_wait(&word, 0, Some(Duration::from_millis(1))).unwrap());
+
+        let waker_word = word.clone();
+        let waker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            waker_word.store(1, Ordering::SeqCst);
+            futex_wake(&waker_word, u32::MAX);
+        });
+        while word.load(Ordering::SeqCst) == 0 {
+            futex_wait(&word, 0, Some(Duration::from_secs(5))).unwrap();
+        }
+        waker.join().unwrap();
+    }
+
+    #[test]
+    fn test_eventfd() {
+        let eventfd = EventFd::new().unwrap();
+        assert_eq!(eventfd.clear().unwrap(), 0);
+        eventfd.notify().unwrap();
+        eventfd.notify().unwrap();
+        assert_eq!(eventfd.clear().unwrap(), 2);
+        assert_eq!(eventfd.clear().unwrap(), 0);
+    }
+}
@@ -112,6 +112,15 @@ pub trait Rand {
     /// Gets the next 64 bit value
     fn next(&mut self) -> u64;
 
+    /// Continues this Rand with the `substream`-th sub-stream of the `stream`-th stream derived from `master_seed`,
+    /// see [`Xoshiro256PlusPlusRand::with_stream`].
+    ///
+    /// Only [`Xoshiro256PlusPlusRand`] continues with the jumped state itself, so that streams never overlap.
+    /// Other rands are merely seeded with the first output of the stream.
+    fn set_stream(&mut self, master_seed: u64, stream: u64, substream: u64) {
+        self.set_seed(Xoshiro256PlusPlusRand::with_stream(master_seed, stream, substream).next());
+    }
+
     /// Gets a value between 0.0 (inclusive) and 1.0 (exclusive)
     #[inline]
     #[expect(clippy::cast_precision_loss)]
@@ -321,6 +330,10 @@ impl Rand for Xoshiro256PlusPlusRand {
 
         ret
     }
+
+    fn set_stream(&mut self, master_seed: u64, stream: u64, substream: u64) {
+        *self = Self::with_stream(master_seed, stream, substream);
+    }
 }
 
 impl Xoshiro256PlusPlusRand {
@@ -331,6 +344,59 @@ impl Xoshiro256PlusPlusRand {
         rand.set_seed(seed);
         rand
     }
+
+    /// Creates the xoshiro256++ rand for the `substream`-th sub-stream of the `stream`-th stream
+    /// derived from `master_seed`.
+    ///
+    /// Streams are 2^192 outputs apart, sub-streams of a stream 2^128 outputs apart,
+    /// so none of them ever overlap.
+    #[must_use]
+    pub fn with_stream(master_seed: u64, stream: u64, substream: u64) -> Self {
+        let mut rand = Self::with_seed(master_seed);
+        for _ in 0..stream {
+            rand.long_jump();
+        }
+        for _ in 0..substream {
+            rand.jump();
+        }
+        rand
+    }
+
+    /// Advances the state as if [`Rand::next`] was called 2^128 times
+    pub fn jump(&mut self) {
+        self.jump_with(&[
+            0x180e_c6d3_3cfd_0aba,
+            0xd5a6_1266_f0c9_392c,
+            0xa958_2618_e03f_c9aa,
+            0x39ab_dc45_29b1_661c,
+        ]);
+    }
+
+    /// Advances the state as if [`Rand::next`] was called 2^192 times
+    pub fn long_jump(&mut self) {
+        self.jump_with(&[
+            0x76e1_5d3e_fefd_cbbf,
+            0xc500_4e44_1c52_2fb3,
+            0x7771_0069_854e_e241,
+            0x3910_9bb0_2acb_e635,
+        ]);
+    }
+
+    // https://prng.di.unimi.it/xoshiro256plusplus.c
+    fn jump_with(&mut self, polynomial: &[u64; 4]) {
+        let mut s = [0; 4];
+        for word in polynomial {
+            for bit in 0..64 {
+                if word & (1 << bit) != 0 {
+                    for (s, state) in s.iter_mut().zip(self.s) {
+                        *s ^= state;
+                    }
+                }
+                self.next();
+            }
+        }
+        self.s = s;
+    }
 }
 
 /// Xorshift64 PRNG
@@ -738,6 +804,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_xoshiro256pp_streams() {
+        // Golden values from the reference implementation's `jump()`, seeded like `with_seed(0)`
+        let golden: [u64; 3] = [0x2107d23f5380538b, 0x860c46fba09246f0, 0xe824e1ac3bb3b014];
+        let mut stream = Xoshiro256PlusPlusRand::with_stream(0, 0, 1);
+        for v in golden {
+            assert_eq!(v, stream.next());
+        }
+
+        let mut rand = Xoshiro256PlusPlusRand::with_seed(0);
+        rand.set_stream(0, 0, 1);
+        for v in golden {
+            assert_eq!(v, rand.next());
+        }
+
+        let seeds = [(1337, 0, 0), (1337, 0, 1), (1337, 1, 0), (1338, 0, 0)].map(
+            |(master_seed, stream, substream)| {
+                Xoshiro256PlusPlusRand::with_stream(master_seed, stream, substream).next()
+            },
+        );
+        for (i, seed) in seeds.iter().enumerate() {
+            assert!(!seeds[i + 1..].contains(seed));
+        }
+    }
+
     #[test]
     fn test_sfc64_golden() {
         // https://github.com/ziglang/zig/blob/130fb5cb0fb9039e79450c9db58d6590c5bee3b3/lib/std/Random/Sfc64.zig#L73-L99
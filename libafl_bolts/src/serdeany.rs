@@ -2,12 +2,13 @@
 
 #[cfg(feature = "stable_anymap")]
 use alloc::borrow::Cow;
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 #[cfg(not(feature = "stable_anymap"))]
 use core::any::TypeId;
-#[cfg(feature = "stable_anymap")]
-use core::any::type_name;
-use core::{any::Any, fmt::Debug};
+use core::{
+    any::{Any, type_name},
+    fmt::Debug,
+};
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeSeed};
 pub use serdeany_registry::*;
@@ -52,6 +53,69 @@ fn type_repr<T>() -> &'static str {
     type_name::<T>()
 }
 
+/// Marker mixed into the [`TypeRepr`] of versioned types on the wire, see [`RegistryBuilder::register_versioned`]
+#[cfg(not(feature = "stable_anymap"))]
+const VERSIONED_REPR_MARK: TypeRepr = 0x7665_7273_696f_6e65_645f_7365_7264_6579;
+
+/// Suffix appended to the [`TypeRepr`] of versioned types on the wire, see [`RegistryBuilder::register_versioned`]
+#[cfg(feature = "stable_anymap")]
+const VERSIONED_REPR_SUFFIX: &str = "#versioned";
+
+/// Magic number at the start of the payload of versioned and opaque entries.
+///
+/// Without `stable_anymap`, any [`TypeRepr`] can be turned into a versioned one, so the repr alone can't tell
+/// whether an unknown entry carries a version and its bytes, or is an unversioned type this binary doesn't know.
+const VERSIONED_ENVELOPE_MAGIC: u64 = 0x4c41_464c_5645_5253;
+
+/// The [`TypeRepr`] a versioned type is serialized with
+#[cfg(not(feature = "stable_anymap"))]
+fn versioned_repr(repr: &TypeRepr) -> TypeRepr {
+    repr ^ VERSIONED_REPR_MARK
+}
+
+/// The [`TypeRepr`] of a versioned type, given the [`TypeRepr`] it was serialized with.
+/// Whether the entry is versioned at all is only known from its [`VERSIONED_ENVELOPE_MAGIC`].
+#[cfg(not(feature = "stable_anymap"))]
+#[expect(clippy::unnecessary_wraps)]
+fn unversioned_repr(repr: &TypeRepr) -> Option<TypeRepr> {
+    Some(repr ^ VERSIONED_REPR_MARK)
+}
+
+/// The [`TypeRepr`] a versioned type is serialized with
+#[cfg(feature = "stable_anymap")]
+fn versioned_repr(repr: &TypeRepr) -> TypeRepr {
+    Cow::Owned(format!("{repr}{VERSIONED_REPR_SUFFIX}"))
+}
+
+/// The [`TypeRepr`] of a versioned type, given the [`TypeRepr`] it was serialized with
+#[cfg(feature = "stable_anymap")]
+fn unversioned_repr(repr: &TypeRepr) -> Option<TypeRepr> {
+    repr.strip_suffix(VERSIONED_REPR_SUFFIX)
+        .map(|repr| Cow::Owned(repr.into()))
+}
+
+/// The [`TypeRepr`] the given [`SerdeAny`] is stored with in a [`SerdeAnyMap`].
+#[cfg_attr(
+    not(feature = "stable_anymap"),
+    expect(
+        clippy::clone_on_copy,
+        reason = "TypeRepr is only Copy without stable_anymap"
+    )
+)]
+fn dyn_type_repr(value: &dyn SerdeAny) -> TypeRepr {
+    if let Some(opaque) = value.as_any().downcast_ref::<OpaqueSerdeAny>() {
+        return opaque.type_repr().clone();
+    }
+    #[cfg(not(feature = "stable_anymap"))]
+    {
+        unpack_type_id(value.as_any().type_id())
+    }
+    #[cfg(feature = "stable_anymap")]
+    {
+        Cow::Borrowed(value.type_name())
+    }
+}
+
 /// A (de)serializable Any trait
 pub trait SerdeAny: Any + erased_serde::Serialize + Debug {
     /// Returns this type as [`Any`] trait.
@@ -81,6 +145,66 @@ where
     }
 }
 
+/// An entry of a versioned [`SerdeAny`] type that is not registered in this binary.
+///
+/// These are only created after [`RegistryBuilder::keep_unknown_as_opaque`] was enabled.
+/// They keep the serialized bytes as they are, so that they survive a round trip through
+/// an older or newer fuzzer binary, and can be decoded again once the type is registered.
+#[derive(Debug, Clone)]
+pub struct OpaqueSerdeAny {
+    type_repr: TypeRepr,
+    version: u32,
+    bytes: Vec<u8>,
+}
+
+impl OpaqueSerdeAny {
+    /// The [`TypeRepr`] this entry was serialized with
+    #[must_use]
+    pub fn type_repr(&self) -> &TypeRepr {
+        &self.type_repr
+    }
+
+    /// The version of the type this entry was serialized with
+    #[must_use]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The serialized bytes of this entry
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+// Never serialized through this impl, see the [`Serialize`] impl of `dyn SerdeAny`.
+impl Serialize for OpaqueSerdeAny {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        (VERSIONED_ENVELOPE_MAGIC, self.version, &self.bytes).serialize(serializer)
+    }
+}
+
+impl SerdeAny for OpaqueSerdeAny {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn as_any_boxed(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+}
+
 /// Callback for [`SerdeAny`] deserialization.
 pub type DeserializeCallback<B> =
     fn(&mut dyn erased_serde::Deserializer) -> Result<Box<B>, erased_serde::Error>;
@@ -114,9 +238,12 @@ where
 /// Each element needs to be registered so that it can be deserialized.
 pub mod serdeany_registry {
 
+    #[cfg(feature = "stable_anymap")]
+    use alloc::borrow::Cow;
     use alloc::{
         boxed::Box,
         string::{String, ToString},
+        vec::Vec,
     };
     use core::{any::TypeId, fmt, hash::BuildHasherDefault};
 
@@ -124,13 +251,13 @@ pub mod serdeany_registry {
         HashMap,
         hash_map::{Values, ValuesMut},
     };
-    use serde::{Deserialize, Serialize, de};
+    use serde::{Deserialize, Deserializer, Serialize, de};
 
     use crate::{
         Error,
         serdeany::{
-            DeserializeCallback, DeserializeCallbackSeed, SerdeAny, TypeRepr, type_repr,
-            type_repr_owned,
+            DeserializeCallback, DeserializeCallbackSeed, OpaqueSerdeAny, SerdeAny, TypeRepr,
+            VERSIONED_ENVELOPE_MAGIC, dyn_type_repr, type_repr, type_repr_owned, unversioned_repr,
         },
     };
 
@@ -138,6 +265,17 @@ pub mod serdeany_registry {
     /// We store the [`TypeId`] to assert we don't have duplicate types in the case of the `stable_anymap` feature.
     type DeserializeCallbackMap = HashMap<TypeRepr, (DeserializeCallback<dyn SerdeAny>, TypeId)>;
 
+    /// Callback migrating a version of a [`SerdeAny`] type to the next one, returning it serialized,
+    /// see [`RegistryBuilder::register_migration`].
+    pub type MigrationCallback =
+        Box<dyn Fn(&mut dyn erased_serde::Deserializer) -> Result<Vec<u8>, erased_serde::Error>>;
+
+    /// The current version of a versioned [`SerdeAny`] type, and the migrations from its older versions.
+    struct VersionInfo {
+        version: u32,
+        migrations: HashMap<u32, MigrationCallback>,
+    }
+
     /// Visitor object used internally for the [`crate::serdeany::SerdeAny`] registry.
     #[derive(Debug)]
     pub struct BoxDynVisitor {}
@@ -156,24 +294,95 @@ pub mod serdeany_registry {
             let id: TypeRepr = visitor.next_element()?.unwrap();
 
             let registry = &raw const REGISTRY;
-            let cb = unsafe {
-                (*registry)
-                    .deserializers
-                    .as_ref()
-                    .ok_or_else(||
-                        de::Error::custom(super::ERR_EMPTY_TYPES_REGISTER))?
-                    .get(&id)
-                    .ok_or_else(|| de::Error::custom(format_args!("Cannot deserialize the unregistered type with id {id}. Enable the `serde_autoreg` feature in libafl_bolts or register all requried types manually.")))?
-                    .0
+            let registry = unsafe { &*registry };
+            let deserializers = registry
+                .deserializers
+                .as_ref()
+                .ok_or_else(|| de::Error::custom(super::ERR_EMPTY_TYPES_REGISTER))?;
+
+            let repr = registry.resolve_alias(&id);
+            if let Some((cb, _)) = deserializers.get(repr) {
+                // Entries without a version were written before the type was versioned, i.e., as version 0.
+                let obj: Self::Value = match registry.migration(repr, 0) {
+                    Some(migration) => {
+                        let bytes = visitor
+                            .next_element_seed(MigrationSeed { migration })?
+                            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                        registry
+                            .migrate(repr, *cb, 1, &bytes)
+                            .map_err(de::Error::custom)?
+                    }
+                    None => visitor
+                        .next_element_seed(DeserializeCallbackSeed::<
+                            dyn crate::serdeany::SerdeAny,
+                        > {
+                            cb: *cb,
+                        })?
+                        .ok_or_else(|| de::Error::invalid_length(1, &self))?,
+                };
+                return Ok(obj);
+            }
+
+            let versioned = unversioned_repr(&id).and_then(|repr| {
+                let repr = registry.resolve_alias(&repr);
+                deserializers
+                    .get_key_value(repr)
+                    .map(|(repr, (cb, _))| (repr, *cb))
+            });
+            let unregistered = || {
+                de::Error::custom(format_args!(
+                    "Cannot deserialize the unregistered type with id {id}. Enable the `serde_autoreg` feature in libafl_bolts or register all requried types manually."
+                ))
+            };
+            if versioned.is_none() && !registry.keep_unknown {
+                return Err(unregistered());
+            }
+
+            // An unknown type without the envelope can't be skipped, so it fails here, instead of being misparsed.
+            let (magic, version, bytes): (u64, u32, Vec<u8>) = visitor
+                .next_element()
+                .map_err(|_| unregistered())?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            if magic != VERSIONED_ENVELOPE_MAGIC {
+                return Err(unregistered());
+            }
+            let Some((repr, cb)) = versioned else {
+                return Ok(Box::new(OpaqueSerdeAny {
+                    type_repr: id,
+                    version,
+                    bytes,
+                }));
             };
-            let seed = DeserializeCallbackSeed::<dyn crate::serdeany::SerdeAny> { cb };
-            let obj: Self::Value = visitor.next_element_seed(seed)?.unwrap();
-            Ok(obj)
+
+            registry
+                .migrate(repr, cb, version, &bytes)
+                .map_err(de::Error::custom)
+        }
+    }
+
+    /// Seed deserializing an older version of a type through a [`MigrationCallback`]
+    struct MigrationSeed<'a> {
+        migration: &'a MigrationCallback,
+    }
+
+    impl<'de> de::DeserializeSeed<'de> for MigrationSeed<'_> {
+        type Value = Vec<u8>;
+
+        fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let mut erased = <dyn erased_serde::Deserializer>::erase(deserializer);
+            (self.migration)(&mut erased).map_err(de::Error::custom)
         }
     }
 
     struct Registry {
         deserializers: Option<DeserializeCallbackMap>,
+        versions: Option<HashMap<TypeRepr, VersionInfo>>,
+        #[cfg(feature = "stable_anymap")]
+        aliases: Option<HashMap<TypeRepr, TypeRepr>>,
+        keep_unknown: bool,
         finalized: bool,
     }
 
@@ -205,13 +414,156 @@ pub mod serdeany_registry {
             );
         }
 
+        pub fn register_versioned<T>(&mut self, version: u32)
+        where
+            T: SerdeAny + Serialize + de::DeserializeOwned,
+        {
+            self.register::<T>();
+            self.versions
+                .get_or_insert_with(HashMap::default)
+                .entry(type_repr_owned::<T>())
+                .or_insert_with(|| VersionInfo {
+                    version,
+                    migrations: HashMap::default(),
+                })
+                .version = version;
+        }
+
+        pub fn register_migration<T>(&mut self, from_version: u32, migration: MigrationCallback)
+        where
+            T: SerdeAny,
+        {
+            assert!(!self.finalized, "Registry is already finalized!");
+
+            let info = self
+                .versions
+                .as_mut()
+                .and_then(|versions| versions.get_mut(&type_repr_owned::<T>()))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Type {} needs to be registered with RegistryBuilder::register_versioned() before adding migrations.",
+                        core::any::type_name::<T>()
+                    )
+                });
+            assert!(
+                from_version < info.version,
+                "Can only migrate type {} from versions older than its current version {}.",
+                core::any::type_name::<T>(),
+                info.version
+            );
+            info.migrations.insert(from_version, migration);
+        }
+
+        #[cfg(feature = "stable_anymap")]
+        pub fn register_alias<T>(&mut self, old_type_name: &'static str)
+        where
+            T: SerdeAny,
+        {
+            assert!(!self.finalized, "Registry is already finalized!");
+
+            self.aliases
+                .get_or_insert_with(HashMap::default)
+                .insert(Cow::Borrowed(old_type_name), type_repr_owned::<T>());
+        }
+
+        /// The [`TypeRepr`] a type formerly serialized as `repr` is registered as now
+        #[cfg(feature = "stable_anymap")]
+        fn resolve_alias<'a>(&'a self, repr: &'a TypeRepr) -> &'a TypeRepr {
+            self.aliases
+                .as_ref()
+                .and_then(|aliases| aliases.get(repr))
+                .unwrap_or(repr)
+        }
+
+        /// Without `stable_anymap`, types are serialized by their [`TypeId`], which has no stable former name.
+        #[cfg(not(feature = "stable_anymap"))]
+        #[expect(clippy::unused_self)]
+        fn resolve_alias<'a>(&'a self, repr: &'a TypeRepr) -> &'a TypeRepr {
+            repr
+        }
+
+        fn migration(&self, repr: &TypeRepr, from_version: u32) -> Option<&MigrationCallback> {
+            self.versions
+                .as_ref()?
+                .get(repr)?
+                .migrations
+                .get(&from_version)
+        }
+
+        /// Deserializes the `bytes` of the type `repr`, serialized with `version`,
+        /// running the migrations from each version to the next up to the current version.
+        fn migrate(
+            &self,
+            repr: &TypeRepr,
+            cb: DeserializeCallback<dyn SerdeAny>,
+            mut version: u32,
+            bytes: &[u8],
+        ) -> Result<Box<dyn SerdeAny>, erased_serde::Error> {
+            let current = self
+                .versions
+                .as_ref()
+                .and_then(|versions| versions.get(repr))
+                .map_or(0, |info| info.version);
+            if version > current {
+                return Err(de::Error::custom(format_args!(
+                    "Type {repr} was serialized with version {version}, newer than the current version {current}."
+                )));
+            }
+
+            let mut migrated = Vec::new();
+            while version < current {
+                let migration = self.migration(repr, version).ok_or_else(|| {
+                    de::Error::custom(format_args!(
+                        "No migration registered for type {repr} from version {version} to version {}.",
+                        version + 1
+                    ))
+                })?;
+                let input = if migrated.is_empty() {
+                    bytes
+                } else {
+                    &migrated
+                };
+                let mut deserializer = postcard::Deserializer::from_bytes(input);
+                migrated = migration(&mut <dyn erased_serde::Deserializer>::erase(
+                    &mut deserializer,
+                ))?;
+                version += 1;
+            }
+
+            let input = if migrated.is_empty() {
+                bytes
+            } else {
+                &migrated
+            };
+            let mut deserializer = postcard::Deserializer::from_bytes(input);
+            cb(&mut <dyn erased_serde::Deserializer>::erase(
+                &mut deserializer,
+            ))
+        }
+
         pub fn finalize(&mut self) {
             self.finalized = true;
         }
     }
 
+    /// The version `repr` is registered with, if it is a versioned type
+    pub(super) fn registered_version(repr: &TypeRepr) -> Option<u32> {
+        let registry = &raw const REGISTRY;
+        unsafe {
+            (*registry)
+                .versions
+                .as_ref()
+                .and_then(|versions| versions.get(repr))
+                .map(|info| info.version)
+        }
+    }
+
     static mut REGISTRY: Registry = Registry {
         deserializers: None,
+        versions: None,
+        #[cfg(feature = "stable_anymap")]
+        aliases: None,
+        keep_unknown: false,
         finalized: false,
     };
 
@@ -237,6 +589,85 @@ pub mod serdeany_registry {
             }
         }
 
+        /// Register a given struct type for trait object (de)serialization, tagging it with a `version`.
+        ///
+        /// Versioned types are serialized together with their version,
+        /// so that their layout may change in later fuzzer binaries:
+        /// entries written with an older version are converted by the migrations added through
+        /// [`RegistryBuilder::register_migration`].
+        /// Entries of a type serialized before it became versioned count as version `0`.
+        ///
+        /// # Safety
+        /// This may never be called concurrently or at the same time as `finalize`.
+        /// It dereferences the `REGISTRY` hashmap and adds the given type to it.
+        pub unsafe fn register_versioned<T>(version: u32)
+        where
+            T: crate::serdeany::SerdeAny + Serialize + serde::de::DeserializeOwned,
+        {
+            let registry = &raw mut REGISTRY;
+            unsafe {
+                (*registry).register_versioned::<T>(version);
+            }
+        }
+
+        /// Register a migration for the versioned type `T`, converting entries serialized as
+        /// the `Old` layout with version `from_version` to the `New` layout of version `from_version + 1`.
+        ///
+        /// Older entries run through the migrations of each version in turn,
+        /// so the last migration, to the current version, has `New = T`.
+        ///
+        /// # Safety
+        /// This may never be called concurrently or at the same time as `finalize`.
+        /// It dereferences the `REGISTRY` hashmap and adds the given migration to it.
+        pub unsafe fn register_migration<T, Old, New>(from_version: u32, migrate: fn(Old) -> New)
+        where
+            T: crate::serdeany::SerdeAny,
+            Old: serde::de::DeserializeOwned + 'static,
+            New: Serialize + 'static,
+        {
+            let migration: MigrationCallback = Box::new(move |de| {
+                let new = migrate(erased_serde::deserialize::<Old>(de)?);
+                postcard::to_allocvec(&new).map_err(serde::ser::Error::custom)
+            });
+            let registry = &raw mut REGISTRY;
+            unsafe {
+                (*registry).register_migration::<T>(from_version, migration);
+            }
+        }
+
+        /// Register `old_type_name` as a former type name of `T`, e.g., before it was renamed or moved,
+        /// so that entries serialized with the old name are deserialized as `T`.
+        ///
+        /// Only available with the `stable_anymap` feature: otherwise, types are serialized by their [`TypeId`],
+        /// which is not stable across builds, so there is no old name to refer to.
+        ///
+        /// # Safety
+        /// This may never be called concurrently or at the same time as `finalize`.
+        /// It dereferences the `REGISTRY` hashmap and adds the given alias to it.
+        #[cfg(feature = "stable_anymap")]
+        pub unsafe fn register_alias<T>(old_type_name: &'static str)
+        where
+            T: crate::serdeany::SerdeAny,
+        {
+            let registry = &raw mut REGISTRY;
+            unsafe {
+                (*registry).register_alias::<T>(old_type_name);
+            }
+        }
+
+        /// Keep entries of versioned types that are not registered in this binary as [`OpaqueSerdeAny`],
+        /// instead of failing to deserialize the whole map.
+        /// Opaque entries are serialized again exactly as they were read.
+        ///
+        /// # Safety
+        /// This may never be called concurrently or during deserialization.
+        pub unsafe fn keep_unknown_as_opaque(keep: bool) {
+            let registry = &raw mut REGISTRY;
+            unsafe {
+                (*registry).keep_unknown = keep;
+            }
+        }
+
         /// Finalize the registry, no more registrations are allowed after this call
         ///
         /// # Safety
@@ -252,12 +683,35 @@ pub mod serdeany_registry {
 
     /// A (de)serializable anymap containing (de)serializable trait objects registered
     /// in the registry
-    #[expect(clippy::unsafe_derive_deserialize)]
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Serialize)]
     pub struct SerdeAnyMap {
         map: HashMap<TypeRepr, Box<dyn SerdeAny>>,
     }
 
+    // Entries are keyed by the type they were deserialized as, which differs from the
+    // serialized key for renamed and opaque types.
+    impl<'de> Deserialize<'de> for SerdeAnyMap {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            #[derive(Deserialize)]
+            #[serde(rename = "SerdeAnyMap")]
+            struct SerializedMap {
+                map: HashMap<TypeRepr, Box<dyn SerdeAny>>,
+            }
+
+            let serialized = SerializedMap::deserialize(deserializer)?;
+            Ok(Self {
+                map: serialized
+                    .map
+                    .into_values()
+                    .map(|value| (dyn_type_repr(value.as_ref()), value))
+                    .collect(),
+            })
+        }
+    }
+
     // Cloning by serializing and deserializing. It ain't fast, but it's honest work.
     // We unwrap postcard, it should not have a reason to fail.
     impl Clone for SerdeAnyMap {
@@ -461,13 +915,35 @@ pub mod serdeany_registry {
     }
 
     /// A serializable [`HashMap`] wrapper for [`crate::serdeany::SerdeAny`] types, addressable by name.
-    #[expect(clippy::unsafe_derive_deserialize)]
     #[expect(unused_qualifications)]
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Serialize)]
     pub struct NamedSerdeAnyMap {
         map: HashMap<TypeRepr, HashMap<String, Box<dyn crate::serdeany::SerdeAny>>>,
     }
 
+    // Entries are keyed by the type they were deserialized as, see [`SerdeAnyMap`].
+    impl<'de> Deserialize<'de> for NamedSerdeAnyMap {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            #[derive(Deserialize)]
+            #[serde(rename = "NamedSerdeAnyMap")]
+            struct SerializedMap {
+                map: HashMap<TypeRepr, HashMap<String, Box<dyn SerdeAny>>>,
+            }
+
+            let serialized = SerializedMap::deserialize(deserializer)?;
+            let mut map: HashMap<TypeRepr, HashMap<String, Box<dyn SerdeAny>>> = HashMap::default();
+            for (name, value) in serialized.map.into_values().flatten() {
+                map.entry(dyn_type_repr(value.as_ref()))
+                    .or_default()
+                    .insert(name, value);
+            }
+            Ok(Self { map })
+        }
+    }
+
     // Cloning by serializing and deserializing. It ain't fast, but it's honest work.
     // We unwrap postcard, it should not have a reason to fail.
     impl Clone for NamedSerdeAnyMap {
@@ -815,7 +1291,7 @@ impl Serialize for dyn crate::serdeany::SerdeAny {
     where
         S: Serializer,
     {
-        use serde::ser::SerializeSeq;
+        use serde::ser::{Error as _, SerializeSeq};
 
         #[cfg(not(feature = "stable_anymap"))]
         let type_id = crate::anymap::unpack_type_id(self.type_id());
@@ -828,8 +1304,23 @@ impl Serialize for dyn crate::serdeany::SerdeAny {
         let type_id = self.type_name();
 
         let mut seq = se.serialize_seq(Some(2))?;
-        seq.serialize_element(type_id)?;
-        seq.serialize_element(&crate::serdeany::Wrap(self))?;
+        if let Some(opaque) = self.as_any().downcast_ref::<OpaqueSerdeAny>() {
+            seq.serialize_element(opaque.type_repr())?;
+            seq.serialize_element(&(
+                crate::serdeany::VERSIONED_ENVELOPE_MAGIC,
+                opaque.version(),
+                opaque.bytes(),
+            ))?;
+        } else if let Some(version) = serdeany_registry::registered_version(&dyn_type_repr(self)) {
+            // Versioned types are serialized as bytes, so they can be kept opaque by binaries that don't know them.
+            let bytes =
+                postcard::to_allocvec(&crate::serdeany::Wrap(self)).map_err(S::Error::custom)?;
+            seq.serialize_element(&versioned_repr(&dyn_type_repr(self)))?;
+            seq.serialize_element(&(crate::serdeany::VERSIONED_ENVELOPE_MAGIC, version, bytes))?;
+        } else {
+            seq.serialize_element(type_id)?;
+            seq.serialize_element(&crate::serdeany::Wrap(self))?;
+        }
         seq.end()
     }
 }
@@ -985,9 +1476,17 @@ macro_rules! impl_serdeany {
 
 #[cfg(test)]
 mod tests {
+    use alloc::{boxed::Box, vec::Vec};
+
+    use hashbrown::HashMap;
     use serde::{Deserialize, Serialize};
+    #[cfg(feature = "std")]
+    use serial_test::serial;
 
-    use crate::serdeany::RegistryBuilder;
+    use crate::serdeany::{
+        OpaqueSerdeAny, RegistryBuilder, SerdeAny, SerdeAnyMap, TypeRepr, type_repr_owned,
+        versioned_repr,
+    };
 
     #[derive(Debug, Serialize, Deserialize)]
     struct MyType(u32);
@@ -1002,6 +1501,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg_attr(feature = "std", serial)]
     fn test_deserialize_serialize() {
         unsafe {
             RegistryBuilder::register::<MyType>();
@@ -1017,4 +1517,170 @@ mod tests {
         );
         assert!(postcard::from_bytes::<inner::MyType>(&serialized).is_err());
     }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct VersionedType {
+        old: u32,
+        new: u32,
+    }
+    impl_serdeany!(VersionedType);
+
+    /// A type that is never registered
+    struct UnknownType;
+
+    /// Mirrors the layout of a serialized [`SerdeAnyMap`]
+    #[derive(Serialize)]
+    struct SerializedMap {
+        map: HashMap<TypeRepr, Box<dyn SerdeAny>>,
+    }
+
+    #[cfg_attr(
+        not(feature = "stable_anymap"),
+        expect(
+            clippy::clone_on_copy,
+            reason = "TypeRepr is only Copy without stable_anymap"
+        )
+    )]
+    fn serialized_opaque(type_repr: TypeRepr, version: u32, bytes: Vec<u8>) -> Vec<u8> {
+        let mut map: HashMap<TypeRepr, Box<dyn SerdeAny>> = HashMap::default();
+        map.insert(
+            type_repr.clone(),
+            Box::new(OpaqueSerdeAny {
+                type_repr,
+                version,
+                bytes,
+            }),
+        );
+        postcard::to_allocvec(&SerializedMap { map }).unwrap()
+    }
+
+    #[test]
+    #[cfg_attr(feature = "std", serial)]
+    fn test_versioned_migration() {
+        unsafe {
+            RegistryBuilder::register_versioned::<VersionedType>(1);
+            RegistryBuilder::register_migration::<VersionedType, u32, _>(0, |old| VersionedType {
+                old,
+                new: 0,
+            });
+        }
+
+        let mut map = SerdeAnyMap::new();
+        map.insert(VersionedType { old: 1, new: 2 });
+        let serialized = postcard::to_allocvec(&map).unwrap();
+        let map: SerdeAnyMap = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(
+            map.get::<VersionedType>(),
+            Some(&VersionedType { old: 1, new: 2 })
+        );
+
+        let serialized = serialized_opaque(
+            versioned_repr(&type_repr_owned::<VersionedType>()),
+            0,
+            postcard::to_allocvec(&42_u32).unwrap(),
+        );
+        let map: SerdeAnyMap = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(
+            map.get::<VersionedType>(),
+            Some(&VersionedType { old: 42, new: 0 })
+        );
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct ChainedType {
+        name: u32,
+        count: u64,
+    }
+    impl_serdeany!(ChainedType);
+
+    #[test]
+    #[cfg_attr(feature = "std", serial)]
+    fn test_chained_migration() {
+        unsafe {
+            RegistryBuilder::register_versioned::<ChainedType>(2);
+            RegistryBuilder::register_migration::<ChainedType, u32, _>(0, |name| (name, 1_u32));
+            RegistryBuilder::register_migration::<ChainedType, (u32, u32), _>(
+                1,
+                |(name, count)| ChainedType {
+                    name,
+                    count: u64::from(count) * 2,
+                },
+            );
+        }
+
+        let serialized = serialized_opaque(
+            versioned_repr(&type_repr_owned::<ChainedType>()),
+            0,
+            postcard::to_allocvec(&7_u32).unwrap(),
+        );
+        let map: SerdeAnyMap = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(
+            map.get::<ChainedType>(),
+            Some(&ChainedType { name: 7, count: 2 })
+        );
+
+        let serialized = serialized_opaque(
+            versioned_repr(&type_repr_owned::<ChainedType>()),
+            1,
+            postcard::to_allocvec(&(7_u32, 3_u32)).unwrap(),
+        );
+        let map: SerdeAnyMap = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(
+            map.get::<ChainedType>(),
+            Some(&ChainedType { name: 7, count: 6 })
+        );
+
+        // Newer than the current version
+        let serialized = serialized_opaque(
+            versioned_repr(&type_repr_owned::<ChainedType>()),
+            3,
+            Vec::new(),
+        );
+        assert!(postcard::from_bytes::<SerdeAnyMap>(&serialized).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(feature = "std", serial)]
+    fn test_unknown_as_opaque() {
+        let serialized = serialized_opaque(
+            versioned_repr(&type_repr_owned::<UnknownType>()),
+            3,
+            Vec::from([1, 2, 3]),
+        );
+        assert!(postcard::from_bytes::<SerdeAnyMap>(&serialized).is_err());
+
+        unsafe {
+            RegistryBuilder::keep_unknown_as_opaque(true);
+        }
+        let map: SerdeAnyMap = postcard::from_bytes(&serialized).unwrap();
+        unsafe {
+            RegistryBuilder::keep_unknown_as_opaque(false);
+        }
+        assert_eq!(map.len(), 1);
+        assert_eq!(postcard::to_allocvec(&map).unwrap(), serialized);
+
+        // An unregistered, unversioned entry has no envelope, so it can't be kept opaque
+        let mut map: HashMap<TypeRepr, Box<dyn SerdeAny>> = HashMap::default();
+        map.insert(type_repr_owned::<UnknownType>(), Box::new(MyType(3)));
+        unsafe {
+            RegistryBuilder::register::<MyType>();
+        }
+        let mut serialized = postcard::to_allocvec(&SerializedMap { map }).unwrap();
+        // Replace the type id of `MyType` with the one of the unregistered type
+        let known = postcard::to_allocvec(&type_repr_owned::<MyType>()).unwrap();
+        let unknown = postcard::to_allocvec(&type_repr_owned::<UnknownType>()).unwrap();
+        let start = serialized
+            .windows(known.len())
+            .rposition(|window| window == known)
+            .unwrap();
+        serialized.splice(start..start + known.len(), unknown);
+        unsafe {
+            RegistryBuilder::keep_unknown_as_opaque(true);
+        }
+        let result = postcard::from_bytes::<SerdeAnyMap>(&serialized);
+        unsafe {
+            RegistryBuilder::keep_unknown_as_opaque(false);
+        }
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,248 @@
+//! The formats used to serialize events and state.
+//! `postcard` is always available and the default, `bincode` can be enabled through the feature of the same name.
+//! `bincode` uses fixed-size integers instead of varints, which can be faster for states dominated by integers.
+//! Which format is faster depends on the state, compare them with the `state_restore` benchmark in `utils/libafl_benches`.
+//!
+//! Further formats can be plugged in through the [`SerdeBackend`] trait.
+//!
+//! Zero-copy formats, such as `rkyv`, are out of scope: they need their own derives on every serialized type,
+//! and cannot be used for the `serde`-based state and metadata maps (see [`crate::serdeany`]).
+//! Instead, the `StateRestorer` serializes states in place into its shared map, if the backend supports it, like `postcard`,
+//! and deserializes them straight from the map. Restoring still has to build the owned state, e.g., allocate every input
+//! of the corpus, which no `serde` format avoids, so restores take about as long with either format.
+//! To keep large corpora from being copied into each client, share their inputs with `libafl`'s `SharedMmapCorpus` instead.
+
+use alloc::{format, vec::Vec};
+use core::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// A serde data format.
+/// The id of each format ends up in shared maps and on the wire, and must never change.
+#[repr(u8)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SerializationFormat {
+    /// `postcard`, compact varint encoding. Always available.
+    #[default]
+    Postcard = 0,
+    /// `bincode`, fixed-size integers, needs the `bincode` feature.
+    Bincode = 1,
+}
+
+impl SerializationFormat {
+    /// The id of this format on the wire
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Get the format for an id read from the wire, if it is known
+    #[must_use]
+    pub const fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Postcard),
+            1 => Some(Self::Bincode),
+            _ => None,
+        }
+    }
+
+    /// The human-readable name of this format
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Postcard => "postcard",
+            Self::Bincode => "bincode",
+        }
+    }
+
+    /// If this build can serialize and deserialize this format
+    #[must_use]
+    pub const fn is_available(self) -> bool {
+        match self {
+            Self::Postcard => true,
+            Self::Bincode => cfg!(feature = "bincode"),
+        }
+    }
+
+    /// The error for formats that are not compiled in
+    #[cfg_attr(feature = "bincode", expect(dead_code))]
+    fn unavailable(self) -> Error {
+        Error::illegal_argument(format!(
+            "Serialization format {self} is not available, enable the `{self}` feature"
+        ))
+    }
+
+    /// Serializes `value` to a new [`Vec`]
+    pub fn serialize<T>(self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self {
+            Self::Postcard => PostcardBackend::serialize(value),
+            #[cfg(feature = "bincode")]
+            Self::Bincode => BincodeBackend::serialize(value),
+            #[cfg(not(feature = "bincode"))]
+            Self::Bincode => Err(self.unavailable()),
+        }
+    }
+
+    /// Serializes `value` into `buf` in place, see [`SerdeBackend::serialize_into`]
+    pub fn serialize_into<T>(self, value: &T, buf: &mut [u8]) -> Result<Option<usize>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self {
+            Self::Postcard => PostcardBackend::serialize_into(value, buf),
+            #[cfg(feature = "bincode")]
+            Self::Bincode => BincodeBackend::serialize_into(value, buf),
+            #[cfg(not(feature = "bincode"))]
+            Self::Bincode => Err(self.unavailable()),
+        }
+    }
+
+    /// Deserializes a `T` from `bytes`
+    pub fn deserialize<'a, T>(self, bytes: &'a [u8]) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+    {
+        match self {
+            Self::Postcard => PostcardBackend::deserialize(bytes),
+            #[cfg(feature = "bincode")]
+            Self::Bincode => BincodeBackend::deserialize(bytes),
+            #[cfg(not(feature = "bincode"))]
+            Self::Bincode => Err(self.unavailable()),
+        }
+    }
+}
+
+/// A `serde` data format backing a [`SerializationFormat`].
+///
+/// To plug in another format, implement this trait for it,
+/// and add a variant with a new id to [`SerializationFormat`] dispatching to it.
+pub trait SerdeBackend {
+    /// The [`SerializationFormat`] this backend implements
+    const FORMAT: SerializationFormat;
+
+    /// Serializes `value` to a new [`Vec`]
+    fn serialize<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized;
+
+    /// Serializes `value` into `buf`, returning the number of bytes written, or `None` if `value` does not fit.
+    /// Backends that can write to a slice directly should override this, to skip the intermediate [`Vec`].
+    fn serialize_into<T>(value: &T, buf: &mut [u8]) -> Result<Option<usize>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let serialized = Self::serialize(value)?;
+        Ok(buf.get_mut(..serialized.len()).map(|dst| {
+            dst.copy_from_slice(&serialized);
+            serialized.len()
+        }))
+    }
+
+    /// Deserializes a `T` from `bytes`, borrowing from them where `T` allows it
+    fn deserialize<'a, T>(bytes: &'a [u8]) -> Result<T, Error>
+    where
+        T: Deserialize<'a>;
+}
+
+/// The [`SerdeBackend`] of [`SerializationFormat::Postcard`]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PostcardBackend;
+
+impl SerdeBackend for PostcardBackend {
+    const FORMAT: SerializationFormat = SerializationFormat::Postcard;
+
+    fn serialize<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(postcard::to_allocvec(value)?)
+    }
+
+    fn serialize_into<T>(value: &T, buf: &mut [u8]) -> Result<Option<usize>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match postcard::to_slice(value, buf) {
+            Ok(written) => Ok(Some(written.len())),
+            Err(postcard::Error::SerializeBufferFull) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn deserialize<'a, T>(bytes: &'a [u8]) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+    {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+}
+
+/// The [`SerdeBackend`] of [`SerializationFormat::Bincode`]
+#[cfg(feature = "bincode")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct BincodeBackend;
+
+#[cfg(feature = "bincode")]
+impl SerdeBackend for BincodeBackend {
+    const FORMAT: SerializationFormat = SerializationFormat::Bincode;
+
+    fn serialize<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        bincode::serialize(value).map_err(|e| Error::serialize(format!("{e}")))
+    }
+
+    fn deserialize<'a, T>(bytes: &'a [u8]) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+    {
+        bincode::deserialize(bytes).map_err(|e| Error::serialize(format!("{e}")))
+    }
+}
+
+impl Display for SerializationFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec, vec::Vec};
+
+    use super::SerializationFormat;
+
+    #[test]
+    fn test_serialization_formats() {
+        let value = (vec![1_u8, 2, 3], String::from("libafl"), 0x1337_u64);
+        for format in [SerializationFormat::Postcard, SerializationFormat::Bincode] {
+            assert_eq!(SerializationFormat::from_id(format.id()), Some(format));
+            if !format.is_available() {
+                assert!(format.serialize(&value).is_err());
+                continue;
+            }
+            let serialized = format.serialize(&value).unwrap();
+            let deserialized: (Vec<u8>, String, u64) = format.deserialize(&serialized).unwrap();
+            assert_eq!(deserialized, value);
+
+            let mut buf = vec![0; serialized.len()];
+            assert_eq!(
+                format.serialize_into(&value, &mut buf).unwrap(),
+                Some(serialized.len())
+            );
+            assert_eq!(buf, serialized);
+            assert_eq!(
+                format
+                    .serialize_into(&value, &mut buf[..serialized.len() - 1])
+                    .unwrap(),
+                None
+            );
+        }
+    }
+}
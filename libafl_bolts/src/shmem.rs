@@ -674,7 +674,6 @@ pub mod unix_shmem {
 
     #[cfg(all(unix, feature = "std", not(target_os = "android")))]
     mod default {
-        #[cfg(target_vendor = "apple")]
         use alloc::string::ToString;
         use core::{
             ffi::CStr,
@@ -688,6 +687,7 @@ pub mod unix_shmem {
             shmctl, shmdt, shmget,
         };
 
+        use super::manifest::{ShMemKind, register_allocation};
         use crate::{
             Error,
             rands::{Rand, StdRand},
@@ -782,6 +782,10 @@ pub mod unix_shmem {
                     // Apple uses it for served shmem provider, which uses fd
                     // Others will just use filename
 
+                    if let Ok(name) = CStr::from_bytes_until_nul(&filename_path) {
+                        register_allocation(ShMemKind::Mmap, &name.to_string_lossy());
+                    }
+
                     #[cfg(target_vendor = "apple")]
                     let id = ShMemId::from_string(&format!("{shm_fd}"));
                     #[cfg(not(target_vendor = "apple"))]
@@ -1067,6 +1071,8 @@ pub mod unix_shmem {
                         return Err(Error::last_os_error("Failed to map the shared mapping"));
                     }
 
+                    register_allocation(ShMemKind::SysV, &os_id.to_string());
+
                     Ok(Self {
                         id: ShMemId::from_int(os_id),
                         map,
@@ -1162,6 +1168,388 @@ pub mod unix_shmem {
         }
     }
 
+    /// A per-campaign manifest of all shared memory segments allocated by [`MmapShMemProvider`]
+    /// and [`CommonUnixShMemProvider`](default::CommonUnixShMemProvider), to reclaim segments
+    /// leaked by processes that died abruptly.
+    ///
+    /// Allocations are only registered if the [`SHMEM_MANIFEST_ENV`] env variable points to a
+    /// manifest file, see [`ShMemManifest::install`]. Child processes inherit it.
+    #[cfg(not(target_os = "android"))]
+    pub mod manifest {
+        use alloc::{
+            string::{String, ToString},
+            vec::Vec,
+        };
+        use core::{fmt, ptr};
+        use std::{
+            env,
+            ffi::CString,
+            fs::{File, OpenOptions},
+            io::{self, Read, Seek, SeekFrom, Write},
+            os::fd::AsRawFd,
+            path::{Path, PathBuf},
+            process,
+        };
+
+        use crate::Error;
+
+        /// The env variable holding the path of the manifest new allocations are registered in
+        pub const SHMEM_MANIFEST_ENV: &str = "LIBAFL_SHMEM_MANIFEST";
+
+        /// The kind of a shared memory segment in the manifest
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ShMemKind {
+            /// A `SysV` segment from `shmget`, identified by its shmid
+            SysV,
+            /// A POSIX segment from `shm_open`, identified by its name
+            Mmap,
+        }
+
+        impl ShMemKind {
+            fn as_str(self) -> &'static str {
+                match self {
+                    Self::SysV => "sysv",
+                    Self::Mmap => "mmap",
+                }
+            }
+        }
+
+        /// A shared memory segment and the process that allocated it
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct ManifestEntry {
+            /// The kind of segment
+            pub kind: ShMemKind,
+            /// The shmid or name of the segment
+            pub id: String,
+            /// The pid of the process that allocated the segment
+            pub pid: u32,
+        }
+
+        impl ManifestEntry {
+            fn parse(line: &str) -> Option<Self> {
+                let mut fields = line.split_whitespace();
+                let kind = match fields.next()? {
+                    "sysv" => ShMemKind::SysV,
+                    "mmap" => ShMemKind::Mmap,
+                    _ => return None,
+                };
+                let id = fields.next()?.to_string();
+                let pid = fields.next()?.parse().ok()?;
+                Some(Self { kind, id, pid })
+            }
+
+            /// If the process that allocated this segment is still running
+            #[must_use]
+            pub fn is_owner_alive(&self) -> bool {
+                let Ok(pid) = libc::pid_t::try_from(self.pid) else {
+                    return false;
+                };
+                let alive = unsafe { libc::kill(pid, 0) } == 0;
+                alive || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+            }
+
+            /// Removes the segment from the system, unless a process is still attached to it.
+            fn reclaim(&self, dry_run: bool) -> Result<Reclaim, Error> {
+                match self.kind {
+                    ShMemKind::SysV => {
+                        let shmid: libc::c_int = self.id.parse()?;
+                        let mut stat: libc::shmid_ds = unsafe { core::mem::zeroed() };
+                        if unsafe { libc::shmctl(shmid, libc::IPC_STAT, &raw mut stat) } != 0 {
+                            return Ok(Reclaim::Gone);
+                        }
+                        // The kernel reuses shmids, don't remove a segment of another process
+                        if u32::try_from(stat.shm_cpid) != Ok(self.pid) {
+                            return Ok(Reclaim::Gone);
+                        }
+                        if stat.shm_nattch != 0 {
+                            return Ok(Reclaim::InUse);
+                        }
+                        if !dry_run
+                            && unsafe { libc::shmctl(shmid, libc::IPC_RMID, ptr::null_mut()) } != 0
+                        {
+                            return Err(Error::last_os_error(format!(
+                                "Failed to remove SysV segment {shmid}"
+                            )));
+                        }
+                        Ok(Reclaim::Removed)
+                    }
+                    ShMemKind::Mmap => {
+                        let name = CString::new(self.id.as_bytes())
+                            .map_err(|err| Error::illegal_argument(format!("{err}")))?;
+                        // POSIX segments have no attach count, only the owners being dead tells us they are unused
+                        let result = if dry_run {
+                            let fd = unsafe { libc::shm_open(name.as_ptr(), libc::O_RDONLY, 0) };
+                            if fd >= 0 {
+                                unsafe {
+                                    libc::close(fd);
+                                }
+                            }
+                            fd
+                        } else {
+                            unsafe { libc::shm_unlink(name.as_ptr()) }
+                        };
+                        if result >= 0 {
+                            return Ok(Reclaim::Removed);
+                        }
+                        let err = io::Error::last_os_error();
+                        if err.raw_os_error() == Some(libc::ENOENT) {
+                            Ok(Reclaim::Gone)
+                        } else {
+                            Err(Error::os_error(
+                                err,
+                                format!("Failed to unlink shared memory {}", self.id),
+                            ))
+                        }
+                    }
+                }
+            }
+        }
+
+        impl fmt::Display for ManifestEntry {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{} {} {}", self.kind.as_str(), self.id, self.pid)
+            }
+        }
+
+        /// What [`ManifestEntry::reclaim`] did with a segment
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        enum Reclaim {
+            /// The segment was removed
+            Removed,
+            /// The segment no longer exists, or now belongs to someone else
+            Gone,
+            /// A process is still attached to the segment
+            InUse,
+        }
+
+        /// The outcome of [`ShMemManifest::cleanup`]
+        #[derive(Debug, Clone, Default)]
+        pub struct CleanupReport {
+            /// Segments that were removed
+            pub reclaimed: Vec<ManifestEntry>,
+            /// Entries whose segments were already gone
+            pub stale: Vec<ManifestEntry>,
+            /// Segments some process is still attached to, kept in the manifest
+            pub in_use: Vec<ManifestEntry>,
+        }
+
+        /// A manifest file, listing the shared memory segments of a campaign
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct ShMemManifest {
+            path: PathBuf,
+        }
+
+        impl ShMemManifest {
+            /// A manifest stored at the given path. The file is created on the first allocation.
+            pub fn new<P: AsRef<Path>>(path: P) -> Self {
+                Self {
+                    path: path.as_ref().to_path_buf(),
+                }
+            }
+
+            /// The manifest set in [`SHMEM_MANIFEST_ENV`], if any
+            #[must_use]
+            pub fn from_env() -> Option<Self> {
+                env::var_os(SHMEM_MANIFEST_ENV).map(Self::new)
+            }
+
+            /// The path of the manifest file
+            #[must_use]
+            pub fn path(&self) -> &Path {
+                &self.path
+            }
+
+            /// Registers all future allocations of this process and its children in this manifest
+            ///
+            /// # Safety
+            /// Sets an env variable, which is not thread-safe
+            pub unsafe fn install(&self) {
+                unsafe {
+                    env::set_var(SHMEM_MANIFEST_ENV, &self.path);
+                }
+            }
+
+            fn open_locked(&self, options: &OpenOptions) -> io::Result<File> {
+                let file = options.open(&self.path)?;
+                if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(file)
+            }
+
+            fn append(&self, entry: &ManifestEntry) -> Result<(), Error> {
+                let mut file = self.open_locked(OpenOptions::new().create(true).append(true))?;
+                writeln!(file, "{entry}")?;
+                Ok(())
+            }
+
+            /// Registers a segment allocated by the current process
+            pub fn register(&self, kind: ShMemKind, id: &str) -> Result<(), Error> {
+                self.append(&ManifestEntry {
+                    kind,
+                    id: id.to_string(),
+                    pid: process::id(),
+                })
+            }
+
+            /// All entries of this manifest
+            pub fn entries(&self) -> Result<Vec<ManifestEntry>, Error> {
+                let mut content = String::new();
+                match self.open_locked(OpenOptions::new().read(true)) {
+                    Ok(mut file) => {
+                        file.read_to_string(&mut content)?;
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err.into()),
+                }
+                Ok(content.lines().filter_map(ManifestEntry::parse).collect())
+            }
+
+            /// Removes the segments in this manifest and compacts it, keeping only the entries of segments still in use.
+            /// With `dry_run`, nothing is removed or changed.
+            ///
+            /// Segments are shared between all processes of a campaign, so this refuses to clean up
+            /// while any process in the manifest is still running.
+            /// `SysV` segments some other process is still attached to are kept.
+            pub fn cleanup(&self, dry_run: bool) -> Result<CleanupReport, Error> {
+                let mut file = match self.open_locked(OpenOptions::new().read(true).write(true)) {
+                    Ok(file) => file,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        return Ok(CleanupReport::default());
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+                let mut content = String::new();
+                file.read_to_string(&mut content)?;
+
+                let mut entries: Vec<ManifestEntry> = Vec::new();
+                for entry in content.lines().filter_map(ManifestEntry::parse) {
+                    if !entries.contains(&entry) {
+                        entries.push(entry);
+                    }
+                }
+
+                let mut alive: Vec<u32> = entries
+                    .iter()
+                    .filter(|entry| entry.is_owner_alive())
+                    .map(|entry| entry.pid)
+                    .collect();
+                alive.sort_unstable();
+                alive.dedup();
+                if !alive.is_empty() {
+                    return Err(Error::illegal_state(format!(
+                        "Processes {alive:?} in {} are still running, stop the campaign before cleaning up",
+                        self.path.display()
+                    )));
+                }
+
+                let mut report = CleanupReport::default();
+                for entry in entries {
+                    match entry.reclaim(dry_run)? {
+                        Reclaim::Removed => report.reclaimed.push(entry),
+                        Reclaim::Gone => report.stale.push(entry),
+                        Reclaim::InUse => report.in_use.push(entry),
+                    }
+                }
+
+                if !dry_run {
+                    file.set_len(0)?;
+                    file.seek(SeekFrom::Start(0))?;
+                    for entry in &report.in_use {
+                        writeln!(file, "{entry}")?;
+                    }
+                    file.sync_all()?;
+                }
+                Ok(report)
+            }
+        }
+
+        /// Registers a new segment in the manifest set in [`SHMEM_MANIFEST_ENV`], if any.
+        /// Failures only log a warning, as they must not stop the allocation.
+        pub(crate) fn register_allocation(kind: ShMemKind, id: &str) {
+            if let Some(manifest) = ShMemManifest::from_env() {
+                if let Err(err) = manifest.register(kind, id) {
+                    log::warn!(
+                        "Failed to register shared memory {id} in {}: {err}",
+                        manifest.path().display()
+                    );
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use std::{env, fs, process};
+
+            use serial_test::serial;
+
+            use super::{ManifestEntry, Reclaim, ShMemKind, ShMemManifest};
+            use crate::shmem::{MmapShMem, ShMem, unix_shmem::UnixShMem};
+
+            #[test]
+            #[serial]
+            #[cfg_attr(miri, ignore)]
+            fn test_shmem_manifest_cleanup() {
+                let mut child = process::Command::new("true").spawn().unwrap();
+                child.wait().unwrap();
+                let dead_pid = child.id();
+
+                let path = env::temp_dir().join(format!("libafl_manifest_{}", process::id()));
+                let manifest = ShMemManifest::new(&path);
+
+                // Nothing is touched while a process of the campaign is running
+                manifest.register(ShMemKind::Mmap, "/libafl_alive").unwrap();
+                assert!(manifest.cleanup(false).is_err());
+                assert!(manifest.cleanup(true).is_err());
+                assert_eq!(manifest.entries().unwrap().len(), 1);
+                fs::remove_file(&path).unwrap();
+
+                // Leaked by a dead process, as `MmapShMem` never unlinks
+                let name = format!("libafl_mft_{}", process::id());
+                drop(MmapShMem::new(1024, &name).unwrap());
+                let leaked = ManifestEntry {
+                    kind: ShMemKind::Mmap,
+                    id: format!("/{name}"),
+                    pid: dead_pid,
+                };
+                manifest.append(&leaked).unwrap();
+                manifest.append(&leaked).unwrap();
+
+                // A live segment reusing the id of a dead process must not be removed
+                let sysv = UnixShMem::new(1024).unwrap();
+                let sysv_id: i32 = sysv.id().into();
+                manifest
+                    .append(&ManifestEntry {
+                        kind: ShMemKind::SysV,
+                        id: format!("{sysv_id}"),
+                        pid: dead_pid,
+                    })
+                    .unwrap();
+                assert_eq!(manifest.entries().unwrap().len(), 3);
+
+                let report = manifest.cleanup(true).unwrap();
+                assert_eq!(report.reclaimed, core::slice::from_ref(&leaked));
+                assert_eq!(manifest.entries().unwrap().len(), 3);
+
+                let report = manifest.cleanup(false).unwrap();
+                assert_eq!(report.reclaimed, [leaked]);
+                assert_eq!(report.stale.len(), 1);
+                assert!(report.in_use.is_empty());
+                assert!(manifest.entries().unwrap().is_empty());
+
+                // The name is free again, and the SysV segment still exists
+                drop(MmapShMem::new(1024, &name).unwrap());
+                assert_eq!(
+                    report.reclaimed[0].reclaim(false).unwrap(),
+                    Reclaim::Removed
+                );
+                assert!(UnixShMem::shmem_from_id_and_size(sysv.id(), 1024).is_ok());
+
+                fs::remove_file(&path).unwrap();
+            }
+        }
+    }
+
     /// Module containing `ashmem` shared memory support, commonly used on Android.
     #[cfg(all(any(target_os = "linux", target_os = "android"), feature = "std"))]
     pub mod ashmem {
@@ -1556,6 +1944,361 @@ pub mod unix_shmem {
             }
         }
     }
+
+    /// Module containing huge page backed, NUMA-aware `memfd` shared memory support for Linux.
+    #[cfg(all(unix, feature = "std", target_os = "linux"))]
+    pub mod hugepage {
+        use alloc::{ffi::CString, string::ToString, vec, vec::Vec};
+        use core::{
+            ops::{Deref, DerefMut},
+            ptr, slice,
+        };
+        use std::{fs, io, os::fd::IntoRawFd};
+
+        use libc::{
+            HUGETLBFS_MAGIC, MAP_SHARED, PROT_READ, PROT_WRITE, c_ulong, close, fstat, fstatfs,
+            ftruncate, mmap, munmap,
+        };
+        use nix::sys::memfd::{MemFdCreateFlag, memfd_create};
+
+        use crate::{
+            Error,
+            core_affinity::CoreId,
+            shmem::{ShMem, ShMemId, ShMemProvider},
+        };
+
+        /// The huge page size we assume if the system does not report one
+        const DEFAULT_HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+        /// `MPOL_BIND` from `linux/mempolicy.h`
+        const MPOL_BIND: libc::c_long = 2;
+
+        /// The default huge page size of this system, as reported by `/proc/meminfo`
+        #[must_use]
+        pub fn huge_page_size() -> usize {
+            fs::read_to_string("/proc/meminfo")
+                .ok()
+                .and_then(|meminfo| {
+                    meminfo
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Hugepagesize:"))?
+                        .trim()
+                        .strip_suffix("kB")?
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                })
+                .map_or(DEFAULT_HUGE_PAGE_SIZE, |kb| kb * 1024)
+        }
+
+        /// A memfd based shared map, backed by huge pages where available
+        #[derive(Clone, Debug)]
+        pub struct HugePageShMem {
+            id: ShMemId,
+            map: *mut u8,
+            map_size: usize,
+            /// The size of the mapping, which is rounded up to whole huge pages
+            mapping_size: usize,
+            huge_pages: bool,
+        }
+
+        impl HugePageShMem {
+            /// Create a new shared memory mapping of `map_size` bytes.
+            ///
+            /// If `huge_page_size` is set, the mapping is backed by huge pages of this size.
+            /// If the system has no huge pages available, this falls back to regular pages.
+            /// If `numa_node` is set, the pages are bound to this NUMA node.
+            pub fn new(
+                map_size: usize,
+                huge_page_size: Option<usize>,
+                numa_node: Option<usize>,
+            ) -> Result<Self, Error> {
+                let shmem = match huge_page_size {
+                    Some(huge_page_size) => Self::create(
+                        map_size,
+                        map_size.next_multiple_of(huge_page_size),
+                        MemFdCreateFlag::MFD_HUGETLB,
+                    )
+                    .or_else(|err| {
+                        log::info!("Huge pages unavailable, falling back to regular pages: {err}");
+                        Self::create(map_size, map_size, MemFdCreateFlag::empty())
+                    })?,
+                    None => Self::create(map_size, map_size, MemFdCreateFlag::empty())?,
+                };
+                if let Some(numa_node) = numa_node {
+                    shmem.bind_to_numa_node(numa_node);
+                }
+                Ok(shmem)
+            }
+
+            fn create(
+                map_size: usize,
+                mapping_size: usize,
+                flags: MemFdCreateFlag,
+            ) -> Result<Self, Error> {
+                unsafe {
+                    let c_str = CString::new("libAFL").unwrap();
+                    let Ok(fd) = memfd_create(&c_str, flags) else {
+                        return Err(Error::last_os_error("Failed to create memfd".to_string()));
+                    };
+                    let fd = fd.into_raw_fd();
+
+                    #[expect(clippy::cast_possible_wrap)]
+                    if ftruncate(fd, mapping_size as i64) == -1 {
+                        close(fd);
+                        return Err(Error::last_os_error(format!(
+                            "Failed to ftruncate memfd to {mapping_size}"
+                        )));
+                    }
+                    let map = mmap(
+                        ptr::null_mut(),
+                        mapping_size,
+                        PROT_READ | PROT_WRITE,
+                        MAP_SHARED,
+                        fd,
+                        0,
+                    );
+                    if ptr::addr_eq(map, libc::MAP_FAILED) {
+                        let err = Error::last_os_error(format!(
+                            "Failed to map the memfd mapping of size {mapping_size}"
+                        ));
+                        close(fd);
+                        return Err(err);
+                    }
+                    Ok(Self {
+                        id: ShMemId::from_int(fd),
+                        map: map as *mut u8,
+                        map_size,
+                        mapping_size,
+                        huge_pages: flags.contains(MemFdCreateFlag::MFD_HUGETLB),
+                    })
+                }
+            }
+
+            /// Bind the pages of this mapping to the given NUMA node.
+            /// This has to happen before the pages are first touched.
+            /// Failing to bind is not fatal, the pages then land on whatever node the kernel picks.
+            fn bind_to_numa_node(&self, numa_node: usize) {
+                let bits = c_ulong::BITS as usize;
+                let mut nodemask: Vec<c_ulong> = vec![0; numa_node / bits + 1];
+                nodemask[numa_node / bits] |= 1 << (numa_node % bits);
+                let ret = unsafe {
+                    libc::syscall(
+                        libc::SYS_mbind,
+                        self.map,
+                        self.mapping_size,
+                        MPOL_BIND,
+                        nodemask.as_ptr(),
+                        nodemask.len() * bits + 1,
+                        0,
+                    )
+                };
+                if ret != 0 {
+                    log::warn!(
+                        "Failed to bind shared map to NUMA node {numa_node}: {}",
+                        io::Error::last_os_error()
+                    );
+                }
+            }
+
+            fn shmem_from_id_and_size(
+                id: ShMemId,
+                map_size: usize,
+                huge_page_size: usize,
+            ) -> Result<Self, Error> {
+                let fd = i32::from(id);
+                unsafe {
+                    let mut stat = core::mem::zeroed();
+                    if fstat(fd, &raw mut stat) == -1 {
+                        return Err(Error::last_os_error(format!(
+                            "Failed to fstat the memfd mapping with fd {fd:?}"
+                        )));
+                    }
+                    let mut statfs = core::mem::zeroed();
+                    if fstatfs(fd, &raw mut statfs) == -1 {
+                        return Err(Error::last_os_error(format!(
+                            "Failed to fstatfs the memfd mapping with fd {fd:?}"
+                        )));
+                    }
+                    let huge_pages = statfs.f_type == HUGETLBFS_MAGIC;
+                    let mapping_size = if huge_pages {
+                        map_size.next_multiple_of(huge_page_size)
+                    } else {
+                        map_size
+                    };
+                    #[expect(clippy::cast_sign_loss)]
+                    if stat.st_size as usize != mapping_size {
+                        return Err(Error::illegal_argument(format!(
+                            "The mapping's size {} differs from the requested size {map_size}",
+                            stat.st_size
+                        )));
+                    }
+                    let map = mmap(
+                        ptr::null_mut(),
+                        mapping_size,
+                        PROT_READ | PROT_WRITE,
+                        MAP_SHARED,
+                        fd,
+                        0,
+                    );
+                    if ptr::addr_eq(map, libc::MAP_FAILED) {
+                        return Err(Error::last_os_error(format!(
+                            "mmap() failed for map with fd {fd:?}"
+                        )));
+                    }
+                    Ok(Self {
+                        id: ShMemId::from_int(fd),
+                        map: map as *mut u8,
+                        map_size,
+                        mapping_size,
+                        huge_pages,
+                    })
+                }
+            }
+
+            /// Returns `true` if this mapping is backed by huge pages
+            #[must_use]
+            pub fn huge_pages(&self) -> bool {
+                self.huge_pages
+            }
+        }
+
+        impl ShMem for HugePageShMem {
+            fn id(&self) -> ShMemId {
+                self.id
+            }
+        }
+
+        impl Deref for HugePageShMem {
+            type Target = [u8];
+
+            fn deref(&self) -> &[u8] {
+                unsafe { slice::from_raw_parts(self.map, self.map_size) }
+            }
+        }
+
+        impl DerefMut for HugePageShMem {
+            fn deref_mut(&mut self) -> &mut [u8] {
+                unsafe { slice::from_raw_parts_mut(self.map, self.map_size) }
+            }
+        }
+
+        /// [`Drop`] implementation for [`HugePageShMem`], which cleans up the mapping.
+        impl Drop for HugePageShMem {
+            fn drop(&mut self) {
+                let fd = i32::from(self.id);
+
+                unsafe {
+                    munmap(self.map as *mut _, self.mapping_size);
+                    close(fd);
+                }
+            }
+        }
+
+        /// A [`ShMemProvider`] handing out memfd mappings backed by huge pages,
+        /// bound to the NUMA node of the core the caller runs on.
+        ///
+        /// Like the [`MemfdShMemProvider`](super::memfd::MemfdShMemProvider), maps are identified by
+        /// their file descriptor, so they can only be shared with forked children.
+        #[derive(Clone, Debug)]
+        pub struct HugePageShMemProvider {
+            huge_page_size: usize,
+            huge_pages: bool,
+            numa_binding: bool,
+            core_id: Option<CoreId>,
+        }
+
+        impl HugePageShMemProvider {
+            /// Use huge pages for new maps, if available (the default), or always use regular pages
+            #[must_use]
+            pub fn with_huge_pages(mut self, huge_pages: bool) -> Self {
+                self.huge_pages = huge_pages;
+                self
+            }
+
+            /// Bind new maps to the NUMA node of the core they are allocated on (the default), or don't bind at all
+            #[must_use]
+            pub fn with_numa_binding(mut self, numa_binding: bool) -> Self {
+                self.numa_binding = numa_binding;
+                self
+            }
+
+            /// Bind new maps to the NUMA node of the given core,
+            /// instead of the core the allocating thread happens to run on
+            #[must_use]
+            pub fn with_core(mut self, core_id: CoreId) -> Self {
+                self.core_id = Some(core_id);
+                self
+            }
+
+            /// The NUMA node new maps get bound to
+            #[must_use]
+            pub fn numa_node(&self) -> Option<usize> {
+                if !self.numa_binding {
+                    return None;
+                }
+                self.core_id.or_else(CoreId::current)?.numa_node()
+            }
+        }
+
+        impl Default for HugePageShMemProvider {
+            fn default() -> Self {
+                Self::new().unwrap()
+            }
+        }
+
+        /// Implement [`ShMemProvider`] for [`HugePageShMemProvider`]
+        impl ShMemProvider for HugePageShMemProvider {
+            type ShMem = HugePageShMem;
+
+            fn new() -> Result<Self, Error> {
+                Ok(Self {
+                    huge_page_size: huge_page_size(),
+                    huge_pages: true,
+                    numa_binding: true,
+                    core_id: None,
+                })
+            }
+
+            fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
+                HugePageShMem::new(
+                    map_size,
+                    self.huge_pages.then_some(self.huge_page_size),
+                    self.numa_node(),
+                )
+            }
+
+            fn shmem_from_id_and_size(
+                &mut self,
+                id: ShMemId,
+                size: usize,
+            ) -> Result<Self::ShMem, Error> {
+                HugePageShMem::shmem_from_id_and_size(id, size, self.huge_page_size)
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use serial_test::serial;
+
+            use super::HugePageShMemProvider;
+            use crate::shmem::{ShMem, ShMemProvider};
+
+            #[test]
+            #[serial]
+            #[cfg_attr(miri, ignore)]
+            fn test_hugepage_shmem() {
+                let mut provider = HugePageShMemProvider::new().unwrap();
+                let mut shmem = provider.new_shmem(1024).unwrap();
+                assert_eq!(shmem.len(), 1024);
+                shmem.fill(0x42);
+
+                let shmem2 = provider.shmem_from_id_and_size(shmem.id(), 1024).unwrap();
+                assert_eq!(shmem2.huge_pages(), shmem.huge_pages());
+                assert!(shmem2.iter().all(|&b| b == 0x42));
+            }
+        }
+    }
 }
 
 /// Then `win32` implementation for shared memory.
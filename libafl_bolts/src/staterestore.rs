@@ -20,7 +20,8 @@ use ahash::RandomState;
 use serde::{Serialize, de::DeserializeOwned};
 
 use crate::{
-    AsSlice, Error,
+    AsSlice, AsSliceMut, Error,
+    serialization::SerializationFormat,
     shmem::{ShMem, ShMemProvider},
 };
 
@@ -31,6 +32,8 @@ const EXITING_MAGIC: &[u8; 16] = b"LIBAFL_EXIT_NOW\0";
 #[repr(C)]
 struct StateShMemContent {
     is_disk: bool,
+    /// The id of the [`SerializationFormat`] of the state
+    format: u8,
     buf_len: usize,
     buf: [u8; 0],
 }
@@ -49,6 +52,16 @@ impl StateShMemContent {
         })
     }
 
+    /// The format the state in this map was serialized with.
+    pub fn format(&self) -> Result<SerializationFormat, Error> {
+        let id = unsafe { read_volatile(&raw const self.format) };
+        SerializationFormat::from_id(id).ok_or_else(|| {
+            Error::illegal_state(format!(
+                "Unknown serialization format {id} in state map. Shared data corrupted?"
+            ))
+        })
+    }
+
     /// Get a length that's safe to deref from this map, or error.
     pub fn buf_len_checked(&self, shmem_size: usize) -> Result<usize, Error> {
         let buf_len = unsafe { read_volatile(&self.buf_len) };
@@ -68,9 +81,13 @@ impl StateShMemContent {
 /// If the state gets larger than the preallocated [`ShMem`] shared map,
 /// it will instead write to disk, and store the file name into the map.
 /// Writing to [`StateRestorer`] multiple times is not allowed.
+///
+/// The state gets serialized with `postcard`, unless another format was picked using [`StateRestorer::with_format`].
+/// The format is stored in the shared map, so restarted clients pick it up, too.
 #[derive(Debug, Clone)]
 pub struct StateRestorer<SHM, SP> {
     shmem: SHM,
+    format: SerializationFormat,
     phantom: PhantomData<*const SP>,
 }
 
@@ -94,23 +111,49 @@ where
 
     /// Create a [`StateRestorer`] from `env` variable name
     pub fn from_env(shmem_provider: &mut SP, env_name: &str) -> Result<Self, Error> {
-        Ok(Self {
+        let mut ret = Self {
             shmem: shmem_provider.existing_from_env(env_name)?,
+            format: SerializationFormat::Postcard,
             phantom: PhantomData,
-        })
+        };
+        // Keep using the format whoever created the map picked.
+        ret.format = ret.content().format()?;
+        Ok(ret)
     }
 
     /// Create a new [`StateRestorer`].
     pub fn new(shmem: SHM) -> Self {
         let mut ret = Self {
             shmem,
+            format: SerializationFormat::Postcard,
             phantom: PhantomData,
         };
         ret.reset();
         ret
     }
 
+    /// Serialize states with the given `format` from now on.
+    /// [`StateRestorer`]s created from the same map using [`StateRestorer::from_env`] use it, too.
+    pub fn with_format(mut self, format: SerializationFormat) -> Result<Self, Error> {
+        if !format.is_available() {
+            return Err(Error::illegal_argument(format!(
+                "Serialization format {format} is not available in this build"
+            )));
+        }
+        self.format = format;
+        self.content_mut().format = format.id();
+        Ok(self)
+    }
+
+    /// The format states get serialized with
+    #[must_use]
+    pub fn format(&self) -> SerializationFormat {
+        self.format
+    }
+
     /// Saves a state to the connected [`ShMem`], or a tmpfile, if its serialized size get too large.
+    /// The state is serialized into the map in place, where the [`SerializationFormat`] supports it,
+    /// and serialized a second time for the tmpfile, if it does not fit.
     pub fn save<S>(&mut self, state: &S) -> Result<(), Error>
     where
         S: Serialize,
@@ -121,65 +164,69 @@ where
             ));
         }
 
-        let serialized = postcard::to_allocvec(state)?;
-
-        if size_of::<StateShMemContent>() + serialized.len() > self.shmem.len() {
-            // generate a filename
-            let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
-            // Using the last few k as randomness for a filename, hoping it's unique.
-            hasher.write(&serialized[serialized.len().saturating_sub(4096)..]);
-
-            let filename = format!("{:016x}.libafl_state", hasher.finish());
-            let tmpfile = temp_dir().join(&filename);
-            File::create(tmpfile)?.write_all(&serialized)?;
-
-            // write the filename to shmem
-            let filename_buf = postcard::to_allocvec(&filename)?;
-
-            let len = filename_buf.len();
-            if len > self.shmem.len() {
-                return Err(Error::illegal_state(format!(
-                    "The state restorer map is too small to fit anything, even the filename! 
-                        It needs to be at least {} bytes. 
-                        The tmpfile was written to {}.",
-                    len,
-                    temp_dir().join(&filename).display()
-                )));
-            }
-
-            /*log::info!(
-                "Storing {} bytes to tmpfile {} (larger than map of {} bytes)",
-                serialized.len(),
-                &filename,
-                self.shmem.len()
-            );*/
-
-            let shmem_content = self.content_mut();
-            unsafe {
-                ptr::copy_nonoverlapping(
-                    filename_buf.as_ptr(),
-                    shmem_content.buf.as_mut_ptr(),
-                    len,
-                );
-            }
-            shmem_content.buf_len = len;
-            shmem_content.is_disk = true;
-        } else {
-            // write to shmem directly
-            let len = serialized.len();
+        let format = self.format.id();
+        // Serialize into the map in place, and only fall back to a tmpfile if the state does not fit
+        let in_place = match self
+            .shmem
+            .as_slice_mut()
+            .get_mut(size_of::<StateShMemContent>()..)
+        {
+            Some(buf) => self.format.serialize_into(state, buf)?,
+            None => None,
+        };
+        if let Some(len) = in_place {
             let shmem_content = self.content_mut();
-            unsafe {
-                ptr::copy_nonoverlapping(serialized.as_ptr(), shmem_content.buf.as_mut_ptr(), len);
-            }
             shmem_content.buf_len = len;
             shmem_content.is_disk = false;
+            shmem_content.format = format;
+            return Ok(());
+        }
+
+        let serialized = self.format.serialize(state)?;
+        // generate a filename
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        // Using the last few k as randomness for a filename, hoping it's unique.
+        hasher.write(&serialized[serialized.len().saturating_sub(4096)..]);
+
+        let filename = format!("{:016x}.libafl_state", hasher.finish());
+        let tmpfile = temp_dir().join(&filename);
+        File::create(tmpfile)?.write_all(&serialized)?;
+
+        // write the filename to shmem
+        let filename_buf = postcard::to_allocvec(&filename)?;
+
+        let len = filename_buf.len();
+        if len > self.shmem.len() {
+            return Err(Error::illegal_state(format!(
+                "The state restorer map is too small to fit anything, even the filename! 
+                    It needs to be at least {} bytes. 
+                    The tmpfile was written to {}.",
+                len,
+                temp_dir().join(&filename).display()
+            )));
+        }
+
+        /*log::info!(
+            "Storing {} bytes to tmpfile {} (larger than map of {} bytes)",
+            serialized.len(),
+            &filename,
+            self.shmem.len()
+        );*/
+
+        let shmem_content = self.content_mut();
+        unsafe {
+            ptr::copy_nonoverlapping(filename_buf.as_ptr(), shmem_content.buf.as_mut_ptr(), len);
         }
+        shmem_content.buf_len = len;
+        shmem_content.is_disk = true;
+        shmem_content.format = format;
         Ok(())
     }
 
     /// Reset this [`StateRestorer`] to an empty state.
     pub fn reset(&mut self) {
         let mapsize = self.mapsize();
+        let format = self.format.id();
         let content_mut = self.content_mut();
         if let Ok(Some(tmpfile)) = content_mut.tmpfile(mapsize) {
             // Remove tmpfile and ignore result
@@ -187,6 +234,7 @@ where
         }
         content_mut.is_disk = false;
         content_mut.buf_len = 0;
+        content_mut.format = format;
     }
 
     /// When called from a child, informs the restarter/parent process
@@ -279,7 +327,7 @@ where
             }
             state = &file_content;
         }
-        let deserialized = postcard::from_bytes(state)?;
+        let deserialized = state_shmem_content.format()?.deserialize(state)?;
         Ok(Some(deserialized))
     }
 }
@@ -351,4 +399,45 @@ mod tests {
         assert!(!state_restorer.has_content());
         assert!(!tmpfile.exists());
     }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(all(feature = "bincode", not(target_os = "haiku")))]
+    fn test_state_restore_bincode() {
+        use alloc::{
+            string::{String, ToString},
+            vec::Vec,
+        };
+
+        use crate::{
+            serialization::SerializationFormat,
+            shmem::{ShMemProvider, StdShMem, StdShMemProvider},
+            staterestore::StateRestorer,
+        };
+
+        let mut shmem_provider = StdShMemProvider::new().unwrap();
+        let shmem = shmem_provider.new_shmem(1024).unwrap();
+        let mut state_restorer = StateRestorer::<StdShMem, StdShMemProvider>::new(shmem)
+            .with_format(SerializationFormat::Bincode)
+            .unwrap();
+
+        state_restorer.save(&"hello world".to_string()).unwrap();
+        assert_eq!(
+            state_restorer.content().format().unwrap(),
+            SerializationFormat::Bincode
+        );
+        let restored = state_restorer.restore::<String>().unwrap().unwrap();
+        assert_eq!(restored, "hello world");
+
+        state_restorer.reset();
+        let too_large = vec![4u8; 1025];
+        state_restorer.save(&too_large).unwrap();
+        assert!(state_restorer.content().is_disk);
+        assert_eq!(
+            state_restorer.restore::<Vec<u8>>().unwrap().unwrap(),
+            too_large
+        );
+        state_restorer.reset();
+    }
 }
@@ -0,0 +1,85 @@
+//! Reclaims the shared memory segments of dead processes listed in `LibAFL` shmem manifests.
+
+#[cfg(all(unix, not(any(target_os = "android", target_os = "haiku"))))]
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[cfg(all(unix, not(any(target_os = "android", target_os = "haiku"))))]
+use clap::Parser;
+#[cfg(all(unix, not(any(target_os = "android", target_os = "haiku"))))]
+use libafl_bolts::shmem::unix_shmem::manifest::ShMemManifest;
+
+#[cfg(all(unix, not(any(target_os = "android", target_os = "haiku"))))]
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+#[command(
+    name = "shmem_cleanup",
+    about,
+    long_about = "Reclaims shared memory segments listed in LibAFL shmem manifests whose owning process is dead"
+)]
+pub struct Opt {
+    #[arg(help = "Shmem manifests to clean up", required = true)]
+    pub manifests: Vec<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        help = "Only list the segments that would be reclaimed, without touching anything"
+    )]
+    pub dry_run: bool,
+
+    #[arg(short, long, help = "Also list the segments that are still in use")]
+    pub verbose: bool,
+}
+
+#[cfg(not(all(unix, not(any(target_os = "android", target_os = "haiku")))))]
+fn main() -> ExitCode {
+    eprintln!("Shmem manifests are only supported on unix");
+    ExitCode::FAILURE
+}
+
+#[cfg(all(unix, not(any(target_os = "android", target_os = "haiku"))))]
+fn main() -> ExitCode {
+    env_logger::init();
+    let opts = Opt::parse();
+
+    let mut failed = false;
+    for path in &opts.manifests {
+        let manifest = ShMemManifest::new(path);
+        let report = match manifest.cleanup(opts.dry_run) {
+            Ok(report) => report,
+            Err(err) => {
+                eprintln!("Failed to clean up {}: {err}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+
+        let action = if opts.dry_run {
+            "would reclaim"
+        } else {
+            "reclaimed"
+        };
+        for entry in &report.reclaimed {
+            println!("{}: {action} {entry}", path.display());
+        }
+        if opts.verbose {
+            for entry in &report.in_use {
+                println!("{}: in use {entry}", path.display());
+            }
+        }
+        println!(
+            "{}: {} {action}, {} already gone, {} still in use",
+            path.display(),
+            report.reclaimed.len(),
+            report.stale.len(),
+            report.in_use.len()
+        );
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}